@@ -2,6 +2,7 @@ pub mod dsp;
 pub mod spc700;
 
 use crate::{
+    savestate::{LoadError, Reader, Writer},
     schedule::{event_slots, Event, Schedule, Timestamp},
     Model,
 };
@@ -41,9 +42,16 @@ impl Apu {
         }
     }
 
-    pub(crate) fn handle_update(&mut self, time: Timestamp, schedule: &mut Schedule) {
+    /// Runs the APU until the next DSP sample and outputs it, mixed with the given audio sample
+    /// coming from the cartridge.
+    pub(crate) fn handle_update(
+        &mut self,
+        time: Timestamp,
+        schedule: &mut Schedule,
+        cart_sample: [dsp::Sample; 2],
+    ) {
         self.run(time);
-        Dsp::output_sample(self);
+        Dsp::output_sample(self, cart_sample);
         self.dsp_timestamp += 1;
         schedule.schedule_event(
             event_slots::APU,
@@ -63,4 +71,20 @@ impl Apu {
     pub(crate) fn run(&mut self, end_main_timestamp: Timestamp) {
         Spc700::run(self, end_main_timestamp);
     }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&(self.model == Model::Pal));
+        writer.write(&self.dsp_timestamp);
+        self.spc700.save_state(writer);
+        self.dsp.save_state(writer);
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        if reader.read::<bool>()? != (self.model == Model::Pal) {
+            return Err(LoadError::ModelMismatch);
+        }
+        self.dsp_timestamp = reader.read()?;
+        self.spc700.load_state(reader)?;
+        self.dsp.load_state(reader)
+    }
 }
@@ -4,7 +4,10 @@ mod io;
 use freq_counter::FreqCounter;
 
 use super::Apu;
-use crate::utils::{bitfield_debug, bounded_int_lit};
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    utils::{bitfield_debug, bounded_int_lit},
+};
 use channel::{Channel, Index};
 
 pub type Sample = i16;
@@ -116,7 +119,7 @@ impl Dsp {
         }
     }
 
-    pub(super) fn output_sample(apu: &mut Apu) {
+    pub(super) fn output_sample(apu: &mut Apu, cart_sample: [Sample; 2]) {
         if apu.dsp_timestamp & 1 == 0 && apu.dsp.internal_key_on | apu.dsp.internal_key_off != 0 {
             for i in 0..8 {
                 if apu.dsp.internal_key_off & 1 << i != 0 {
@@ -239,11 +242,95 @@ impl Dsp {
                 .saturating_add(((echo_r as i32 * apu.dsp.echo_volume[1] as i32) >> 7) as i16);
         }
 
-        apu.dsp.sample_chunk.push([left_output, right_output]);
+        // Cartridge audio is mixed in after the DSP's amplifier
+        apu.dsp.sample_chunk.push([
+            left_output.saturating_add(cart_sample[0]),
+            right_output.saturating_add(cart_sample[1]),
+        ]);
         if apu.dsp.sample_chunk.len() >= apu.dsp.sample_chunk_len {
             apu.dsp
                 .backend
                 .handle_sample_chunk(&mut apu.dsp.sample_chunk);
         }
     }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&(self.sample_chunk.len() as u32));
+        for sample in &self.sample_chunk {
+            writer.write(sample);
+        }
+
+        for channel in &self.channels {
+            channel.save_state(writer);
+        }
+        writer.write(&self.main_volume);
+        writer.write(&self.flags.0);
+        writer.write(&self.unused);
+        writer.write(&self.pitch_mod_mask);
+        writer.write(&self.sample_table_base);
+
+        writer.write(&self.key_on);
+        writer.write(&self.key_off);
+        writer.write(&self.internal_key_on);
+        writer.write(&self.internal_key_off);
+
+        writer.write(&self.ended_channels);
+
+        writer.write(&self.noise_mask);
+        writer.write(&self.noise_value);
+        writer.write(&self.noise_rate);
+        self.noise_counter.save_state(writer);
+
+        writer.write(&self.echo_volume);
+        writer.write(&self.echo_feedback_volume);
+        writer.write(&self.echo_channel_mask);
+        writer.write(&self.echo_buffer_base);
+        writer.write(&self.echo_buffer_delay);
+        writer.write(&self.echo_fir_coeffs);
+        writer.write(&self.echo_buffer_off);
+        writer.write(&self.echo_buffer_len);
+        writer.write(&self.echo_samples);
+        writer.write(&self.echo_sample_pos.get());
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        let sample_chunk_len = reader.read::<u32>()? as usize;
+        self.sample_chunk.clear();
+        for _ in 0..sample_chunk_len {
+            self.sample_chunk.push(reader.read()?);
+        }
+
+        for channel in &mut self.channels {
+            channel.load_state(reader)?;
+        }
+        self.main_volume = reader.read()?;
+        self.flags = Flags(reader.read()?);
+        self.unused = reader.read()?;
+        self.pitch_mod_mask = reader.read()?;
+        self.sample_table_base = reader.read()?;
+
+        self.key_on = reader.read()?;
+        self.key_off = reader.read()?;
+        self.internal_key_on = reader.read()?;
+        self.internal_key_off = reader.read()?;
+
+        self.ended_channels = reader.read()?;
+
+        self.noise_mask = reader.read()?;
+        self.noise_value = reader.read()?;
+        self.noise_rate = reader.read()?;
+        self.noise_counter.load_state(reader)?;
+
+        self.echo_volume = reader.read()?;
+        self.echo_feedback_volume = reader.read()?;
+        self.echo_channel_mask = reader.read()?;
+        self.echo_buffer_base = reader.read()?;
+        self.echo_buffer_delay = reader.read()?;
+        self.echo_fir_coeffs = reader.read()?;
+        self.echo_buffer_off = reader.read()?;
+        self.echo_buffer_len = reader.read()?;
+        self.echo_samples = reader.read()?;
+        self.echo_sample_pos = EchoSamplePos::new(reader.read::<u8>()? & 7);
+        Ok(())
+    }
 }
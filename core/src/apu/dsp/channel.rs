@@ -1,6 +1,7 @@
 use super::FreqCounter;
 use crate::{
     apu::Apu,
+    savestate::{LoadError, Reader, Writer},
     utils::{bitfield_debug, bounded_int_lit},
 };
 
@@ -466,4 +467,112 @@ impl Channel {
 
         sample
     }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.volume);
+        writer.write(&self.pitch);
+        writer.write(&self.source_number);
+        writer.write(&self.adsr_control.0);
+        writer.write(&self.gain_control.0);
+        writer.write(&self.envelope);
+        writer.write(&self.last_sample);
+
+        writer.write(&self.cur_addr);
+        writer.write(&self.loop_addr);
+
+        writer.write(&self.pitch_counter);
+
+        writer.write(&self.last_brr_samples);
+        for (sample, filter) in &self.brr_samples {
+            writer.write(sample);
+            writer.write(&filter.get());
+        }
+        writer.write(&match self.brr_block_end {
+            BrrBlockEnd::Normal => 0_u8,
+            BrrBlockEnd::Mute => 1,
+            BrrBlockEnd::Loop => 2,
+        });
+        writer.write(&self.last_sample_index);
+
+        let (state, remaining) = match self.state {
+            State::Stopped => (0_u8, 0),
+            State::JustStarted(remaining) => (1, remaining),
+            State::Adsr => (2, 0),
+            State::DirectGain => (3, 0),
+            State::CustomGain => (4, 0),
+            State::Release => (5, 0),
+        };
+        writer.write(&state);
+        writer.write(&remaining);
+        writer.write(&match self.mode {
+            Mode::Attack => 0_u8,
+            Mode::Decay => 1,
+            Mode::Sustain => 2,
+        });
+        writer.write(&self.internal_envelope);
+        self.envelope_counter.save_state(writer);
+        writer.write(&self.envelope_step);
+        writer.write(&self.envelope_sustain_level);
+        writer.write(&self.direct_gain_envelope);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.volume = reader.read()?;
+        self.pitch = reader.read()?;
+        self.source_number = reader.read()?;
+        // Not using the setters, as they would reset the envelope state
+        self.adsr_control = AdsrControl(reader.read()?);
+        self.gain_control = GainControl(reader.read()?);
+        self.envelope = reader.read()?;
+        self.last_sample = reader.read()?;
+
+        self.cur_addr = reader.read()?;
+        self.loop_addr = reader.read()?;
+
+        self.pitch_counter = reader.read()?;
+
+        self.last_brr_samples = reader.read()?;
+        for (sample, filter) in &mut self.brr_samples {
+            *sample = reader.read()?;
+            let raw_filter = reader.read::<u8>()?;
+            if raw_filter > 3 {
+                return Err(LoadError::InvalidData);
+            }
+            *filter = Filter::new(raw_filter);
+        }
+        self.brr_block_end = match reader.read::<u8>()? {
+            0 => BrrBlockEnd::Normal,
+            1 => BrrBlockEnd::Mute,
+            2 => BrrBlockEnd::Loop,
+            _ => return Err(LoadError::InvalidData),
+        };
+        self.last_sample_index = reader.read()?;
+        if self.last_sample_index as usize > self.brr_samples.len() {
+            return Err(LoadError::InvalidData);
+        }
+
+        let state = reader.read::<u8>()?;
+        let remaining = reader.read::<u8>()?;
+        self.state = match state {
+            0 => State::Stopped,
+            1 => State::JustStarted(remaining),
+            2 => State::Adsr,
+            3 => State::DirectGain,
+            4 => State::CustomGain,
+            5 => State::Release,
+            _ => return Err(LoadError::InvalidData),
+        };
+        self.mode = match reader.read::<u8>()? {
+            0 => Mode::Attack,
+            1 => Mode::Decay,
+            2 => Mode::Sustain,
+            _ => return Err(LoadError::InvalidData),
+        };
+        self.internal_envelope = reader.read()?;
+        self.envelope_counter.load_state(reader)?;
+        self.envelope_step = reader.read()?;
+        self.envelope_sustain_level = reader.read()?;
+        self.direct_gain_envelope = reader.read()?;
+        Ok(())
+    }
 }
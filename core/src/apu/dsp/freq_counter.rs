@@ -1,4 +1,7 @@
-use crate::schedule::Timestamp;
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    schedule::Timestamp,
+};
 
 #[rustfmt::skip]
 static STEP_RATES: [(u8, u8); 0x20] = [
@@ -53,4 +56,20 @@ impl FreqCounter {
         }
         false
     }
+
+    pub fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.reset);
+        writer.write(&self.counter);
+        writer.write(&self.shift);
+    }
+
+    pub fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.reset = reader.read()?;
+        self.counter = reader.read()?;
+        self.shift = reader.read()?;
+        if self.shift as u32 >= Timestamp::BITS {
+            return Err(LoadError::InvalidData);
+        }
+        Ok(())
+    }
 }
@@ -8,6 +8,7 @@ mod interpreter;
 
 use super::Apu;
 use crate::{
+    savestate::{LoadError, Reader, Writer},
     schedule::Timestamp,
     utils::{bitfield_debug, zeroed_box, Bytes},
     Model,
@@ -97,4 +98,32 @@ impl Spc700 {
         } as Timestamp;
         interpreter::run(apu, end_timestamp);
     }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.cur_timestamp);
+        self.regs.save_state(writer);
+        writer.write_bytes(&self.memory[..]);
+        writer.write(&self.control.0);
+        for timer in &self.timers {
+            timer.save_state(writer);
+        }
+        writer.write(&self.cpu_to_apu);
+        writer.write(&self.apu_to_cpu);
+        writer.write(&self.dsp_reg_index);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.cur_timestamp = reader.read()?;
+        self.regs.load_state(reader)?;
+        reader.read_bytes(&mut self.memory[..])?;
+        // Not using `set_control`, as it would reset the ports and timers
+        self.control = Control(reader.read()?);
+        for timer in &mut self.timers {
+            timer.load_state(reader)?;
+        }
+        self.cpu_to_apu = reader.read()?;
+        self.apu_to_cpu = reader.read()?;
+        self.dsp_reg_index = reader.read()?;
+        Ok(())
+    }
 }
@@ -1,4 +1,7 @@
-use crate::utils::bitfield_debug;
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    utils::bitfield_debug,
+};
 
 bitfield_debug!(
     #[derive(Clone, Copy, PartialEq, Eq)]
@@ -63,4 +66,23 @@ impl Regs {
     pub(crate) fn direct_page_base(&self) -> u16 {
         self.direct_page_base
     }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.a);
+        writer.write(&self.x);
+        writer.write(&self.y);
+        writer.write(&self.sp);
+        writer.write(&self.pc);
+        writer.write(&self.psw.0);
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.a = reader.read()?;
+        self.x = reader.read()?;
+        self.y = reader.read()?;
+        self.sp = reader.read()?;
+        self.pc = reader.read()?;
+        self.set_psw(Psw(reader.read()?));
+        Ok(())
+    }
 }
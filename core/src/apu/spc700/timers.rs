@@ -1,5 +1,8 @@
 use super::bus::AccessType;
-use crate::schedule::Timestamp;
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    schedule::Timestamp,
+};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Timer {
@@ -87,4 +90,24 @@ impl Timer {
         }
         result
     }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.enabled);
+        writer.write(&self.internal_counter);
+        writer.write(&self.up_counter);
+        writer.write(&self.internal_counter_max);
+        writer.write(&self.last_update);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.enabled = reader.read()?;
+        self.internal_counter = reader.read()?;
+        self.up_counter = reader.read()?;
+        self.internal_counter_max = reader.read()?;
+        if !(1..=256).contains(&self.internal_counter_max) {
+            return Err(LoadError::InvalidData);
+        }
+        self.last_update = reader.read()?;
+        Ok(())
+    }
 }
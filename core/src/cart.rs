@@ -1,60 +1,424 @@
+pub mod bs_memory;
+pub mod cx4;
+mod date_time;
+pub mod gsu;
 pub mod info;
 mod map;
+pub mod mcc;
+pub mod obc1;
+pub mod patch;
+pub mod rom;
+pub mod sa1;
+pub mod sdd1;
+pub mod spc7110;
+pub mod srtc;
+#[cfg(feature = "st018")]
+pub mod st018;
+pub mod sufami_turbo;
+pub mod upd7725;
 
-use crate::utils::BoxedByteSlice;
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    schedule::{event_slots, Event, Schedule, Timestamp},
+    utils::BoxedByteSlice,
+    Model,
+};
+use bs_memory::Flash;
+use core::fmt::{self, Display};
+use cx4::Cx4;
+use date_time::DateTime;
+use gsu::Gsu;
 use info::Info;
-use map::Map;
+use map::{Map, ReadHandler, WriteHandler};
+use mcc::Mcc;
+use obc1::Obc1;
+use sa1::Sa1;
+use sdd1::Sdd1;
+use spc7110::Spc7110;
+use srtc::SRtc;
+#[cfg(feature = "st018")]
+use st018::St018;
+use std::error::Error as StdError;
+use upd7725::Upd7725;
+
+#[derive(Clone)]
+pub enum Coprocessor {
+    Sa1(Box<Sa1>),
+    Gsu(Box<Gsu>),
+    Upd7725(Box<Upd7725>),
+    Cx4(Box<Cx4>),
+    Sdd1(Box<Sdd1>),
+    Spc7110(Box<Spc7110>),
+    SRtc(Box<SRtc>),
+    Obc1(Box<Obc1>),
+    #[cfg(feature = "st018")]
+    St018(Box<St018>),
+    Mcc(Box<Mcc>),
+}
+
+/// Coprocessor firmware that isn't part of the cartridge ROM dump, and has to be supplied
+/// separately.
+#[derive(Clone, Default)]
+pub struct Firmware {
+    pub program_rom: Option<BoxedByteSlice>,
+    pub data_rom: Option<BoxedByteSlice>,
+}
+
+/// A cartridge inserted into one of the board's external slots, with its own ROM and save RAM;
+/// either a Sufami Turbo mini-cart or a BS Memory pack, whose ROM is rewritable flash memory and
+/// which has no RAM.
+#[derive(Clone)]
+pub struct SlotCart {
+    rom: BoxedByteSlice,
+    ram: BoxedByteSlice,
+    rom_modified: bool,
+    ram_modified: bool,
+    flash: Option<Flash>,
+}
+
+impl SlotCart {
+    pub fn new(rom: BoxedByteSlice, ram: BoxedByteSlice) -> Self {
+        SlotCart {
+            rom,
+            ram,
+            rom_modified: false,
+            ram_modified: false,
+            flash: None,
+        }
+    }
+
+    #[inline]
+    pub fn rom(&self) -> &BoxedByteSlice {
+        &self.rom
+    }
+
+    #[inline]
+    pub fn ram(&self) -> &BoxedByteSlice {
+        &self.ram
+    }
+
+    #[inline]
+    pub fn modify_ram(&mut self, f: impl FnOnce(&mut BoxedByteSlice)) {
+        f(&mut self.ram);
+        self.ram_modified = true;
+    }
+
+    /// Returns whether the ROM was rewritten (which is only possible for BS Memory packs) since it
+    /// was last marked as flushed.
+    #[inline]
+    pub fn rom_modified(&self) -> bool {
+        self.rom_modified
+    }
+
+    #[inline]
+    pub fn mark_rom_flushed(&mut self) {
+        self.rom_modified = false;
+    }
+
+    #[inline]
+    pub fn ram_modified(&self) -> bool {
+        self.ram_modified
+    }
+
+    #[inline]
+    pub fn mark_ram_flushed(&mut self) {
+        self.ram_modified = false;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    MissingFirmware,
+    InvalidFirmwareSize,
+    CoprocessorDisabled,
+}
+
+impl StdError for Error {}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::MissingFirmware => "Missing coprocessor firmware",
+            Self::InvalidFirmwareSize => "Invalid coprocessor firmware size",
+            Self::CoprocessorDisabled => "Coprocessor support is disabled in this build",
+        })
+    }
+}
 
 #[derive(Clone)]
 pub struct Cart {
     rom: BoxedByteSlice,
+    /// The CRC32 of the ROM, used to identify it in save states.
+    rom_crc32: u32,
     ram: BoxedByteSlice,
     ram_modified: bool,
+    slot_carts: Vec<Option<SlotCart>>,
     map: Map,
+    coprocessor: Option<Coprocessor>,
+    cur_time: Timestamp,
+    gp_dma_channel: Option<u8>,
 }
 
 impl Cart {
-    #[allow(clippy::single_match)]
-    pub fn new(rom: BoxedByteSlice, ram: BoxedByteSlice, info: &Info) -> Option<Self> {
+    /// Creates a cartridge, failing if the firmware its coprocessor needs is missing or has the
+    /// wrong size, or if support for its coprocessor wasn't compiled in.
+    ///
+    /// `slot_carts` contains the cartridges inserted into each of the slots described by `info`,
+    /// in the same order.
+    pub fn new(
+        rom: BoxedByteSlice,
+        ram: BoxedByteSlice,
+        info: &Info,
+        firmware: Firmware,
+        mut slot_carts: Vec<Option<SlotCart>>,
+    ) -> Result<Self, Error> {
         let mut map = Map::new();
-        for region in &info.rom_map {
-            let mut size = region.size.unwrap_or(rom.len() as u32);
-            let offset = map::mirror(region.offset, size);
-            size -= offset;
-            for addr_range in &region.address_ranges {
-                map.map::<true, false>(
-                    Some(Self::handle_rom_read),
+        let coprocessor = match info.coprocessor {
+            Some(info::Coprocessor::Sa1) => {
+                // The SA-1's memory map is fixed, with ROM and BW-RAM accesses going through its
+                // mapper
+                Self::map_sa1(&mut map);
+                Some(Coprocessor::Sa1(Box::new(Sa1::new())))
+            }
+            Some(info::Coprocessor::Gsu) => {
+                Self::map_gsu(&mut map);
+                Some(Coprocessor::Gsu(Box::new(Gsu::new(
+                    gsu::Revision::for_rom_size(rom.len()),
+                ))))
+            }
+            Some(info::Coprocessor::Upd7725) => Some(Coprocessor::Upd7725(Box::new(
+                Upd7725::new(
+                    upd7725::Variant::Upd7725,
+                    &firmware.program_rom.ok_or(Error::MissingFirmware)?[..],
+                    &firmware.data_rom.ok_or(Error::MissingFirmware)?[..],
+                    info.coprocessor_map.first().map_or(0, |region| region.mask),
+                    info.coprocessor_frequency,
+                )
+                .ok_or(Error::InvalidFirmwareSize)?,
+            ))),
+            Some(info::Coprocessor::Upd96050) => Some(Coprocessor::Upd7725(Box::new(
+                Upd7725::new(
+                    upd7725::Variant::Upd96050,
+                    &firmware.program_rom.ok_or(Error::MissingFirmware)?[..],
+                    &firmware.data_rom.ok_or(Error::MissingFirmware)?[..],
+                    info.coprocessor_map.first().map_or(0, |region| region.mask),
+                    info.coprocessor_frequency,
+                )
+                .ok_or(Error::InvalidFirmwareSize)?,
+            ))),
+            Some(info::Coprocessor::Cx4) => {
+                Self::map_cx4(&mut map);
+                Some(Coprocessor::Cx4(Box::new(
+                    Cx4::new(&firmware.data_rom.ok_or(Error::MissingFirmware)?[..])
+                        .ok_or(Error::InvalidFirmwareSize)?,
+                )))
+            }
+            Some(info::Coprocessor::Sdd1) => {
+                Self::map_sdd1(&mut map);
+                Some(Coprocessor::Sdd1(Box::new(Sdd1::new())))
+            }
+            Some(info::Coprocessor::Spc7110) => {
+                Self::map_spc7110(&mut map, info.has_rtc);
+                Some(Coprocessor::Spc7110(Box::new(Spc7110::new(info.has_rtc))))
+            }
+            Some(info::Coprocessor::SRtc) => Some(Coprocessor::SRtc(Box::new(SRtc::new()))),
+            Some(info::Coprocessor::Obc1) => Some(Coprocessor::Obc1(Box::new(Obc1::new(&ram)))),
+            #[cfg(feature = "st018")]
+            Some(info::Coprocessor::St018) => Some(Coprocessor::St018(Box::new(
+                St018::new(
+                    &firmware.program_rom.ok_or(Error::MissingFirmware)?[..],
+                    &firmware.data_rom.ok_or(Error::MissingFirmware)?[..],
+                )
+                .ok_or(Error::InvalidFirmwareSize)?,
+            ))),
+            #[cfg(not(feature = "st018"))]
+            Some(info::Coprocessor::St018) => return Err(Error::CoprocessorDisabled),
+            Some(info::Coprocessor::Mcc) => {
+                Self::map_mcc(&mut map, &info.coprocessor_map);
+                Some(Coprocessor::Mcc(Box::new(Mcc::new(
+                    info.slots
+                        .iter()
+                        .position(|slot| slot.ty == info::SlotType::BsMemory),
+                ))))
+            }
+            None => None,
+        };
+        // ROM and RAM accesses need to be arbitrated with the GSU, which can take over their buses,
+        // the Cx4 replaces the ROM's interrupt vectors while it's busy, and the SPC7110 can disable
+        // RAM accesses
+        let (rom_read_fn, ram_read_fn, ram_write_fn): (ReadHandler, ReadHandler, WriteHandler) =
+            match coprocessor {
+                Some(Coprocessor::Gsu(_)) => (
+                    Self::handle_gsu_rom_read,
+                    Self::handle_gsu_ram_read,
+                    Self::handle_gsu_ram_write,
+                ),
+                Some(Coprocessor::Cx4(_)) => (
+                    Self::handle_cx4_rom_read,
+                    Self::handle_ram_read,
+                    Self::handle_ram_write,
+                ),
+                Some(Coprocessor::Spc7110(_)) => (
+                    Self::handle_rom_read,
+                    Self::handle_spc7110_ram_read,
+                    Self::handle_spc7110_ram_write,
+                ),
+                _ => (
+                    Self::handle_rom_read,
+                    Self::handle_ram_read,
+                    Self::handle_ram_write,
+                ),
+            };
+        // The SA-1 already mapped all of its memories, including the ones listed in the board, and
+        // the S-DD1, SPC7110 and MCC map ROM through their own bank registers
+        let (rom_map, ram_map) = match coprocessor {
+            Some(Coprocessor::Sa1(_)) => (&[][..], &[][..]),
+            Some(Coprocessor::Sdd1(_) | Coprocessor::Spc7110(_) | Coprocessor::Mcc(_)) => {
+                (&[][..], &info.ram_map[..])
+            }
+            _ => (&info.rom_map[..], &info.ram_map[..]),
+        };
+        Self::map_memory(&mut map, rom_map, rom_read_fn, None, rom.len() as u32);
+        Self::map_memory(
+            &mut map,
+            ram_map,
+            ram_read_fn,
+            Some(ram_write_fn),
+            info.ram_size,
+        );
+        // Sufami Turbo slots map each mini-cart's ROM and RAM to their own regions, leaving them
+        // unmapped if the slot is empty, while BS Memory slots map the pack's flash memory (unless
+        // it's only accessible through the MCC)
+        slot_carts.resize(info.slots.len(), None);
+        for (i, (slot, slot_cart)) in info.slots.iter().zip(&mut slot_carts).enumerate() {
+            let slot_cart = match slot_cart {
+                Some(slot_cart) => slot_cart,
+                None => continue,
+            };
+            if slot.ty == info::SlotType::BsMemory {
+                slot_cart.flash = Some(Flash::new());
+                if let (false, Some((read_fn, write_fn))) =
+                    (slot_cart.rom.is_empty(), Self::bs_memory_handlers(i))
+                {
+                    Self::map_memory(
+                        &mut map,
+                        &slot.map,
+                        read_fn,
+                        Some(write_fn),
+                        slot_cart.rom.len() as u32,
+                    );
+                }
+            } else if let (info::SlotType::SufamiTurbo, Some(handlers)) =
+                (slot.ty, Self::slot_handlers(i))
+            {
+                let (rom_read_fn, ram_read_fn, ram_write_fn) = handlers;
+                Self::map_memory(
+                    &mut map,
+                    &slot.rom_map,
+                    rom_read_fn,
                     None,
-                    addr_range.banks,
-                    addr_range.addrs,
-                    offset,
-                    size,
-                    region.mask,
+                    slot_cart.rom.len() as u32,
                 );
+                if !slot_cart.ram.is_empty() {
+                    Self::map_memory(
+                        &mut map,
+                        &slot.ram_map,
+                        ram_read_fn,
+                        Some(ram_write_fn),
+                        slot_cart.ram.len() as u32,
+                    );
+                }
             }
         }
-        for region in &info.ram_map {
-            let mut size = region.size.unwrap_or(info.ram_size);
-            let offset = map::mirror(region.offset, size);
-            size -= offset;
-            for addr_range in &region.address_ranges {
-                map.map::<true, true>(
-                    Some(Self::handle_ram_read),
-                    Some(Self::handle_ram_write),
-                    addr_range.banks,
-                    addr_range.addrs,
-                    offset,
-                    size,
-                    region.mask,
-                );
-            }
+        // The DSP's registers overlap the ROM in some boards' fallback mappings, and the OBC1
+        // replaces the save RAM mappings
+        match &coprocessor {
+            Some(Coprocessor::Upd7725(upd7725)) => Self::map_upd7725(
+                &mut map,
+                &info.coprocessor_map,
+                &info.coprocessor_data_ram_map,
+                upd7725.variant(),
+            ),
+            Some(Coprocessor::SRtc(_)) => Self::map_srtc(&mut map, &info.coprocessor_map),
+            Some(Coprocessor::Obc1(_)) => Self::map_obc1(&mut map, &info.coprocessor_map),
+            #[cfg(feature = "st018")]
+            Some(Coprocessor::St018(_)) => Self::map_st018(&mut map, &info.coprocessor_map),
+            _ => {}
         }
-        Some(Cart {
+        let mut cart = Cart {
+            rom_crc32: patch::crc32(&rom[..]),
             rom,
             ram,
             ram_modified: false,
+            slot_carts,
             map,
-        })
+            coprocessor,
+            cur_time: 0,
+            gp_dma_channel: None,
+        };
+        if let Some(Coprocessor::Mcc(_)) = cart.coprocessor {
+            cart.remap_mcc();
+        }
+        Ok(cart)
+    }
+
+    pub(crate) fn setup(
+        &mut self,
+        model: Model,
+        schedule: &mut Schedule,
+        #[cfg(feature = "log")] logger: &slog::Logger,
+    ) {
+        match &mut self.coprocessor {
+            Some(Coprocessor::Sa1(sa1)) => sa1.setup(
+                model,
+                schedule.cur_time,
+                #[cfg(feature = "log")]
+                logger.new(slog::o!("sa1" => "")),
+            ),
+            Some(Coprocessor::Gsu(gsu)) => gsu.setup(schedule.cur_time),
+            Some(Coprocessor::Upd7725(upd7725)) => upd7725.setup(model, schedule.cur_time),
+            Some(Coprocessor::Cx4(cx4)) => cx4.setup(model, schedule.cur_time),
+            #[cfg(feature = "st018")]
+            Some(Coprocessor::St018(st018)) => st018.setup(model, schedule.cur_time),
+            Some(Coprocessor::Spc7110(spc7110)) => {
+                spc7110.setup(model, schedule.cur_time);
+                return;
+            }
+            Some(Coprocessor::SRtc(srtc)) => {
+                srtc.setup(model, schedule.cur_time);
+                return;
+            }
+            Some(Coprocessor::Sdd1(_) | Coprocessor::Obc1(_) | Coprocessor::Mcc(_)) | None => {
+                return
+            }
+        }
+        schedule.set_event(event_slots::CART, Event::Cart);
+        schedule.schedule_event(event_slots::CART, schedule.cur_time + Self::SYNC_INTERVAL);
+    }
+
+    pub(crate) fn soft_reset(&mut self) {
+        match &mut self.coprocessor {
+            Some(Coprocessor::Sa1(sa1)) => sa1.soft_reset(),
+            Some(Coprocessor::Gsu(gsu)) => gsu.soft_reset(),
+            Some(Coprocessor::Upd7725(upd7725)) => upd7725.soft_reset(),
+            Some(Coprocessor::Cx4(cx4)) => cx4.soft_reset(),
+            Some(Coprocessor::Sdd1(sdd1)) => sdd1.soft_reset(),
+            Some(Coprocessor::Spc7110(spc7110)) => spc7110.soft_reset(),
+            Some(Coprocessor::SRtc(srtc)) => srtc.soft_reset(),
+            Some(Coprocessor::Obc1(obc1)) => obc1.reset(&self.ram),
+            #[cfg(feature = "st018")]
+            Some(Coprocessor::St018(st018)) => st018.soft_reset(),
+            Some(Coprocessor::Mcc(mcc)) => {
+                mcc.soft_reset();
+                self.remap_mcc();
+            }
+            None => {}
+        }
+        for slot_cart in self.slot_carts.iter_mut().flatten() {
+            if let Some(flash) = &mut slot_cart.flash {
+                flash.reset();
+            }
+        }
     }
 
     #[inline]
@@ -83,20 +447,182 @@ impl Cart {
         self.ram_modified = false;
     }
 
+    /// Returns the cartridges inserted into each of the board's slots.
+    #[inline]
+    pub fn slot_carts(&self) -> &[Option<SlotCart>] {
+        &self.slot_carts
+    }
+
+    #[inline]
+    pub fn slot_cart_mut(&mut self, slot: usize) -> Option<&mut SlotCart> {
+        self.slot_carts.get_mut(slot)?.as_mut()
+    }
+
+    #[inline]
+    pub fn coprocessor(&self) -> Option<&Coprocessor> {
+        self.coprocessor.as_ref()
+    }
+
+    /// Returns the persistent state of the cartridge's real-time clock, if it has one, to be stored
+    /// alongside its save RAM; `unix_time` is the current host time, in seconds since the Unix
+    /// epoch.
+    pub fn rtc_data(&mut self, unix_time: u64) -> Option<Vec<u8>> {
+        let time = self.cur_time;
+        match &mut self.coprocessor {
+            Some(Coprocessor::Spc7110(spc7110)) => spc7110.rtc_mut().map(|rtc| {
+                rtc.sync(time);
+                rtc.data(unix_time).to_vec()
+            }),
+            Some(Coprocessor::SRtc(srtc)) => {
+                srtc.sync(time);
+                Some(srtc.data(unix_time).to_vec())
+            }
+            _ => None,
+        }
+    }
+
+    /// Restores the real-time clock state returned by [`Cart::rtc_data`], advancing it by the host
+    /// time elapsed since it was saved. Returns whether the data was valid.
+    pub fn load_rtc_data(&mut self, data: &[u8], unix_time: u64) -> bool {
+        match &mut self.coprocessor {
+            Some(Coprocessor::Spc7110(spc7110)) => match spc7110.rtc_mut() {
+                Some(rtc) => rtc.load_data(data, unix_time),
+                None => false,
+            },
+            Some(Coprocessor::SRtc(srtc)) => srtc.load_data(data, unix_time),
+            _ => false,
+        }
+    }
+
+    /// Sets the cartridge's real-time clock, if it has one, to the given host time in seconds since
+    /// the Unix epoch; used to initialize it when there's no persistent state to restore.
+    pub fn set_rtc_time(&mut self, unix_time: u64) {
+        let date_time = DateTime::from_unix_time(unix_time);
+        match &mut self.coprocessor {
+            Some(Coprocessor::Spc7110(spc7110)) => {
+                if let Some(rtc) = spc7110.rtc_mut() {
+                    rtc.set_time(&date_time);
+                }
+            }
+            Some(Coprocessor::SRtc(srtc)) => srtc.set_time(&date_time),
+            _ => {}
+        }
+    }
+
+    /// Returns whether the cartridge is requesting an IRQ from the main CPU.
+    #[inline]
+    pub fn irq_requested(&self) -> bool {
+        match &self.coprocessor {
+            Some(Coprocessor::Sa1(sa1)) => sa1.s_cpu_irq_requested(),
+            Some(Coprocessor::Gsu(gsu)) => gsu.s_cpu_irq_requested(),
+            Some(Coprocessor::Cx4(cx4)) => cx4.s_cpu_irq_requested(),
+            Some(
+                Coprocessor::Upd7725(_)
+                | Coprocessor::Sdd1(_)
+                | Coprocessor::Spc7110(_)
+                | Coprocessor::SRtc(_)
+                | Coprocessor::Obc1(_)
+                | Coprocessor::Mcc(_),
+            )
+            | None => false,
+            #[cfg(feature = "st018")]
+            Some(Coprocessor::St018(_)) => false,
+        }
+    }
+
+    /// Reads from the cartridge at the given main CPU timestamp, which coprocessors will be caught
+    /// up to if needed.
     #[inline]
-    pub fn read_data(&mut self, addr: u32) -> Option<u8> {
+    pub fn read_data(&mut self, addr: u32, time: Timestamp) -> Option<u8> {
+        self.cur_time = time;
         self.map
             .read_data(addr)
             .map(|(read, addr)| read(self, addr))
     }
 
+    /// Writes to the cartridge at the given main CPU timestamp, which coprocessors will be caught up
+    /// to if needed.
     #[inline]
-    pub fn write_data(&mut self, addr: u32, value: u8) -> Option<()> {
+    pub fn write_data(&mut self, addr: u32, value: u8, time: Timestamp) -> Option<()> {
+        self.cur_time = time;
         self.map
             .write_data(addr)
             .map(|(write, addr)| write(self, addr, value))
     }
 
+    /// Sets the general-purpose DMA channel whose transfer is currently reading from the cartridge,
+    /// if any.
+    #[inline]
+    pub(crate) fn set_gp_dma_channel(&mut self, channel: Option<u8>) {
+        self.gp_dma_channel = channel;
+    }
+
+    /// Notifies the cartridge that a general-purpose DMA transfer on the given channel ended.
+    pub(crate) fn finish_gp_dma(&mut self, channel: u8) {
+        if let Some(Coprocessor::Sdd1(sdd1)) = &mut self.coprocessor {
+            sdd1.finish_dma(channel);
+        }
+    }
+
+    // The maximum amount of master cycles coprocessors can lag behind the main CPU when it's not
+    // accessing them.
+    const SYNC_INTERVAL: Timestamp = 256;
+
+    pub(crate) fn handle_event(&mut self, time: Timestamp, schedule: &mut Schedule) {
+        self.cur_time = time;
+        if let Some(mut context) = self.sa1_context() {
+            context.run_until(time);
+        } else if let Some(mut context) = self.gsu_context() {
+            context.run_until(time);
+        } else if let Some(mut context) = self.cx4_context() {
+            context.run_until(time);
+        } else {
+            match &mut self.coprocessor {
+                Some(Coprocessor::Upd7725(upd7725)) => upd7725.run_until(time),
+                #[cfg(feature = "st018")]
+                Some(Coprocessor::St018(st018)) => st018.run_until(time),
+                _ => {}
+            }
+        }
+        schedule.schedule_event(event_slots::CART, time + Self::SYNC_INTERVAL);
+    }
+
+    fn sa1_context(&mut self) -> Option<sa1::Context<'_>> {
+        match &mut self.coprocessor {
+            Some(Coprocessor::Sa1(sa1)) => Some(sa1::Context {
+                sa1,
+                rom: &self.rom,
+                bw_ram: &mut self.ram,
+                ram_modified: &mut self.ram_modified,
+            }),
+            _ => None,
+        }
+    }
+
+    fn gsu_context(&mut self) -> Option<gsu::Context<'_>> {
+        match &mut self.coprocessor {
+            Some(Coprocessor::Gsu(gsu)) => Some(gsu::Context {
+                gsu,
+                rom: &self.rom,
+                ram: &mut self.ram,
+                ram_modified: &mut self.ram_modified,
+            }),
+            _ => None,
+        }
+    }
+
+    fn cx4_context(&mut self) -> Option<cx4::Context<'_>> {
+        match &mut self.coprocessor {
+            Some(Coprocessor::Cx4(cx4)) => Some(cx4::Context {
+                cx4,
+                rom: &self.rom,
+                ram: &mut self.ram,
+                ram_modified: &mut self.ram_modified,
+            }),
+            _ => None,
+        }
+    }
+
     fn handle_rom_read(&mut self, offset: u32) -> u8 {
         self.rom[offset as usize]
     }
@@ -109,4 +635,229 @@ impl Cart {
         self.ram_modified = true;
         self.ram[offset as usize] = value;
     }
+
+    /// Maps the given regions to a memory of the given size, unless they specify their own size.
+    fn map_memory(
+        map: &mut Map,
+        regions: &[info::MapRegion],
+        read_fn: ReadHandler,
+        write_fn: Option<WriteHandler>,
+        memory_size: u32,
+    ) {
+        for region in regions {
+            let mut size = region.size.unwrap_or(memory_size);
+            let offset = map::mirror(region.offset, size);
+            size -= offset;
+            for addr_range in &region.address_ranges {
+                if write_fn.is_some() {
+                    map.map::<true, true>(
+                        Some(read_fn),
+                        write_fn,
+                        addr_range.banks,
+                        addr_range.addrs,
+                        offset,
+                        size,
+                        region.mask,
+                    );
+                } else {
+                    map.map::<true, false>(
+                        Some(read_fn),
+                        None,
+                        addr_range.banks,
+                        addr_range.addrs,
+                        offset,
+                        size,
+                        region.mask,
+                    );
+                }
+            }
+        }
+    }
+
+    fn slot_handlers(slot: usize) -> Option<(ReadHandler, ReadHandler, WriteHandler)> {
+        match slot {
+            0 => Some((
+                Self::handle_slot_rom_read::<0>,
+                Self::handle_slot_ram_read::<0>,
+                Self::handle_slot_ram_write::<0>,
+            )),
+            1 => Some((
+                Self::handle_slot_rom_read::<1>,
+                Self::handle_slot_ram_read::<1>,
+                Self::handle_slot_ram_write::<1>,
+            )),
+            _ => None,
+        }
+    }
+
+    // Slot handlers are only mapped for slots that contain a cartridge
+    fn slot_cart<const SLOT: usize>(&mut self) -> &mut SlotCart {
+        self.slot_carts[SLOT].as_mut().unwrap()
+    }
+
+    fn handle_slot_rom_read<const SLOT: usize>(&mut self, offset: u32) -> u8 {
+        self.slot_cart::<SLOT>().rom[offset as usize]
+    }
+
+    fn handle_slot_ram_read<const SLOT: usize>(&mut self, offset: u32) -> u8 {
+        self.slot_cart::<SLOT>().ram[offset as usize]
+    }
+
+    fn handle_slot_ram_write<const SLOT: usize>(&mut self, offset: u32, value: u8) {
+        let slot_cart = self.slot_cart::<SLOT>();
+        slot_cart.ram_modified = true;
+        slot_cart.ram[offset as usize] = value;
+    }
+
+    fn bs_memory_handlers(slot: usize) -> Option<(ReadHandler, WriteHandler)> {
+        match slot {
+            0 => Some((
+                Self::handle_bs_memory_read::<0>,
+                Self::handle_bs_memory_write::<0>,
+            )),
+            1 => Some((
+                Self::handle_bs_memory_read::<1>,
+                Self::handle_bs_memory_write::<1>,
+            )),
+            _ => None,
+        }
+    }
+
+    // BS Memory slots always get a flash memory state when they contain a pack
+    fn handle_bs_memory_read<const SLOT: usize>(&mut self, offset: u32) -> u8 {
+        let slot_cart = self.slot_cart::<SLOT>();
+        slot_cart
+            .flash
+            .as_ref()
+            .unwrap()
+            .read(&slot_cart.rom, offset)
+    }
+
+    fn handle_bs_memory_write<const SLOT: usize>(&mut self, offset: u32, value: u8) {
+        // The MCC can write-protect the pack
+        if let Some(Coprocessor::Mcc(mcc)) = &self.coprocessor {
+            if !mcc.bs_memory_writable() {
+                return;
+            }
+        }
+        let slot_cart = self.slot_cart::<SLOT>();
+        if slot_cart
+            .flash
+            .as_mut()
+            .unwrap()
+            .write(&mut slot_cart.rom, offset, value)
+        {
+            slot_cart.rom_modified = true;
+        }
+    }
+
+    fn coprocessor_id(&self) -> u8 {
+        match &self.coprocessor {
+            None => 0,
+            Some(Coprocessor::Sa1(_)) => 1,
+            Some(Coprocessor::Gsu(_)) => 2,
+            Some(Coprocessor::Upd7725(_)) => 3,
+            Some(Coprocessor::Cx4(_)) => 4,
+            Some(Coprocessor::Sdd1(_)) => 5,
+            Some(Coprocessor::Spc7110(_)) => 6,
+            Some(Coprocessor::SRtc(_)) => 7,
+            Some(Coprocessor::Obc1(_)) => 8,
+            #[cfg(feature = "st018")]
+            Some(Coprocessor::St018(_)) => 9,
+            Some(Coprocessor::Mcc(_)) => 10,
+        }
+    }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.rom_crc32);
+        writer.write(&self.coprocessor_id());
+        writer.write(&(self.rom.len() as u32));
+        writer.write(&(self.ram.len() as u32));
+        writer.write_bytes(&self.ram[..]);
+        writer.write(&(self.slot_carts.len() as u32));
+        for slot_cart in &self.slot_carts {
+            writer.write(&slot_cart.is_some());
+            if let Some(slot_cart) = slot_cart {
+                writer.write(&(slot_cart.rom.len() as u32));
+                writer.write(&(slot_cart.ram.len() as u32));
+                writer.write_bytes(&slot_cart.ram[..]);
+                writer.write(&slot_cart.flash.is_some());
+                if let Some(flash) = &slot_cart.flash {
+                    flash.save_state(writer);
+                    writer.write_bytes(&slot_cart.rom[..]);
+                }
+            }
+        }
+        writer.write(&self.cur_time);
+        match &self.coprocessor {
+            Some(Coprocessor::Sa1(sa1)) => sa1.save_state(writer),
+            Some(Coprocessor::Gsu(gsu)) => gsu.save_state(writer),
+            Some(Coprocessor::Upd7725(upd7725)) => upd7725.save_state(writer),
+            Some(Coprocessor::Cx4(cx4)) => cx4.save_state(writer),
+            Some(Coprocessor::Sdd1(sdd1)) => sdd1.save_state(writer),
+            Some(Coprocessor::Spc7110(spc7110)) => spc7110.save_state(writer),
+            Some(Coprocessor::SRtc(srtc)) => srtc.save_state(writer),
+            Some(Coprocessor::Obc1(obc1)) => obc1.save_state(writer),
+            #[cfg(feature = "st018")]
+            Some(Coprocessor::St018(st018)) => st018.save_state(writer),
+            Some(Coprocessor::Mcc(mcc)) => mcc.save_state(writer),
+            None => {}
+        }
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        if reader.read::<u32>()? != self.rom_crc32
+            || reader.read::<u8>()? != self.coprocessor_id()
+            || reader.read::<u32>()? as usize != self.rom.len()
+            || reader.read::<u32>()? as usize != self.ram.len()
+        {
+            return Err(LoadError::CartMismatch);
+        }
+        reader.read_bytes(&mut self.ram[..])?;
+        self.ram_modified = true;
+        if reader.read::<u32>()? as usize != self.slot_carts.len() {
+            return Err(LoadError::CartMismatch);
+        }
+        for slot_cart in &mut self.slot_carts {
+            if reader.read::<bool>()? != slot_cart.is_some() {
+                return Err(LoadError::CartMismatch);
+            }
+            if let Some(slot_cart) = slot_cart {
+                if reader.read::<u32>()? as usize != slot_cart.rom.len()
+                    || reader.read::<u32>()? as usize != slot_cart.ram.len()
+                {
+                    return Err(LoadError::CartMismatch);
+                }
+                reader.read_bytes(&mut slot_cart.ram[..])?;
+                slot_cart.ram_modified = true;
+                if reader.read::<bool>()? != slot_cart.flash.is_some() {
+                    return Err(LoadError::CartMismatch);
+                }
+                if let Some(flash) = &mut slot_cart.flash {
+                    flash.load_state(reader)?;
+                    reader.read_bytes(&mut slot_cart.rom[..])?;
+                    slot_cart.rom_modified = true;
+                }
+            }
+        }
+        self.cur_time = reader.read()?;
+        match &mut self.coprocessor {
+            Some(Coprocessor::Sa1(sa1)) => sa1.load_state(reader)?,
+            Some(Coprocessor::Gsu(gsu)) => gsu.load_state(reader)?,
+            Some(Coprocessor::Upd7725(upd7725)) => upd7725.load_state(reader)?,
+            Some(Coprocessor::Cx4(cx4)) => cx4.load_state(reader)?,
+            Some(Coprocessor::Sdd1(sdd1)) => sdd1.load_state(reader)?,
+            Some(Coprocessor::Spc7110(spc7110)) => spc7110.load_state(reader)?,
+            Some(Coprocessor::SRtc(srtc)) => srtc.load_state(reader)?,
+            Some(Coprocessor::Obc1(obc1)) => obc1.load_state(reader)?,
+            #[cfg(feature = "st018")]
+            Some(Coprocessor::St018(st018)) => st018.load_state(reader)?,
+            Some(Coprocessor::Mcc(mcc)) => {
+                mcc.load_state(reader)?;
+                self.remap_mcc();
+            }
+            None => {}
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,170 @@
+use super::map::mirror;
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    utils::BoxedByteSlice,
+};
+
+// Erases operate on 64 KiB blocks
+const BLOCK_SIZE: u32 = 0x1_0000;
+
+// The compatible status register's bits
+const STATUS_READY: u8 = 1 << 7;
+const STATUS_ERASE_ERROR: u8 = 1 << 5;
+const STATUS_PROGRAM_ERROR: u8 = 1 << 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    ReadArray,
+    Status,
+    ExtendedStatus,
+    VendorInfo,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Pending {
+    None,
+    Program,
+    EraseBlock,
+    EraseChip,
+    VendorInfo,
+}
+
+/// The command state of a BS Memory pack's flash memory, a Sharp chip using (a subset of) the
+/// LH28F800SU command set, written to any address in the pack.
+///
+/// Programming and erasing complete instantly, so the chip is always reported as ready.
+#[derive(Clone)]
+pub struct Flash {
+    mode: Mode,
+    pending: Pending,
+    status: u8,
+    vendor_info_unlocked: bool,
+}
+
+impl Flash {
+    pub(super) fn new() -> Self {
+        Flash {
+            mode: Mode::ReadArray,
+            pending: Pending::None,
+            status: 0,
+            vendor_info_unlocked: false,
+        }
+    }
+
+    pub(super) fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Returns the chip's identification info, as read from $xxFF00-$xxFF13 after unlocking it.
+    fn vendor_info(contents: &BoxedByteSlice, offset: u32) -> u8 {
+        match offset & 0xFF {
+            0x00 => b'M',
+            0x02 => b'P',
+            // Type 2 (rewritable), followed by the size as a power of two in KiB
+            0x06 => 0x20 | (contents.len().trailing_zeros().saturating_sub(10) as u8 & 0xF),
+            _ => 0,
+        }
+    }
+
+    pub(super) fn read(&self, contents: &BoxedByteSlice, offset: u32) -> u8 {
+        match self.mode {
+            Mode::ReadArray => contents[mirror(offset, contents.len() as u32) as usize],
+            Mode::Status => self.status | STATUS_READY,
+            // Only the global status register is implemented, with the same contents as the
+            // compatible one
+            Mode::ExtendedStatus => {
+                if offset & 0xFFFF == 2 {
+                    self.status | STATUS_READY
+                } else {
+                    STATUS_READY
+                }
+            }
+            Mode::VendorInfo => Self::vendor_info(contents, offset),
+        }
+    }
+
+    /// Handles a write to the flash memory, returning whether its contents were modified.
+    pub(super) fn write(&mut self, contents: &mut BoxedByteSlice, offset: u32, value: u8) -> bool {
+        let offset = mirror(offset, contents.len() as u32);
+        match self.pending {
+            Pending::Program => {
+                // Programming can only clear bits
+                self.pending = Pending::None;
+                self.mode = Mode::Status;
+                contents[offset as usize] &= value;
+                return true;
+            }
+            Pending::EraseBlock | Pending::EraseChip => {
+                let pending = self.pending;
+                self.pending = Pending::None;
+                self.mode = Mode::Status;
+                if value != 0xD0 {
+                    self.status |= STATUS_ERASE_ERROR | STATUS_PROGRAM_ERROR;
+                    return false;
+                }
+                let range = if pending == Pending::EraseBlock {
+                    let start = (offset & !(BLOCK_SIZE - 1)) as usize;
+                    start..(start + BLOCK_SIZE as usize).min(contents.len())
+                } else {
+                    0..contents.len()
+                };
+                contents[range].fill(0xFF);
+                return true;
+            }
+            Pending::VendorInfo => {
+                self.pending = Pending::None;
+                if value == 0xD0 {
+                    self.vendor_info_unlocked = true;
+                    return false;
+                }
+            }
+            Pending::None => {}
+        }
+
+        match value {
+            0x00 | 0xFF => {
+                self.mode = Mode::ReadArray;
+                self.vendor_info_unlocked = false;
+            }
+            0x10 | 0x40 => self.pending = Pending::Program,
+            0x20 => self.pending = Pending::EraseBlock,
+            0xA7 => self.pending = Pending::EraseChip,
+            0x38 => self.pending = Pending::VendorInfo,
+            0x50 => self.status = 0,
+            0x70 => self.mode = Mode::Status,
+            0x71 => self.mode = Mode::ExtendedStatus,
+            // Reading the page buffer returns the vendor info if it was unlocked beforehand
+            0x75 if self.vendor_info_unlocked => self.mode = Mode::VendorInfo,
+            _ => {}
+        }
+        false
+    }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&(self.mode as u8));
+        writer.write(&(self.pending as u8));
+        writer.write(&self.status);
+        writer.write(&self.vendor_info_unlocked);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.mode = match reader.read::<u8>()? {
+            0 => Mode::ReadArray,
+            1 => Mode::Status,
+            2 => Mode::ExtendedStatus,
+            3 => Mode::VendorInfo,
+            _ => return Err(LoadError::InvalidData),
+        };
+        self.pending = match reader.read::<u8>()? {
+            0 => Pending::None,
+            1 => Pending::Program,
+            2 => Pending::EraseBlock,
+            3 => Pending::EraseChip,
+            4 => Pending::VendorInfo,
+            _ => return Err(LoadError::InvalidData),
+        };
+        self.status = reader.read()?;
+        self.vendor_info_unlocked = reader.read()?;
+        Ok(())
+    }
+}
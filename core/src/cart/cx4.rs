@@ -0,0 +1,759 @@
+mod instrs;
+
+use super::{
+    map::{mirror, Map, ReadHandler, WriteHandler},
+    Cart,
+};
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    schedule::Timestamp,
+    utils::BoxedByteSlice,
+    Model,
+};
+
+pub const DATA_ROM_SIZE: usize = 0xC00;
+
+const DATA_ROM_WORDS: usize = DATA_ROM_SIZE / 3;
+const DATA_RAM_SIZE: usize = 0xC00;
+const CACHE_PAGE_WORDS: usize = 0x100;
+const STACK_LEN: usize = 8;
+const FREQUENCY: u128 = 20_000_000;
+const NO_CACHE_ADDR: u32 = u32::MAX;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Regs {
+    pub pb: u16,
+    pub pc: u8,
+    pub n: bool,
+    pub z: bool,
+    pub c: bool,
+    pub v: bool,
+    pub a: u32,
+    pub p: u16,
+    pub mul: u64,
+    pub mdr: u32,
+    pub rom: u32,
+    pub ram: u32,
+    pub mar: u32,
+    pub dpr: u32,
+    pub gprs: [u32; 16],
+}
+
+#[derive(Clone, Copy)]
+struct Cache {
+    load_pending: bool,
+    page: u8,
+    locked: [bool; 2],
+    page_addrs: [u32; 2],
+    base: u32,
+    pb: u16,
+    pc: u8,
+}
+
+#[derive(Clone, Copy)]
+struct Dma {
+    pending: bool,
+    src: u32,
+    dst: u32,
+    len: u16,
+}
+
+#[derive(Clone, Copy)]
+struct BusAccess {
+    cycles_left: u8,
+    is_write: bool,
+    addr: u32,
+}
+
+/// The state of the Hitachi HG51BS169 (also known as Cx4), a 24-bit DSP running at 20 MHz used
+/// mainly for wireframe 3D and sprite transformations.
+///
+/// It executes 16-bit instructions from two 256-word cache pages loaded from the cartridge ROM,
+/// and can copy data between its own RAM and the cartridge memories through DMA; the main CPU
+/// controls it through a register window at $6000-$7FFF, and is caught up to before every access
+/// to it.
+#[derive(Clone)]
+pub struct Cx4 {
+    regs: Regs,
+    cur_cycle: u64,
+    master_clock_frequency: u128,
+
+    halted: bool,
+    locked: bool,
+    irq_disabled: bool,
+    irq_requested: bool,
+    single_rom: bool,
+    rom_wait_states: u8,
+    ram_wait_states: u8,
+    suspended: bool,
+    suspend_cycles: u8,
+    vectors: [u8; 0x20],
+
+    cache: Cache,
+    dma: Dma,
+    bus_access: BusAccess,
+    stack: [u32; STACK_LEN],
+
+    program_cache: Box<[[u16; CACHE_PAGE_WORDS]; 2]>,
+    data_rom: Box<[u32; DATA_ROM_WORDS]>,
+    data_ram: Box<[u8; DATA_RAM_SIZE]>,
+}
+
+impl Cx4 {
+    /// Creates a Cx4 from its data ROM (containing little-endian 24-bit words), returning `None`
+    /// if its size is invalid.
+    pub(super) fn new(data_rom: &[u8]) -> Option<Self> {
+        if data_rom.len() != DATA_ROM_SIZE {
+            return None;
+        }
+        let mut result = Cx4 {
+            regs: Regs::default(),
+            cur_cycle: 0,
+            master_clock_frequency: 21_477_270,
+
+            halted: true,
+            locked: false,
+            irq_disabled: false,
+            irq_requested: false,
+            single_rom: true,
+            rom_wait_states: 3,
+            ram_wait_states: 3,
+            suspended: false,
+            suspend_cycles: 0,
+            vectors: [0; 0x20],
+
+            cache: Cache {
+                load_pending: false,
+                page: 0,
+                locked: [false; 2],
+                page_addrs: [NO_CACHE_ADDR; 2],
+                base: 0,
+                pb: 0,
+                pc: 0,
+            },
+            dma: Dma {
+                pending: false,
+                src: 0,
+                dst: 0,
+                len: 0,
+            },
+            bus_access: BusAccess {
+                cycles_left: 0,
+                is_write: false,
+                addr: 0,
+            },
+            stack: [0; STACK_LEN],
+
+            program_cache: Box::new([[0; CACHE_PAGE_WORDS]; 2]),
+            data_rom: Box::new([0; DATA_ROM_WORDS]),
+            data_ram: Box::new([0; DATA_RAM_SIZE]),
+        };
+        for (word, bytes) in result.data_rom.iter_mut().zip(data_rom.chunks_exact(3)) {
+            *word = bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16;
+        }
+        Some(result)
+    }
+
+    pub(super) fn setup(&mut self, model: Model, time: Timestamp) {
+        self.master_clock_frequency = match model {
+            Model::Ntsc => 21_477_270,
+            Model::Pal => 21_281_370,
+        };
+        self.cur_cycle = self.cycle_for_time(time);
+    }
+
+    pub(super) fn soft_reset(&mut self) {
+        let data_rom = self.data_rom.clone();
+        *self = Cx4 {
+            cur_cycle: self.cur_cycle,
+            master_clock_frequency: self.master_clock_frequency,
+            data_rom,
+            ..Cx4::new(&[0; DATA_ROM_SIZE]).unwrap()
+        };
+    }
+
+    #[inline]
+    pub fn regs(&self) -> &Regs {
+        &self.regs
+    }
+
+    #[inline]
+    pub fn data_ram(&self) -> &[u8; DATA_RAM_SIZE] {
+        &self.data_ram
+    }
+
+    /// Returns whether the Cx4 is requesting an IRQ from the main CPU.
+    #[inline]
+    pub fn s_cpu_irq_requested(&self) -> bool {
+        self.irq_requested
+    }
+
+    fn cycle_for_time(&self, time: Timestamp) -> u64 {
+        (time as u128 * FREQUENCY / self.master_clock_frequency) as u64
+    }
+
+    /// Returns whether the Cx4 is currently accessing the cartridge bus, during which the main CPU
+    /// can't read from the ROM.
+    #[inline]
+    fn is_busy(&self) -> bool {
+        self.cache.load_pending || self.dma.pending || self.bus_access.cycles_left != 0
+    }
+
+    #[inline]
+    fn is_running(&self) -> bool {
+        self.is_busy() || !self.halted
+    }
+
+    fn halt(&mut self) {
+        self.halted = true;
+        if !self.irq_disabled {
+            self.irq_requested = true;
+        }
+    }
+
+    #[inline]
+    fn is_rom_addr(addr: u32) -> bool {
+        addr & 0x40_8000 == 0x00_8000 || addr & 0xC0_0000 == 0xC0_0000
+    }
+
+    #[inline]
+    fn is_ram_addr(addr: u32) -> bool {
+        addr & 0xF8_8000 == 0x70_0000
+    }
+
+    /// The amount of cycles taken by a Cx4 bus access to the given address.
+    fn access_cycles(&self, addr: u32) -> u64 {
+        if Self::is_rom_addr(addr) {
+            1 + self.rom_wait_states as u64
+        } else if Self::is_ram_addr(addr) {
+            1 + self.ram_wait_states as u64
+        } else {
+            1
+        }
+    }
+
+    #[inline]
+    fn data_ram_offset(addr: u32) -> usize {
+        let offset = addr as usize & 0xFFF;
+        if offset >= DATA_RAM_SIZE {
+            offset - 0x400
+        } else {
+            offset
+        }
+    }
+
+    fn start_bus_access(&mut self, is_write: bool) {
+        self.bus_access = BusAccess {
+            cycles_left: self.access_cycles(self.regs.mar) as u8,
+            is_write,
+            addr: self.regs.mar,
+        };
+    }
+
+    /// Reads from the internal register file, as addressed by instruction operands.
+    fn read_reg(&mut self, i: u8) -> u32 {
+        let regs = &self.regs;
+        match i & 0x7F {
+            0x01 => (regs.mul >> 24) as u32 & 0xFF_FFFF,
+            0x02 => regs.mul as u32 & 0xFF_FFFF,
+            0x03 => regs.mdr,
+            0x08 => regs.rom,
+            0x0C => regs.ram,
+            0x13 => regs.mar,
+            0x1C => regs.dpr,
+            0x20 => regs.pc as u32,
+            0x28 => regs.p as u32,
+            // Reading these starts a bus read from MAR into MDR
+            0x2E | 0x2F => {
+                self.start_bus_access(false);
+                0
+            }
+            0x50 => 0x00_0000,
+            0x51 => 0xFF_FFFF,
+            0x52 => 0x00_FF00,
+            0x53 => 0xFF_0000,
+            0x54 => 0x00_FFFF,
+            0x55 => 0xFF_FF00,
+            0x56 => 0x80_0000,
+            0x57 => 0x7F_FFFF,
+            0x58 => 0x00_8000,
+            0x59 => 0x00_7FFF,
+            0x5A => 0xFF_7FFF,
+            0x5B => 0xFF_FF7F,
+            0x5C => 0x01_0000,
+            0x5D => 0xFE_FFFF,
+            0x5E => 0x00_0100,
+            0x5F => 0x00_FEFF,
+            0x60..=0x7F => regs.gprs[(i & 0xF) as usize],
+            _ => 0,
+        }
+    }
+
+    /// Writes to the internal register file, as addressed by instruction operands.
+    fn write_reg(&mut self, i: u8, value: u32) {
+        let value = value & 0xFF_FFFF;
+        let regs = &mut self.regs;
+        match i & 0x7F {
+            0x01 => regs.mul = (value as u64) << 24 | (regs.mul & 0xFF_FFFF),
+            0x02 => regs.mul = (regs.mul & 0xFFFF_FF00_0000) | value as u64,
+            0x03 => regs.mdr = value,
+            0x08 => regs.rom = value,
+            0x0C => regs.ram = value,
+            0x13 => regs.mar = value,
+            0x1C => regs.dpr = value,
+            0x20 => regs.pc = value as u8,
+            0x28 => regs.p = value as u16 & 0x7FFF,
+            // Writing these starts a bus write from MDR to MAR
+            0x2E | 0x2F => self.start_bus_access(true),
+            0x60..=0x7F => regs.gprs[(i & 0xF) as usize] = value,
+            _ => {}
+        }
+    }
+
+    pub(super) fn read_s_cpu_io(&mut self, addr: u16) -> u8 {
+        let addr = 0x7C00 | (addr & 0x3FF);
+        match addr {
+            0x7F40..=0x7F42 => (self.dma.src >> ((addr - 0x7F40) << 3)) as u8,
+            0x7F43 => self.dma.len as u8,
+            0x7F44 => (self.dma.len >> 8) as u8,
+            0x7F45..=0x7F47 => (self.dma.dst >> ((addr - 0x7F45) << 3)) as u8,
+            0x7F48 => self.cache.page,
+            0x7F49..=0x7F4B => (self.cache.base >> ((addr - 0x7F49) << 3)) as u8,
+            0x7F4C => self.cache.locked[0] as u8 | (self.cache.locked[1] as u8) << 1,
+            0x7F4D => self.cache.pb as u8,
+            0x7F4E => (self.cache.pb >> 8) as u8,
+            0x7F4F => self.cache.pc,
+            0x7F50 => self.ram_wait_states | self.rom_wait_states << 4,
+            0x7F51 => self.irq_disabled as u8,
+            0x7F52 => self.single_rom as u8,
+            0x7F53..=0x7F5F => {
+                self.suspended as u8
+                    | (self.irq_requested as u8) << 1
+                    | (self.is_running() as u8) << 6
+                    | (self.is_busy() as u8) << 7
+            }
+            0x7F60..=0x7F7F => self.vectors[(addr & 0x1F) as usize],
+            0x7F80..=0x7FAF | 0x7FC0..=0x7FEF => {
+                let i = addr & 0x3F;
+                (self.regs.gprs[(i / 3) as usize] >> ((i % 3) << 3)) as u8
+            }
+            _ => 0,
+        }
+    }
+
+    pub(super) fn write_s_cpu_io(&mut self, addr: u16, value: u8) {
+        #[inline]
+        fn set_byte(dst: &mut u32, i: u16, value: u8) {
+            let shift = i << 3;
+            *dst = (*dst & !(0xFF << shift)) | (value as u32) << shift;
+        }
+
+        let addr = 0x7C00 | (addr & 0x3FF);
+        match addr {
+            0x7F40..=0x7F42 => set_byte(&mut self.dma.src, addr - 0x7F40, value),
+            0x7F43 => self.dma.len = (self.dma.len & 0xFF00) | value as u16,
+            0x7F44 => self.dma.len = (value as u16) << 8 | (self.dma.len & 0xFF),
+            0x7F45..=0x7F47 => {
+                set_byte(&mut self.dma.dst, addr - 0x7F45, value);
+                // Writing the high byte of the destination starts a transfer
+                if addr == 0x7F47 && self.halted {
+                    self.dma.pending = true;
+                }
+            }
+            0x7F48 => {
+                self.cache.page = value & 1;
+                if self.halted {
+                    self.cache.load_pending = true;
+                }
+            }
+            0x7F49..=0x7F4B => set_byte(&mut self.cache.base, addr - 0x7F49, value),
+            0x7F4C => self.cache.locked = [value & 1 != 0, value & 2 != 0],
+            0x7F4D => self.cache.pb = (self.cache.pb & 0x7F00) | value as u16,
+            0x7F4E => self.cache.pb = ((value & 0x7F) as u16) << 8 | (self.cache.pb & 0xFF),
+            0x7F4F => {
+                // Writing the starting PC starts execution if the Cx4 was halted
+                self.cache.pc = value;
+                if self.halted {
+                    self.halted = false;
+                    self.regs.pb = self.cache.pb;
+                    self.regs.pc = value;
+                }
+            }
+            0x7F50 => {
+                self.ram_wait_states = value & 7;
+                self.rom_wait_states = value >> 4 & 7;
+            }
+            0x7F51 => {
+                self.irq_disabled = value & 1 != 0;
+                if self.irq_disabled {
+                    self.irq_requested = false;
+                }
+            }
+            0x7F52 => self.single_rom = value & 1 != 0,
+            0x7F53 => {
+                self.locked = false;
+                self.halted = true;
+            }
+            0x7F55..=0x7F5C => {
+                self.suspended = true;
+                // A duration of 0 suspends the Cx4 until it's manually resumed
+                self.suspend_cycles = ((addr - 0x7F55) << 5) as u8;
+            }
+            0x7F5D => self.suspended = false,
+            0x7F5E => self.irq_requested = false,
+            0x7F60..=0x7F7F => self.vectors[(addr & 0x1F) as usize] = value,
+            0x7F80..=0x7FAF | 0x7FC0..=0x7FEF => {
+                let i = addr & 0x3F;
+                set_byte(&mut self.regs.gprs[(i / 3) as usize], i % 3, value);
+            }
+            _ => {}
+        }
+    }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        let regs = &self.regs;
+        writer.write(&self.cur_cycle);
+        writer.write(&regs.pb);
+        writer.write(&regs.pc);
+        writer.write(&regs.n);
+        writer.write(&regs.z);
+        writer.write(&regs.c);
+        writer.write(&regs.v);
+        writer.write(&regs.a);
+        writer.write(&regs.p);
+        writer.write(&regs.mul);
+        writer.write(&regs.mdr);
+        writer.write(&regs.rom);
+        writer.write(&regs.ram);
+        writer.write(&regs.mar);
+        writer.write(&regs.dpr);
+        writer.write(&regs.gprs);
+
+        writer.write(&self.halted);
+        writer.write(&self.locked);
+        writer.write(&self.irq_disabled);
+        writer.write(&self.irq_requested);
+        writer.write(&self.single_rom);
+        writer.write(&self.rom_wait_states);
+        writer.write(&self.ram_wait_states);
+        writer.write(&self.suspended);
+        writer.write(&self.suspend_cycles);
+        writer.write(&self.vectors);
+
+        writer.write(&self.cache.load_pending);
+        writer.write(&self.cache.page);
+        writer.write(&self.cache.locked);
+        writer.write(&self.cache.page_addrs);
+        writer.write(&self.cache.base);
+        writer.write(&self.cache.pb);
+        writer.write(&self.cache.pc);
+        writer.write(&self.dma.pending);
+        writer.write(&self.dma.src);
+        writer.write(&self.dma.dst);
+        writer.write(&self.dma.len);
+        writer.write(&self.bus_access.cycles_left);
+        writer.write(&self.bus_access.is_write);
+        writer.write(&self.bus_access.addr);
+        writer.write(&self.stack);
+
+        for page in self.program_cache.iter() {
+            for word in page {
+                writer.write(word);
+            }
+        }
+        writer.write_bytes(&self.data_ram[..]);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        let regs = &mut self.regs;
+        self.cur_cycle = reader.read()?;
+        regs.pb = reader.read()?;
+        regs.pc = reader.read()?;
+        regs.n = reader.read()?;
+        regs.z = reader.read()?;
+        regs.c = reader.read()?;
+        regs.v = reader.read()?;
+        regs.a = reader.read()?;
+        regs.p = reader.read()?;
+        regs.mul = reader.read()?;
+        regs.mdr = reader.read()?;
+        regs.rom = reader.read()?;
+        regs.ram = reader.read()?;
+        regs.mar = reader.read()?;
+        regs.dpr = reader.read()?;
+        regs.gprs = reader.read()?;
+
+        self.halted = reader.read()?;
+        self.locked = reader.read()?;
+        self.irq_disabled = reader.read()?;
+        self.irq_requested = reader.read()?;
+        self.single_rom = reader.read()?;
+        self.rom_wait_states = reader.read()?;
+        self.ram_wait_states = reader.read()?;
+        self.suspended = reader.read()?;
+        self.suspend_cycles = reader.read()?;
+        self.vectors = reader.read()?;
+
+        self.cache.load_pending = reader.read()?;
+        self.cache.page = reader.read()?;
+        self.cache.locked = reader.read()?;
+        self.cache.page_addrs = reader.read()?;
+        self.cache.base = reader.read()?;
+        self.cache.pb = reader.read()?;
+        self.cache.pc = reader.read()?;
+        self.dma.pending = reader.read()?;
+        self.dma.src = reader.read()?;
+        self.dma.dst = reader.read()?;
+        self.dma.len = reader.read()?;
+        self.bus_access.cycles_left = reader.read()?;
+        self.bus_access.is_write = reader.read()?;
+        self.bus_access.addr = reader.read()?;
+        self.stack = reader.read()?;
+
+        for page in self.program_cache.iter_mut() {
+            for word in page {
+                *word = reader.read()?;
+            }
+        }
+        reader.read_bytes(&mut self.data_ram[..])?;
+
+        if self.cache.page > 1 {
+            return Err(LoadError::InvalidData);
+        }
+        Ok(())
+    }
+}
+
+/// The Cx4 together with the cartridge memories it has access to.
+pub(super) struct Context<'a> {
+    pub cx4: &'a mut Cx4,
+    pub rom: &'a BoxedByteSlice,
+    pub ram: &'a mut BoxedByteSlice,
+    pub ram_modified: &'a mut bool,
+}
+
+impl<'a> Context<'a> {
+    pub(super) fn run_until(&mut self, end_time: Timestamp) {
+        let end_cycle = self.cx4.cycle_for_time(end_time);
+        while self.cx4.cur_cycle < end_cycle {
+            if self.cx4.locked
+                || (self.cx4.suspended && self.cx4.suspend_cycles == 0)
+                || (self.cx4.halted && !self.cx4.cache.load_pending && !self.cx4.dma.pending)
+            {
+                // Nothing but a pending bus access can make progress until the main CPU intervenes
+                if self.cx4.bus_access.cycles_left == 0 {
+                    self.cx4.cur_cycle = end_cycle;
+                    break;
+                }
+                self.add_cycles(1);
+            } else if self.cx4.suspended {
+                self.add_cycles(self.cx4.suspend_cycles as u64);
+                self.cx4.suspended = false;
+                self.cx4.suspend_cycles = 0;
+            } else if self.cx4.cache.load_pending {
+                self.load_cache();
+            } else if self.cx4.dma.pending {
+                self.run_dma();
+            } else {
+                self.run_instr();
+            }
+        }
+    }
+
+    /// Advances the Cx4's clock, completing pending bus accesses if enough time has passed.
+    fn add_cycles(&mut self, cycles: u64) {
+        self.cx4.cur_cycle += cycles;
+        let bus_access = &mut self.cx4.bus_access;
+        if bus_access.cycles_left == 0 {
+            return;
+        }
+        if bus_access.cycles_left as u64 > cycles {
+            bus_access.cycles_left -= cycles as u8;
+        } else {
+            bus_access.cycles_left = 0;
+            let addr = bus_access.addr;
+            if bus_access.is_write {
+                self.write_bus(addr, self.cx4.regs.mdr as u8);
+            } else {
+                self.cx4.regs.mdr = self.read_bus(addr) as u32;
+            }
+        }
+    }
+
+    fn read_bus(&mut self, addr: u32) -> u8 {
+        if Cx4::is_rom_addr(addr) {
+            if self.rom.is_empty() {
+                return 0;
+            }
+            let offset = (addr & 0x3F_0000) >> 1 | (addr & 0x7FFF);
+            self.rom[mirror(offset, self.rom.len() as u32) as usize]
+        } else if Cx4::is_ram_addr(addr) {
+            if self.ram.is_empty() {
+                return 0;
+            }
+            let offset = (addr & 0x07_0000) >> 1 | (addr & 0x7FFF);
+            self.ram[mirror(offset, self.ram.len() as u32) as usize]
+        } else if addr & 0x40_E000 == 0x00_6000 {
+            if addr & 0xC00 == 0xC00 {
+                self.cx4.read_s_cpu_io(addr as u16)
+            } else {
+                self.cx4.data_ram[addr as usize & 0xFFF]
+            }
+        } else {
+            0
+        }
+    }
+
+    fn write_bus(&mut self, addr: u32, value: u8) {
+        if Cx4::is_ram_addr(addr) {
+            if self.ram.is_empty() {
+                return;
+            }
+            let offset = (addr & 0x07_0000) >> 1 | (addr & 0x7FFF);
+            let offset = mirror(offset, self.ram.len() as u32);
+            *self.ram_modified = true;
+            self.ram[offset as usize] = value;
+        } else if addr & 0x40_E000 == 0x00_6000 {
+            if addr & 0xC00 == 0xC00 {
+                self.cx4.write_s_cpu_io(addr as u16, value);
+            } else {
+                self.cx4.data_ram[addr as usize & 0xFFF] = value;
+            }
+        }
+    }
+
+    /// Makes sure the current program page is present in one of the two cache pages, loading it
+    /// from the bus if needed; returns `false` if both pages are locked and can't be replaced.
+    fn load_cache(&mut self) -> bool {
+        let cx4 = &mut *self.cx4;
+        cx4.cache.load_pending = false;
+        let mut addr = cx4.cache.base.wrapping_add((cx4.regs.pb as u32) << 9) & 0xFF_FFFF;
+        if cx4.cache.page_addrs[cx4.cache.page as usize] == addr {
+            return true;
+        }
+        cx4.cache.page ^= 1;
+        if cx4.cache.page_addrs[cx4.cache.page as usize] == addr {
+            return true;
+        }
+        if cx4.cache.locked[cx4.cache.page as usize] {
+            cx4.cache.page ^= 1;
+            if cx4.cache.locked[cx4.cache.page as usize] {
+                return false;
+            }
+        }
+
+        let page = cx4.cache.page as usize;
+        cx4.cache.page_addrs[page] = addr;
+        for i in 0..CACHE_PAGE_WORDS {
+            self.add_cycles(self.cx4.access_cycles(addr));
+            let low = self.read_bus(addr);
+            let high = self.read_bus((addr + 1) & 0xFF_FFFF);
+            self.cx4.program_cache[page][i] = u16::from_le_bytes([low, high]);
+            addr = (addr + 2) & 0xFF_FFFF;
+        }
+        true
+    }
+
+    fn run_dma(&mut self) {
+        self.cx4.dma.pending = false;
+        for i in 0..self.cx4.dma.len as u32 {
+            let src = (self.cx4.dma.src + i) & 0xFF_FFFF;
+            let dst = (self.cx4.dma.dst + i) & 0xFF_FFFF;
+            // Transfers within the same memory lock up the Cx4 until it's manually stopped
+            if (Cx4::is_rom_addr(src) && Cx4::is_rom_addr(dst))
+                || (Cx4::is_ram_addr(src) && Cx4::is_ram_addr(dst))
+            {
+                self.cx4.locked = true;
+                return;
+            }
+            self.add_cycles(self.cx4.access_cycles(src));
+            let value = self.read_bus(src);
+            self.add_cycles(self.cx4.access_cycles(dst));
+            self.write_bus(dst, value);
+        }
+    }
+
+    /// Advances PC to the next instruction, switching to the second cache page (and loading the
+    /// program page selected by P into it) when crossing the end of the first one.
+    fn advance_pc(&mut self) {
+        self.cx4.regs.pc = self.cx4.regs.pc.wrapping_add(1);
+        if self.cx4.regs.pc != 0 {
+            return;
+        }
+        if self.cx4.cache.page == 1 {
+            return self.cx4.halt();
+        }
+        self.cx4.cache.page = 1;
+        if self.cx4.cache.locked[1] {
+            return self.cx4.halt();
+        }
+        self.cx4.regs.pb = self.cx4.regs.p;
+        if !self.load_cache() {
+            self.cx4.halt();
+        }
+    }
+
+    fn run_instr(&mut self) {
+        if !self.load_cache() {
+            return self.cx4.halt();
+        }
+        let opcode =
+            self.cx4.program_cache[self.cx4.cache.page as usize][self.cx4.regs.pc as usize];
+        self.advance_pc();
+        self.add_cycles(1);
+        self.execute(opcode);
+    }
+}
+
+impl Cart {
+    pub(super) fn map_cx4(map: &mut Map) {
+        // The handlers receive the unmodified bus address, to tell data RAM and register accesses
+        // apart
+        for banks in [(0x00, 0x3F), (0x80, 0xBF)] {
+            map.map::<true, true>(
+                Some(Self::handle_cx4_io_read as ReadHandler),
+                Some(Self::handle_cx4_io_write as WriteHandler),
+                banks,
+                (0x6000, 0x7FFF),
+                0,
+                1 << 24,
+                0,
+            );
+        }
+    }
+
+    fn synced_cx4_context(&mut self) -> Context<'_> {
+        let time = self.cur_time;
+        let mut context = self.cx4_context().unwrap();
+        context.run_until(time);
+        context
+    }
+
+    fn handle_cx4_io_read(&mut self, addr: u32) -> u8 {
+        let cx4 = self.synced_cx4_context().cx4;
+        if addr & 0xC00 == 0xC00 {
+            cx4.read_s_cpu_io(addr as u16)
+        } else {
+            cx4.data_ram[addr as usize & 0xFFF]
+        }
+    }
+
+    fn handle_cx4_io_write(&mut self, addr: u32, value: u8) {
+        let cx4 = self.synced_cx4_context().cx4;
+        if addr & 0xC00 == 0xC00 {
+            cx4.write_s_cpu_io(addr as u16, value);
+        } else {
+            cx4.data_ram[addr as usize & 0xFFF] = value;
+        }
+    }
+
+    pub(super) fn handle_cx4_rom_read(&mut self, offset: u32) -> u8 {
+        // While the Cx4 is using the bus, the main CPU sees its vector registers in place of the
+        // ROM's interrupt vectors, which lets it handle interrupts without the ROM
+        let cx4 = self.synced_cx4_context().cx4;
+        if cx4.is_busy() && offset & !0x1F == 0x7FE0 {
+            return cx4.vectors[(offset & 0x1F) as usize];
+        }
+        self.rom[offset as usize]
+    }
+}
@@ -0,0 +1,336 @@
+use super::{Context, Cx4, STACK_LEN};
+
+// The amounts A can be shifted left by before being used as an ALU operand
+const A_SHIFTS: [u8; 4] = [0, 1, 8, 16];
+
+#[inline]
+fn sign_extend_24(value: u32) -> i64 {
+    ((value << 8) as i32 >> 8) as i64
+}
+
+impl Cx4 {
+    #[inline]
+    fn set_nz(&mut self, value: u32) -> u32 {
+        let value = value & 0xFF_FFFF;
+        self.regs.n = value & 0x80_0000 != 0;
+        self.regs.z = value == 0;
+        value
+    }
+
+    fn add(&mut self, a: u32, b: u32) -> u32 {
+        let result = a + b;
+        self.regs.c = result > 0xFF_FFFF;
+        self.regs.v = !(a ^ b) & (a ^ result) & 0x80_0000 != 0;
+        self.set_nz(result)
+    }
+
+    fn sub(&mut self, a: u32, b: u32) -> u32 {
+        let result = a.wrapping_sub(b);
+        self.regs.c = a >= b;
+        self.regs.v = (a ^ b) & (a ^ result) & 0x80_0000 != 0;
+        self.set_nz(result)
+    }
+
+    #[inline]
+    fn shifted_a(&self, opcode: u16) -> u32 {
+        self.regs.a << A_SHIFTS[(opcode >> 8 & 3) as usize] & 0xFF_FFFF
+    }
+
+    fn push_pc(&mut self) {
+        self.stack.copy_within(0..STACK_LEN - 1, 1);
+        self.stack[0] = (self.regs.pb as u32) << 8 | self.regs.pc as u32;
+    }
+
+    fn pop_pc(&mut self) {
+        let value = self.stack[0];
+        self.stack.copy_within(1.., 0);
+        self.stack[STACK_LEN - 1] = 0;
+        self.regs.pb = (value >> 8) as u16 & 0x7FFF;
+        self.regs.pc = value as u8;
+    }
+}
+
+impl<'a> Context<'a> {
+    /// Reads the second ALU operand, which is either a register or an 8-bit immediate depending on
+    /// bit 10 of the opcode.
+    fn alu_operand(&mut self, opcode: u16) -> u32 {
+        if opcode & 0x400 != 0 {
+            opcode as u32 & 0xFF
+        } else {
+            self.cx4.read_reg(opcode as u8)
+        }
+    }
+
+    /// Reads the shift amount for shift and rotate instructions, which is either a register or a
+    /// 5-bit immediate depending on bit 10 of the opcode.
+    fn shift_operand(&mut self, opcode: u16) -> u32 {
+        if opcode & 0x400 != 0 {
+            opcode as u32 & 0x1F
+        } else {
+            self.cx4.read_reg(opcode as u8) & 0x1F
+        }
+    }
+
+    fn jump(&mut self, opcode: u16, condition: bool, call: bool) {
+        if !condition {
+            return;
+        }
+        if call {
+            self.cx4.push_pc();
+        }
+        // Far jumps switch to the program page selected by P
+        if opcode & 0x200 != 0 {
+            self.cx4.regs.pb = self.cx4.regs.p;
+        }
+        self.cx4.regs.pc = opcode as u8;
+        self.add_cycles(2);
+    }
+
+    pub(super) fn execute(&mut self, opcode: u16) {
+        match opcode >> 10 {
+            // NOP
+            0x00 | 0x01 => {}
+
+            // JMP, JMP EQ/GE/MI/VS
+            0x02 => self.jump(opcode, true, false),
+            0x03 => self.jump(opcode, self.cx4.regs.z, false),
+            0x04 => self.jump(opcode, self.cx4.regs.c, false),
+            0x05 => self.jump(opcode, self.cx4.regs.n, false),
+            0x06 => self.jump(opcode, self.cx4.regs.v, false),
+
+            // WAIT
+            0x07 => {
+                let cycles = self.cx4.bus_access.cycles_left as u64;
+                if cycles != 0 {
+                    self.add_cycles(cycles);
+                }
+            }
+
+            0x08 => {}
+
+            // SKIP V/C/Z/N: skips the next instruction if the flag matches bit 0
+            0x09 => {
+                let flag = match opcode >> 8 & 3 {
+                    0 => self.cx4.regs.v,
+                    1 => self.cx4.regs.c,
+                    2 => self.cx4.regs.z,
+                    _ => self.cx4.regs.n,
+                };
+                if flag == (opcode & 1 != 0) {
+                    self.advance_pc();
+                    self.add_cycles(1);
+                }
+            }
+
+            // JSR, JSR EQ/GE/MI/VS
+            0x0A => self.jump(opcode, true, true),
+            0x0B => self.jump(opcode, self.cx4.regs.z, true),
+            0x0C => self.jump(opcode, self.cx4.regs.c, true),
+            0x0D => self.jump(opcode, self.cx4.regs.n, true),
+            0x0E => self.jump(opcode, self.cx4.regs.v, true),
+
+            // RTS
+            0x0F => {
+                self.cx4.pop_pc();
+                self.add_cycles(2);
+            }
+
+            // INC MAR
+            0x10 => self.cx4.regs.mar = (self.cx4.regs.mar + 1) & 0xFF_FFFF,
+
+            0x11 => {}
+
+            // CMPR (operand - A << s)
+            0x12 | 0x13 => {
+                let a = self.cx4.shifted_a(opcode);
+                let operand = self.alu_operand(opcode);
+                self.cx4.sub(operand, a);
+            }
+
+            // CMP (A << s - operand)
+            0x14 | 0x15 => {
+                let a = self.cx4.shifted_a(opcode);
+                let operand = self.alu_operand(opcode);
+                self.cx4.sub(a, operand);
+            }
+
+            // SXT: sign-extends A from 8 or 16 bits
+            0x16 => match opcode >> 8 & 3 {
+                1 => self.cx4.regs.a = sign_extend_24((self.cx4.regs.a as u8 as i8) as u32) as u32,
+                2 => {
+                    self.cx4.regs.a = sign_extend_24((self.cx4.regs.a as u16 as i16) as u32) as u32
+                }
+                _ => {}
+            },
+
+            0x17 => {}
+
+            // LD A/MDR/MAR/P, reg/imm
+            0x18 | 0x19 => {
+                let value = if opcode & 0x400 != 0 {
+                    opcode as u32 & 0xFF
+                } else if opcode >> 8 & 3 == 3 {
+                    self.cx4.regs.gprs[(opcode & 0xF) as usize]
+                } else {
+                    self.cx4.read_reg(opcode as u8)
+                };
+                let regs = &mut self.cx4.regs;
+                match opcode >> 8 & 3 {
+                    0 => regs.a = value & 0xFF_FFFF,
+                    1 => regs.mdr = value & 0xFF_FFFF,
+                    2 => regs.mar = value & 0xFF_FFFF,
+                    _ => regs.p = value as u16 & 0x7FFF,
+                }
+            }
+
+            // RDRAM byte, [A] / [DPR + imm]
+            0x1A | 0x1B => {
+                let byte = opcode >> 8 & 3;
+                if byte != 3 {
+                    let addr = if opcode & 0x400 != 0 {
+                        self.cx4.regs.dpr.wrapping_add(opcode as u32 & 0xFF)
+                    } else {
+                        self.cx4.regs.a
+                    };
+                    let value = self.cx4.data_ram[Cx4::data_ram_offset(addr)] as u32;
+                    let shift = byte << 3;
+                    self.cx4.regs.ram = (self.cx4.regs.ram & !(0xFF << shift)) | value << shift;
+                }
+            }
+
+            // RDROM [A] / [imm]
+            0x1C | 0x1D => {
+                let addr = if opcode & 0x400 != 0 {
+                    opcode as u32
+                } else {
+                    self.cx4.regs.a
+                };
+                self.cx4.regs.rom = self.cx4.data_rom[addr as usize & 0x3FF];
+            }
+
+            // LD PL/PH, imm
+            0x1F => match opcode >> 8 & 3 {
+                0 => self.cx4.regs.p = (self.cx4.regs.p & 0x7F00) | (opcode & 0xFF),
+                1 => self.cx4.regs.p = (opcode & 0x7F) << 8 | (self.cx4.regs.p & 0xFF),
+                _ => {}
+            },
+
+            0x1E => {}
+
+            // ADD (A << s + operand)
+            0x20 | 0x21 => {
+                let a = self.cx4.shifted_a(opcode);
+                let operand = self.alu_operand(opcode);
+                self.cx4.regs.a = self.cx4.add(a, operand);
+            }
+
+            // SUBR (operand - A << s)
+            0x22 | 0x23 => {
+                let a = self.cx4.shifted_a(opcode);
+                let operand = self.alu_operand(opcode);
+                self.cx4.regs.a = self.cx4.sub(operand, a);
+            }
+
+            // SUB (A << s - operand)
+            0x24 | 0x25 => {
+                let a = self.cx4.shifted_a(opcode);
+                let operand = self.alu_operand(opcode);
+                self.cx4.regs.a = self.cx4.sub(a, operand);
+            }
+
+            // MUL (signed 24x24 -> 48 bits)
+            0x26 | 0x27 => {
+                let operand = self.alu_operand(opcode);
+                let regs = &mut self.cx4.regs;
+                regs.mul =
+                    (sign_extend_24(regs.a) * sign_extend_24(operand)) as u64 & 0xFFFF_FFFF_FFFF;
+            }
+
+            // XNOR, XOR, AND, OR
+            0x28..=0x2F => {
+                let a = self.cx4.shifted_a(opcode);
+                let operand = self.alu_operand(opcode);
+                let result = match opcode >> 11 & 3 {
+                    0 => !a ^ operand,
+                    1 => a ^ operand,
+                    2 => a & operand,
+                    _ => a | operand,
+                };
+                self.cx4.regs.a = self.cx4.set_nz(result);
+            }
+
+            // LSR, ASR, ROR, SHL
+            0x30..=0x37 => {
+                let amount = self.shift_operand(opcode);
+                let a = self.cx4.regs.a;
+                let result = match opcode >> 11 & 3 {
+                    0 => a >> amount.min(24),
+                    1 => (sign_extend_24(a) >> amount.min(24)) as u32,
+                    2 => {
+                        let amount = amount % 24;
+                        a >> amount | a << (24 - amount)
+                    }
+                    _ => a << amount.min(24),
+                };
+                self.cx4.regs.a = self.cx4.set_nz(result);
+            }
+
+            // ST reg, A/MDR
+            0x38 => match opcode >> 8 & 3 {
+                0 => {
+                    let value = self.cx4.regs.a;
+                    self.cx4.write_reg(opcode as u8, value);
+                }
+                1 => {
+                    let value = self.cx4.regs.mdr;
+                    self.cx4.write_reg(opcode as u8, value);
+                }
+                _ => {}
+            },
+
+            0x39 => {}
+
+            // WRRAM byte, [A] / [DPR + imm]
+            0x3A | 0x3B => {
+                let byte = opcode >> 8 & 3;
+                if byte != 3 {
+                    let addr = if opcode & 0x400 != 0 {
+                        self.cx4.regs.dpr.wrapping_add(opcode as u32 & 0xFF)
+                    } else {
+                        self.cx4.regs.a
+                    };
+                    self.cx4.data_ram[Cx4::data_ram_offset(addr)] =
+                        (self.cx4.regs.ram >> (byte << 3)) as u8;
+                }
+            }
+
+            // SWAP A, gpr
+            0x3C => {
+                if opcode >> 8 & 3 == 0 {
+                    let regs = &mut self.cx4.regs;
+                    core::mem::swap(&mut regs.a, &mut regs.gprs[(opcode & 0xF) as usize]);
+                }
+            }
+
+            0x3D => {}
+
+            // CLEAR
+            0x3E => {
+                if opcode >> 8 & 3 == 0 {
+                    let regs = &mut self.cx4.regs;
+                    regs.a = 0;
+                    regs.p = 0;
+                    regs.ram = 0;
+                    regs.dpr = 0;
+                }
+            }
+
+            // HALT
+            _ => {
+                if opcode >> 8 & 3 == 0 {
+                    self.cx4.halt();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+/// A date and time of day in the proleptic Gregorian calendar, used to set real-time clocks from
+/// the host's clock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct DateTime {
+    pub year: i64,
+    pub month: u8,
+    pub day: u8,
+    /// The day of the week, starting from Sunday (0).
+    pub weekday: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+pub(crate) fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+pub(crate) fn days_in_month(year: i64, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Returns the number of days between the Unix epoch and the given date.
+fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns the day of the week of the given date, starting from Sunday (0).
+pub(crate) fn weekday(year: i64, month: u8, day: u8) -> u8 {
+    // The Unix epoch was a Thursday
+    (days_from_civil(year, month, day) + 4).rem_euclid(7) as u8
+}
+
+impl DateTime {
+    pub fn from_unix_time(unix_time: u64) -> Self {
+        let days = (unix_time / 86_400) as i64;
+        let secs = (unix_time % 86_400) as u32;
+
+        let shifted_days = days + 719_468;
+        let era = shifted_days.div_euclid(146_097);
+        let day_of_era = shifted_days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        // Months are counted starting from March, so that leap days are at the end of the year
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u8;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        } as u8;
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+        DateTime {
+            year,
+            month,
+            day,
+            weekday: (days + 4).rem_euclid(7) as u8,
+            hour: (secs / 3600) as u8,
+            minute: (secs / 60 % 60) as u8,
+            second: (secs % 60) as u8,
+        }
+    }
+}
@@ -0,0 +1,751 @@
+mod instrs;
+
+use super::{
+    map::{mirror, Map, ReadHandler, WriteHandler},
+    Cart,
+};
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    schedule::Timestamp,
+    utils::BoxedByteSlice,
+};
+
+const CACHE_SIZE: usize = 0x200;
+
+// SFR bits
+const SFR_Z: u16 = 1 << 1;
+const SFR_CY: u16 = 1 << 2;
+const SFR_S: u16 = 1 << 3;
+const SFR_OV: u16 = 1 << 4;
+const SFR_GO: u16 = 1 << 5;
+const SFR_ROM_READ: u16 = 1 << 6;
+const SFR_ALT1: u16 = 1 << 8;
+const SFR_ALT2: u16 = 1 << 9;
+const SFR_B: u16 = 1 << 12;
+const SFR_IRQ: u16 = 1 << 15;
+
+// SCMR bits
+const SCREEN_MODE_RAM_OWNED: u8 = 1 << 3;
+const SCREEN_MODE_ROM_OWNED: u8 = 1 << 4;
+
+// POR bits
+const PLOT_TRANSPARENT: u8 = 1 << 0;
+const PLOT_DITHER: u8 = 1 << 1;
+const PLOT_HIGH_NIBBLE: u8 = 1 << 2;
+const PLOT_FREEZE_HIGH: u8 = 1 << 3;
+const PLOT_OBJ: u8 = 1 << 4;
+
+// CFGR bits
+const CONFIG_FAST_MULT: u8 = 1 << 5;
+const CONFIG_IRQ_MASK: u8 = 1 << 7;
+
+// While the GSU owns the ROM bus, the main CPU reads these values instead of the actual ROM
+// contents, pointing all of its interrupt vectors to small handlers in RAM
+const ROM_BLOCKED_VALUES: [u8; 16] = [
+    0x00, 0x01, 0x00, 0x01, 0x04, 0x01, 0x00, 0x01, 0x00, 0x01, 0x08, 0x01, 0x00, 0x01, 0x0C, 0x01,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Revision {
+    /// The original Mario Chip and GSU-1, only able to run at 10.74 MHz.
+    Gsu1,
+    /// The GSU-2, which can also run at the full 21.47 MHz master clock rate and address up to
+    /// 2 MiB of ROM.
+    Gsu2,
+}
+
+impl Revision {
+    /// Guesses the GSU revision present on a board based on the size of its ROM, as only GSU-2
+    /// boards have more than 1 MiB of it.
+    pub fn for_rom_size(rom_size: usize) -> Self {
+        if rom_size > 0x10_0000 {
+            Revision::Gsu2
+        } else {
+            Revision::Gsu1
+        }
+    }
+
+    #[inline]
+    fn version(self) -> u8 {
+        match self {
+            Revision::Gsu1 => 1,
+            Revision::Gsu2 => 4,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct PixelCache {
+    offset: u16,
+    pending: u8,
+    data: [u8; 8],
+}
+
+impl PixelCache {
+    const fn new() -> Self {
+        PixelCache {
+            offset: 0,
+            pending: 0,
+            data: [0; 8],
+        }
+    }
+}
+
+/// The state of the GSU (also known as Super FX), a 16-bit RISC processor with a 512-byte
+/// instruction cache, a pixel plotting unit rendering to SNES tiles in the cartridge RAM and
+/// buffered accesses to the cartridge ROM and RAM.
+///
+/// The main CPU can only access the ROM and RAM while the GSU doesn't own them (as selected
+/// through SCMR); like the SA-1, the GSU is caught up to the main CPU before every access to its
+/// registers or the shared memories, and periodically through a schedule event.
+#[derive(Clone)]
+pub struct Gsu {
+    revision: Revision,
+    cur_time: Timestamp,
+
+    regs: [u16; 16],
+    sfr: u16,
+    src_reg: u8,
+    dst_reg: u8,
+    r14_modified: bool,
+    r15_modified: bool,
+    pipeline: u8,
+
+    program_bank: u8,
+    rom_bank: u8,
+    ram_bank: u8,
+    cache_base: u16,
+    screen_base: u8,
+    screen_mode: u8,
+    color: u8,
+    plot_options: u8,
+    backup_ram_enabled: bool,
+    config: u8,
+    fast_clock: bool,
+
+    rom_buffer: u8,
+    rom_buffer_ready_time: Timestamp,
+    ram_addr: u16,
+    ram_buffer_ready_time: Timestamp,
+
+    cache: Box<[u8; CACHE_SIZE]>,
+    cache_valid_lines: u32,
+    pixel_caches: [PixelCache; 2],
+}
+
+impl Gsu {
+    pub(super) fn new(revision: Revision) -> Self {
+        Gsu {
+            revision,
+            cur_time: 0,
+
+            regs: [0; 16],
+            sfr: 0,
+            src_reg: 0,
+            dst_reg: 0,
+            r14_modified: false,
+            r15_modified: false,
+            // NOP
+            pipeline: 0x01,
+
+            program_bank: 0,
+            rom_bank: 0,
+            ram_bank: 0,
+            cache_base: 0,
+            screen_base: 0,
+            screen_mode: 0,
+            color: 0,
+            plot_options: 0,
+            backup_ram_enabled: false,
+            config: 0,
+            fast_clock: false,
+
+            rom_buffer: 0,
+            rom_buffer_ready_time: 0,
+            ram_addr: 0,
+            ram_buffer_ready_time: 0,
+
+            cache: Box::new([0; CACHE_SIZE]),
+            cache_valid_lines: 0,
+            pixel_caches: [PixelCache::new(); 2],
+        }
+    }
+
+    pub(super) fn setup(&mut self, time: Timestamp) {
+        self.cur_time = time;
+        self.rom_buffer_ready_time = time;
+        self.ram_buffer_ready_time = time;
+    }
+
+    pub(super) fn soft_reset(&mut self) {
+        *self = Gsu {
+            cur_time: self.cur_time,
+            rom_buffer_ready_time: self.cur_time,
+            ram_buffer_ready_time: self.cur_time,
+            ..Gsu::new(self.revision)
+        };
+    }
+
+    #[inline]
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    #[inline]
+    pub fn cur_time(&self) -> Timestamp {
+        self.cur_time
+    }
+
+    #[inline]
+    pub fn regs(&self) -> &[u16; 16] {
+        &self.regs
+    }
+
+    #[inline]
+    pub fn cache(&self) -> &[u8; CACHE_SIZE] {
+        &self.cache
+    }
+
+    /// Returns whether the GSU is requesting an IRQ from the main CPU.
+    #[inline]
+    pub fn s_cpu_irq_requested(&self) -> bool {
+        self.sfr & SFR_IRQ != 0
+    }
+
+    #[inline]
+    fn is_running(&self) -> bool {
+        self.sfr & SFR_GO != 0
+    }
+
+    #[inline]
+    fn owns_rom(&self) -> bool {
+        self.is_running() && self.screen_mode & SCREEN_MODE_ROM_OWNED != 0
+    }
+
+    #[inline]
+    fn owns_ram(&self) -> bool {
+        self.is_running() && self.screen_mode & SCREEN_MODE_RAM_OWNED != 0
+    }
+
+    #[inline]
+    fn is_fast(&self) -> bool {
+        self.fast_clock && self.revision == Revision::Gsu2
+    }
+
+    /// The amount of master cycles taken by a single GSU cycle.
+    #[inline]
+    fn clock_divider(&self) -> Timestamp {
+        if self.is_fast() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// The amount of master cycles taken by a ROM or RAM access.
+    #[inline]
+    fn mem_access_cycles(&self) -> Timestamp {
+        if self.is_fast() {
+            5
+        } else {
+            6
+        }
+    }
+
+    #[inline]
+    fn flag(&self, flag: u16) -> bool {
+        self.sfr & flag != 0
+    }
+
+    #[inline]
+    fn set_flag(&mut self, flag: u16, value: bool) {
+        if value {
+            self.sfr |= flag;
+        } else {
+            self.sfr &= !flag;
+        }
+    }
+
+    #[inline]
+    fn set_reg(&mut self, i: usize, value: u16) {
+        self.regs[i] = value;
+        match i {
+            14 => self.r14_modified = true,
+            15 => self.r15_modified = true,
+            _ => {}
+        }
+    }
+
+    #[inline]
+    fn src(&self) -> u16 {
+        self.regs[self.src_reg as usize]
+    }
+
+    #[inline]
+    fn set_dst(&mut self, value: u16) {
+        self.set_reg(self.dst_reg as usize, value);
+    }
+
+    /// Resets the effects of the ALT1/2/3, TO, FROM and WITH prefixes, done after every
+    /// non-prefix instruction.
+    #[inline]
+    fn reset_prefixes(&mut self) {
+        self.sfr &= !(SFR_B | SFR_ALT1 | SFR_ALT2);
+        self.src_reg = 0;
+        self.dst_reg = 0;
+    }
+
+    #[inline]
+    fn flush_cache(&mut self) {
+        self.cache_valid_lines = 0;
+    }
+
+    fn apply_color_options(&self, value: u8) -> u8 {
+        if self.plot_options & PLOT_HIGH_NIBBLE != 0 {
+            (self.color & 0xF0) | value >> 4
+        } else if self.plot_options & PLOT_FREEZE_HIGH != 0 {
+            (self.color & 0xF0) | (value & 0xF)
+        } else {
+            value
+        }
+    }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.cur_time);
+
+        writer.write(&self.regs);
+        writer.write(&self.sfr);
+        writer.write(&self.src_reg);
+        writer.write(&self.dst_reg);
+        writer.write(&self.r14_modified);
+        writer.write(&self.r15_modified);
+        writer.write(&self.pipeline);
+
+        writer.write(&self.program_bank);
+        writer.write(&self.rom_bank);
+        writer.write(&self.ram_bank);
+        writer.write(&self.cache_base);
+        writer.write(&self.screen_base);
+        writer.write(&self.screen_mode);
+        writer.write(&self.color);
+        writer.write(&self.plot_options);
+        writer.write(&self.backup_ram_enabled);
+        writer.write(&self.config);
+        writer.write(&self.fast_clock);
+
+        writer.write(&self.rom_buffer);
+        writer.write(&self.rom_buffer_ready_time);
+        writer.write(&self.ram_addr);
+        writer.write(&self.ram_buffer_ready_time);
+
+        writer.write_bytes(&self.cache[..]);
+        writer.write(&self.cache_valid_lines);
+        for pixel_cache in &self.pixel_caches {
+            writer.write(&pixel_cache.offset);
+            writer.write(&pixel_cache.pending);
+            writer.write(&pixel_cache.data);
+        }
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.cur_time = reader.read()?;
+
+        self.regs = reader.read()?;
+        self.sfr = reader.read()?;
+        self.src_reg = reader.read()?;
+        self.dst_reg = reader.read()?;
+        self.r14_modified = reader.read()?;
+        self.r15_modified = reader.read()?;
+        self.pipeline = reader.read()?;
+
+        self.program_bank = reader.read()?;
+        self.rom_bank = reader.read()?;
+        self.ram_bank = reader.read()?;
+        self.cache_base = reader.read()?;
+        self.screen_base = reader.read()?;
+        self.screen_mode = reader.read()?;
+        self.color = reader.read()?;
+        self.plot_options = reader.read()?;
+        self.backup_ram_enabled = reader.read()?;
+        self.config = reader.read()?;
+        self.fast_clock = reader.read()?;
+
+        self.rom_buffer = reader.read()?;
+        self.rom_buffer_ready_time = reader.read()?;
+        self.ram_addr = reader.read()?;
+        self.ram_buffer_ready_time = reader.read()?;
+
+        reader.read_bytes(&mut self.cache[..])?;
+        self.cache_valid_lines = reader.read()?;
+        for pixel_cache in &mut self.pixel_caches {
+            pixel_cache.offset = reader.read()?;
+            pixel_cache.pending = reader.read()?;
+            pixel_cache.data = reader.read()?;
+        }
+
+        if self.src_reg > 15 || self.dst_reg > 15 {
+            return Err(LoadError::InvalidData);
+        }
+        Ok(())
+    }
+}
+
+/// The GSU together with the cartridge memories it has access to.
+pub(super) struct Context<'a> {
+    pub gsu: &'a mut Gsu,
+    pub rom: &'a BoxedByteSlice,
+    pub ram: &'a mut BoxedByteSlice,
+    pub ram_modified: &'a mut bool,
+}
+
+impl<'a> Context<'a> {
+    pub(super) fn run_until(&mut self, end_time: Timestamp) {
+        while self.gsu.cur_time < end_time {
+            if !self.gsu.is_running() {
+                self.gsu.cur_time = end_time;
+                break;
+            }
+            // The GSU always fetches the next opcode while executing the current one, so the
+            // instruction following a jump or branch is always executed
+            let opcode = self.gsu.pipeline;
+            self.gsu.pipeline = self.read_opcode(self.gsu.regs[15]);
+            self.gsu.r15_modified = false;
+            self.execute(opcode);
+            if self.gsu.r14_modified {
+                self.gsu.r14_modified = false;
+                self.update_rom_buffer();
+            }
+            if self.gsu.r15_modified {
+                self.gsu.r15_modified = false;
+            } else {
+                self.gsu.regs[15] = self.gsu.regs[15].wrapping_add(1);
+            }
+        }
+    }
+
+    #[inline]
+    fn add_cycles(&mut self, cycles: Timestamp) {
+        self.gsu.cur_time += cycles;
+    }
+
+    fn read_rom(&self, offset: u32) -> u8 {
+        if self.rom.is_empty() {
+            return 0;
+        }
+        self.rom[mirror(offset, self.rom.len() as u32) as usize]
+    }
+
+    fn read_bus(&self, addr: u32) -> u8 {
+        match (addr >> 16) as u8 {
+            0x00..=0x3F => self.read_rom((addr & 0x3F_0000) >> 1 | (addr & 0x7FFF)),
+            0x40..=0x5F => self.read_rom(addr & 0x1F_FFFF),
+            0x60..=0x7F if !self.ram.is_empty() => {
+                self.ram[mirror(addr & 0x1F_FFFF, self.ram.len() as u32) as usize]
+            }
+            _ => 0,
+        }
+    }
+
+    fn write_bus(&mut self, addr: u32, value: u8) {
+        if matches!((addr >> 16) as u8, 0x60..=0x7F) && !self.ram.is_empty() {
+            let offset = mirror(addr & 0x1F_FFFF, self.ram.len() as u32);
+            *self.ram_modified = true;
+            self.ram[offset as usize] = value;
+        }
+    }
+
+    fn read_opcode(&mut self, addr: u16) -> u8 {
+        let cache_offset = addr.wrapping_sub(self.gsu.cache_base);
+        if cache_offset < CACHE_SIZE as u16 {
+            let line = cache_offset >> 4;
+            if self.gsu.cache_valid_lines & 1 << line == 0 {
+                let line_base = cache_offset & !0xF;
+                let bank_base = (self.gsu.program_bank as u32) << 16;
+                for i in line_base..line_base + 0x10 {
+                    self.add_cycles(self.gsu.mem_access_cycles());
+                    self.gsu.cache[i as usize] =
+                        self.read_bus(bank_base | self.gsu.cache_base.wrapping_add(i) as u32);
+                }
+                self.gsu.cache_valid_lines |= 1 << line;
+            } else {
+                self.add_cycles(self.gsu.clock_divider());
+            }
+            return self.gsu.cache[cache_offset as usize];
+        }
+        if self.gsu.program_bank <= 0x5F {
+            self.sync_rom_buffer();
+        } else {
+            self.sync_ram_buffer();
+        }
+        self.add_cycles(self.gsu.mem_access_cycles());
+        self.read_bus((self.gsu.program_bank as u32) << 16 | addr as u32)
+    }
+
+    fn update_rom_buffer(&mut self) {
+        // The ROM contents can't change while the read is in progress, and ROMB waits for it to
+        // finish before switching banks, so the read can be done immediately
+        self.gsu.rom_buffer_ready_time = self.gsu.cur_time + self.gsu.mem_access_cycles();
+        self.gsu.rom_buffer =
+            self.read_bus((self.gsu.rom_bank as u32) << 16 | self.gsu.regs[14] as u32);
+    }
+
+    fn sync_rom_buffer(&mut self) {
+        self.gsu.cur_time = self.gsu.cur_time.max(self.gsu.rom_buffer_ready_time);
+    }
+
+    fn read_rom_buffer(&mut self) -> u8 {
+        self.sync_rom_buffer();
+        self.gsu.rom_buffer
+    }
+
+    fn sync_ram_buffer(&mut self) {
+        self.gsu.cur_time = self.gsu.cur_time.max(self.gsu.ram_buffer_ready_time);
+    }
+
+    fn ram_buffer_addr(&self, addr: u16) -> u32 {
+        0x70_0000 | (self.gsu.ram_bank as u32) << 16 | addr as u32
+    }
+
+    fn read_ram_buffer(&mut self, addr: u16) -> u8 {
+        self.sync_ram_buffer();
+        self.read_bus(self.ram_buffer_addr(addr))
+    }
+
+    fn write_ram_buffer(&mut self, addr: u16, value: u8) {
+        self.sync_ram_buffer();
+        self.gsu.ram_buffer_ready_time = self.gsu.cur_time + self.gsu.mem_access_cycles();
+        self.write_bus(self.ram_buffer_addr(addr), value);
+    }
+
+    fn char_addr(&self, x: u8, y: u8) -> (u32, u8) {
+        let mode = self.gsu.screen_mode & 3;
+        let bpp = 2 << (mode - (mode >> 1));
+        let height = if self.gsu.plot_options & PLOT_OBJ != 0 {
+            3
+        } else {
+            (self.gsu.screen_mode >> 2 & 1) | (self.gsu.screen_mode >> 4 & 2)
+        };
+        let (x, y) = (x as u32, y as u32);
+        let char_index = match height {
+            0 => ((x & 0xF8) << 1) + ((y & 0xF8) >> 3),
+            1 => ((x & 0xF8) << 1) + ((x & 0xF8) >> 1) + ((y & 0xF8) >> 3),
+            2 => ((x & 0xF8) << 1) + (x & 0xF8) + ((y & 0xF8) >> 3),
+            _ => ((y & 0x80) << 2) + ((x & 0x80) << 1) + ((y & 0x78) << 1) + ((x & 0x78) >> 3),
+        };
+        (
+            0x70_0000
+                + char_index * ((bpp as u32) << 3)
+                + ((self.gsu.screen_base as u32) << 10)
+                + ((y & 7) << 1),
+            bpp,
+        )
+    }
+
+    fn flush_pixel_cache(&mut self, i: usize) {
+        let cache = self.gsu.pixel_caches[i];
+        if cache.pending == 0 {
+            return;
+        }
+        let x = (cache.offset << 3) as u8;
+        let y = (cache.offset >> 5) as u8;
+        let (addr, bpp) = self.char_addr(x, y);
+        for plane in 0..bpp {
+            let byte_addr = addr + ((plane as u32 >> 1) << 4) + (plane as u32 & 1);
+            let mut value = 0;
+            for (bit, pixel) in cache.data.iter().enumerate() {
+                value |= (pixel >> plane & 1) << bit;
+            }
+            if cache.pending != 0xFF {
+                self.add_cycles(self.gsu.mem_access_cycles());
+                value = (value & cache.pending) | (self.read_bus(byte_addr) & !cache.pending);
+            }
+            self.add_cycles(self.gsu.mem_access_cycles());
+            self.write_bus(byte_addr, value);
+        }
+        self.gsu.pixel_caches[i].pending = 0;
+    }
+
+    fn plot(&mut self, x: u8, y: u8) {
+        let mut color = self.gsu.color;
+        let mode = self.gsu.screen_mode & 3;
+        if self.gsu.plot_options & PLOT_TRANSPARENT == 0 {
+            let transparent_mask = if mode != 3 || self.gsu.plot_options & PLOT_FREEZE_HIGH != 0 {
+                0xF
+            } else {
+                0xFF
+            };
+            if color & transparent_mask == 0 {
+                return;
+            }
+        }
+        if self.gsu.plot_options & PLOT_DITHER != 0 && mode != 3 {
+            if (x ^ y) & 1 != 0 {
+                color >>= 4;
+            }
+            color &= 0xF;
+        }
+
+        let offset = (y as u16) << 5 | (x >> 3) as u16;
+        if offset != self.gsu.pixel_caches[0].offset {
+            self.flush_pixel_cache(1);
+            self.gsu.pixel_caches[1] = self.gsu.pixel_caches[0];
+            self.gsu.pixel_caches[0].pending = 0;
+            self.gsu.pixel_caches[0].offset = offset;
+        }
+        let bit = (x & 7) ^ 7;
+        self.gsu.pixel_caches[0].data[bit as usize] = color;
+        self.gsu.pixel_caches[0].pending |= 1 << bit;
+        if self.gsu.pixel_caches[0].pending == 0xFF {
+            self.flush_pixel_cache(1);
+            self.gsu.pixel_caches[1] = self.gsu.pixel_caches[0];
+            self.gsu.pixel_caches[0].pending = 0;
+        }
+    }
+
+    fn read_pixel(&mut self, x: u8, y: u8) -> u8 {
+        self.flush_pixel_cache(1);
+        self.flush_pixel_cache(0);
+        let (addr, bpp) = self.char_addr(x, y);
+        let bit = (x & 7) ^ 7;
+        let mut result = 0;
+        for plane in 0..bpp {
+            let byte_addr = addr + ((plane as u32 >> 1) << 4) + (plane as u32 & 1);
+            self.add_cycles(self.gsu.mem_access_cycles());
+            result |= (self.read_bus(byte_addr) >> bit & 1) << plane;
+        }
+        result
+    }
+
+    pub(super) fn read_s_cpu_io(&mut self, addr: u16) -> u8 {
+        let gsu = &mut *self.gsu;
+        match addr {
+            0x3000..=0x301F => (gsu.regs[(addr >> 1 & 0xF) as usize] >> ((addr & 1) << 3)) as u8,
+            0x3030 => {
+                let rom_read_pending = gsu.cur_time < gsu.rom_buffer_ready_time;
+                (gsu.sfr & !SFR_ROM_READ) as u8
+                    | if rom_read_pending {
+                        SFR_ROM_READ as u8
+                    } else {
+                        0
+                    }
+            }
+            0x3031 => {
+                // Reading the high byte acknowledges the IRQ
+                let result = (gsu.sfr >> 8) as u8;
+                gsu.sfr &= !SFR_IRQ;
+                result
+            }
+            0x3034 => gsu.program_bank,
+            0x3036 => gsu.rom_bank,
+            0x303B => gsu.revision.version(),
+            0x303C => gsu.ram_bank,
+            0x303E => gsu.cache_base as u8,
+            0x303F => (gsu.cache_base >> 8) as u8,
+            0x3100..=0x32FF => {
+                gsu.cache[((addr - 0x3100).wrapping_add(gsu.cache_base) & 0x1FF) as usize]
+            }
+            _ => 0,
+        }
+    }
+
+    pub(super) fn write_s_cpu_io(&mut self, addr: u16, value: u8) {
+        let gsu = &mut *self.gsu;
+        match addr {
+            0x3000..=0x301F => {
+                let i = (addr >> 1 & 0xF) as usize;
+                gsu.regs[i] = if addr & 1 == 0 {
+                    (gsu.regs[i] & 0xFF00) | value as u16
+                } else {
+                    (value as u16) << 8 | (gsu.regs[i] & 0xFF)
+                };
+                if i == 14 {
+                    self.update_rom_buffer();
+                }
+                // Writing to the high byte of R15 starts the GSU
+                if addr == 0x301F {
+                    self.gsu.sfr |= SFR_GO;
+                }
+            }
+            0x3030 => {
+                let was_running = gsu.is_running();
+                gsu.sfr = (gsu.sfr & 0xFF00) | value as u16;
+                if was_running && !gsu.is_running() {
+                    gsu.cache_base = 0;
+                    gsu.flush_cache();
+                }
+            }
+            0x3031 => gsu.sfr = (value as u16) << 8 | (gsu.sfr & 0xFF),
+            0x3033 => gsu.backup_ram_enabled = value & 1 != 0,
+            0x3034 => {
+                gsu.program_bank = value & 0x7F;
+                gsu.flush_cache();
+            }
+            0x3037 => gsu.config = value,
+            0x3038 => gsu.screen_base = value,
+            0x3039 => gsu.fast_clock = value & 1 != 0,
+            0x303A => gsu.screen_mode = value,
+            0x3100..=0x32FF => {
+                let offset = (addr - 0x3100).wrapping_add(gsu.cache_base) & 0x1FF;
+                gsu.cache[offset as usize] = value;
+                if offset & 0xF == 0xF {
+                    gsu.cache_valid_lines |= 1 << (offset >> 4);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Cart {
+    pub(super) fn map_gsu(map: &mut Map) {
+        // The I/O handlers receive the unmodified bus address, like the SA-1's
+        for banks in [(0x00, 0x3F), (0x80, 0xBF)] {
+            map.map::<true, true>(
+                Some(Self::handle_gsu_io_read as ReadHandler),
+                Some(Self::handle_gsu_io_write as WriteHandler),
+                banks,
+                (0x3000, 0x33FF),
+                0,
+                1 << 24,
+                0,
+            );
+        }
+    }
+
+    fn synced_gsu_context(&mut self) -> Context<'_> {
+        let time = self.cur_time;
+        let mut context = self.gsu_context().unwrap();
+        context.run_until(time);
+        context
+    }
+
+    fn handle_gsu_io_read(&mut self, addr: u32) -> u8 {
+        self.synced_gsu_context().read_s_cpu_io(addr as u16)
+    }
+
+    fn handle_gsu_io_write(&mut self, addr: u32, value: u8) {
+        self.synced_gsu_context().write_s_cpu_io(addr as u16, value);
+    }
+
+    pub(super) fn handle_gsu_rom_read(&mut self, offset: u32) -> u8 {
+        if self.synced_gsu_context().gsu.owns_rom() {
+            return ROM_BLOCKED_VALUES[offset as usize & 0xF];
+        }
+        self.rom[offset as usize]
+    }
+
+    pub(super) fn handle_gsu_ram_read(&mut self, offset: u32) -> u8 {
+        if self.synced_gsu_context().gsu.owns_ram() {
+            return 0;
+        }
+        self.ram[offset as usize]
+    }
+
+    pub(super) fn handle_gsu_ram_write(&mut self, offset: u32, value: u8) {
+        if self.synced_gsu_context().gsu.owns_ram() {
+            return;
+        }
+        self.ram_modified = true;
+        self.ram[offset as usize] = value;
+    }
+}
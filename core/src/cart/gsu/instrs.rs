@@ -0,0 +1,458 @@
+use super::{
+    Context, CONFIG_FAST_MULT, CONFIG_IRQ_MASK, SFR_ALT1, SFR_ALT2, SFR_B, SFR_CY, SFR_GO, SFR_IRQ,
+    SFR_OV, SFR_S, SFR_Z,
+};
+
+impl<'a> Context<'a> {
+    #[inline]
+    fn set_sz(&mut self, value: u16) {
+        self.gsu.set_flag(SFR_S, value & 0x8000 != 0);
+        self.gsu.set_flag(SFR_Z, value == 0);
+    }
+
+    /// Writes `value` to the destination register and updates the sign and zero flags based on
+    /// it.
+    #[inline]
+    fn set_dst_sz(&mut self, value: u16) {
+        self.gsu.set_dst(value);
+        self.set_sz(value);
+    }
+
+    /// Reads an immediate byte from the instruction stream, advancing the pipeline.
+    fn fetch_imm(&mut self) -> u8 {
+        let result = self.gsu.pipeline;
+        self.gsu.regs[15] = self.gsu.regs[15].wrapping_add(1);
+        self.gsu.pipeline = self.read_opcode(self.gsu.regs[15]);
+        self.gsu.r15_modified = false;
+        result
+    }
+
+    fn fetch_imm_16(&mut self) -> u16 {
+        let low = self.fetch_imm();
+        (self.fetch_imm() as u16) << 8 | low as u16
+    }
+
+    fn branch(&mut self, taken: bool) {
+        let offset = self.fetch_imm() as i8;
+        if taken {
+            let target = self.gsu.regs[15].wrapping_add(offset as u16);
+            self.gsu.set_reg(15, target);
+        }
+    }
+
+    fn read_ram_word(&mut self, addr: u16) -> u16 {
+        self.gsu.ram_addr = addr;
+        let low = self.read_ram_buffer(addr);
+        (self.read_ram_buffer(addr ^ 1) as u16) << 8 | low as u16
+    }
+
+    fn write_ram_word(&mut self, addr: u16, value: u16) {
+        self.gsu.ram_addr = addr;
+        self.write_ram_buffer(addr, value as u8);
+        self.write_ram_buffer(addr ^ 1, (value >> 8) as u8);
+    }
+
+    pub(super) fn execute(&mut self, opcode: u8) {
+        let alt1 = self.gsu.flag(SFR_ALT1);
+        let alt2 = self.gsu.flag(SFR_ALT2);
+        let reg = (opcode & 0xF) as usize;
+        // The ALT2 variants of arithmetic and logic instructions use the register number as an
+        // immediate operand
+        let operand = if alt2 { reg as u16 } else { self.gsu.regs[reg] };
+
+        match opcode {
+            // STOP
+            0x00 => {
+                if self.gsu.config & CONFIG_IRQ_MASK == 0 {
+                    self.gsu.sfr |= SFR_IRQ;
+                }
+                self.gsu.sfr &= !SFR_GO;
+                self.gsu.pipeline = 0x01;
+            }
+
+            // NOP
+            0x01 => {}
+
+            // CACHE
+            0x02 => {
+                let cache_base = self.gsu.regs[15] & 0xFFF0;
+                if self.gsu.cache_base != cache_base {
+                    self.gsu.cache_base = cache_base;
+                    self.gsu.flush_cache();
+                }
+            }
+
+            // LSR
+            0x03 => {
+                let src = self.gsu.src();
+                self.gsu.set_flag(SFR_CY, src & 1 != 0);
+                self.set_dst_sz(src >> 1);
+            }
+
+            // ROL
+            0x04 => {
+                let src = self.gsu.src();
+                let result = src << 1 | self.gsu.flag(SFR_CY) as u16;
+                self.gsu.set_flag(SFR_CY, src & 0x8000 != 0);
+                self.set_dst_sz(result);
+            }
+
+            // Branches, which don't reset prefixes
+            0x05..=0x0F => {
+                let sfr = self.gsu.sfr;
+                let flag = |mask| sfr & mask != 0;
+                let taken = match opcode {
+                    0x05 => true,
+                    0x06 => flag(SFR_S) == flag(SFR_OV),
+                    0x07 => flag(SFR_S) != flag(SFR_OV),
+                    0x08 => !flag(SFR_Z),
+                    0x09 => flag(SFR_Z),
+                    0x0A => !flag(SFR_S),
+                    0x0B => flag(SFR_S),
+                    0x0C => !flag(SFR_CY),
+                    0x0D => flag(SFR_CY),
+                    0x0E => !flag(SFR_OV),
+                    _ => flag(SFR_OV),
+                };
+                self.branch(taken);
+                return;
+            }
+
+            // TO/MOVE
+            0x10..=0x1F => {
+                if !self.gsu.flag(SFR_B) {
+                    self.gsu.dst_reg = reg as u8;
+                    return;
+                }
+                let src = self.gsu.src();
+                self.gsu.set_reg(reg, src);
+            }
+
+            // WITH
+            0x20..=0x2F => {
+                self.gsu.src_reg = reg as u8;
+                self.gsu.dst_reg = reg as u8;
+                self.gsu.sfr |= SFR_B;
+                return;
+            }
+
+            // STW/STB
+            0x30..=0x3B => {
+                let addr = self.gsu.regs[reg];
+                let src = self.gsu.src();
+                self.gsu.ram_addr = addr;
+                self.write_ram_buffer(addr, src as u8);
+                if !alt1 {
+                    self.write_ram_buffer(addr ^ 1, (src >> 8) as u8);
+                }
+            }
+
+            // LOOP
+            0x3C => {
+                let counter = self.gsu.regs[12].wrapping_sub(1);
+                self.gsu.set_reg(12, counter);
+                self.set_sz(counter);
+                if counter != 0 {
+                    let target = self.gsu.regs[13];
+                    self.gsu.set_reg(15, target);
+                }
+            }
+
+            // ALT1/ALT2/ALT3
+            0x3D..=0x3F => {
+                self.gsu.sfr &= !(SFR_B | SFR_ALT1 | SFR_ALT2);
+                self.gsu.sfr |= ((opcode - 0x3C) as u16) << 8;
+                return;
+            }
+
+            // LDW/LDB
+            0x40..=0x4B => {
+                let addr = self.gsu.regs[reg];
+                let result = if alt1 {
+                    self.gsu.ram_addr = addr;
+                    self.read_ram_buffer(addr) as u16
+                } else {
+                    self.read_ram_word(addr)
+                };
+                self.gsu.set_dst(result);
+            }
+
+            // PLOT/RPIX
+            0x4C => {
+                let (x, y) = (self.gsu.regs[1] as u8, self.gsu.regs[2] as u8);
+                if alt1 {
+                    let result = self.read_pixel(x, y) as u16;
+                    self.set_dst_sz(result);
+                } else {
+                    self.plot(x, y);
+                    let x = self.gsu.regs[1].wrapping_add(1);
+                    self.gsu.set_reg(1, x);
+                }
+            }
+
+            // SWAP
+            0x4D => {
+                let result = self.gsu.src().swap_bytes();
+                self.set_dst_sz(result);
+            }
+
+            // COLOR/CMODE
+            0x4E => {
+                let src = self.gsu.src() as u8;
+                if alt1 {
+                    self.gsu.plot_options = src & 0x1F;
+                } else {
+                    self.gsu.color = self.gsu.apply_color_options(src);
+                }
+            }
+
+            // NOT
+            0x4F => {
+                let result = !self.gsu.src();
+                self.set_dst_sz(result);
+            }
+
+            // ADD/ADC
+            0x50..=0x5F => {
+                let src = self.gsu.src();
+                let carry = alt1 && self.gsu.flag(SFR_CY);
+                let result = src as u32 + operand as u32 + carry as u32;
+                self.gsu.set_flag(
+                    SFR_OV,
+                    !(src ^ operand) & (operand ^ result as u16) & 0x8000 != 0,
+                );
+                self.gsu.set_flag(SFR_CY, result > 0xFFFF);
+                self.set_dst_sz(result as u16);
+            }
+
+            // SUB/SBC/CMP
+            0x60..=0x6F => {
+                // SBC and CMP only have register forms, as ALT3 selects CMP
+                let operand = if alt1 { self.gsu.regs[reg] } else { operand };
+                let src = self.gsu.src();
+                let borrow = alt1 && !alt2 && !self.gsu.flag(SFR_CY);
+                let result = src as i32 - operand as i32 - borrow as i32;
+                self.gsu.set_flag(
+                    SFR_OV,
+                    (src ^ operand) & (src ^ result as u16) & 0x8000 != 0,
+                );
+                self.gsu.set_flag(SFR_CY, result >= 0);
+                self.set_sz(result as u16);
+                if !(alt1 && alt2) {
+                    self.gsu.set_dst(result as u16);
+                }
+            }
+
+            // MERGE
+            0x70 => {
+                let result = (self.gsu.regs[7] & 0xFF00) | self.gsu.regs[8] >> 8;
+                self.gsu.set_dst(result);
+                self.gsu.set_flag(SFR_OV, result & 0xC0C0 != 0);
+                self.gsu.set_flag(SFR_S, result & 0x8080 != 0);
+                self.gsu.set_flag(SFR_CY, result & 0xE0E0 != 0);
+                self.gsu.set_flag(SFR_Z, result & 0xF0F0 != 0);
+            }
+
+            // AND/BIC
+            0x71..=0x7F => {
+                let operand = if alt1 { !operand } else { operand };
+                let result = self.gsu.src() & operand;
+                self.set_dst_sz(result);
+            }
+
+            // MULT/UMULT
+            0x80..=0x8F => {
+                let src = self.gsu.src();
+                let result = if alt1 {
+                    (src as u8 as u16).wrapping_mul(operand as u8 as u16)
+                } else {
+                    (src as i8 as i16).wrapping_mul(operand as i8 as i16) as u16
+                };
+                self.set_dst_sz(result);
+                if self.gsu.config & CONFIG_FAST_MULT == 0 {
+                    self.add_cycles(self.gsu.clock_divider());
+                }
+            }
+
+            // SBK
+            0x90 => {
+                let (addr, src) = (self.gsu.ram_addr, self.gsu.src());
+                self.write_ram_word(addr, src);
+            }
+
+            // LINK
+            0x91..=0x94 => {
+                let result = self.gsu.regs[15].wrapping_add(reg as u16);
+                self.gsu.set_reg(11, result);
+            }
+
+            // SEX
+            0x95 => {
+                let result = self.gsu.src() as i8 as u16;
+                self.set_dst_sz(result);
+            }
+
+            // ASR/DIV2
+            0x96 => {
+                let src = self.gsu.src();
+                self.gsu.set_flag(SFR_CY, src & 1 != 0);
+                // DIV2 rounds -1 to 0 instead of keeping it at -1
+                let result = if alt1 && src == 0xFFFF {
+                    0
+                } else {
+                    (src as i16 >> 1) as u16
+                };
+                self.set_dst_sz(result);
+            }
+
+            // ROR
+            0x97 => {
+                let src = self.gsu.src();
+                let result = (self.gsu.flag(SFR_CY) as u16) << 15 | src >> 1;
+                self.gsu.set_flag(SFR_CY, src & 1 != 0);
+                self.set_dst_sz(result);
+            }
+
+            // JMP/LJMP
+            0x98..=0x9D => {
+                if alt1 {
+                    self.gsu.program_bank = self.gsu.regs[reg] as u8 & 0x7F;
+                    let target = self.gsu.src();
+                    self.gsu.set_reg(15, target);
+                    self.gsu.cache_base = target & 0xFFF0;
+                    self.gsu.flush_cache();
+                } else {
+                    let target = self.gsu.regs[reg];
+                    self.gsu.set_reg(15, target);
+                }
+            }
+
+            // LOB
+            0x9E => {
+                let result = self.gsu.src() & 0xFF;
+                self.gsu.set_dst(result);
+                self.gsu.set_flag(SFR_S, result & 0x80 != 0);
+                self.gsu.set_flag(SFR_Z, result == 0);
+            }
+
+            // FMULT/LMULT
+            0x9F => {
+                let result = (self.gsu.src() as i16 as i32 * self.gsu.regs[6] as i16 as i32) as u32;
+                if alt1 {
+                    self.gsu.set_reg(4, result as u16);
+                }
+                self.gsu.set_dst((result >> 16) as u16);
+                self.gsu.set_flag(SFR_S, result & 0x8000_0000 != 0);
+                self.gsu.set_flag(SFR_CY, result & 0x8000 != 0);
+                self.gsu.set_flag(SFR_Z, result >> 16 == 0);
+                let cycles = if self.gsu.config & CONFIG_FAST_MULT != 0 {
+                    3
+                } else {
+                    7
+                };
+                self.add_cycles(cycles * self.gsu.clock_divider());
+            }
+
+            // IBT/LMS/SMS
+            0xA0..=0xAF => {
+                if alt1 {
+                    let addr = (self.fetch_imm() as u16) << 1;
+                    let result = self.read_ram_word(addr);
+                    self.gsu.set_reg(reg, result);
+                } else if alt2 {
+                    let addr = (self.fetch_imm() as u16) << 1;
+                    let value = self.gsu.regs[reg];
+                    self.write_ram_word(addr, value);
+                } else {
+                    let result = self.fetch_imm() as i8 as u16;
+                    self.gsu.set_reg(reg, result);
+                }
+            }
+
+            // FROM/MOVES
+            0xB0..=0xBF => {
+                if !self.gsu.flag(SFR_B) {
+                    self.gsu.src_reg = reg as u8;
+                    return;
+                }
+                let result = self.gsu.regs[reg];
+                self.gsu.set_flag(SFR_OV, result & 0x80 != 0);
+                self.set_dst_sz(result);
+            }
+
+            // HIB
+            0xC0 => {
+                let result = self.gsu.src() >> 8;
+                self.gsu.set_dst(result);
+                self.gsu.set_flag(SFR_S, result & 0x80 != 0);
+                self.gsu.set_flag(SFR_Z, result == 0);
+            }
+
+            // OR/XOR
+            0xC1..=0xCF => {
+                let src = self.gsu.src();
+                let result = if alt1 { src ^ operand } else { src | operand };
+                self.set_dst_sz(result);
+            }
+
+            // INC
+            0xD0..=0xDE => {
+                let result = self.gsu.regs[reg].wrapping_add(1);
+                self.gsu.set_reg(reg, result);
+                self.set_sz(result);
+            }
+
+            // GETC/RAMB/ROMB
+            0xDF => {
+                if !alt2 {
+                    let value = self.read_rom_buffer();
+                    self.gsu.color = self.gsu.apply_color_options(value);
+                } else if !alt1 {
+                    self.sync_ram_buffer();
+                    self.gsu.ram_bank = self.gsu.src() as u8 & 1;
+                } else {
+                    self.sync_rom_buffer();
+                    self.gsu.rom_bank = self.gsu.src() as u8 & 0x7F;
+                }
+            }
+
+            // DEC
+            0xE0..=0xEE => {
+                let result = self.gsu.regs[reg].wrapping_sub(1);
+                self.gsu.set_reg(reg, result);
+                self.set_sz(result);
+            }
+
+            // GETB/GETBH/GETBL/GETBS
+            0xEF => {
+                let value = self.read_rom_buffer();
+                let src = self.gsu.src();
+                let result = match (alt1, alt2) {
+                    (false, false) => value as u16,
+                    (true, false) => (value as u16) << 8 | (src & 0xFF),
+                    (false, true) => (src & 0xFF00) | value as u16,
+                    (true, true) => value as i8 as u16,
+                };
+                self.gsu.set_dst(result);
+            }
+
+            // IWT/LM/SM
+            0xF0..=0xFF => {
+                if alt1 {
+                    let addr = self.fetch_imm_16();
+                    let result = self.read_ram_word(addr);
+                    self.gsu.set_reg(reg, result);
+                } else if alt2 {
+                    let addr = self.fetch_imm_16();
+                    let value = self.gsu.regs[reg];
+                    self.write_ram_word(addr, value);
+                } else {
+                    let result = self.fetch_imm_16();
+                    self.gsu.set_reg(reg, result);
+                }
+            }
+        }
+
+        self.gsu.reset_prefixes();
+    }
+}
@@ -4,7 +4,11 @@ pub mod header;
 
 use super::map;
 use crate::utils::ByteSlice;
+use core::fmt::{self, Display};
+pub use db::{RamContent, RomContent, SlotType};
 use header::Header;
+use serde::{Deserialize, Serialize};
+use std::error::Error as StdError;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct MapAddrRange {
@@ -22,6 +26,170 @@ pub struct MapRegion {
 
 pub type Map = Vec<MapRegion>;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Coprocessor {
+    Sa1,
+    Gsu,
+    Upd7725,
+    Upd96050,
+    Cx4,
+    Sdd1,
+    Spc7110,
+    SRtc,
+    Obc1,
+    St018,
+    Mcc,
+}
+
+/// The address ranges save RAM is mapped to in boards without coprocessors, which can't be
+/// detected from the ROM header and so are picked from its map mode when guessing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SaveRamLayout {
+    /// $70-7D,F0-FF:0000-7FFF
+    LoRom,
+    /// $20-3F,A0-BF:6000-7FFF
+    HiRom,
+    /// $80-BF:6000-7FFF
+    ExHiRom,
+}
+
+impl SaveRamLayout {
+    pub fn map(self) -> Map {
+        match self {
+            SaveRamLayout::LoRom => vec![MapRegion {
+                address_ranges: vec![
+                    MapAddrRange {
+                        banks: (0x70, 0x7D),
+                        addrs: (0x0000, 0x7FFF),
+                    },
+                    MapAddrRange {
+                        banks: (0xF0, 0xFF),
+                        addrs: (0x0000, 0x7FFF),
+                    },
+                ],
+                offset: 0,
+                size: None,
+                mask: 0x8000,
+            }],
+            SaveRamLayout::HiRom => vec![MapRegion {
+                address_ranges: vec![
+                    MapAddrRange {
+                        banks: (0x20, 0x3F),
+                        addrs: (0x6000, 0x7FFF),
+                    },
+                    MapAddrRange {
+                        banks: (0xA0, 0xBF),
+                        addrs: (0x6000, 0x7FFF),
+                    },
+                ],
+                offset: 0,
+                size: None,
+                mask: 0xE000,
+            }],
+            SaveRamLayout::ExHiRom => vec![MapRegion {
+                address_ranges: vec![MapAddrRange {
+                    banks: (0x80, 0xBF),
+                    addrs: (0x6000, 0x7FFF),
+                }],
+                offset: 0,
+                size: None,
+                mask: 0xE000,
+            }],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Memory {
+    Rom {
+        content: RomContent,
+        architecture: Option<String>,
+        map: Map,
+    },
+    Ram {
+        content: RamContent,
+        architecture: Option<String>,
+        map: Map,
+    },
+    RtcTime {
+        manufacturer: Option<String>,
+    },
+}
+
+/// An external cartridge slot on the board.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Slot {
+    pub ty: SlotType,
+    pub map: Map,
+    /// For slots whose cartridges contain both ROM and RAM, the regions each one is mapped to.
+    pub rom_map: Map,
+    pub ram_map: Map,
+}
+
+/// A processor's memory controller, which maps its memories and slots into the S-CPU's address
+/// space.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mcu {
+    pub map: Map,
+    pub memories: Vec<Memory>,
+    pub slots: Vec<Slot>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Processor {
+    pub manufacturer: Option<String>,
+    /// The processor's architecture, for programmable ones (i.e. `W65C816S` or `uPD7725`).
+    pub architecture: Option<String>,
+    /// The processor's chip name (i.e. `SDD1`), or the name of the program running on it.
+    pub identifier: Option<String>,
+    pub revision: Option<String>,
+    /// The regions the processor's registers are mapped to.
+    pub map: Map,
+    pub memories: Vec<Memory>,
+    pub slots: Vec<Slot>,
+    pub mcu: Option<Mcu>,
+    pub has_oscillator: bool,
+    pub has_dip_switches: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rtc {
+    pub manufacturer: Option<String>,
+    pub map: Map,
+    pub memories: Vec<Memory>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    UnsupportedCoprocessor {
+        architecture: Option<String>,
+        identifier: Option<String>,
+    },
+}
+
+impl StdError for Error {}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedCoprocessor {
+                architecture,
+                identifier,
+            } => {
+                f.write_str("Unsupported coprocessor")?;
+                match (architecture, identifier) {
+                    (Some(architecture), Some(identifier)) => {
+                        write!(f, ": {} ({})", architecture, identifier)
+                    }
+                    (Some(name), None) | (None, Some(name)) => write!(f, ": {}", name),
+                    (None, None) => Ok(()),
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Info {
     pub title: Option<String>,
@@ -29,6 +197,24 @@ pub struct Info {
     pub has_battery: bool,
     pub rom_map: Map,
     pub ram_map: Map,
+    pub coprocessor: Option<Coprocessor>,
+    pub has_rtc: bool,
+    /// The regions the coprocessor's own registers are mapped to, for coprocessors that don't have
+    /// a fixed memory map.
+    pub coprocessor_map: Map,
+    /// The regions the coprocessor's data RAM is directly mapped to, for coprocessors that expose
+    /// it to the main CPU (i.e. the uPD96050).
+    pub coprocessor_data_ram_map: Map,
+    /// The frequency of the coprocessor's oscillator in Hz, if the board specifies one.
+    pub coprocessor_frequency: Option<u32>,
+    /// The name of the coprocessor firmware needed by the cartridge (i.e. `dsp1b`), if its program
+    /// isn't included in the ROM.
+    pub firmware_name: Option<String>,
+    /// The board's processors, external slots and RTC as described by the board database; these
+    /// are left empty if the cartridge's info was guessed.
+    pub processors: Vec<Processor>,
+    pub slots: Vec<Slot>,
+    pub rtc: Option<Rtc>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -39,39 +225,64 @@ pub enum Source {
 }
 
 impl Info {
+    /// Looks up the cartridge's info in the database if provided, falling back to guessing it
+    /// from the ROM header; fails if the database describes hardware that can't be emulated.
     pub fn new(
         rom: ByteSlice,
         db_data: Option<(&db::Db, [u8; 32])>,
-    ) -> (Self, Option<Header>, Source) {
-        db_data
-            .and_then(|(db, rom_hash)| {
-                Self::from_db(db, &rom_hash).and_then(|info| {
-                    for region in &info.rom_map {
-                        for addr_range in &region.address_ranges {
-                            if addr_range.banks.0 == 0
-                                && addr_range.addrs.0 <= 0xFFB0
-                                && addr_range.addrs.1 >= 0xFFDF
-                            {
-                                let size = region.size.unwrap_or(rom.len() as u32);
-                                let base_offset = map::mirror(region.offset, size);
-                                let offset = (base_offset
-                                    + map::mirror(
-                                        map::reduce(0xFFB0, region.mask),
-                                        size - base_offset,
-                                    )) as usize;
-                                return Header::new(
-                                    ByteSlice::new(&rom[offset..offset + 0x30]),
-                                    None,
-                                )
-                                .map(|header| (info, Some(header), Source::Db));
-                            }
-                        }
-                    }
-                    None
-                })
-            })
-            .or_else(|| Self::guess(rom).map(|(info, header)| (info, Some(header), Source::Guess)))
-            .unwrap_or_else(|| (Default::default(), None, Source::Default))
+    ) -> Result<(Self, Option<Header>, Source), Error> {
+        if let Some((db, rom_hash)) = db_data {
+            if let Some(info) = Self::from_db(db, &rom_hash) {
+                let info = info?;
+                if let Some(header) = info.find_header(rom) {
+                    return Ok((info, Some(header), Source::Db));
+                }
+            }
+        }
+        Ok(Self::guess(rom)
+            .map(|(info, header)| (info, Some(header), Source::Guess))
+            .unwrap_or_else(|| (Default::default(), None, Source::Default)))
+    }
+
+    /// Replaces the save RAM mapping with the given layout, i.e. to override a guessed one; save
+    /// RAM is left unmapped if the cartridge doesn't have any.
+    pub fn set_save_ram_layout(&mut self, layout: SaveRamLayout) {
+        self.ram_map = if self.ram_size != 0 {
+            layout.map()
+        } else {
+            vec![]
+        };
+    }
+
+    fn find_header(&self, rom: ByteSlice) -> Option<Header> {
+        // SA-1, S-DD1, SPC7110 and MCC boards map the ROM through the coprocessor, which always
+        // places the header at the end of the first LoROM or HiROM bank
+        let fixed_header_offset = match self.coprocessor {
+            Some(Coprocessor::Sa1 | Coprocessor::Sdd1 | Coprocessor::Mcc) => Some(0x7FB0),
+            Some(Coprocessor::Spc7110) => Some(0xFFB0),
+            _ => None,
+        };
+        if let Some(offset) = fixed_header_offset {
+            return rom[..]
+                .get(offset..offset + 0x50)
+                .and_then(|header_bytes| Header::new(ByteSlice::new(header_bytes), None));
+        }
+        for region in &self.rom_map {
+            for addr_range in &region.address_ranges {
+                if addr_range.banks.0 == 0
+                    && addr_range.addrs.0 <= 0xFFB0
+                    && addr_range.addrs.1 >= 0xFFDF
+                {
+                    let size = region.size.unwrap_or(rom.len() as u32);
+                    let base_offset = map::mirror(region.offset, size);
+                    let offset = (base_offset
+                        + map::mirror(map::reduce(0xFFB0, region.mask), size - base_offset))
+                        as usize;
+                    return Header::new(ByteSlice::new(&rom[offset..offset + 0x30]), None);
+                }
+            }
+        }
+        None
     }
 }
 
@@ -105,6 +316,15 @@ impl Default for Info {
                 mask: 0x8000,
             }],
             ram_map: vec![],
+            coprocessor: None,
+            has_rtc: false,
+            coprocessor_map: vec![],
+            coprocessor_data_ram_map: vec![],
+            coprocessor_frequency: None,
+            firmware_name: None,
+            processors: vec![],
+            slots: vec![],
+            rtc: None,
         }
     }
 }
@@ -22,11 +22,14 @@ macro_rules! parse_hex {
 
 pub mod bml;
 mod boards;
-pub use boards::LoadError as BoardsLoadError;
+pub use boards::{LoadError as BoardsLoadError, RamContent, RomContent, SlotType};
 mod carts;
 pub use carts::LoadError as CartsLoadError;
 
-use super::{Info, MapAddrRange, MapRegion};
+use super::{
+    Coprocessor, Error as InfoError, Info, MapAddrRange, MapRegion, Mcu, Memory, Processor, Rtc,
+    Slot,
+};
 use core::fmt::{self, Display};
 use std::error::Error;
 
@@ -62,55 +65,130 @@ impl Db {
 }
 
 impl Info {
-    pub(super) fn from_db(db: &Db, hash: &[u8; 32]) -> Option<Info> {
+    pub(super) fn from_db(db: &Db, hash: &[u8; 32]) -> Option<Result<Info, InfoError>> {
         let cart = db.carts.get(hash)?;
         let board = db.boards.get(&cart.board)?;
 
         let mut rom_map = vec![];
         let mut ram_map = vec![];
+        let mut coprocessor = None;
+        let mut coprocessor_map = vec![];
+        let mut coprocessor_data_ram_map = vec![];
+        let mut has_rtc = false;
+        let mut processors = vec![];
+        let mut slots = vec![];
+        let mut rtc = None;
         for hardware in board {
             match hardware {
-                boards::Hardware::Rom {
-                    content: boards::RomContent::Program,
+                boards::Hardware::Processor {
+                    manufacturer,
+                    architecture,
+                    identifier,
+                    revision,
                     map: db_map,
+                    memories,
+                    mcu,
+                    has_oscillator,
+                    has_dip_switches,
                 } => {
-                    rom_map.extend(db_map.iter().map(|db_map_region| {
-                        MapRegion {
-                            address_ranges: db_map_region
-                                .address_ranges
-                                .iter()
-                                .map(|db_addr_range| MapAddrRange {
-                                    addrs: db_addr_range.addrs,
-                                    banks: db_addr_range.banks,
-                                })
-                                .collect(),
-                            offset: db_map_region.offset,
-                            size: db_map_region.size,
-                            mask: db_map_region.mask,
+                    // Processors are identified by their architecture if they're programmable, and
+                    // by their chip name otherwise
+                    coprocessor = Some(match (architecture.as_deref(), identifier.as_deref()) {
+                        (Some("W65C816S"), _) => Coprocessor::Sa1,
+                        (Some("GSU"), _) => Coprocessor::Gsu,
+                        (Some("uPD7725"), _) => {
+                            coprocessor_map.extend(convert_map(db_map));
+                            Coprocessor::Upd7725
                         }
-                    }));
+                        (Some("uPD96050"), _) => {
+                            coprocessor_map.extend(convert_map(db_map));
+                            for memory in memories {
+                                if let boards::Hardware::Ram {
+                                    content: boards::RamContent::Data,
+                                    map: db_map,
+                                    ..
+                                } = memory
+                                {
+                                    coprocessor_data_ram_map.extend(convert_map(db_map));
+                                }
+                            }
+                            Coprocessor::Upd96050
+                        }
+                        (Some("HG51BS169"), _) => Coprocessor::Cx4,
+                        (Some("ARM6"), _) => {
+                            coprocessor_map.extend(convert_map(db_map));
+                            Coprocessor::St018
+                        }
+                        (None, Some("SDD1")) => Coprocessor::Sdd1,
+                        (None, Some("SPC7110")) => Coprocessor::Spc7110,
+                        (None, Some("OBC1")) => {
+                            coprocessor_map.extend(convert_map(db_map));
+                            Coprocessor::Obc1
+                        }
+                        // The BS-X cartridge's MCC maps the BIOS ROM, PSRAM and BS Memory slot
+                        // through its own registers
+                        (None, Some("MCC")) => {
+                            coprocessor_map.extend(convert_map(db_map));
+                            if let Some(mcu) = mcu {
+                                slots.extend(convert_memories(&mcu.memories).1);
+                            }
+                            Coprocessor::Mcc
+                        }
+                        _ => {
+                            return Some(Err(InfoError::UnsupportedCoprocessor {
+                                architecture: architecture.clone(),
+                                identifier: identifier.clone(),
+                            }))
+                        }
+                    });
+                    // The processor's program ROM and save RAM are visible to the S-CPU through
+                    // the coprocessor's bus
+                    for memory in memories {
+                        add_memory_map(memory, &mut rom_map, &mut ram_map);
+                    }
+
+                    let (memories, processor_slots) = convert_memories(memories);
+                    processors.push(Processor {
+                        manufacturer: manufacturer.clone(),
+                        architecture: architecture.clone(),
+                        identifier: identifier.clone(),
+                        revision: revision.clone(),
+                        map: convert_map(db_map).collect(),
+                        memories,
+                        slots: processor_slots,
+                        mcu: mcu.as_ref().map(|mcu| {
+                            let (memories, slots) = convert_memories(&mcu.memories);
+                            Mcu {
+                                map: convert_map(&mcu.map).collect(),
+                                memories,
+                                slots,
+                            }
+                        }),
+                        has_oscillator: *has_oscillator,
+                        has_dip_switches: *has_dip_switches,
+                    });
                 }
-                boards::Hardware::Ram {
-                    content: boards::RamContent::Save,
+                // The S-RTC is a standalone chip, while Epson's RTC-4513 is accessed through the
+                // SPC7110
+                boards::Hardware::Rtc {
+                    manufacturer,
                     map: db_map,
+                    memories,
                 } => {
-                    ram_map.extend(db_map.iter().map(|db_map_region| {
-                        MapRegion {
-                            address_ranges: db_map_region
-                                .address_ranges
-                                .iter()
-                                .map(|db_addr_range| MapAddrRange {
-                                    addrs: db_addr_range.addrs,
-                                    banks: db_addr_range.banks,
-                                })
-                                .collect(),
-                            offset: db_map_region.offset,
-                            size: db_map_region.size,
-                            mask: db_map_region.mask,
-                        }
-                    }));
+                    if manufacturer.as_deref() == Some("Sharp") {
+                        coprocessor_map.extend(convert_map(db_map));
+                        coprocessor = Some(Coprocessor::SRtc);
+                    } else {
+                        has_rtc = true;
+                    }
+                    rtc = Some(Rtc {
+                        manufacturer: manufacturer.clone(),
+                        map: convert_map(db_map).collect(),
+                        memories: convert_memories(memories).0,
+                    });
                 }
-                _ => {}
+                boards::Hardware::Slot { .. } => slots.extend(convert_slot(hardware)),
+                _ => add_memory_map(hardware, &mut rom_map, &mut ram_map),
             }
         }
 
@@ -127,12 +205,122 @@ impl Info {
             })
             .unwrap_or(0);
 
-        Some(Info {
+        // Coprocessor firmware is identified by the chip it was originally stored in (i.e. DSP1B)
+        let firmware_name = cart.hardware.iter().find_map(|hardware| match hardware {
+            carts::Hardware::Rom(carts::Rom {
+                architecture: Some(_),
+                identifier: Some(identifier),
+                ..
+            }) => Some(identifier.to_ascii_lowercase()),
+            _ => None,
+        });
+
+        let coprocessor_frequency = cart.hardware.iter().find_map(|hardware| match hardware {
+            carts::Hardware::Oscillator(carts::Oscillator { frequency }) => Some(*frequency as u32),
+            _ => None,
+        });
+
+        Some(Ok(Info {
             title: Some(cart.name.clone()),
             ram_size: save_ram_size,
             has_battery: save_ram_size != 0,
             rom_map,
             ram_map,
-        })
+            coprocessor,
+            has_rtc,
+            coprocessor_map,
+            coprocessor_data_ram_map,
+            coprocessor_frequency,
+            firmware_name,
+            processors,
+            slots,
+            rtc,
+        }))
+    }
+}
+
+fn convert_map(db_map: &[boards::MapRegion]) -> impl Iterator<Item = MapRegion> + '_ {
+    db_map.iter().map(|db_map_region| MapRegion {
+        address_ranges: db_map_region
+            .address_ranges
+            .iter()
+            .map(|db_addr_range| MapAddrRange {
+                addrs: db_addr_range.addrs,
+                banks: db_addr_range.banks,
+            })
+            .collect(),
+        offset: db_map_region.offset,
+        size: db_map_region.size,
+        mask: db_map_region.mask,
+    })
+}
+
+fn convert_slot(hardware: &boards::Hardware) -> Option<Slot> {
+    match hardware {
+        boards::Hardware::Slot {
+            ty,
+            map,
+            rom_map,
+            ram_map,
+        } => Some(Slot {
+            ty: *ty,
+            map: convert_map(map).collect(),
+            rom_map: convert_map(rom_map).collect(),
+            ram_map: convert_map(ram_map).collect(),
+        }),
+        _ => None,
+    }
+}
+
+/// Splits the hardware attached to a processor, MCU or RTC into its memories and slots.
+fn convert_memories(hardware: &[boards::Hardware]) -> (Vec<Memory>, Vec<Slot>) {
+    let mut memories = vec![];
+    let mut slots = vec![];
+    for hardware in hardware {
+        match hardware {
+            boards::Hardware::Rom {
+                content,
+                architecture,
+                map,
+            } => memories.push(Memory::Rom {
+                content: *content,
+                architecture: architecture.clone(),
+                map: convert_map(map).collect(),
+            }),
+            boards::Hardware::Ram {
+                content,
+                architecture,
+                map,
+            } => memories.push(Memory::Ram {
+                content: *content,
+                architecture: architecture.clone(),
+                map: convert_map(map).collect(),
+            }),
+            boards::Hardware::RtcTime { manufacturer } => memories.push(Memory::RtcTime {
+                manufacturer: manufacturer.clone(),
+            }),
+            _ => slots.extend(convert_slot(hardware)),
+        }
+    }
+    (memories, slots)
+}
+
+fn add_memory_map(
+    hardware: &boards::Hardware,
+    rom_map: &mut Vec<MapRegion>,
+    ram_map: &mut Vec<MapRegion>,
+) {
+    match hardware {
+        boards::Hardware::Rom {
+            content: boards::RomContent::Program,
+            architecture: None,
+            map: db_map,
+        } => rom_map.extend(convert_map(db_map)),
+        boards::Hardware::Ram {
+            content: boards::RamContent::Save,
+            architecture: None,
+            map: db_map,
+        } => ram_map.extend(convert_map(db_map)),
+        _ => {}
     }
 }
@@ -35,6 +35,7 @@ pub enum LoadError<'a> {
         memory_ty: &'static str,
         content: Cow<'a, str>,
     },
+    UnknownSlotType(Cow<'a, str>),
     MissingMapAttr {
         name: &'static str,
     },
@@ -75,6 +76,7 @@ impl<'a> Display for LoadError<'a> {
                 Self::UnexpectedHardwareAttrs { .. } => "Unexpected board hardware attribute",
                 Self::UnknownMemoryType(_) => "Unknown board memory type",
                 Self::UnknownMemoryContent { .. } => "Unknown board memory content",
+                Self::UnknownSlotType(_) => "Unknown board slot type",
                 Self::MissingMapAttr { .. } => "Missing board memory map required attribute",
                 Self::MissingMapAttrValue { .. } => {
                     "Missing board memory map required attribute value"
@@ -97,6 +99,8 @@ pub enum RomContent {
     Boot,
     Data,
     Expansion,
+    /// One of the numbered data ROMs used by competition carts.
+    Level(u8),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -107,6 +111,13 @@ pub enum RamContent {
     Download,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlotType {
+    SufamiTurbo,
+    BsMemory,
+    GameBoy,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct MapAddrRange {
     pub banks: (u8, u8),
@@ -125,16 +136,48 @@ pub struct MapRegion {
 pub enum Hardware {
     Rom {
         content: RomContent,
+        architecture: Option<String>,
         map: Vec<MapRegion>,
     },
     Ram {
         content: RamContent,
+        architecture: Option<String>,
+        map: Vec<MapRegion>,
+    },
+    RtcTime {
+        manufacturer: Option<String>,
+    },
+    Slot {
+        ty: SlotType,
         map: Vec<MapRegion>,
+        rom_map: Vec<MapRegion>,
+        ram_map: Vec<MapRegion>,
     },
-    // TODO: External slots and processors
-    Slot,
-    Processor,
-    Rtc,
+    Processor {
+        manufacturer: Option<String>,
+        architecture: Option<String>,
+        identifier: Option<String>,
+        revision: Option<String>,
+        map: Vec<MapRegion>,
+        /// The memories and slots directly attached to the processor.
+        memories: Vec<Hardware>,
+        mcu: Option<Mcu>,
+        has_oscillator: bool,
+        has_dip_switches: bool,
+    },
+    Rtc {
+        manufacturer: Option<String>,
+        map: Vec<MapRegion>,
+        memories: Vec<Hardware>,
+    },
+}
+
+/// The memory controller of a processor, which maps memories and slots into the S-CPU's address
+/// space on its behalf.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mcu {
+    pub map: Vec<MapRegion>,
+    pub memories: Vec<Hardware>,
 }
 
 pub type Entry = Vec<Hardware>;
@@ -171,224 +214,360 @@ pub fn load(input: &str) -> Result<Db, LoadError> {
         };
 
         let mut result_hardware = Vec::new();
-        for mut hardware in node.attrs {
-            macro_rules! remove_value_attr {
-                (opt $ty: expr, $name: expr) => {
-                    match hardware.remove_value_attr($name) {
-                        Ok(value) => Some(value),
-                        Err(bml::ValueAttrError::Missing) => None,
-                        Err(bml::ValueAttrError::MissingValue) => {
-                            return Err(Error::MissingHardwareAttrValue {
-                                ty: $ty,
-                                name: $name,
-                            })
-                        }
-                        Err(bml::ValueAttrError::UnexpectedAttrs(attrs)) => {
-                            return Err(Error::UnexpectedHardwareAttrAttrs {
-                                ty: $ty,
-                                name: $name,
-                                attrs,
-                            })
-                        }
-                    }
-                };
-                ($ty: expr, $name: expr) => {
-                    hardware.remove_value_attr($name).map_err(|err| match err {
-                        bml::ValueAttrError::Missing => LoadError::MissingHardwareAttr {
-                            ty: $ty,
-                            name: $name,
-                        },
-                        bml::ValueAttrError::MissingValue => LoadError::MissingHardwareAttrValue {
-                            ty: $ty,
-                            name: $name,
-                        },
-                        bml::ValueAttrError::UnexpectedAttrs(attrs) => {
-                            LoadError::UnexpectedHardwareAttrAttrs {
-                                ty: $ty,
-                                name: $name,
-                                attrs,
-                            }
-                        }
-                    })?
-                };
-            }
+        for hardware in node.attrs {
+            result_hardware.push(parse_hardware(hardware)?);
+        }
 
-            if hardware.value.is_some() {
-                return Err(LoadError::UnexpectedHardware(hardware));
-            }
-            match hardware.name {
-                "memory" => {
-                    let ty = remove_value_attr!("memory", "type");
-                    let content = remove_value_attr!("memory", "content");
-
-                    let mut result_map = vec![];
-                    for mut map in hardware.attrs.drain_filter(|attr| attr.name == "map") {
-                        macro_rules! remove_value_attr {
-                            (opt $name: expr) => {
-                                match map.remove_value_attr($name) {
-                                    Ok(value) => Some(value),
-                                    Err(bml::ValueAttrError::Missing) => None,
-                                    Err(bml::ValueAttrError::MissingValue) => {
-                                        return Err(LoadError::MissingMapAttrValue { name: $name })
-                                    }
-                                    Err(bml::ValueAttrError::UnexpectedAttrs(attrs)) => {
-                                        return Err(LoadError::UnexpectedMapAttrAttrs {
-                                            name: $name,
-                                            attrs,
-                                        })
-                                    }
-                                }
-                            };
-                            ($name: expr) => {
-                                map.remove_value_attr($name).map_err(|err| match err {
-                                    bml::ValueAttrError::Missing => {
-                                        LoadError::MissingMapAttr { name: $name }
-                                    }
-                                    bml::ValueAttrError::MissingValue => {
-                                        LoadError::MissingMapAttrValue { name: $name }
-                                    }
-                                    bml::ValueAttrError::UnexpectedAttrs(attrs) => {
-                                        LoadError::UnexpectedMapAttrAttrs { name: $name, attrs }
-                                    }
-                                })?
-                            };
-                        }
-
-                        let addr_ranges = remove_value_attr!("address");
-                        let mask = remove_value_attr!(opt "mask").unwrap_or(Cow::Borrowed("0"));
-                        let offset = remove_value_attr!(opt "base").unwrap_or(Cow::Borrowed("0"));
-                        let size = remove_value_attr!(opt "size");
-
-                        result_map.push(MapRegion {
-                            address_ranges: {
-                                let (bank_ranges, address_range) = unwrap_or_err!(
-                                    addr_ranges.split_once(':'),
-                                    LoadError::InvalidAddress(addr_ranges)
-                                );
-
-                                let mut result_addr_ranges = vec![];
-
-                                let addrs = {
-                                    let (start_address, end_address) = unwrap_or_err!(
-                                        address_range.split_once('-'),
-                                        LoadError::InvalidAddress(addr_ranges)
-                                    );
-                                    (
-                                        parse_hex!(
-                                            u16,
-                                            start_address,
-                                            LoadError::InvalidAddress(addr_ranges)
-                                        ),
-                                        parse_hex!(
-                                            u16,
-                                            end_address,
-                                            LoadError::InvalidAddress(addr_ranges)
-                                        ),
-                                    )
-                                };
-
-                                for bank_range in bank_ranges.split(',') {
-                                    let banks = {
-                                        let (start_bank, end_bank) = unwrap_or_err!(
-                                            bank_range.split_once('-'),
-                                            LoadError::InvalidAddress(addr_ranges)
-                                        );
-                                        (
-                                            parse_hex!(
-                                                u8,
-                                                start_bank,
-                                                LoadError::InvalidAddress(addr_ranges)
-                                            ),
-                                            parse_hex!(
-                                                u8,
-                                                end_bank,
-                                                LoadError::InvalidAddress(addr_ranges)
-                                            ),
-                                        )
-                                    };
-                                    result_addr_ranges.push(MapAddrRange { banks, addrs })
-                                }
-
-                                result_addr_ranges
-                            },
-                            offset: parse_hex!(u32, offset, LoadError::InvalidMapOffset(offset)),
-                            size: match size {
-                                Some(size) => {
-                                    Some(parse_hex!(u32, size, LoadError::InvalidMapSize(size)))
-                                }
-                                None => None,
-                            },
-                            mask: parse_hex!(u32, mask, LoadError::InvalidMapMask(mask)),
-                        });
-                    }
+        for name in names.drain(..names.len().saturating_sub(1)) {
+            result.insert(name, result_hardware.clone());
+        }
+        if let Some(name) = names.pop() {
+            result.insert(name, result_hardware);
+        }
+    }
+    Ok(result)
+}
 
-                    result_hardware.push(match ty.as_ref() {
-                        "ROM" => Hardware::Rom {
-                            content: match content.as_ref() {
-                                "Program" => RomContent::Program,
-                                "Boot" => RomContent::Boot,
-                                "Data" => RomContent::Data,
-                                "Expansion" => RomContent::Expansion,
-                                _ => {
-                                    return Err(LoadError::UnknownMemoryContent {
-                                        memory_ty: "ROM",
-                                        content,
-                                    })
-                                }
-                            },
-                            map: result_map,
-                        },
-
-                        "RAM" => Hardware::Ram {
-                            content: match content.as_ref() {
-                                "Save" => RamContent::Save,
-                                "Internal" => RamContent::Internal,
-                                "Data" => RamContent::Data,
-                                "Download" => RamContent::Download,
-                                _ => {
-                                    return Err(LoadError::UnknownMemoryContent {
-                                        memory_ty: "RAM",
-                                        content,
-                                    })
-                                }
-                            },
-                            map: result_map,
-                        },
-
-                        _ => return Err(LoadError::UnknownMemoryType(ty)),
-                    });
-
-                    if !hardware.attrs.is_empty() {
-                        return Err(LoadError::UnexpectedHardwareAttrs {
-                            ty: "memory",
-                            attrs: hardware.attrs,
-                        });
+macro_rules! remove_hardware_value_attr {
+    ($hardware: expr, opt $ty: expr, $name: expr) => {
+        match $hardware.remove_value_attr($name) {
+            Ok(value) => Some(value),
+            Err(bml::ValueAttrError::Missing) => None,
+            Err(bml::ValueAttrError::MissingValue) => {
+                return Err(LoadError::MissingHardwareAttrValue {
+                    ty: $ty,
+                    name: $name,
+                })
+            }
+            Err(bml::ValueAttrError::UnexpectedAttrs(attrs)) => {
+                return Err(LoadError::UnexpectedHardwareAttrAttrs {
+                    ty: $ty,
+                    name: $name,
+                    attrs,
+                })
+            }
+        }
+    };
+    ($hardware: expr, $ty: expr, $name: expr) => {
+        $hardware
+            .remove_value_attr($name)
+            .map_err(|err| match err {
+                bml::ValueAttrError::Missing => LoadError::MissingHardwareAttr {
+                    ty: $ty,
+                    name: $name,
+                },
+                bml::ValueAttrError::MissingValue => LoadError::MissingHardwareAttrValue {
+                    ty: $ty,
+                    name: $name,
+                },
+                bml::ValueAttrError::UnexpectedAttrs(attrs) => {
+                    LoadError::UnexpectedHardwareAttrAttrs {
+                        ty: $ty,
+                        name: $name,
+                        attrs,
                     }
                 }
+            })?
+    };
+}
 
-                // TODO: External slots and processors
-                "slot" => {
-                    result_hardware.push(Hardware::Slot);
+macro_rules! remove_hardware_marker {
+    ($hardware: expr, $ty: expr, $name: expr) => {
+        $hardware.remove_marker($name).map_err(|err| match err {
+            bml::MarkerAttrError::UnexpectedValue(value) => {
+                LoadError::UnexpectedHardwareAttrValue {
+                    ty: $ty,
+                    name: $name,
+                    value,
                 }
-
-                "processor" => {
-                    result_hardware.push(Hardware::Processor);
+            }
+            bml::MarkerAttrError::UnexpectedAttrs(attrs) => {
+                LoadError::UnexpectedHardwareAttrAttrs {
+                    ty: $ty,
+                    name: $name,
+                    attrs,
                 }
+            }
+        })?
+    };
+}
+
+fn check_no_attrs_left<'a>(node: bml::Node<'a>, ty: &'a str) -> Result<(), LoadError<'a>> {
+    if node.attrs.is_empty() {
+        Ok(())
+    } else {
+        Err(LoadError::UnexpectedHardwareAttrs {
+            ty,
+            attrs: node.attrs,
+        })
+    }
+}
 
-                "rtc" => {
-                    result_hardware.push(Hardware::Rtc);
+fn parse_hardware(mut hardware: bml::Node) -> Result<Hardware, LoadError> {
+    if hardware.value.is_some() {
+        return Err(LoadError::UnexpectedHardware(hardware));
+    }
+    Ok(match hardware.name {
+        "memory" => parse_memory(hardware)?,
+
+        "slot" => parse_slot(hardware)?,
+
+        "processor" => {
+            let manufacturer =
+                remove_hardware_value_attr!(hardware, opt "processor", "manufacturer");
+            let architecture =
+                remove_hardware_value_attr!(hardware, opt "processor", "architecture");
+            let identifier = remove_hardware_value_attr!(hardware, opt "processor", "identifier");
+            let revision = remove_hardware_value_attr!(hardware, opt "processor", "revision");
+            let has_oscillator = remove_hardware_marker!(hardware, "processor", "oscillator");
+            let has_dip_switches = remove_hardware_marker!(hardware, "processor", "dip");
+            let map = parse_map(&mut hardware)?;
+            let memories = parse_attached_hardware(&mut hardware)?;
+            let mcu = match hardware.remove_attr("mcu") {
+                Some(mut mcu) => {
+                    if mcu.value.is_some() {
+                        return Err(LoadError::UnexpectedHardware(mcu));
+                    }
+                    let map = parse_map(&mut mcu)?;
+                    let memories = parse_attached_hardware(&mut mcu)?;
+                    check_no_attrs_left(mcu, "mcu")?;
+                    Some(Mcu { map, memories })
                 }
+                None => None,
+            };
+            check_no_attrs_left(hardware, "processor")?;
+            Hardware::Processor {
+                manufacturer: manufacturer.map(Cow::into_owned),
+                architecture: architecture.map(Cow::into_owned),
+                identifier: identifier.map(Cow::into_owned),
+                revision: revision.map(Cow::into_owned),
+                map,
+                memories,
+                mcu,
+                has_oscillator,
+                has_dip_switches,
+            }
+        }
 
-                _ => return Err(LoadError::UnexpectedHardware(hardware)),
+        "rtc" => {
+            let manufacturer = remove_hardware_value_attr!(hardware, opt "rtc", "manufacturer");
+            let map = parse_map(&mut hardware)?;
+            let memories = parse_attached_hardware(&mut hardware)?;
+            check_no_attrs_left(hardware, "rtc")?;
+            Hardware::Rtc {
+                manufacturer: manufacturer.map(Cow::into_owned),
+                map,
+                memories,
             }
         }
 
-        for name in names.drain(..names.len().saturating_sub(1)) {
-            result.insert(name, result_hardware.clone());
+        _ => return Err(LoadError::UnexpectedHardware(hardware)),
+    })
+}
+
+/// Parses the memories and slots nested inside a processor, MCU or RTC.
+fn parse_attached_hardware<'a>(node: &mut bml::Node<'a>) -> Result<Vec<Hardware>, LoadError<'a>> {
+    let mut result = vec![];
+    for hardware in node
+        .attrs
+        .drain_filter(|attr| attr.name == "memory" || attr.name == "slot")
+    {
+        if hardware.value.is_some() {
+            return Err(LoadError::UnexpectedHardware(hardware));
         }
-        if let Some(name) = names.pop() {
-            result.insert(name, result_hardware);
+        result.push(if hardware.name == "memory" {
+            parse_memory(hardware)?
+        } else {
+            parse_slot(hardware)?
+        });
+    }
+    Ok(result)
+}
+
+fn parse_map<'a>(node: &mut bml::Node<'a>) -> Result<Vec<MapRegion>, LoadError<'a>> {
+    let mut result = vec![];
+    for map_region in node.attrs.drain_filter(|attr| attr.name == "map") {
+        result.push(parse_map_region(map_region)?);
+    }
+    Ok(result)
+}
+
+fn parse_slot(mut slot: bml::Node) -> Result<Hardware, LoadError> {
+    let ty = remove_hardware_value_attr!(slot, "slot", "type");
+    let ty = match ty.as_ref() {
+        "SufamiTurbo" => SlotType::SufamiTurbo,
+        "BSMemory" => SlotType::BsMemory,
+        "GameBoy" => SlotType::GameBoy,
+        _ => return Err(LoadError::UnknownSlotType(ty)),
+    };
+    let map = parse_map(&mut slot)?;
+
+    // Slots with more than one memory specify where each of them is mapped separately
+    let mut rom_map = vec![];
+    let mut ram_map = vec![];
+    for (name, memory_map) in [("rom", &mut rom_map), ("ram", &mut ram_map)] {
+        if let Some(mut memory) = slot.remove_attr(name) {
+            if memory.value.is_some() {
+                return Err(LoadError::UnexpectedHardware(memory));
+            }
+            *memory_map = parse_map(&mut memory)?;
+            check_no_attrs_left(memory, "slot")?;
+        }
+    }
+
+    check_no_attrs_left(slot, "slot")?;
+    Ok(Hardware::Slot {
+        ty,
+        map,
+        rom_map,
+        ram_map,
+    })
+}
+
+fn parse_memory(mut memory: bml::Node) -> Result<Hardware, LoadError> {
+    let ty = remove_hardware_value_attr!(memory, "memory", "type");
+    let content = remove_hardware_value_attr!(memory, "memory", "content");
+    let architecture =
+        remove_hardware_value_attr!(memory, opt "memory", "architecture").map(Cow::into_owned);
+    let map = parse_map(&mut memory)?;
+
+    let result = match ty.as_ref() {
+        "ROM" => Hardware::Rom {
+            content: match content.as_ref() {
+                "Program" => RomContent::Program,
+                "Boot" => RomContent::Boot,
+                "Data" => RomContent::Data,
+                "Expansion" => RomContent::Expansion,
+                _ => match content
+                    .strip_prefix("Level-")
+                    .and_then(|level| level.parse().ok())
+                {
+                    Some(level) => RomContent::Level(level),
+                    None => {
+                        return Err(LoadError::UnknownMemoryContent {
+                            memory_ty: "ROM",
+                            content,
+                        })
+                    }
+                },
+            },
+            architecture,
+            map,
+        },
+
+        "RAM" => Hardware::Ram {
+            content: match content.as_ref() {
+                "Save" => RamContent::Save,
+                "Internal" => RamContent::Internal,
+                "Data" => RamContent::Data,
+                "Download" => RamContent::Download,
+                _ => {
+                    return Err(LoadError::UnknownMemoryContent {
+                        memory_ty: "RAM",
+                        content,
+                    })
+                }
+            },
+            architecture,
+            map,
+        },
+
+        // The RTC's own time counters, which are only accessible through its registers
+        "RTC" if content == "Time" => Hardware::RtcTime {
+            manufacturer: remove_hardware_value_attr!(memory, opt "memory", "manufacturer")
+                .map(Cow::into_owned),
+        },
+        "RTC" => {
+            return Err(LoadError::UnknownMemoryContent {
+                memory_ty: "RTC",
+                content,
+            })
         }
+
+        _ => return Err(LoadError::UnknownMemoryType(ty)),
+    };
+
+    if !memory.attrs.is_empty() {
+        return Err(LoadError::UnexpectedHardwareAttrs {
+            ty: "memory",
+            attrs: memory.attrs,
+        });
     }
+
     Ok(result)
 }
+
+fn parse_map_region(mut map: bml::Node) -> Result<MapRegion, LoadError> {
+    macro_rules! remove_value_attr {
+        (opt $name: expr) => {
+            match map.remove_value_attr($name) {
+                Ok(value) => Some(value),
+                Err(bml::ValueAttrError::Missing) => None,
+                Err(bml::ValueAttrError::MissingValue) => {
+                    return Err(LoadError::MissingMapAttrValue { name: $name })
+                }
+                Err(bml::ValueAttrError::UnexpectedAttrs(attrs)) => {
+                    return Err(LoadError::UnexpectedMapAttrAttrs { name: $name, attrs })
+                }
+            }
+        };
+        ($name: expr) => {
+            map.remove_value_attr($name).map_err(|err| match err {
+                bml::ValueAttrError::Missing => LoadError::MissingMapAttr { name: $name },
+                bml::ValueAttrError::MissingValue => LoadError::MissingMapAttrValue { name: $name },
+                bml::ValueAttrError::UnexpectedAttrs(attrs) => {
+                    LoadError::UnexpectedMapAttrAttrs { name: $name, attrs }
+                }
+            })?
+        };
+    }
+
+    let addr_ranges = remove_value_attr!("address");
+    let mask = remove_value_attr!(opt "mask").unwrap_or(Cow::Borrowed("0"));
+    let offset = remove_value_attr!(opt "base").unwrap_or(Cow::Borrowed("0"));
+    let size = remove_value_attr!(opt "size");
+
+    Ok(MapRegion {
+        address_ranges: {
+            let (bank_ranges, address_ranges) = unwrap_or_err!(
+                addr_ranges.split_once(':'),
+                LoadError::InvalidAddress(addr_ranges)
+            );
+
+            // Both bank and address ranges can be lists, and either end of a range can be omitted
+            // for single values
+            let mut result_addr_ranges = vec![];
+            for bank_range in bank_ranges.split(',') {
+                let banks = {
+                    let (start_bank, end_bank) = bank_range
+                        .split_once('-')
+                        .unwrap_or((bank_range, bank_range));
+                    (
+                        parse_hex!(u8, start_bank, LoadError::InvalidAddress(addr_ranges)),
+                        parse_hex!(u8, end_bank, LoadError::InvalidAddress(addr_ranges)),
+                    )
+                };
+                for address_range in address_ranges.split(',') {
+                    let addrs = {
+                        let (start_address, end_address) = address_range
+                            .split_once('-')
+                            .unwrap_or((address_range, address_range));
+                        (
+                            parse_hex!(u16, start_address, LoadError::InvalidAddress(addr_ranges)),
+                            parse_hex!(u16, end_address, LoadError::InvalidAddress(addr_ranges)),
+                        )
+                    };
+                    result_addr_ranges.push(MapAddrRange { banks, addrs });
+                }
+            }
+
+            result_addr_ranges
+        },
+        offset: parse_hex!(u32, offset, LoadError::InvalidMapOffset(offset)),
+        size: match size {
+            Some(size) => Some(parse_hex!(u32, size, LoadError::InvalidMapSize(size))),
+            None => None,
+        },
+        mask: parse_hex!(u32, mask, LoadError::InvalidMapMask(mask)),
+    })
+}
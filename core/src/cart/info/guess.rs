@@ -1,4 +1,7 @@
-use super::{header, Header, Info, MapAddrRange, MapRegion};
+use super::{
+    super::rom::header_candidates, header, Coprocessor, Header, Info, MapAddrRange, MapRegion,
+    SaveRamLayout,
+};
 use crate::utils::ByteSlice;
 
 impl Info {
@@ -7,30 +10,363 @@ impl Info {
             return None;
         }
 
-        let header = rom[..]
-            .get(0x40_FFB0..0x41_0000)
-            .and_then(|header_bytes| {
+        // Try the most plausible header locations first, falling back to the others if the header
+        // can't be parsed
+        let header = header_candidates(rom)
+            .into_iter()
+            .find_map(|(base_map_mode, offset)| {
                 Header::new(
-                    ByteSlice::new(header_bytes),
-                    Some(header::BaseMapMode::ExHiRom),
-                )
-            })
-            .or_else(|| {
-                rom[..].get(0xFFB0..0x1_0000).and_then(|header_bytes| {
-                    Header::new(
-                        ByteSlice::new(header_bytes),
-                        Some(header::BaseMapMode::HiRom),
-                    )
-                })
-            })
-            .or_else(|| {
-                Header::new(
-                    ByteSlice::new(&rom[0x7FB0..0x8000]),
-                    Some(header::BaseMapMode::LoRom),
+                    ByteSlice::new(&rom[offset..offset + 0x50]),
+                    Some(base_map_mode),
                 )
             })?;
 
+        let coprocessor = if header.map_mode == header::MapMode::LoRomSa1
+            || header.chipset.coprocessor == header::Coprocessor::Sa1
+        {
+            Some(Coprocessor::Sa1)
+        } else if header.chipset.coprocessor == header::Coprocessor::Gsu {
+            Some(Coprocessor::Gsu)
+        } else if header.chipset.coprocessor == header::Coprocessor::Dsp {
+            Some(Coprocessor::Upd7725)
+        } else if header.chipset.coprocessor == header::Coprocessor::St010St011 {
+            Some(Coprocessor::Upd96050)
+        } else if header.chipset.coprocessor == header::Coprocessor::St018 {
+            Some(Coprocessor::St018)
+        } else if header.chipset.coprocessor == header::Coprocessor::Cx4 {
+            Some(Coprocessor::Cx4)
+        } else if header.map_mode == header::MapMode::LoRomSdd1
+            || header.chipset.coprocessor == header::Coprocessor::SDd1
+        {
+            Some(Coprocessor::Sdd1)
+        } else if header.map_mode == header::MapMode::HiRomSpc7110
+            || header.chipset.coprocessor == header::Coprocessor::Spc7110
+        {
+            Some(Coprocessor::Spc7110)
+        } else if header.chipset.coprocessor == header::Coprocessor::SRtc {
+            Some(Coprocessor::SRtc)
+        } else if header.chipset.coprocessor == header::Coprocessor::Obc1 {
+            Some(Coprocessor::Obc1)
+        } else {
+            None
+        };
+
+        // DSP-n chips are mapped to different ranges depending on the board, with the lowest
+        // address bit not covered by the mask selecting between the DR and SR registers
+        let coprocessor_map = match coprocessor {
+            Some(Coprocessor::Upd7725) => {
+                let (banks, addrs, mask) = match header.map_mode.base() {
+                    header::BaseMapMode::LoRom if rom.len() > 0x10_0000 => {
+                        ([(0x60, 0x6F), (0xE0, 0xEF)], (0x0000, 0x7FFF), 0x3FFF)
+                    }
+                    header::BaseMapMode::LoRom => {
+                        ([(0x30, 0x3F), (0xB0, 0xBF)], (0x8000, 0xFFFF), 0x3FFF)
+                    }
+                    _ => ([(0x00, 0x1F), (0x80, 0x9F)], (0x6000, 0x7FFF), 0xFFF),
+                };
+                vec![MapRegion {
+                    address_ranges: banks
+                        .iter()
+                        .map(|&banks| MapAddrRange { banks, addrs })
+                        .collect(),
+                    offset: 0,
+                    size: None,
+                    mask,
+                }]
+            }
+            // All ST010 and ST011 boards have the same layout, with SR at odd addresses
+            Some(Coprocessor::Upd96050) => vec![MapRegion {
+                address_ranges: vec![
+                    MapAddrRange {
+                        banks: (0x60, 0x67),
+                        addrs: (0x0000, 0x3FFF),
+                    },
+                    MapAddrRange {
+                        banks: (0xE0, 0xE7),
+                        addrs: (0x0000, 0x3FFF),
+                    },
+                ],
+                offset: 0,
+                size: None,
+                mask: 0,
+            }],
+            Some(Coprocessor::St018) => vec![MapRegion {
+                address_ranges: vec![
+                    MapAddrRange {
+                        banks: (0x00, 0x3F),
+                        addrs: (0x3800, 0x38FF),
+                    },
+                    MapAddrRange {
+                        banks: (0x80, 0xBF),
+                        addrs: (0x3800, 0x38FF),
+                    },
+                ],
+                offset: 0,
+                size: None,
+                mask: 0,
+            }],
+            Some(Coprocessor::SRtc) => vec![MapRegion {
+                address_ranges: vec![
+                    MapAddrRange {
+                        banks: (0x00, 0x3F),
+                        addrs: (0x2800, 0x29FF),
+                    },
+                    MapAddrRange {
+                        banks: (0x80, 0xBF),
+                        addrs: (0x2800, 0x29FF),
+                    },
+                ],
+                offset: 0,
+                size: None,
+                mask: 0,
+            }],
+            // The OBC1 decodes the save RAM ranges, which are the same in all of its boards
+            Some(Coprocessor::Obc1) => vec![
+                MapRegion {
+                    address_ranges: vec![
+                        MapAddrRange {
+                            banks: (0x00, 0x3F),
+                            addrs: (0x6000, 0x7FFF),
+                        },
+                        MapAddrRange {
+                            banks: (0x80, 0xBF),
+                            addrs: (0x6000, 0x7FFF),
+                        },
+                    ],
+                    offset: 0,
+                    size: None,
+                    mask: 0xE000,
+                },
+                MapRegion {
+                    address_ranges: vec![
+                        MapAddrRange {
+                            banks: (0x70, 0x71),
+                            addrs: (0x6000, 0x7FFF),
+                        },
+                        MapAddrRange {
+                            banks: (0x70, 0x71),
+                            addrs: (0xE000, 0xFFFF),
+                        },
+                        MapAddrRange {
+                            banks: (0xF0, 0xF1),
+                            addrs: (0x6000, 0x7FFF),
+                        },
+                        MapAddrRange {
+                            banks: (0xF0, 0xF1),
+                            addrs: (0xE000, 0xFFFF),
+                        },
+                    ],
+                    offset: 0,
+                    size: None,
+                    mask: 0xE000,
+                },
+            ],
+            _ => vec![],
+        };
+        let coprocessor_data_ram_map = if coprocessor == Some(Coprocessor::Upd96050) {
+            vec![MapRegion {
+                address_ranges: vec![
+                    MapAddrRange {
+                        banks: (0x68, 0x6F),
+                        addrs: (0x0000, 0x7FFF),
+                    },
+                    MapAddrRange {
+                        banks: (0xE8, 0xEF),
+                        addrs: (0x0000, 0x7FFF),
+                    },
+                ],
+                offset: 0,
+                size: None,
+                mask: 0x8000,
+            }]
+        } else {
+            vec![]
+        };
+
+        let ram_size = if coprocessor == Some(Coprocessor::Gsu) {
+            // The GSU's work RAM is always present, even in carts without save RAM, and its size is
+            // only sometimes reported through the expansion RAM size
+            header.ram_size.max(header.expansion_ram_size).max(0x8000)
+        } else if header.chipset.has_ram {
+            header.ram_size
+        } else {
+            0
+        };
+
         let (rom_map, ram_map) = match header.map_mode.base() {
+            // The SA-1 has a fixed memory map
+            _ if coprocessor == Some(Coprocessor::Sa1) => (vec![], vec![]),
+            // The S-DD1 maps ROM through its own bank registers, and save RAM is always mapped to
+            // the same ranges
+            _ if coprocessor == Some(Coprocessor::Sdd1) => (
+                vec![],
+                if header.ram_size != 0 {
+                    vec![
+                        MapRegion {
+                            address_ranges: vec![
+                                MapAddrRange {
+                                    banks: (0x00, 0x3F),
+                                    addrs: (0x6000, 0x7FFF),
+                                },
+                                MapAddrRange {
+                                    banks: (0x80, 0xBF),
+                                    addrs: (0x6000, 0x7FFF),
+                                },
+                            ],
+                            offset: 0,
+                            size: None,
+                            mask: 0xE000,
+                        },
+                        MapRegion {
+                            address_ranges: vec![MapAddrRange {
+                                banks: (0x70, 0x73),
+                                addrs: (0x0000, 0xFFFF),
+                            }],
+                            offset: 0,
+                            size: None,
+                            mask: 0x8000,
+                        },
+                    ]
+                } else {
+                    vec![]
+                },
+            ),
+            // The SPC7110 maps ROM through its own bank registers
+            _ if coprocessor == Some(Coprocessor::Spc7110) => (
+                vec![],
+                if header.ram_size != 0 {
+                    vec![MapRegion {
+                        address_ranges: vec![
+                            MapAddrRange {
+                                banks: (0x00, 0x3F),
+                                addrs: (0x6000, 0x7FFF),
+                            },
+                            MapAddrRange {
+                                banks: (0x80, 0xBF),
+                                addrs: (0x6000, 0x7FFF),
+                            },
+                        ],
+                        offset: 0,
+                        size: None,
+                        mask: 0xE000,
+                    }]
+                } else {
+                    vec![]
+                },
+            ),
+            // The only ST018 board maps ROM as LoROM, leaving banks 70-7D and F0-FF to save RAM
+            _ if coprocessor == Some(Coprocessor::St018) => (
+                vec![MapRegion {
+                    address_ranges: vec![
+                        MapAddrRange {
+                            banks: (0x00, 0x7D),
+                            addrs: (0x8000, 0xFFFF),
+                        },
+                        MapAddrRange {
+                            banks: (0x80, 0xFF),
+                            addrs: (0x8000, 0xFFFF),
+                        },
+                        MapAddrRange {
+                            banks: (0x40, 0x6F),
+                            addrs: (0x0000, 0x7FFF),
+                        },
+                        MapAddrRange {
+                            banks: (0xC0, 0xEF),
+                            addrs: (0x0000, 0x7FFF),
+                        },
+                    ],
+                    offset: 0,
+                    size: None,
+                    mask: 0x8000,
+                }],
+                if header.ram_size != 0 {
+                    vec![MapRegion {
+                        address_ranges: vec![
+                            MapAddrRange {
+                                banks: (0x70, 0x7D),
+                                addrs: (0x0000, 0xFFFF),
+                            },
+                            MapAddrRange {
+                                banks: (0xF0, 0xFF),
+                                addrs: (0x0000, 0xFFFF),
+                            },
+                        ],
+                        offset: 0,
+                        size: None,
+                        mask: 0,
+                    }]
+                } else {
+                    vec![]
+                },
+            ),
+            // All GSU boards share the same layout, other than the biggest ones also exposing ROM
+            // as HiROM in banks 40-5F
+            _ if coprocessor == Some(Coprocessor::Gsu) => (
+                vec![
+                    MapRegion {
+                        address_ranges: vec![
+                            MapAddrRange {
+                                banks: (0x00, 0x3F),
+                                addrs: (0x8000, 0xFFFF),
+                            },
+                            MapAddrRange {
+                                banks: (0x80, 0xBF),
+                                addrs: (0x8000, 0xFFFF),
+                            },
+                        ],
+                        offset: 0,
+                        size: None,
+                        mask: 0x8000,
+                    },
+                    MapRegion {
+                        address_ranges: vec![
+                            MapAddrRange {
+                                banks: (0x40, 0x5F),
+                                addrs: (0x0000, 0xFFFF),
+                            },
+                            MapAddrRange {
+                                banks: (0xC0, 0xDF),
+                                addrs: (0x0000, 0xFFFF),
+                            },
+                        ],
+                        offset: 0,
+                        size: None,
+                        mask: 0,
+                    },
+                ],
+                vec![
+                    MapRegion {
+                        address_ranges: vec![
+                            MapAddrRange {
+                                banks: (0x00, 0x3F),
+                                addrs: (0x6000, 0x7FFF),
+                            },
+                            MapAddrRange {
+                                banks: (0x80, 0xBF),
+                                addrs: (0x6000, 0x7FFF),
+                            },
+                        ],
+                        offset: 0,
+                        size: Some(0x2000),
+                        mask: 0,
+                    },
+                    MapRegion {
+                        address_ranges: vec![
+                            MapAddrRange {
+                                banks: (0x70, 0x71),
+                                addrs: (0x0000, 0xFFFF),
+                            },
+                            MapAddrRange {
+                                banks: (0xF0, 0xF1),
+                                addrs: (0x0000, 0xFFFF),
+                            },
+                        ],
+                        offset: 0,
+                        size: None,
+                        mask: 0,
+                    },
+                ],
+            ),
             header::BaseMapMode::LoRom => {
                 let mut rom_ranges = vec![
                     MapAddrRange {
@@ -42,7 +378,7 @@ impl Info {
                         addrs: (0x8000, 0xFFFF),
                     },
                 ];
-                if header.ram_size == 0 {
+                if ram_size == 0 {
                     rom_ranges.extend_from_slice(&[
                         MapAddrRange {
                             banks: (0x40, 0x7D),
@@ -61,9 +397,12 @@ impl Info {
                         size: None,
                         mask: 0x8000,
                     }],
-                    // TODO: Same issue as HiROM, although only the exact ranges inside banks
-                    // differ. In addition, ROM banks can be wildly different.
-                    vec![],
+                    // Most LoROM boards map save RAM to the lower half of banks 70-7D and F0-FF
+                    if ram_size != 0 {
+                        SaveRamLayout::LoRom.map()
+                    } else {
+                        vec![]
+                    },
                 )
             }
             header::BaseMapMode::HiRom => (
@@ -90,10 +429,13 @@ impl Info {
                     size: None,
                     mask: 0,
                 }],
-                // TODO: Some HiROM boards put save RAM in banks 20-3F and A0-BF, others put them in
-                // banks 10-1F, 30-3F, 90-9F and B0-BF, how to guess which one of those layouts
-                // is needed?
-                vec![],
+                // Most HiROM boards map save RAM to banks 20-3F and A0-BF; the ones mapping it to
+                // 10-1F, 30-3F, 90-9F and B0-BF instead are usually accessed through banks 30-3F
+                if ram_size != 0 {
+                    SaveRamLayout::HiRom.map()
+                } else {
+                    vec![]
+                },
             ),
             header::BaseMapMode::ExHiRom => (
                 vec![
@@ -128,16 +470,8 @@ impl Info {
                         mask: 0xC0_0000,
                     },
                 ],
-                if header.ram_size != 0 {
-                    vec![MapRegion {
-                        address_ranges: vec![MapAddrRange {
-                            banks: (0x80, 0xBF),
-                            addrs: (0x6000, 0x7FFF),
-                        }],
-                        offset: 0,
-                        size: None,
-                        mask: 0xE000,
-                    }]
+                if ram_size != 0 {
+                    SaveRamLayout::ExHiRom.map()
                 } else {
                     vec![]
                 },
@@ -147,14 +481,27 @@ impl Info {
         Some((
             Info {
                 title: header.title.clone(),
-                ram_size: if header.chipset.has_ram {
-                    header.ram_size
-                } else {
-                    0
-                },
+                ram_size,
                 has_battery: header.chipset.has_battery,
                 rom_map,
                 ram_map,
+                coprocessor,
+                has_rtc: header.chipset.has_rtc,
+                coprocessor_map,
+                coprocessor_data_ram_map,
+                coprocessor_frequency: None,
+                // The DSP-1B's firmware is a bugfixed superset of the DSP-1's, and by far the most
+                // common, so use it when the exact chip can't be known
+                firmware_name: match coprocessor {
+                    Some(Coprocessor::Upd7725) => Some("dsp1b".to_string()),
+                    Some(Coprocessor::Upd96050) => Some("st010".to_string()),
+                    Some(Coprocessor::St018) => Some("st018".to_string()),
+                    Some(Coprocessor::Cx4) => Some("cx4".to_string()),
+                    _ => None,
+                },
+                processors: vec![],
+                slots: vec![],
+                rtc: None,
             },
             header,
         ))
@@ -77,8 +77,8 @@ impl MapMode {
     pub fn base(self) -> BaseMapMode {
         match self {
             Self::LoRom | Self::LoRomSdd1 | Self::LoRomSa1 => BaseMapMode::LoRom,
-            Self::HiRom => BaseMapMode::HiRom,
-            Self::ExHiRom | Self::HiRomSpc7110 => BaseMapMode::ExHiRom,
+            Self::HiRom | Self::HiRomSpc7110 => BaseMapMode::HiRom,
+            Self::ExHiRom => BaseMapMode::ExHiRom,
         }
     }
 }
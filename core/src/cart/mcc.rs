@@ -0,0 +1,286 @@
+use super::{
+    info,
+    map::{Map, ReadHandler, WriteHandler},
+    Cart, Coprocessor,
+};
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    utils::BoxedByteSlice,
+};
+
+const PSRAM_SIZE: usize = 0x8_0000;
+
+// Register indices, each of which stores a single bit; register 0 is the (read-only) IRQ flag, 14
+// commits the written values and 13 and 15 have no known effect
+const IRQ_ENABLE: u8 = 1;
+const HIROM: u8 = 2;
+const PSRAM_ENABLE_LO: u8 = 3;
+const PSRAM_ENABLE_HI: u8 = 4;
+const PSRAM_MAPPING: u8 = 5; // 2 bits
+const ROM_ENABLE_LO: u8 = 7;
+const ROM_ENABLE_HI: u8 = 8;
+const BS_MEMORY_ENABLE_LO: u8 = 9;
+const BS_MEMORY_ENABLE_HI: u8 = 10;
+const BS_MEMORY_MAPPING: u8 = 11;
+const BS_MEMORY_WRITABLE: u8 = 12;
+const COMMIT: u8 = 14;
+
+const POWER_ON_REGS: u16 = 1 << HIROM
+    | 1 << PSRAM_ENABLE_LO
+    | 1 << PSRAM_MAPPING
+    | 1 << ROM_ENABLE_LO
+    | 1 << ROM_ENABLE_HI
+    | 1 << BS_MEMORY_ENABLE_LO
+    | 1 << BS_MEMORY_MAPPING;
+
+// The regions whose contents are selected by the MCC's registers
+const CONTROLLED_RANGES: [((u8, u8), (u16, u16)); 6] = [
+    ((0x00, 0x3F), (0x8000, 0xFFFF)),
+    ((0x80, 0xBF), (0x8000, 0xFFFF)),
+    ((0x40, 0x7D), (0x0000, 0xFFFF)),
+    ((0xC0, 0xFF), (0x0000, 0xFFFF)),
+    ((0x20, 0x3F), (0x6000, 0x7FFF)),
+    ((0xA0, 0xBF), (0x6000, 0x7FFF)),
+];
+
+/// The state of the MCC, the BS-X cartridge's memory controller, which maps its BIOS ROM, 512 KiB
+/// of PSRAM (used to hold downloaded programs) and the BS Memory slot's contents.
+///
+/// Its 16 one-bit registers are accessed through bit 7 of $00-0F:5000-5FFF, with the bank
+/// selecting the register; writes only take effect once they're committed through register 14.
+#[derive(Clone)]
+pub struct Mcc {
+    regs: u16,
+    written_regs: u16,
+    psram: BoxedByteSlice,
+    bs_memory_slot: Option<usize>,
+}
+
+impl Mcc {
+    pub(super) fn new(bs_memory_slot: Option<usize>) -> Self {
+        Mcc {
+            regs: POWER_ON_REGS,
+            written_regs: POWER_ON_REGS,
+            psram: BoxedByteSlice::new_zeroed(PSRAM_SIZE),
+            bs_memory_slot,
+        }
+    }
+
+    pub(super) fn soft_reset(&mut self) {
+        self.regs = POWER_ON_REGS;
+        self.written_regs = POWER_ON_REGS;
+    }
+
+    /// Returns the currently active register values, with register `i` in bit `i`.
+    #[inline]
+    pub fn regs(&self) -> u16 {
+        self.regs
+    }
+
+    #[inline]
+    pub fn psram(&self) -> &BoxedByteSlice {
+        &self.psram
+    }
+
+    #[inline]
+    pub fn irq_enabled(&self) -> bool {
+        self.regs & 1 << IRQ_ENABLE != 0
+    }
+
+    #[inline]
+    pub fn bs_memory_writable(&self) -> bool {
+        self.regs & 1 << BS_MEMORY_WRITABLE != 0
+    }
+
+    fn reg(&self, i: u8) -> bool {
+        self.regs & 1 << i != 0
+    }
+
+    fn read_reg(&self, i: u8) -> u8 {
+        match i {
+            // No interrupt sources are emulated, so the IRQ flag is never set
+            0 | COMMIT | 15 => 0,
+            _ => (self.reg(i) as u8) << 7,
+        }
+    }
+
+    /// Writes to the given register, returning whether the written values were committed.
+    fn write_reg(&mut self, i: u8, value: u8) -> bool {
+        match i {
+            0 | 15 => false,
+            COMMIT => {
+                if value & 0x80 != 0 {
+                    self.regs = self.written_regs;
+                }
+                value & 0x80 != 0
+            }
+            _ => {
+                self.written_regs = (self.written_regs & !(1 << i)) | ((value >> 7) as u16) << i;
+                false
+            }
+        }
+    }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.regs);
+        writer.write(&self.written_regs);
+        writer.write_bytes(&self.psram[..]);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.regs = reader.read()?;
+        self.written_regs = reader.read()?;
+        reader.read_bytes(&mut self.psram[..])
+    }
+}
+
+impl Cart {
+    pub(super) fn map_mcc(map: &mut Map, io_map: &info::Map) {
+        for region in io_map {
+            for addr_range in &region.address_ranges {
+                map.map::<true, true>(
+                    Some(Self::handle_mcc_read as ReadHandler),
+                    Some(Self::handle_mcc_write as WriteHandler),
+                    addr_range.banks,
+                    addr_range.addrs,
+                    0,
+                    1 << 24,
+                    0,
+                );
+            }
+        }
+    }
+
+    /// Updates the regions controlled by the MCC according to its current register values.
+    pub(super) fn remap_mcc(&mut self) {
+        let (regs, bs_memory_slot) = match &self.coprocessor {
+            Some(Coprocessor::Mcc(mcc)) => (mcc.regs, mcc.bs_memory_slot),
+            _ => unreachable!(),
+        };
+        let reg = |i: u8| regs & 1 << i != 0;
+        let hirom = reg(HIROM);
+        let map = &mut self.map;
+        for (banks, addrs) in CONTROLLED_RANGES {
+            map.map::<true, true>(None, None, banks, addrs, 0, 1, 0);
+        }
+
+        // Mappings are applied in reverse priority order, so that the BIOS ROM takes precedence
+        // over the PSRAM, which in turn takes precedence over the BS Memory pack
+        if let Some((slot, (read_fn, write_fn))) =
+            bs_memory_slot.and_then(|slot| Some((slot, Self::bs_memory_handlers(slot)?)))
+        {
+            let size = match &self.slot_carts[slot] {
+                Some(slot_cart) => slot_cart.rom.len() as u32,
+                None => 0,
+            };
+            if size != 0 {
+                for (enable_bit, base) in [(BS_MEMORY_ENABLE_LO, 0x00), (BS_MEMORY_ENABLE_HI, 0x80)]
+                {
+                    if !reg(enable_bit) {
+                        continue;
+                    }
+                    let (banks, addrs, mask) = match (hirom, reg(BS_MEMORY_MAPPING)) {
+                        (false, false) => ((base, base + 0x1F), (0x8000, 0xFFFF), 0x8000),
+                        (false, true) => ((base + 0x40, base + 0x5F), (0x0000, 0xFFFF), 0x8000),
+                        (true, false) => ((base, base + 0x0F), (0x8000, 0xFFFF), 0),
+                        (true, true) => ((base + 0x40, base + 0x4F), (0x0000, 0xFFFF), 0),
+                    };
+                    map.map::<true, true>(
+                        Some(read_fn),
+                        Some(write_fn),
+                        banks,
+                        addrs,
+                        0,
+                        size,
+                        mask,
+                    );
+                }
+            }
+        }
+
+        let psram_bank = (regs >> PSRAM_MAPPING & 3) as u8 * 0x20;
+        for (enable_bit, base) in [(PSRAM_ENABLE_LO, 0x00), (PSRAM_ENABLE_HI, 0x80)] {
+            if !reg(enable_bit) {
+                continue;
+            }
+            let bank = base + psram_bank;
+            let addrs = if psram_bank < 0x40 {
+                (0x8000, 0xFFFF)
+            } else {
+                (0x0000, 0xFFFF)
+            };
+            let ranges = if hirom {
+                [
+                    ((bank, bank + 0x07), addrs, 0),
+                    ((base + 0x20, base + 0x3F), (0x6000, 0x7FFF), 0xE000),
+                    ((base + 0x70, base + 0x77), (0x0000, 0xFFFF), 0),
+                ]
+                .to_vec()
+            } else {
+                // Banks $7E-$7F always contain WRAM
+                let end = if base == 0 { 0x7D } else { 0xFF };
+                [
+                    ((bank, bank + 0x0F), addrs, 0x8000),
+                    ((base + 0x70, end), (0x0000, 0x7FFF), 0x8000),
+                ]
+                .to_vec()
+            };
+            for (banks, addrs, mask) in ranges {
+                map.map::<true, true>(
+                    Some(Self::handle_mcc_psram_read as ReadHandler),
+                    Some(Self::handle_mcc_psram_write as WriteHandler),
+                    banks,
+                    addrs,
+                    0,
+                    PSRAM_SIZE as u32,
+                    mask,
+                );
+            }
+        }
+
+        for (enable_bit, banks) in [(ROM_ENABLE_LO, (0x00, 0x3F)), (ROM_ENABLE_HI, (0x80, 0xBF))] {
+            if reg(enable_bit) && !self.rom.is_empty() {
+                map.map::<true, true>(
+                    Some(Self::handle_rom_read as ReadHandler),
+                    None,
+                    banks,
+                    (0x8000, 0xFFFF),
+                    0,
+                    self.rom.len() as u32,
+                    0x8000,
+                );
+            }
+        }
+    }
+
+    fn handle_mcc_read(&mut self, addr: u32) -> u8 {
+        match &self.coprocessor {
+            Some(Coprocessor::Mcc(mcc)) => mcc.read_reg((addr >> 16 & 0xF) as u8),
+            _ => unreachable!(),
+        }
+    }
+
+    fn handle_mcc_write(&mut self, addr: u32, value: u8) {
+        let committed = match &mut self.coprocessor {
+            Some(Coprocessor::Mcc(mcc)) => mcc.write_reg((addr >> 16 & 0xF) as u8, value),
+            _ => unreachable!(),
+        };
+        if committed {
+            self.remap_mcc();
+        }
+    }
+
+    fn handle_mcc_psram_read(&mut self, offset: u32) -> u8 {
+        match &self.coprocessor {
+            Some(Coprocessor::Mcc(mcc)) => mcc.psram[offset as usize],
+            _ => unreachable!(),
+        }
+    }
+
+    fn handle_mcc_psram_write(&mut self, offset: u32, value: u8) {
+        match &mut self.coprocessor {
+            Some(Coprocessor::Mcc(mcc)) => mcc.psram[offset as usize] = value,
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use super::{
+    info,
+    map::{mirror, Map, ReadHandler, WriteHandler},
+    Cart,
+};
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    utils::BoxedByteSlice,
+};
+
+/// The state of the OBC1, an OAM manager that lets the main CPU build sprite tables in save RAM
+/// by sprite index, packing the high table's size and X position bits automatically.
+///
+/// Its registers are in the last bytes of the 8 KiB RAM window, at $7FF0-$7FF7: $7FF0-$7FF3 access
+/// the low table entry and $7FF4 the high table bits of the selected sprite, $7FF5 selects which
+/// of the two OAM copies in RAM is used, and $7FF6 selects the sprite.
+#[derive(Clone)]
+pub struct Obc1 {
+    base: u16,
+    index: u8,
+    shift: u8,
+}
+
+fn read_ram(ram: &BoxedByteSlice, addr: u16) -> u8 {
+    if ram.is_empty() {
+        return 0;
+    }
+    ram[mirror(addr as u32, ram.len() as u32) as usize]
+}
+
+fn write_ram(ram: &mut BoxedByteSlice, addr: u16, value: u8) {
+    if ram.is_empty() {
+        return;
+    }
+    let addr = mirror(addr as u32, ram.len() as u32);
+    ram[addr as usize] = value;
+}
+
+impl Obc1 {
+    pub(super) fn new(ram: &BoxedByteSlice) -> Self {
+        let mut result = Obc1 {
+            base: 0,
+            index: 0,
+            shift: 0,
+        };
+        result.reset(ram);
+        result
+    }
+
+    /// Reloads the OBC1's registers from their copies in RAM.
+    pub(super) fn reset(&mut self, ram: &BoxedByteSlice) {
+        self.set_base(read_ram(ram, 0x1FF5));
+        self.set_index(read_ram(ram, 0x1FF6));
+    }
+
+    #[inline]
+    pub fn base(&self) -> u16 {
+        self.base
+    }
+
+    #[inline]
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    fn set_base(&mut self, value: u8) {
+        self.base = if value & 1 != 0 { 0x1800 } else { 0x1C00 };
+    }
+
+    fn set_index(&mut self, value: u8) {
+        self.index = value & 0x7F;
+        self.shift = (value & 3) << 1;
+    }
+
+    fn low_table_addr(&self, addr: u16) -> u16 {
+        self.base + ((self.index as u16) << 2 | (addr & 3))
+    }
+
+    fn high_table_addr(&self) -> u16 {
+        self.base + 0x200 + (self.index >> 2) as u16
+    }
+
+    fn read(&self, ram: &BoxedByteSlice, addr: u16) -> u8 {
+        match addr {
+            0x1FF0..=0x1FF3 => read_ram(ram, self.low_table_addr(addr)),
+            0x1FF4 => read_ram(ram, self.high_table_addr()),
+            _ => read_ram(ram, addr),
+        }
+    }
+
+    fn write(&mut self, ram: &mut BoxedByteSlice, addr: u16, value: u8) {
+        match addr {
+            0x1FF0..=0x1FF3 => write_ram(ram, self.low_table_addr(addr), value),
+            0x1FF4 => {
+                let addr = self.high_table_addr();
+                let prev = read_ram(ram, addr);
+                write_ram(
+                    ram,
+                    addr,
+                    (prev & !(3 << self.shift)) | (value & 3) << self.shift,
+                );
+            }
+            _ => {
+                match addr {
+                    0x1FF5 => self.set_base(value),
+                    0x1FF6 => self.set_index(value),
+                    _ => {}
+                }
+                write_ram(ram, addr, value);
+            }
+        }
+    }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.base);
+        writer.write(&self.index);
+        writer.write(&self.shift);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.base = reader.read()?;
+        self.index = reader.read()?;
+        self.shift = reader.read()?;
+        if !matches!(self.base, 0x1800 | 0x1C00) || self.index > 0x7F || self.shift > 6 {
+            return Err(LoadError::InvalidData);
+        }
+        Ok(())
+    }
+}
+
+impl Cart {
+    pub(super) fn map_obc1(map: &mut Map, io_map: &info::Map) {
+        // Save RAM is only accessible through the OBC1, which decodes the low 13 address bits
+        for region in io_map {
+            for addr_range in &region.address_ranges {
+                map.map::<true, true>(
+                    Some(Self::handle_obc1_read as ReadHandler),
+                    Some(Self::handle_obc1_write as WriteHandler),
+                    addr_range.banks,
+                    addr_range.addrs,
+                    0,
+                    1 << 24,
+                    0,
+                );
+            }
+        }
+    }
+
+    fn handle_obc1_read(&mut self, addr: u32) -> u8 {
+        match &self.coprocessor {
+            Some(super::Coprocessor::Obc1(obc1)) => obc1.read(&self.ram, addr as u16 & 0x1FFF),
+            _ => unreachable!(),
+        }
+    }
+
+    fn handle_obc1_write(&mut self, addr: u32, value: u8) {
+        match &mut self.coprocessor {
+            Some(super::Coprocessor::Obc1(obc1)) => {
+                self.ram_modified = true;
+                obc1.write(&mut self.ram, addr as u16 & 0x1FFF, value);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::utils::BoxedByteSlice;
+use core::fmt::{self, Display};
+use std::error::Error as StdError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Ips,
+    Ups,
+    Bps,
+}
+
+impl Format {
+    /// Detects the format of a patch from its magic bytes.
+    pub fn detect(patch: &[u8]) -> Option<Self> {
+        if patch.starts_with(b"PATCH") {
+            Some(Format::Ips)
+        } else if patch.starts_with(b"UPS1") {
+            Some(Format::Ups)
+        } else if patch.starts_with(b"BPS1") {
+            Some(Format::Bps)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    UnknownFormat,
+    /// The patch ended before all of its records were read, or referenced data outside of the
+    /// source or target files.
+    Malformed,
+    PatchChecksumMismatch,
+    /// The patch wasn't made for this ROM.
+    SourceMismatch,
+    TargetMismatch,
+}
+
+impl StdError for Error {}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::UnknownFormat => "Unknown patch format",
+            Self::Malformed => "Malformed patch",
+            Self::PatchChecksumMismatch => "Patch checksum mismatch, the patch file is corrupted",
+            Self::SourceMismatch => "Source checksum mismatch, the patch is for a different ROM",
+            Self::TargetMismatch => "Target checksum mismatch, the patched ROM is invalid",
+        })
+    }
+}
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut value = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            value = if value & 1 != 0 {
+                value >> 1 ^ 0xEDB8_8320
+            } else {
+                value >> 1
+            };
+            j += 1;
+        }
+        table[i] = value;
+        i += 1;
+    }
+    table
+}
+
+static CRC32_TABLE: [u32; 256] = crc32_table();
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, byte| {
+        CRC32_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ crc >> 8
+    })
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, Error> {
+        let result = *self.data.get(self.pos).ok_or(Error::Malformed)?;
+        self.pos += 1;
+        Ok(result)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let result = self
+            .data
+            .get(self.pos..self.pos.checked_add(len).ok_or(Error::Malformed)?)
+            .ok_or(Error::Malformed)?;
+        self.pos += len;
+        Ok(result)
+    }
+
+    fn be(&mut self, len: usize) -> Result<usize, Error> {
+        Ok(self
+            .bytes(len)?
+            .iter()
+            .fold(0, |acc, byte| acc << 8 | *byte as usize))
+    }
+
+    /// Reads a variable-length number, as used by UPS and BPS patches.
+    fn var_int(&mut self) -> Result<usize, Error> {
+        let mut result = 0_usize;
+        let mut shift = 1_usize;
+        loop {
+            let byte = self.byte()?;
+            result = (byte as usize & 0x7F)
+                .checked_mul(shift)
+                .and_then(|value| result.checked_add(value))
+                .ok_or(Error::Malformed)?;
+            if byte & 0x80 != 0 {
+                return Ok(result);
+            }
+            shift = shift.checked_shl(7).ok_or(Error::Malformed)?;
+            result = result.checked_add(shift).ok_or(Error::Malformed)?;
+        }
+    }
+}
+
+fn to_boxed_byte_slice(data: &[u8]) -> BoxedByteSlice {
+    let mut result = BoxedByteSlice::new_zeroed(data.len());
+    result.copy_from_slice(data);
+    result
+}
+
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<BoxedByteSlice, Error> {
+    let mut reader = Reader {
+        data: patch,
+        pos: 5,
+    };
+    let mut target = rom.to_vec();
+    loop {
+        let offset = reader.be(3)?;
+        if offset == 0x454F46 {
+            // "EOF", optionally followed by the size to truncate the target to
+            if let Ok(len) = reader.be(3) {
+                target.truncate(len);
+            }
+            break;
+        }
+        let (len, data) = match reader.be(2)? {
+            // RLE records
+            0 => (reader.be(2)?, None),
+            len => (len, Some(reader.bytes(len)?)),
+        };
+        if target.len() < offset + len {
+            target.resize(offset + len, 0);
+        }
+        let target_slice = &mut target[offset..offset + len];
+        match data {
+            Some(data) => target_slice.copy_from_slice(data),
+            None => target_slice.fill(reader.byte()?),
+        }
+    }
+    Ok(to_boxed_byte_slice(&target))
+}
+
+/// Checks the CRC32 footer shared by UPS and BPS patches, returning the expected target CRC32.
+fn check_footer(rom: &[u8], patch: &[u8]) -> Result<u32, Error> {
+    if patch.len() < 16 {
+        return Err(Error::Malformed);
+    }
+    let footer = &patch[patch.len() - 12..];
+    let read_crc = |i: usize| u32::from_le_bytes(footer[i..i + 4].try_into().unwrap());
+    if crc32(&patch[..patch.len() - 4]) != read_crc(8) {
+        return Err(Error::PatchChecksumMismatch);
+    }
+    if crc32(rom) != read_crc(0) {
+        return Err(Error::SourceMismatch);
+    }
+    Ok(read_crc(4))
+}
+
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<BoxedByteSlice, Error> {
+    let target_crc = check_footer(rom, patch)?;
+    let mut reader = Reader {
+        data: &patch[..patch.len() - 12],
+        pos: 4,
+    };
+    if reader.var_int()? != rom.len() {
+        return Err(Error::SourceMismatch);
+    }
+    let target_len = reader.var_int()?;
+    let mut target = BoxedByteSlice::new_zeroed(target_len);
+    let copied_len = rom.len().min(target_len);
+    target[..copied_len].copy_from_slice(&rom[..copied_len]);
+
+    // Each record XORs the source with the patch data starting at a relative offset, until a zero
+    // byte (which is also applied)
+    let mut offset = 0_usize;
+    while reader.pos < reader.data.len() {
+        offset = offset
+            .checked_add(reader.var_int()?)
+            .ok_or(Error::Malformed)?;
+        loop {
+            let byte = reader.byte()?;
+            if let Some(target_byte) = target.get_mut(offset) {
+                *target_byte = rom.get(offset).copied().unwrap_or(0) ^ byte;
+            }
+            offset += 1;
+            if byte == 0 {
+                break;
+            }
+        }
+    }
+
+    if crc32(&target[..]) != target_crc {
+        return Err(Error::TargetMismatch);
+    }
+    Ok(target)
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<BoxedByteSlice, Error> {
+    let target_crc = check_footer(rom, patch)?;
+    let mut reader = Reader {
+        data: &patch[..patch.len() - 12],
+        pos: 4,
+    };
+    if reader.var_int()? != rom.len() {
+        return Err(Error::SourceMismatch);
+    }
+    let target_len = reader.var_int()?;
+    let metadata_len = reader.var_int()?;
+    reader.bytes(metadata_len)?;
+
+    let mut target = BoxedByteSlice::new_zeroed(target_len);
+    let mut output_offset = 0_usize;
+    let mut source_offset = 0_usize;
+    let mut target_offset = 0_usize;
+
+    let relative_offset = |reader: &mut Reader, offset: usize| -> Result<usize, Error> {
+        let data = reader.var_int()?;
+        if data & 1 != 0 {
+            offset.checked_sub(data >> 1)
+        } else {
+            offset.checked_add(data >> 1)
+        }
+        .ok_or(Error::Malformed)
+    };
+
+    while reader.pos < reader.data.len() {
+        let data = reader.var_int()?;
+        let len = (data >> 2) + 1;
+        let output_end = output_offset
+            .checked_add(len)
+            .filter(|end| *end <= target_len)
+            .ok_or(Error::Malformed)?;
+        match data & 3 {
+            // Source read
+            0 => target[output_offset..output_end]
+                .copy_from_slice(rom.get(output_offset..output_end).ok_or(Error::Malformed)?),
+            // Target read
+            1 => target[output_offset..output_end].copy_from_slice(reader.bytes(len)?),
+            // Source copy
+            2 => {
+                source_offset = relative_offset(&mut reader, source_offset)?;
+                let source = source_offset
+                    .checked_add(len)
+                    .and_then(|source_end| rom.get(source_offset..source_end))
+                    .ok_or(Error::Malformed)?;
+                target[output_offset..output_end].copy_from_slice(source);
+                source_offset += len;
+            }
+            // Target copy, which can overlap with the bytes being written
+            _ => {
+                target_offset = relative_offset(&mut reader, target_offset)?;
+                if target_offset >= output_offset {
+                    return Err(Error::Malformed);
+                }
+                for i in output_offset..output_end {
+                    target[i] = target[target_offset];
+                    target_offset += 1;
+                }
+            }
+        }
+        output_offset = output_end;
+    }
+
+    if crc32(&target[..]) != target_crc {
+        return Err(Error::TargetMismatch);
+    }
+    Ok(target)
+}
+
+/// Applies an IPS, UPS or BPS patch (detected automatically) to the given ROM, returning the
+/// patched one.
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<BoxedByteSlice, Error> {
+    match Format::detect(patch).ok_or(Error::UnknownFormat)? {
+        Format::Ips => apply_ips(rom, patch),
+        Format::Ups => apply_ups(rom, patch),
+        Format::Bps => apply_bps(rom, patch),
+    }
+}
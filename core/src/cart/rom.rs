@@ -0,0 +1,198 @@
+use super::info::header::BaseMapMode;
+use crate::utils::{BoxedByteSlice, ByteSlice};
+
+/// The 512-byte header prepended to ROM images by some copiers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopierHeader {
+    /// Super Magicom, also used as a generic header by most tools.
+    Smc,
+    /// Super Wild Card.
+    Swc,
+    /// Pro Fighter.
+    Fig,
+}
+
+const COPIER_HEADER_LEN: usize = 0x200;
+
+/// A ROM image, with its copier header removed and its contents de-interleaved.
+pub struct Image {
+    pub rom: BoxedByteSlice,
+    pub copier_header: Option<CopierHeader>,
+    pub deinterleaved: bool,
+}
+
+fn detect_copier_header(data: &[u8]) -> Option<CopierHeader> {
+    // Dumps are always a multiple of 1 KiB in size, so a 512-byte remainder can only be a header
+    if data.len() % 0x400 != COPIER_HEADER_LEN {
+        return None;
+    }
+    Some(if data[8..11] == [0xAA, 0xBB, 0x04] {
+        CopierHeader::Swc
+    } else if matches!(
+        (data[4], data[5]),
+        (0x77, 0x83) | (0x00, 0x80) | (0x47, 0x83) | (0x11, 0x02) | (0xDD, 0x82) | (0xF7, 0x83)
+    ) {
+        CopierHeader::Fig
+    } else {
+        CopierHeader::Smc
+    })
+}
+
+/// Computes the checksum stored in the ROM header, the 16-bit sum of all bytes in the ROM; for
+/// sizes that aren't a power of two, the remainder is mirrored up to the next one.
+pub fn checksum(rom: &[u8]) -> u16 {
+    fn sum(bytes: &[u8]) -> u32 {
+        bytes
+            .iter()
+            .fold(0_u32, |acc, byte| acc.wrapping_add(*byte as u32))
+    }
+
+    if rom.is_empty() {
+        return 0;
+    }
+    let base_len = 1 << (usize::BITS - 1 - rom.len().leading_zeros());
+    let (base, rest) = rom.split_at(base_len);
+    let mut result = sum(base);
+    if !rest.is_empty() {
+        result = result.wrapping_add(sum(rest).wrapping_mul((base_len / rest.len()) as u32));
+    }
+    result as u16
+}
+
+/// The offset of the header for each base map mode, if it's present in a ROM of the given size.
+fn header_offset(rom_len: usize, base_map_mode: BaseMapMode) -> Option<usize> {
+    let offset = match base_map_mode {
+        BaseMapMode::LoRom => 0x7FB0,
+        BaseMapMode::HiRom => 0xFFB0,
+        BaseMapMode::ExHiRom => 0x40_FFB0,
+    };
+    if offset + 0x50 <= rom_len {
+        Some(offset)
+    } else {
+        None
+    }
+}
+
+/// Rates how likely it is for the header at the location for the given map mode to be valid,
+/// based on its checksum and complement, its reset vector and its map mode byte; returns `None` if
+/// the ROM is too small to contain it.
+pub fn score_header(rom: ByteSlice, base_map_mode: BaseMapMode) -> Option<i32> {
+    let offset = header_offset(rom.len(), base_map_mode)?;
+    let header = &rom[offset..offset + 0x50];
+    let mut score = 0;
+
+    let complement = u16::from_le_bytes([header[0x2C], header[0x2D]]);
+    let header_checksum = u16::from_le_bytes([header[0x2E], header[0x2F]]);
+    if complement ^ header_checksum == 0xFFFF {
+        score += 4;
+        if header_checksum == checksum(&rom[..]) {
+            score += 8;
+        }
+    }
+
+    // Games can only start executing from ROM, which is always mapped to $8000-$FFFF in bank 0
+    let reset_vector = u16::from_le_bytes([header[0x4C], header[0x4D]]);
+    if reset_vector >= 0x8000 {
+        score += 4;
+    } else {
+        score -= 8;
+    }
+
+    let map_mode = header[0x25];
+    let map_mode_matches = match base_map_mode {
+        BaseMapMode::LoRom => matches!(map_mode & 0xF, 0 | 2 | 3),
+        BaseMapMode::HiRom => matches!(map_mode & 0xF, 1 | 0xA),
+        BaseMapMode::ExHiRom => map_mode & 0xF == 5,
+    };
+    if map_mode_matches {
+        score += 4;
+        if map_mode & 0xE0 == 0x20 {
+            score += 2;
+        }
+    } else {
+        score -= 4;
+    }
+
+    Some(score)
+}
+
+/// Returns the base map modes whose header locations are present in the ROM, from the most to the
+/// least likely one to contain the actual header, along with the header's offset.
+pub fn header_candidates(rom: ByteSlice) -> Vec<(BaseMapMode, usize)> {
+    let mut candidates = [BaseMapMode::ExHiRom, BaseMapMode::HiRom, BaseMapMode::LoRom]
+        .into_iter()
+        .filter_map(|base_map_mode| {
+            Some((
+                base_map_mode,
+                header_offset(rom.len(), base_map_mode)?,
+                score_header(rom, base_map_mode)?,
+            ))
+        })
+        .collect::<Vec<_>>();
+    // The sort is stable, so ties are resolved in favor of ExHiROM, then HiROM
+    candidates.sort_by_key(|(_, _, score)| -score);
+    candidates
+        .into_iter()
+        .map(|(base_map_mode, offset, _)| (base_map_mode, offset))
+        .collect()
+}
+
+/// Reorders an interleaved HiROM image, in which the upper 32 KiB halves of all 64 KiB banks are
+/// stored before all lower halves.
+fn deinterleave(rom: &[u8]) -> BoxedByteSlice {
+    let banks = rom.len() >> 16;
+    let mut result = BoxedByteSlice::new_zeroed(rom.len());
+    for i in 0..banks {
+        let (lower, upper) = result[i << 16..(i + 1) << 16].split_at_mut(0x8000);
+        lower.copy_from_slice(&rom[(banks + i) << 15..(banks + i + 1) << 15]);
+        upper.copy_from_slice(&rom[i << 15..(i + 1) << 15]);
+    }
+    result
+}
+
+/// Detects whether the image is an interleaved HiROM dump, which places the HiROM header at the
+/// LoROM header's location (while a regular HiROM dump with a copy of its header there would still
+/// have a more valid-looking one at the HiROM location).
+fn is_interleaved(rom: ByteSlice) -> bool {
+    if rom.len() < 0x2_0000 || rom.len() & 0xFFFF != 0 {
+        return false;
+    }
+    let (lorom_score, hirom_score) = match (
+        score_header(rom, BaseMapMode::LoRom),
+        score_header(rom, BaseMapMode::HiRom),
+    ) {
+        (Some(lorom_score), Some(hirom_score)) => (lorom_score, hirom_score),
+        _ => return false,
+    };
+    matches!(rom[0x7FD5] & 0xF, 1 | 0xA) && hirom_score < lorom_score && {
+        let deinterleaved = deinterleave(&rom[..]);
+        matches!(
+            score_header(deinterleaved.as_byte_slice(), BaseMapMode::HiRom),
+            Some(score) if score > lorom_score
+        )
+    }
+}
+
+/// Loads a ROM image as stored in a file, removing its copier header if present and
+/// de-interleaving it if needed.
+pub fn load(data: &[u8]) -> Image {
+    let copier_header = detect_copier_header(data);
+    let data = if copier_header.is_some() {
+        &data[COPIER_HEADER_LEN..]
+    } else {
+        data
+    };
+    let deinterleaved = is_interleaved(ByteSlice::new(data));
+    let rom = if deinterleaved {
+        deinterleave(data)
+    } else {
+        let mut rom = BoxedByteSlice::new_zeroed(data.len());
+        rom.copy_from_slice(data);
+        rom
+    };
+    Image {
+        rom,
+        copier_header,
+        deinterleaved,
+    }
+}
@@ -0,0 +1,1161 @@
+use super::{
+    map::{mirror, Map, ReadHandler, WriteHandler},
+    Cart,
+};
+use crate::{
+    cpu::{
+        interpreter::{self, Core},
+        regs::Regs,
+    },
+    savestate::{LoadError, Reader, Writer},
+    schedule::Timestamp,
+    utils::BoxedByteSlice,
+    Model,
+};
+
+const IRAM_SIZE: usize = 0x800;
+const VERSION: u8 = 0x23;
+
+// CCNT bits
+const CONTROL_WAIT: u8 = 1 << 6;
+const CONTROL_RESET: u8 = 1 << 5;
+
+// SFR/SIE/SIC bits
+const S_CPU_IRQ_SA1: u8 = 1 << 7;
+const S_CPU_IRQ_CHAR_CONV_DMA: u8 = 1 << 5;
+const S_CPU_IRQ_MASK: u8 = S_CPU_IRQ_SA1 | S_CPU_IRQ_CHAR_CONV_DMA;
+
+// CFR/CIE/CIC bits
+const SA1_IRQ_S_CPU: u8 = 1 << 7;
+const SA1_IRQ_TIMER: u8 = 1 << 6;
+const SA1_IRQ_DMA: u8 = 1 << 5;
+const SA1_NMI_S_CPU: u8 = 1 << 4;
+const SA1_IRQ_MASK: u8 = SA1_IRQ_S_CPU | SA1_IRQ_TIMER | SA1_IRQ_DMA;
+
+// DCNT bits
+const DMA_ENABLED: u8 = 1 << 7;
+const DMA_CHAR_CONV: u8 = 1 << 5;
+const DMA_CHAR_CONV_TYPE_1: u8 = 1 << 4;
+const DMA_DST_BW_RAM: u8 = 1 << 2;
+
+// TMC bits
+const TIMER_LINEAR: u8 = 1 << 7;
+const TIMER_V_ENABLED: u8 = 1 << 1;
+const TIMER_H_ENABLED: u8 = 1 << 0;
+
+/// The state of the SA-1, a 65C816 core running at 10.74 MHz alongside the main CPU, with 2 KiB of
+/// internal RAM, a memory mapper for the ROM and BW-RAM (the cartridge's save RAM), a DMA unit
+/// able to convert bitmaps to SNES tiles, an arithmetic unit and timers.
+///
+/// It's kept in sync with the main CPU by running it up to the current time before every access
+/// to its registers and shared memory, and periodically through a schedule event.
+#[derive(Clone)]
+pub struct Sa1 {
+    #[cfg(feature = "log")]
+    logger: slog::Logger,
+    regs: Regs,
+    cur_time: Timestamp,
+    mdr: u8,
+    stopped: bool,
+    waiting_for_exception: bool,
+    iram: Box<[u8; IRAM_SIZE]>,
+    scanlines: u16,
+
+    control: u8,
+    s_cpu_irqs_enabled: u8,
+    s_cpu_irq_flags: u8,
+    sa1_control: u8,
+    sa1_irqs_enabled: u8,
+    sa1_irq_flags: u8,
+    nmi_pending: bool,
+    reset_vector: u16,
+    nmi_vector: u16,
+    irq_vector: u16,
+    s_cpu_nmi_vector: u16,
+    s_cpu_irq_vector: u16,
+
+    timer_control: u8,
+    timer_h_target: u16,
+    timer_v_target: u16,
+    timer_base_time: Timestamp,
+    timer_irq_time: Option<Timestamp>,
+    latched_h_counter: u16,
+    latched_v_counter: u16,
+
+    rom_banks: [u8; 4],
+    s_cpu_bw_ram_bank: u8,
+    sa1_bw_ram_bank: u8,
+    s_cpu_bw_ram_writable: bool,
+    sa1_bw_ram_writable: bool,
+    bw_ram_protected_size_shift: u8,
+    s_cpu_iram_writable_mask: u8,
+    sa1_iram_writable_mask: u8,
+    bitmap_2bpp: bool,
+
+    dma_control: u8,
+    char_conv_control: u8,
+    dma_src_addr: u32,
+    dma_dst_addr: u32,
+    dma_len: u16,
+    char_conv_1_active: bool,
+    char_conv_2_line: u8,
+    bitmap_regs: [u8; 16],
+
+    math_control: u8,
+    math_a: u16,
+    math_b: u16,
+    math_result: u64,
+    math_overflow: bool,
+
+    var_len_control: u8,
+    var_len_addr: u32,
+    var_len_bit: u8,
+}
+
+impl Sa1 {
+    pub(super) fn new() -> Self {
+        Sa1 {
+            #[cfg(feature = "log")]
+            logger: slog::Logger::root(slog::Discard, slog::o!()),
+            regs: Regs::new(),
+            cur_time: 0,
+            mdr: 0,
+            stopped: false,
+            waiting_for_exception: false,
+            iram: Box::new([0; IRAM_SIZE]),
+            scanlines: 262,
+
+            control: CONTROL_RESET,
+            s_cpu_irqs_enabled: 0,
+            s_cpu_irq_flags: 0,
+            sa1_control: 0,
+            sa1_irqs_enabled: 0,
+            sa1_irq_flags: 0,
+            nmi_pending: false,
+            reset_vector: 0,
+            nmi_vector: 0,
+            irq_vector: 0,
+            s_cpu_nmi_vector: 0,
+            s_cpu_irq_vector: 0,
+
+            timer_control: 0,
+            timer_h_target: 0,
+            timer_v_target: 0,
+            timer_base_time: 0,
+            timer_irq_time: None,
+            latched_h_counter: 0,
+            latched_v_counter: 0,
+
+            rom_banks: [0, 1, 2, 3],
+            s_cpu_bw_ram_bank: 0,
+            sa1_bw_ram_bank: 0,
+            s_cpu_bw_ram_writable: false,
+            sa1_bw_ram_writable: false,
+            bw_ram_protected_size_shift: 0,
+            s_cpu_iram_writable_mask: 0,
+            sa1_iram_writable_mask: 0,
+            bitmap_2bpp: false,
+
+            dma_control: 0,
+            char_conv_control: 0,
+            dma_src_addr: 0,
+            dma_dst_addr: 0,
+            dma_len: 0,
+            char_conv_1_active: false,
+            char_conv_2_line: 0,
+            bitmap_regs: [0; 16],
+
+            math_control: 0,
+            math_a: 0,
+            math_b: 0,
+            math_result: 0,
+            math_overflow: false,
+
+            var_len_control: 0,
+            var_len_addr: 0,
+            var_len_bit: 0,
+        }
+    }
+
+    pub(super) fn setup(
+        &mut self,
+        model: Model,
+        time: Timestamp,
+        #[cfg(feature = "log")] logger: slog::Logger,
+    ) {
+        #[cfg(feature = "log")]
+        {
+            self.logger = logger;
+        }
+        self.scanlines = match model {
+            Model::Ntsc => 262,
+            Model::Pal => 312,
+        };
+        self.cur_time = time;
+        self.timer_base_time = time;
+    }
+
+    pub(super) fn soft_reset(&mut self) {
+        *self = Sa1 {
+            #[cfg(feature = "log")]
+            logger: self.logger.clone(),
+            cur_time: self.cur_time,
+            iram: self.iram.clone(),
+            scanlines: self.scanlines,
+            timer_base_time: self.cur_time,
+            ..Sa1::new()
+        };
+    }
+
+    #[inline]
+    pub fn regs(&self) -> &Regs {
+        &self.regs
+    }
+
+    #[inline]
+    pub fn cur_time(&self) -> Timestamp {
+        self.cur_time
+    }
+
+    #[inline]
+    pub fn iram(&self) -> &[u8; IRAM_SIZE] {
+        &self.iram
+    }
+
+    /// Returns whether the SA-1 is requesting an IRQ from the main CPU.
+    #[inline]
+    pub fn s_cpu_irq_requested(&self) -> bool {
+        self.s_cpu_irq_flags & self.s_cpu_irqs_enabled != 0
+    }
+
+    #[inline]
+    fn irq_requested(&self) -> bool {
+        self.sa1_irq_flags & self.sa1_irqs_enabled & SA1_IRQ_MASK != 0
+    }
+
+    fn reset_cpu(&mut self) {
+        self.stopped = false;
+        self.waiting_for_exception = false;
+        self.regs.set_psw(
+            self.regs
+                .psw()
+                .with_a_is_8_bit(true)
+                .with_index_regs_are_8_bit(true)
+                .with_decimal_mode(false)
+                .with_irqs_disabled(true),
+        );
+        self.regs.set_emulation_mode::<true>(true);
+        self.regs.direct_page_offset = 0;
+        self.regs.sp = 0x1FF;
+        self.regs.set_data_bank(0);
+        self.regs.set_code_bank(0);
+        self.regs.pc = self.reset_vector;
+    }
+
+    fn rom_offset(&self, addr: u32) -> u32 {
+        let (bank, offset) = if addr & 0x40_0000 == 0 {
+            // LoROM-style area in banks 00-3F and 80-BF, where each 1 MiB block can either be fixed
+            // or follow its bank register
+            let block = (addr >> 21 & 1 | addr >> 22 & 2) as usize;
+            let bank_reg = self.rom_banks[block];
+            let bank = if bank_reg & 0x80 != 0 {
+                bank_reg & 7
+            } else {
+                block as u8
+            };
+            (bank, (addr >> 1 & 0xF_8000) | (addr & 0x7FFF))
+        } else {
+            // HiROM-style area in banks C0-FF, always following the bank registers
+            (
+                self.rom_banks[(addr >> 20 & 3) as usize] & 7,
+                addr & 0xF_FFFF,
+            )
+        };
+        (bank as u32) << 20 | offset
+    }
+
+    // Timer positions are measured in master cycles since the counters were last reset, with lines
+    // of 1364 cycles in H/V mode (matching the PPU's) and of 2048 cycles in linear mode.
+    fn timer_dimensions(&self) -> (Timestamp, Timestamp) {
+        if self.timer_control & TIMER_LINEAR != 0 {
+            (2048, 512)
+        } else {
+            (1364, self.scanlines as Timestamp)
+        }
+    }
+
+    fn timer_counters(&self, time: Timestamp) -> (u16, u16) {
+        let (line_len, lines) = self.timer_dimensions();
+        let pos = (time - self.timer_base_time) % (line_len * lines);
+        ((pos % line_len) as u16, (pos / line_len) as u16)
+    }
+
+    fn next_timer_irq_time(&self, after: Timestamp) -> Option<Timestamp> {
+        let (line_len, lines) = self.timer_dimensions();
+        let h_target = self.timer_h_target as Timestamp * 4;
+        let v_target = self.timer_v_target as Timestamp;
+        let (target, interval) = match (
+            self.timer_control & TIMER_H_ENABLED != 0,
+            self.timer_control & TIMER_V_ENABLED != 0,
+        ) {
+            (false, false) => return None,
+            (true, false) => (h_target, line_len),
+            (false, true) => (v_target * line_len, line_len * lines),
+            (true, true) => (v_target * line_len + h_target, line_len * lines),
+        };
+        if h_target >= line_len || v_target >= lines {
+            return None;
+        }
+        let pos = (after - self.timer_base_time) % interval;
+        Some(after + (target + interval - pos - 1) % interval + 1)
+    }
+
+    fn update_timer_irq_time(&mut self) {
+        self.timer_irq_time = self.next_timer_irq_time(self.cur_time);
+    }
+
+    fn poll_timer(&mut self) {
+        if let Some(time) = self.timer_irq_time {
+            if time <= self.cur_time {
+                self.sa1_irq_flags |= SA1_IRQ_TIMER;
+                self.timer_irq_time = self.next_timer_irq_time(self.cur_time);
+            }
+        }
+    }
+
+    fn start_math(&mut self) {
+        if self.math_control & 2 != 0 {
+            // Cumulative sum, 40 bits wide
+            let product = self.math_a as i16 as i64 * self.math_b as i16 as i64;
+            self.math_result = self.math_result.wrapping_add(product as u64);
+            self.math_overflow = self.math_result >> 40 != 0;
+            self.math_result &= (1 << 40) - 1;
+            self.math_b = 0;
+        } else if self.math_control & 1 == 0 {
+            // Signed multiplication
+            self.math_result =
+                (self.math_a as i16 as i32 * self.math_b as i16 as i32) as u32 as u64;
+            self.math_b = 0;
+        } else {
+            // Signed dividend, unsigned divisor
+            if self.math_b == 0 {
+                self.math_result = 0;
+            } else {
+                let dividend = self.math_a as i16 as i32;
+                let divisor = self.math_b as i32;
+                let remainder = dividend.rem_euclid(divisor);
+                let quotient = (dividend - remainder) / divisor;
+                self.math_result =
+                    ((remainder as u16 as u32) << 16 | quotient as u16 as u32) as u64;
+            }
+            self.math_a = 0;
+            self.math_b = 0;
+        }
+    }
+
+    fn advance_var_len_addr(&mut self) {
+        let bits = match self.var_len_control & 0xF {
+            0 => 16,
+            bits => bits,
+        };
+        self.var_len_bit += bits;
+        self.var_len_addr = (self.var_len_addr + (self.var_len_bit >> 3) as u32) & 0xFF_FFFF;
+        self.var_len_bit &= 7;
+    }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        self.regs.save_state(writer);
+        writer.write(&self.cur_time);
+        writer.write(&self.mdr);
+        writer.write(&self.stopped);
+        writer.write(&self.waiting_for_exception);
+        writer.write_bytes(&self.iram[..]);
+
+        writer.write(&self.control);
+        writer.write(&self.s_cpu_irqs_enabled);
+        writer.write(&self.s_cpu_irq_flags);
+        writer.write(&self.sa1_control);
+        writer.write(&self.sa1_irqs_enabled);
+        writer.write(&self.sa1_irq_flags);
+        writer.write(&self.nmi_pending);
+        writer.write(&self.reset_vector);
+        writer.write(&self.nmi_vector);
+        writer.write(&self.irq_vector);
+        writer.write(&self.s_cpu_nmi_vector);
+        writer.write(&self.s_cpu_irq_vector);
+
+        writer.write(&self.timer_control);
+        writer.write(&self.timer_h_target);
+        writer.write(&self.timer_v_target);
+        writer.write(&self.timer_base_time);
+        writer.write(&self.timer_irq_time);
+        writer.write(&self.latched_h_counter);
+        writer.write(&self.latched_v_counter);
+
+        writer.write(&self.rom_banks);
+        writer.write(&self.s_cpu_bw_ram_bank);
+        writer.write(&self.sa1_bw_ram_bank);
+        writer.write(&self.s_cpu_bw_ram_writable);
+        writer.write(&self.sa1_bw_ram_writable);
+        writer.write(&self.bw_ram_protected_size_shift);
+        writer.write(&self.s_cpu_iram_writable_mask);
+        writer.write(&self.sa1_iram_writable_mask);
+        writer.write(&self.bitmap_2bpp);
+
+        writer.write(&self.dma_control);
+        writer.write(&self.char_conv_control);
+        writer.write(&self.dma_src_addr);
+        writer.write(&self.dma_dst_addr);
+        writer.write(&self.dma_len);
+        writer.write(&self.char_conv_1_active);
+        writer.write(&self.char_conv_2_line);
+        writer.write(&self.bitmap_regs);
+
+        writer.write(&self.math_control);
+        writer.write(&self.math_a);
+        writer.write(&self.math_b);
+        writer.write(&self.math_result);
+        writer.write(&self.math_overflow);
+
+        writer.write(&self.var_len_control);
+        writer.write(&self.var_len_addr);
+        writer.write(&self.var_len_bit);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.regs.load_state(reader)?;
+        self.cur_time = reader.read()?;
+        self.mdr = reader.read()?;
+        self.stopped = reader.read()?;
+        self.waiting_for_exception = reader.read()?;
+        reader.read_bytes(&mut self.iram[..])?;
+
+        self.control = reader.read()?;
+        self.s_cpu_irqs_enabled = reader.read()?;
+        self.s_cpu_irq_flags = reader.read()?;
+        self.sa1_control = reader.read()?;
+        self.sa1_irqs_enabled = reader.read()?;
+        self.sa1_irq_flags = reader.read()?;
+        self.nmi_pending = reader.read()?;
+        self.reset_vector = reader.read()?;
+        self.nmi_vector = reader.read()?;
+        self.irq_vector = reader.read()?;
+        self.s_cpu_nmi_vector = reader.read()?;
+        self.s_cpu_irq_vector = reader.read()?;
+
+        self.timer_control = reader.read()?;
+        self.timer_h_target = reader.read()?;
+        self.timer_v_target = reader.read()?;
+        self.timer_base_time = reader.read()?;
+        self.timer_irq_time = reader.read()?;
+        self.latched_h_counter = reader.read()?;
+        self.latched_v_counter = reader.read()?;
+
+        self.rom_banks = reader.read()?;
+        self.s_cpu_bw_ram_bank = reader.read()?;
+        self.sa1_bw_ram_bank = reader.read()?;
+        self.s_cpu_bw_ram_writable = reader.read()?;
+        self.sa1_bw_ram_writable = reader.read()?;
+        self.bw_ram_protected_size_shift = reader.read()?;
+        self.s_cpu_iram_writable_mask = reader.read()?;
+        self.sa1_iram_writable_mask = reader.read()?;
+        self.bitmap_2bpp = reader.read()?;
+
+        self.dma_control = reader.read()?;
+        self.char_conv_control = reader.read()?;
+        self.dma_src_addr = reader.read()?;
+        self.dma_dst_addr = reader.read()?;
+        self.dma_len = reader.read()?;
+        self.char_conv_1_active = reader.read()?;
+        self.char_conv_2_line = reader.read()?;
+        self.bitmap_regs = reader.read()?;
+
+        self.math_control = reader.read()?;
+        self.math_a = reader.read()?;
+        self.math_b = reader.read()?;
+        self.math_result = reader.read()?;
+        self.math_overflow = reader.read()?;
+
+        self.var_len_control = reader.read()?;
+        self.var_len_addr = reader.read()?;
+        self.var_len_bit = reader.read()?;
+
+        if self.timer_base_time > self.cur_time || self.char_conv_2_line > 15 {
+            return Err(LoadError::InvalidData);
+        }
+        Ok(())
+    }
+}
+
+/// The SA-1 together with the cartridge memories it has access to.
+pub(super) struct Context<'a> {
+    pub sa1: &'a mut Sa1,
+    pub rom: &'a BoxedByteSlice,
+    pub bw_ram: &'a mut BoxedByteSlice,
+    pub ram_modified: &'a mut bool,
+}
+
+impl<'a> Core for Context<'a> {
+    #[cfg(feature = "log")]
+    #[inline]
+    fn logger(&self) -> &slog::Logger {
+        &self.sa1.logger
+    }
+
+    #[inline]
+    fn regs(&self) -> &Regs {
+        &self.sa1.regs
+    }
+
+    #[inline]
+    fn regs_mut(&mut self) -> &mut Regs {
+        &mut self.sa1.regs
+    }
+
+    #[inline]
+    fn add_io_cycles(&mut self, cycles: u8) {
+        self.sa1.cur_time += cycles as Timestamp * 2;
+    }
+
+    #[inline]
+    fn read_8(&mut self, addr: u32) -> u8 {
+        let cycles = Self::access_cycles(addr);
+        let result = self.read_sa1(addr);
+        self.sa1.cur_time += cycles;
+        result
+    }
+
+    #[inline]
+    fn write_8(&mut self, addr: u32, value: u8) {
+        let cycles = Self::access_cycles(addr);
+        self.write_sa1(addr, value);
+        self.sa1.cur_time += cycles;
+    }
+
+    // IRQs are masked by checking the I flag directly before every instruction.
+    #[inline]
+    fn set_irqs_enabled(&mut self, _value: bool) {}
+
+    fn wait_for_exception(&mut self) {
+        self.sa1.waiting_for_exception = true;
+    }
+
+    fn stop(&mut self) {
+        self.sa1.stopped = true;
+    }
+}
+
+impl<'a> Context<'a> {
+    pub(super) fn run_until(&mut self, end_time: Timestamp) {
+        while self.sa1.cur_time < end_time {
+            self.sa1.poll_timer();
+            if self.sa1.control & (CONTROL_RESET | CONTROL_WAIT) != 0 || self.sa1.stopped {
+                self.sa1.cur_time = end_time;
+                break;
+            }
+            if self.sa1.nmi_pending {
+                self.sa1.nmi_pending = false;
+                self.sa1.waiting_for_exception = false;
+                let nmi_vector = self.sa1.nmi_vector;
+                interpreter::handle_interrupt(self, nmi_vector);
+            } else if self.sa1.irq_requested() {
+                self.sa1.waiting_for_exception = false;
+                if !self.sa1.regs.psw().irqs_disabled() {
+                    let irq_vector = self.sa1.irq_vector;
+                    interpreter::handle_interrupt(self, irq_vector);
+                }
+            }
+            if self.sa1.waiting_for_exception {
+                self.sa1.cur_time = self
+                    .sa1
+                    .timer_irq_time
+                    .map_or(end_time, |time| time.min(end_time));
+                continue;
+            }
+            interpreter::run_instr(self);
+        }
+    }
+
+    fn access_cycles(addr: u32) -> Timestamp {
+        match (addr >> 16) as u8 {
+            0x00..=0x3F | 0x80..=0xBF if addr & 0xE000 == 0x6000 => 4,
+            0x40..=0x4F | 0x60..=0x6F => 4,
+            _ => 2,
+        }
+    }
+
+    fn read_rom(&self, addr: u32) -> u8 {
+        self.rom[mirror(self.sa1.rom_offset(addr), self.rom.len() as u32) as usize]
+    }
+
+    fn read_bw_ram(&self, offset: u32) -> Option<u8> {
+        if self.bw_ram.is_empty() {
+            return None;
+        }
+        Some(self.bw_ram[mirror(offset, self.bw_ram.len() as u32) as usize])
+    }
+
+    fn write_bw_ram(&mut self, offset: u32, value: u8, writable: bool) {
+        if self.bw_ram.is_empty() {
+            return;
+        }
+        let offset = mirror(offset, self.bw_ram.len() as u32);
+        if !writable && offset < 0x100 << self.sa1.bw_ram_protected_size_shift {
+            return;
+        }
+        self.bw_ram[offset as usize] = value;
+        *self.ram_modified = true;
+    }
+
+    fn read_bitmap(&self, pixel: u32) -> Option<u8> {
+        Some(if self.sa1.bitmap_2bpp {
+            self.read_bw_ram(pixel >> 2)? >> ((pixel & 3) << 1) & 3
+        } else {
+            self.read_bw_ram(pixel >> 1)? >> ((pixel & 1) << 2) & 0xF
+        })
+    }
+
+    fn write_bitmap(&mut self, pixel: u32, value: u8) {
+        let (offset, shift, mask) = if self.sa1.bitmap_2bpp {
+            (pixel >> 2, (pixel & 3) << 1, 3)
+        } else {
+            (pixel >> 1, (pixel & 1) << 2, 0xF)
+        };
+        if let Some(prev) = self.read_bw_ram(offset) {
+            let value = (prev & !(mask << shift)) | (value & mask) << shift;
+            self.write_bw_ram(offset, value, self.sa1.sa1_bw_ram_writable);
+        }
+    }
+
+    fn write_iram(&mut self, addr: u32, value: u8, writable_mask: u8) {
+        let offset = addr as usize & (IRAM_SIZE - 1);
+        if writable_mask & 1 << (offset >> 8) != 0 {
+            self.sa1.iram[offset] = value;
+        }
+    }
+
+    fn sa1_bw_ram_window_addr(&self, addr: u32) -> (bool, u32) {
+        let bank = self.sa1.sa1_bw_ram_bank;
+        if bank & 0x80 != 0 {
+            (true, ((bank & 0x7F) as u32) << 13 | (addr & 0x1FFF))
+        } else {
+            (false, ((bank & 0x1F) as u32) << 13 | (addr & 0x1FFF))
+        }
+    }
+
+    fn read_sa1(&mut self, addr: u32) -> u8 {
+        let result = match (addr >> 16) as u8 {
+            0x00..=0x3F | 0x80..=0xBF => match addr as u16 {
+                0x0000..=0x07FF | 0x3000..=0x37FF => {
+                    Some(self.sa1.iram[addr as usize & (IRAM_SIZE - 1)])
+                }
+                0x2200..=0x23FF => self.read_sa1_io(addr as u16),
+                0x6000..=0x7FFF => match self.sa1_bw_ram_window_addr(addr) {
+                    (true, pixel) => self.read_bitmap(pixel),
+                    (false, offset) => self.read_bw_ram(offset),
+                },
+                0x8000..=0xFFFF => Some(self.read_rom(addr)),
+                _ => None,
+            },
+            0x40..=0x4F => self.read_bw_ram(addr & 0xF_FFFF),
+            0x60..=0x6F => self.read_bitmap(addr & 0xF_FFFF),
+            0xC0..=0xFF => Some(self.read_rom(addr)),
+            _ => None,
+        };
+        if let Some(result) = result {
+            self.sa1.mdr = result;
+        }
+        self.sa1.mdr
+    }
+
+    fn write_sa1(&mut self, addr: u32, value: u8) {
+        self.sa1.mdr = value;
+        match (addr >> 16) as u8 {
+            0x00..=0x3F | 0x80..=0xBF => match addr as u16 {
+                0x0000..=0x07FF | 0x3000..=0x37FF => {
+                    self.write_iram(addr, value, self.sa1.sa1_iram_writable_mask)
+                }
+                0x2200..=0x23FF => self.write_sa1_io(addr as u16, value),
+                0x6000..=0x7FFF => match self.sa1_bw_ram_window_addr(addr) {
+                    (true, pixel) => self.write_bitmap(pixel, value),
+                    (false, offset) => {
+                        self.write_bw_ram(offset, value, self.sa1.sa1_bw_ram_writable)
+                    }
+                },
+                _ => {}
+            },
+            0x40..=0x4F => self.write_bw_ram(addr & 0xF_FFFF, value, self.sa1.sa1_bw_ram_writable),
+            0x60..=0x6F => self.write_bitmap(addr & 0xF_FFFF, value),
+            _ => {}
+        }
+    }
+
+    fn read_var_len_source(&self, addr: u32) -> u8 {
+        match (addr >> 16) as u8 {
+            0x00..=0x3F | 0x80..=0xBF => match addr as u16 {
+                0x0000..=0x07FF | 0x3000..=0x37FF => self.sa1.iram[addr as usize & (IRAM_SIZE - 1)],
+                0x8000..=0xFFFF => self.read_rom(addr),
+                _ => 0,
+            },
+            0xC0..=0xFF => self.read_rom(addr),
+            _ => 0,
+        }
+    }
+
+    fn read_var_len_data(&self) -> u16 {
+        let addr = self.sa1.var_len_addr;
+        let data = self.read_var_len_source(addr) as u32
+            | (self.read_var_len_source((addr + 1) & 0xFF_FFFF) as u32) << 8
+            | (self.read_var_len_source((addr + 2) & 0xFF_FFFF) as u32) << 16;
+        (data >> self.sa1.var_len_bit) as u16
+    }
+
+    fn read_sa1_io(&mut self, addr: u16) -> Option<u8> {
+        Some(match addr {
+            0x2301 => self.sa1.sa1_irq_flags | (self.sa1.control & 0xF),
+            0x2302 => {
+                let (h, v) = self.sa1.timer_counters(self.sa1.cur_time);
+                self.sa1.latched_h_counter = h >> 2;
+                self.sa1.latched_v_counter = v;
+                self.sa1.latched_h_counter as u8
+            }
+            0x2303 => (self.sa1.latched_h_counter >> 8) as u8,
+            0x2304 => self.sa1.latched_v_counter as u8,
+            0x2305 => (self.sa1.latched_v_counter >> 8) as u8,
+            0x2306..=0x230A => (self.sa1.math_result >> ((addr - 0x2306) << 3)) as u8,
+            0x230B => (self.sa1.math_overflow as u8) << 7,
+            0x230C => self.read_var_len_data() as u8,
+            0x230D => {
+                let result = (self.read_var_len_data() >> 8) as u8;
+                if self.sa1.var_len_control & 0x80 != 0 {
+                    self.sa1.advance_var_len_addr();
+                }
+                result
+            }
+            0x230E => VERSION,
+            _ => return None,
+        })
+    }
+
+    pub(super) fn read_s_cpu_io(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x2300 => self.sa1.s_cpu_irq_flags | (self.sa1.sa1_control & 0x5F),
+            0x230E => VERSION,
+            _ => 0,
+        }
+    }
+
+    fn write_shared_io(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x2231 => {
+                if value & 0x80 != 0 {
+                    self.sa1.char_conv_1_active = false;
+                }
+                let size = (value >> 2 & 7).min(5);
+                let color_mode = (value & 3).min(2);
+                self.sa1.char_conv_control = size << 2 | color_mode;
+            }
+            0x2232 => self.sa1.dma_src_addr = (self.sa1.dma_src_addr & 0xFF_FF00) | value as u32,
+            0x2233 => {
+                self.sa1.dma_src_addr = (self.sa1.dma_src_addr & 0xFF_00FF) | (value as u32) << 8;
+            }
+            0x2234 => {
+                self.sa1.dma_src_addr = (self.sa1.dma_src_addr & 0x00_FFFF) | (value as u32) << 16;
+            }
+            0x2235 => self.sa1.dma_dst_addr = (self.sa1.dma_dst_addr & 0xFF_FF00) | value as u32,
+            0x2236 => {
+                self.sa1.dma_dst_addr = (self.sa1.dma_dst_addr & 0xFF_00FF) | (value as u32) << 8;
+                let control = self.sa1.dma_control;
+                if control & DMA_ENABLED != 0 {
+                    if control & DMA_CHAR_CONV == 0 {
+                        if control & DMA_DST_BW_RAM == 0 {
+                            self.run_normal_dma();
+                        }
+                    } else if control & DMA_CHAR_CONV_TYPE_1 != 0 {
+                        self.sa1.char_conv_1_active = true;
+                        self.sa1.s_cpu_irq_flags |= S_CPU_IRQ_CHAR_CONV_DMA;
+                    }
+                }
+            }
+            0x2237 => {
+                self.sa1.dma_dst_addr = (self.sa1.dma_dst_addr & 0x00_FFFF) | (value as u32) << 16;
+                let control = self.sa1.dma_control;
+                if control & (DMA_ENABLED | DMA_CHAR_CONV | DMA_DST_BW_RAM)
+                    == DMA_ENABLED | DMA_DST_BW_RAM
+                {
+                    self.run_normal_dma();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(super) fn write_s_cpu_io(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x2200 => {
+                let prev = self.sa1.control;
+                self.sa1.control = value & 0x6F;
+                if value & 0x80 != 0 {
+                    self.sa1.sa1_irq_flags |= SA1_IRQ_S_CPU;
+                }
+                if value & 0x10 != 0 {
+                    self.sa1.sa1_irq_flags |= SA1_NMI_S_CPU;
+                    if self.sa1.sa1_irqs_enabled & SA1_NMI_S_CPU != 0 {
+                        self.sa1.nmi_pending = true;
+                    }
+                }
+                if prev & CONTROL_RESET != 0 && value & CONTROL_RESET == 0 {
+                    self.sa1.reset_cpu();
+                }
+            }
+            0x2201 => self.sa1.s_cpu_irqs_enabled = value & S_CPU_IRQ_MASK,
+            0x2202 => self.sa1.s_cpu_irq_flags &= !value,
+            0x2203 => self.sa1.reset_vector = (self.sa1.reset_vector & 0xFF00) | value as u16,
+            0x2204 => self.sa1.reset_vector = (self.sa1.reset_vector & 0xFF) | (value as u16) << 8,
+            0x2205 => self.sa1.nmi_vector = (self.sa1.nmi_vector & 0xFF00) | value as u16,
+            0x2206 => self.sa1.nmi_vector = (self.sa1.nmi_vector & 0xFF) | (value as u16) << 8,
+            0x2207 => self.sa1.irq_vector = (self.sa1.irq_vector & 0xFF00) | value as u16,
+            0x2208 => self.sa1.irq_vector = (self.sa1.irq_vector & 0xFF) | (value as u16) << 8,
+            0x2220..=0x2223 => self.sa1.rom_banks[(addr & 3) as usize] = value & 0x87,
+            0x2224 => self.sa1.s_cpu_bw_ram_bank = value & 0x1F,
+            0x2226 => self.sa1.s_cpu_bw_ram_writable = value & 0x80 != 0,
+            0x2228 => self.sa1.bw_ram_protected_size_shift = value & 0xF,
+            0x2229 => self.sa1.s_cpu_iram_writable_mask = value,
+            0x2231..=0x2237 => self.write_shared_io(addr, value),
+            _ => {}
+        }
+    }
+
+    fn write_sa1_io(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x2209 => {
+                self.sa1.sa1_control = value & 0x5F;
+                if value & 0x80 != 0 {
+                    self.sa1.s_cpu_irq_flags |= S_CPU_IRQ_SA1;
+                }
+            }
+            0x220A => {
+                let newly_enabled = value & !self.sa1.sa1_irqs_enabled;
+                self.sa1.sa1_irqs_enabled = value & 0xF0;
+                if newly_enabled & self.sa1.sa1_irq_flags & SA1_NMI_S_CPU != 0 {
+                    self.sa1.nmi_pending = true;
+                }
+            }
+            0x220B => {
+                self.sa1.sa1_irq_flags &= !(value & 0xF0);
+                if value & SA1_NMI_S_CPU != 0 {
+                    self.sa1.nmi_pending = false;
+                }
+            }
+            0x220C => {
+                self.sa1.s_cpu_nmi_vector = (self.sa1.s_cpu_nmi_vector & 0xFF00) | value as u16;
+            }
+            0x220D => {
+                self.sa1.s_cpu_nmi_vector =
+                    (self.sa1.s_cpu_nmi_vector & 0xFF) | (value as u16) << 8;
+            }
+            0x220E => {
+                self.sa1.s_cpu_irq_vector = (self.sa1.s_cpu_irq_vector & 0xFF00) | value as u16;
+            }
+            0x220F => {
+                self.sa1.s_cpu_irq_vector =
+                    (self.sa1.s_cpu_irq_vector & 0xFF) | (value as u16) << 8;
+            }
+            0x2210 => {
+                self.sa1.timer_control = value & 0x83;
+                self.sa1.update_timer_irq_time();
+            }
+            0x2211 => {
+                self.sa1.timer_base_time = self.sa1.cur_time;
+                self.sa1.update_timer_irq_time();
+            }
+            0x2212 => {
+                self.sa1.timer_h_target = (self.sa1.timer_h_target & 0x100) | value as u16;
+                self.sa1.update_timer_irq_time();
+            }
+            0x2213 => {
+                self.sa1.timer_h_target =
+                    (self.sa1.timer_h_target & 0xFF) | (value as u16 & 1) << 8;
+                self.sa1.update_timer_irq_time();
+            }
+            0x2214 => {
+                self.sa1.timer_v_target = (self.sa1.timer_v_target & 0x100) | value as u16;
+                self.sa1.update_timer_irq_time();
+            }
+            0x2215 => {
+                self.sa1.timer_v_target =
+                    (self.sa1.timer_v_target & 0xFF) | (value as u16 & 1) << 8;
+                self.sa1.update_timer_irq_time();
+            }
+            0x2225 => self.sa1.sa1_bw_ram_bank = value,
+            0x2227 => self.sa1.sa1_bw_ram_writable = value & 0x80 != 0,
+            0x222A => self.sa1.sa1_iram_writable_mask = value,
+            0x2230 => {
+                self.sa1.dma_control = value & 0xF7;
+                self.sa1.char_conv_2_line = 0;
+            }
+            0x2231..=0x2237 => self.write_shared_io(addr, value),
+            0x2238 => self.sa1.dma_len = (self.sa1.dma_len & 0xFF00) | value as u16,
+            0x2239 => self.sa1.dma_len = (self.sa1.dma_len & 0xFF) | (value as u16) << 8,
+            0x223F => self.sa1.bitmap_2bpp = value & 0x80 != 0,
+            0x2240..=0x224F => {
+                self.sa1.bitmap_regs[(addr & 0xF) as usize] = value;
+                if addr & 7 == 7
+                    && self.sa1.dma_control & (DMA_ENABLED | DMA_CHAR_CONV | DMA_CHAR_CONV_TYPE_1)
+                        == DMA_ENABLED | DMA_CHAR_CONV
+                {
+                    self.run_char_conv_2();
+                }
+            }
+            0x2250 => {
+                self.sa1.math_control = value & 3;
+                if value & 2 != 0 {
+                    self.sa1.math_result = 0;
+                }
+            }
+            0x2251 => self.sa1.math_a = (self.sa1.math_a & 0xFF00) | value as u16,
+            0x2252 => self.sa1.math_a = (self.sa1.math_a & 0xFF) | (value as u16) << 8,
+            0x2253 => self.sa1.math_b = (self.sa1.math_b & 0xFF00) | value as u16,
+            0x2254 => {
+                self.sa1.math_b = (self.sa1.math_b & 0xFF) | (value as u16) << 8;
+                self.sa1.start_math();
+            }
+            0x2258 => {
+                self.sa1.var_len_control = value & 0x8F;
+                if value & 0x80 == 0 {
+                    self.sa1.advance_var_len_addr();
+                }
+            }
+            0x2259 => self.sa1.var_len_addr = (self.sa1.var_len_addr & 0xFF_FF00) | value as u32,
+            0x225A => {
+                self.sa1.var_len_addr = (self.sa1.var_len_addr & 0xFF_00FF) | (value as u32) << 8;
+            }
+            0x225B => {
+                self.sa1.var_len_addr = (self.sa1.var_len_addr & 0x00_FFFF) | (value as u32) << 16;
+                self.sa1.var_len_bit = 0;
+            }
+            _ => {
+                #[cfg(feature = "log")]
+                slog::warn!(
+                    self.sa1.logger,
+                    "Unknown SA-1 IO write @ {:#06X}: {:#04X}",
+                    addr,
+                    value
+                );
+            }
+        }
+    }
+
+    fn run_normal_dma(&mut self) {
+        let src_kind = self.sa1.dma_control & 3;
+        let to_bw_ram = self.sa1.dma_control & DMA_DST_BW_RAM != 0;
+        while self.sa1.dma_len != 0 {
+            let src_addr = self.sa1.dma_src_addr;
+            let dst_addr = self.sa1.dma_dst_addr;
+            let value = match src_kind {
+                0 => self.read_rom(src_addr),
+                1 => self.read_bw_ram(src_addr).unwrap_or(0),
+                _ => self.sa1.iram[src_addr as usize & (IRAM_SIZE - 1)],
+            };
+            if to_bw_ram {
+                self.write_bw_ram(dst_addr, value, true);
+            } else {
+                self.sa1.iram[dst_addr as usize & (IRAM_SIZE - 1)] = value;
+            }
+            self.sa1.dma_src_addr = (src_addr + 1) & 0xFF_FFFF;
+            self.sa1.dma_dst_addr = (dst_addr + 1) & 0xFF_FFFF;
+            self.sa1.dma_len -= 1;
+        }
+        self.sa1.sa1_irq_flags |= SA1_IRQ_DMA;
+    }
+
+    /// Handles a main CPU read from BW-RAM while character conversion DMA type 1 is active: the
+    /// bitmap data in BW-RAM gets converted to SNES tiles one character at a time, buffered in
+    /// I-RAM and returned from there.
+    fn read_char_conv_1(&mut self, offset: u32) -> u8 {
+        let color_mode = (self.sa1.char_conv_control & 3) as u32;
+        let size = (self.sa1.char_conv_control >> 2 & 7) as u32;
+        let char_bytes_shift = 6 - color_mode;
+        let char_mask = (1 << char_bytes_shift) - 1;
+        let src_start = self.sa1.dma_src_addr;
+
+        if offset & char_mask == 0 {
+            let bytes_per_pixel_row = 8 >> color_mode;
+            let bytes_per_line = (8 << size) >> color_mode;
+            let bw_ram_mask = (self.bw_ram.len() as u32)
+                .next_power_of_two()
+                .wrapping_sub(1);
+            let tile = (offset.wrapping_sub(src_start) & bw_ram_mask) >> char_bytes_shift;
+            let tile_y = tile >> size;
+            let tile_x = tile & ((1 << size) - 1);
+            let mut src_addr =
+                src_start + tile_y * 8 * bytes_per_line + tile_x * bytes_per_pixel_row;
+
+            for y in 0..8 {
+                let mut data = 0_u64;
+                for byte in 0..bytes_per_pixel_row {
+                    data |= (self.read_bw_ram(src_addr + byte).unwrap_or(0) as u64) << (byte << 3);
+                }
+                src_addr += bytes_per_line;
+
+                let mut planes = [0_u8; 8];
+                for x in 0..8 {
+                    for plane in &mut planes[..bytes_per_pixel_row as usize] {
+                        *plane |= ((data & 1) as u8) << (7 - x);
+                        data >>= 1;
+                    }
+                }
+
+                for (byte, plane) in planes[..bytes_per_pixel_row as usize].iter().enumerate() {
+                    let addr =
+                        self.sa1.dma_dst_addr as usize + (y << 1) + ((byte & 6) << 3) + (byte & 1);
+                    self.sa1.iram[addr & (IRAM_SIZE - 1)] = *plane;
+                }
+            }
+        }
+
+        self.sa1.iram[(self.sa1.dma_dst_addr + (offset & char_mask)) as usize & (IRAM_SIZE - 1)]
+    }
+
+    /// Converts the pixel row of an 8x8 tile stored in the bitmap registers into SNES tile data in
+    /// I-RAM, as done by character conversion DMA type 2.
+    fn run_char_conv_2(&mut self) {
+        let color_mode = (self.sa1.char_conv_control & 3) as usize;
+        let line = self.sa1.char_conv_2_line as usize;
+        let regs_base = (line & 1) << 3;
+        let bytes_per_pixel_row = 8 >> color_mode;
+
+        let mut addr = self.sa1.dma_dst_addr as usize & (IRAM_SIZE - 1);
+        addr &= !((1 << (7 - color_mode)) - 1);
+        addr += (line & 8) * bytes_per_pixel_row;
+        addr += (line & 7) << 1;
+
+        for byte in 0..bytes_per_pixel_row {
+            let mut plane = 0;
+            for bit in 0..8 {
+                plane |= (self.sa1.bitmap_regs[regs_base + bit] >> byte & 1) << (7 - bit);
+            }
+            self.sa1.iram[(addr + ((byte & 6) << 3) + (byte & 1)) & (IRAM_SIZE - 1)] = plane;
+        }
+
+        self.sa1.char_conv_2_line = ((line + 1) & 15) as u8;
+    }
+}
+
+impl Cart {
+    pub(super) fn map_sa1(map: &mut Map) {
+        // All handlers receive the unmodified bus address, as the mapping is dynamic
+        let mut map_raw = |read_fn: Option<ReadHandler>,
+                           write_fn: Option<WriteHandler>,
+                           banks: (u8, u8),
+                           addrs: (u16, u16)| {
+            map.map::<true, true>(read_fn, write_fn, banks, addrs, 0, 1 << 24, 0);
+        };
+        for banks in [(0x00, 0x3F), (0x80, 0xBF)] {
+            map_raw(
+                Some(Self::handle_sa1_io_read),
+                Some(Self::handle_sa1_io_write),
+                banks,
+                (0x2200, 0x23FF),
+            );
+            map_raw(
+                Some(Self::handle_sa1_iram_read),
+                Some(Self::handle_sa1_iram_write),
+                banks,
+                (0x3000, 0x37FF),
+            );
+            map_raw(
+                Some(Self::handle_sa1_bw_ram_read),
+                Some(Self::handle_sa1_bw_ram_write),
+                banks,
+                (0x6000, 0x7FFF),
+            );
+            map_raw(
+                Some(Self::handle_sa1_rom_read),
+                None,
+                banks,
+                (0x8000, 0xFFFF),
+            );
+        }
+        map_raw(
+            Some(Self::handle_sa1_bw_ram_read),
+            Some(Self::handle_sa1_bw_ram_write),
+            (0x40, 0x4F),
+            (0x0000, 0xFFFF),
+        );
+        map_raw(
+            Some(Self::handle_sa1_rom_read),
+            None,
+            (0xC0, 0xFF),
+            (0x0000, 0xFFFF),
+        );
+    }
+
+    fn synced_sa1_context(&mut self) -> Context<'_> {
+        let time = self.cur_time;
+        let mut context = self.sa1_context().unwrap();
+        context.run_until(time);
+        context
+    }
+
+    fn handle_sa1_io_read(&mut self, addr: u32) -> u8 {
+        self.synced_sa1_context().read_s_cpu_io(addr as u16)
+    }
+
+    fn handle_sa1_io_write(&mut self, addr: u32, value: u8) {
+        self.synced_sa1_context().write_s_cpu_io(addr as u16, value);
+    }
+
+    fn handle_sa1_iram_read(&mut self, addr: u32) -> u8 {
+        self.synced_sa1_context().sa1.iram[addr as usize & (IRAM_SIZE - 1)]
+    }
+
+    fn handle_sa1_iram_write(&mut self, addr: u32, value: u8) {
+        let mut context = self.synced_sa1_context();
+        let writable_mask = context.sa1.s_cpu_iram_writable_mask;
+        context.write_iram(addr, value, writable_mask);
+    }
+
+    fn s_cpu_bw_ram_offset(sa1: &Sa1, addr: u32) -> u32 {
+        if addr & 0x40_0000 == 0 {
+            (sa1.s_cpu_bw_ram_bank as u32) << 13 | (addr & 0x1FFF)
+        } else {
+            addr & 0xF_FFFF
+        }
+    }
+
+    fn handle_sa1_bw_ram_read(&mut self, addr: u32) -> u8 {
+        let mut context = self.synced_sa1_context();
+        let offset = Self::s_cpu_bw_ram_offset(context.sa1, addr);
+        if context.sa1.char_conv_1_active {
+            context.read_char_conv_1(offset)
+        } else {
+            context.read_bw_ram(offset).unwrap_or(0)
+        }
+    }
+
+    fn handle_sa1_bw_ram_write(&mut self, addr: u32, value: u8) {
+        let mut context = self.synced_sa1_context();
+        let offset = Self::s_cpu_bw_ram_offset(context.sa1, addr);
+        let writable = context.sa1.s_cpu_bw_ram_writable;
+        context.write_bw_ram(offset, value, writable);
+    }
+
+    fn handle_sa1_rom_read(&mut self, addr: u32) -> u8 {
+        // The main CPU's NMI and IRQ vectors can be overridden by the SA-1
+        if addr & 0xFF_FFF0 == 0x00_FFE0 {
+            let context = self.synced_sa1_context();
+            let sa1 = &*context.sa1;
+            match addr as u16 {
+                0xFFEA | 0xFFEB if sa1.sa1_control & 0x10 != 0 => {
+                    return (sa1.s_cpu_nmi_vector >> ((addr & 1) << 3)) as u8;
+                }
+                0xFFEE | 0xFFEF if sa1.sa1_control & 0x40 != 0 => {
+                    return (sa1.s_cpu_irq_vector >> ((addr & 1) << 3)) as u8;
+                }
+                _ => {}
+            }
+        }
+        self.sa1_context().unwrap().read_rom(addr)
+    }
+}
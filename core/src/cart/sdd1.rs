@@ -0,0 +1,197 @@
+mod decompressor;
+
+use super::{
+    map::{mirror, Map, ReadHandler, WriteHandler},
+    Cart,
+};
+use crate::savestate::{LoadError, Reader, Writer};
+use decompressor::Decompressor;
+
+/// A view of the ROM as seen through the S-DD1's memory controller, which maps a selectable 1 MiB
+/// ROM bank to each quarter of banks C0-FF.
+struct Mmc<'a> {
+    rom_banks: [u8; 4],
+    rom: &'a [u8],
+}
+
+impl<'a> Mmc<'a> {
+    fn read(&self, addr: u32) -> u8 {
+        if self.rom.is_empty() {
+            return 0;
+        }
+        let bank = self.rom_banks[(addr >> 20 & 3) as usize] & 0xF;
+        let offset = (bank as u32) << 20 | (addr & 0xF_FFFF);
+        self.rom[mirror(offset, self.rom.len() as u32) as usize]
+    }
+}
+
+/// The state of the S-DD1, a memory controller that can switch between 1 MiB ROM banks and
+/// decompress graphics data on the fly while it's being read by DMA.
+///
+/// Decompression is performed for a DMA channel if it's enabled in both $4800 and $4801, and it
+/// reads from banks C0-FF; $4801 is cleared once the transfer ends.
+#[derive(Clone)]
+pub struct Sdd1 {
+    dma_enabled: u8,
+    decompression_enabled: u8,
+    rom_banks: [u8; 4],
+    decompressing: bool,
+    decompressor: Decompressor,
+}
+
+impl Sdd1 {
+    pub(super) fn new() -> Self {
+        Sdd1 {
+            dma_enabled: 0,
+            decompression_enabled: 0,
+            rom_banks: [0, 1, 2, 3],
+            decompressing: false,
+            decompressor: Decompressor::new(),
+        }
+    }
+
+    pub(super) fn soft_reset(&mut self) {
+        *self = Sdd1::new();
+    }
+
+    #[inline]
+    pub fn rom_banks(&self) -> [u8; 4] {
+        self.rom_banks
+    }
+
+    /// Returns whether data read by the given DMA channel should be decompressed.
+    #[inline]
+    fn decompresses_for(&self, channel: u8) -> bool {
+        self.dma_enabled & self.decompression_enabled & 1 << channel != 0
+    }
+
+    pub(super) fn finish_dma(&mut self, channel: u8) {
+        if self.decompresses_for(channel) {
+            self.decompressing = false;
+            self.decompression_enabled &= !(1 << channel);
+        }
+    }
+
+    fn read_io(&self, addr: u16) -> u8 {
+        match addr {
+            0x4800 => self.dma_enabled,
+            0x4801 => self.decompression_enabled,
+            0x4804..=0x4807 => self.rom_banks[(addr & 3) as usize],
+            _ => 0,
+        }
+    }
+
+    fn write_io(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x4800 => self.dma_enabled = value,
+            0x4801 => self.decompression_enabled = value,
+            0x4804..=0x4807 => self.rom_banks[(addr & 3) as usize] = value & 0x8F,
+            _ => {}
+        }
+    }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.dma_enabled);
+        writer.write(&self.decompression_enabled);
+        writer.write(&self.rom_banks);
+        writer.write(&self.decompressing);
+        self.decompressor.save_state(writer);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.dma_enabled = reader.read()?;
+        self.decompression_enabled = reader.read()?;
+        self.rom_banks = reader.read()?;
+        self.decompressing = reader.read()?;
+        self.decompressor.load_state(reader)
+    }
+}
+
+impl Cart {
+    pub(super) fn map_sdd1(map: &mut Map) {
+        // All handlers receive the unmodified bus address, as the ROM mapping depends on the
+        // S-DD1's registers
+        for banks in [(0x00, 0x3F), (0x80, 0xBF)] {
+            map.map::<true, true>(
+                Some(Self::handle_sdd1_io_read as ReadHandler),
+                Some(Self::handle_sdd1_io_write as WriteHandler),
+                banks,
+                (0x4800, 0x49FF),
+                0,
+                1 << 24,
+                0,
+            );
+            map.map::<true, false>(
+                Some(Self::handle_sdd1_lorom_read as ReadHandler),
+                None,
+                banks,
+                (0x8000, 0xFFFF),
+                0,
+                1 << 24,
+                0,
+            );
+        }
+        map.map::<true, false>(
+            Some(Self::handle_sdd1_hirom_read as ReadHandler),
+            None,
+            (0xC0, 0xFF),
+            (0x0000, 0xFFFF),
+            0,
+            1 << 24,
+            0,
+        );
+    }
+
+    fn sdd1(&mut self) -> &mut Sdd1 {
+        match &mut self.coprocessor {
+            Some(super::Coprocessor::Sdd1(sdd1)) => sdd1,
+            _ => unreachable!(),
+        }
+    }
+
+    fn handle_sdd1_io_read(&mut self, addr: u32) -> u8 {
+        self.sdd1().read_io(addr as u16)
+    }
+
+    fn handle_sdd1_io_write(&mut self, addr: u32, value: u8) {
+        self.sdd1().write_io(addr as u16, value);
+    }
+
+    fn handle_sdd1_lorom_read(&mut self, addr: u32) -> u8 {
+        if self.rom.is_empty() {
+            return 0;
+        }
+        let mut bank = addr >> 16 & 0x3F;
+        // Banks 20-3F and A0-BF can be made to mirror 00-1F and 80-9F instead
+        let rom_banks = self.sdd1().rom_banks;
+        if bank & 0x20 != 0 && rom_banks[(1 | addr >> 22 & 2) as usize] & 0x80 != 0 {
+            bank &= !0x20;
+        }
+        let offset = bank << 15 | (addr & 0x7FFF);
+        self.rom[mirror(offset, self.rom.len() as u32) as usize]
+    }
+
+    fn handle_sdd1_hirom_read(&mut self, addr: u32) -> u8 {
+        let gp_dma_channel = self.gp_dma_channel;
+        let sdd1 = match &mut self.coprocessor {
+            Some(super::Coprocessor::Sdd1(sdd1)) => sdd1,
+            _ => unreachable!(),
+        };
+        let mmc = Mmc {
+            rom_banks: sdd1.rom_banks,
+            rom: &self.rom[..],
+        };
+        match gp_dma_channel {
+            // The S-DD1 expects DMAs to use a fixed source address, so any read from a
+            // decompressing channel is treated as a request for the next decompressed byte
+            Some(channel) if sdd1.decompresses_for(channel) => {
+                if !sdd1.decompressing {
+                    sdd1.decompressor.start(&mmc, addr);
+                    sdd1.decompressing = true;
+                }
+                sdd1.decompressor.read_byte(&mmc)
+            }
+            _ => mmc.read(addr),
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use super::Mmc;
+use crate::savestate::{LoadError, Reader, Writer};
+
+// The MPS run lengths encoded by Golomb codewords starting with a 1 bit, indexed by the codeword
+// bits for the current code number (including the leading 1)
+const RUN_COUNTS: [u8; 0x100] = {
+    let mut result = [0; 0x100];
+    let mut i = 2;
+    while i < 0x100 {
+        let len = 7 - (i as u8).leading_zeros();
+        let bits = !i & ((1 << len) - 1);
+        let mut reversed = 0;
+        let mut j = 0;
+        while j < len {
+            reversed |= (bits >> j & 1) << (len - 1 - j);
+            j += 1;
+        }
+        result[i] = reversed as u8;
+        i += 1;
+    }
+    result
+};
+
+#[derive(Clone, Copy)]
+struct State {
+    code_number: u8,
+    next_if_mps: u8,
+    next_if_lps: u8,
+}
+
+macro_rules! states {
+    ($(($code_number: expr, $next_if_mps: expr, $next_if_lps: expr)),*$(,)?) => {
+        [$(State {
+            code_number: $code_number,
+            next_if_mps: $next_if_mps,
+            next_if_lps: $next_if_lps,
+        }),*]
+    };
+}
+
+static EVOLUTION_TABLE: [State; 33] = states![
+    (0, 25, 25),
+    (0, 2, 1),
+    (0, 3, 1),
+    (0, 4, 2),
+    (0, 5, 3),
+    (1, 6, 4),
+    (1, 7, 5),
+    (1, 8, 6),
+    (1, 9, 7),
+    (2, 10, 8),
+    (2, 11, 9),
+    (2, 12, 10),
+    (2, 13, 11),
+    (3, 14, 12),
+    (3, 15, 13),
+    (3, 16, 14),
+    (3, 17, 15),
+    (4, 18, 16),
+    (4, 19, 17),
+    (5, 20, 18),
+    (5, 21, 19),
+    (6, 22, 20),
+    (6, 23, 21),
+    (7, 24, 22),
+    (7, 24, 23),
+    (0, 26, 1),
+    (1, 27, 2),
+    (2, 28, 4),
+    (3, 29, 8),
+    (4, 30, 12),
+    (5, 31, 16),
+    (6, 32, 18),
+    (7, 24, 22),
+];
+
+#[derive(Clone, Copy, Default)]
+struct BitGenerator {
+    mps_count: u8,
+    lps_pending: bool,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Context {
+    state: u8,
+    mps: u8,
+}
+
+/// The S-DD1's streaming decompressor, which decodes bitplane data compressed with an adaptive
+/// binary arithmetic coder built on Golomb codes, with the probability of each bit being
+/// estimated based on the previously decoded bits in the same bitplane.
+#[derive(Clone)]
+pub(super) struct Decompressor {
+    // Input
+    in_addr: u32,
+    in_bit_count: u8,
+
+    // Bit generation
+    bit_generators: [BitGenerator; 8],
+    contexts: [Context; 32],
+
+    // Context model
+    bitplanes_info: u8,
+    context_bits_info: u8,
+    bit_number: u8,
+    cur_bitplane: u8,
+    prev_bitplane_bits: [u16; 8],
+
+    // Output
+    out_mask: u8,
+    out_plane_0: u8,
+    out_plane_1: u8,
+}
+
+impl Decompressor {
+    pub(super) fn new() -> Self {
+        Decompressor {
+            in_addr: 0,
+            in_bit_count: 0,
+
+            bit_generators: [BitGenerator::default(); 8],
+            contexts: [Context::default(); 32],
+
+            bitplanes_info: 0,
+            context_bits_info: 0,
+            bit_number: 0,
+            cur_bitplane: 0,
+            prev_bitplane_bits: [0; 8],
+
+            out_mask: 0,
+            out_plane_0: 0,
+            out_plane_1: 0,
+        }
+    }
+
+    /// Starts decompressing the stream at the given address, whose first byte's top nibble
+    /// selects the bitplane layout and the context model.
+    pub(super) fn start(&mut self, mmc: &Mmc, addr: u32) {
+        let header = mmc.read(addr);
+        *self = Decompressor {
+            in_addr: addr,
+            in_bit_count: 4,
+            bitplanes_info: header & 0xC0,
+            context_bits_info: header & 0x30,
+            cur_bitplane: match header & 0xC0 {
+                0x00 => 1,
+                0x40 => 7,
+                0x80 => 3,
+                _ => 0,
+            },
+            out_mask: 1,
+            ..Decompressor::new()
+        };
+    }
+
+    fn read_code_word(&mut self, mmc: &Mmc, code_len: u8) -> u8 {
+        let mut result = mmc.read(self.in_addr) << self.in_bit_count;
+        self.in_bit_count += 1;
+        if result & 0x80 != 0 {
+            result |=
+                (mmc.read(self.in_addr.wrapping_add(1)) as u16 >> (9 - self.in_bit_count)) as u8;
+            self.in_bit_count += code_len;
+        }
+        if self.in_bit_count & 8 != 0 {
+            self.in_addr = self.in_addr.wrapping_add(1);
+            self.in_bit_count &= 7;
+        }
+        result
+    }
+
+    /// Returns the next bit produced by the given bit generator, and whether it was the last one
+    /// in its run.
+    fn generate_bit(&mut self, mmc: &Mmc, code_number: u8) -> (u8, bool) {
+        let mut generator = self.bit_generators[code_number as usize];
+        if generator.mps_count == 0 && !generator.lps_pending {
+            let code_word = self.read_code_word(mmc, code_number);
+            if code_word & 0x80 != 0 {
+                generator.lps_pending = true;
+                generator.mps_count = RUN_COUNTS[(code_word >> (code_number ^ 7)) as usize];
+            } else {
+                generator.mps_count = 1 << code_number;
+            }
+        }
+        let bit = if generator.mps_count != 0 {
+            generator.mps_count -= 1;
+            0
+        } else {
+            generator.lps_pending = false;
+            1
+        };
+        self.bit_generators[code_number as usize] = generator;
+        (bit, generator.mps_count == 0 && !generator.lps_pending)
+    }
+
+    fn estimate_bit(&mut self, mmc: &Mmc, context_i: u8) -> u8 {
+        let context = self.contexts[context_i as usize];
+        let state = EVOLUTION_TABLE[context.state as usize];
+        let (bit, end_of_run) = self.generate_bit(mmc, state.code_number);
+        if end_of_run {
+            let context = &mut self.contexts[context_i as usize];
+            if bit != 0 {
+                if context.state & 0xFE == 0 {
+                    context.mps ^= 1;
+                }
+                context.state = state.next_if_lps;
+            } else {
+                context.state = state.next_if_mps;
+            }
+        }
+        bit ^ context.mps
+    }
+
+    fn decode_bit(&mut self, mmc: &Mmc) -> u8 {
+        match self.bitplanes_info {
+            0x00 => self.cur_bitplane ^= 1,
+            0x40 => {
+                self.cur_bitplane ^= 1;
+                if self.bit_number & 0x7F == 0 {
+                    self.cur_bitplane = (self.cur_bitplane + 2) & 7;
+                }
+            }
+            0x80 => {
+                self.cur_bitplane ^= 1;
+                if self.bit_number & 0x7F == 0 {
+                    self.cur_bitplane ^= 2;
+                }
+            }
+            _ => self.cur_bitplane = self.bit_number & 7,
+        }
+
+        let context_bits = self.prev_bitplane_bits[self.cur_bitplane as usize];
+        let context = (self.cur_bitplane & 1) << 4
+            | match self.context_bits_info {
+                0x00 => ((context_bits & 0x1C0) >> 5) | (context_bits & 1),
+                0x10 => ((context_bits & 0x180) >> 5) | (context_bits & 1),
+                0x20 => ((context_bits & 0xC0) >> 5) | (context_bits & 1),
+                _ => ((context_bits & 0x180) >> 5) | (context_bits & 3),
+            } as u8;
+
+        let bit = self.estimate_bit(mmc, context);
+        self.prev_bitplane_bits[self.cur_bitplane as usize] = context_bits << 1 | bit as u16;
+        self.bit_number = self.bit_number.wrapping_add(1);
+        bit
+    }
+
+    /// Decompresses the next output byte; 2-bitplane layouts decode a pair of bitplane bytes at a
+    /// time, outputting them one after the other.
+    pub(super) fn read_byte(&mut self, mmc: &Mmc) -> u8 {
+        if self.bitplanes_info == 0xC0 {
+            let mut result = 0;
+            for i in 0..8 {
+                result |= self.decode_bit(mmc) << i;
+            }
+            return result;
+        }
+        if self.out_mask == 0 {
+            self.out_mask = 0xFF;
+            return self.out_plane_1;
+        }
+        self.out_plane_0 = 0;
+        self.out_plane_1 = 0;
+        for i in (0..8).rev() {
+            self.out_plane_0 |= self.decode_bit(mmc) << i;
+            self.out_plane_1 |= self.decode_bit(mmc) << i;
+        }
+        self.out_mask = 0;
+        self.out_plane_0
+    }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.in_addr);
+        writer.write(&self.in_bit_count);
+        for generator in &self.bit_generators {
+            writer.write(&generator.mps_count);
+            writer.write(&generator.lps_pending);
+        }
+        for context in &self.contexts {
+            writer.write(&context.state);
+            writer.write(&context.mps);
+        }
+        writer.write(&self.bitplanes_info);
+        writer.write(&self.context_bits_info);
+        writer.write(&self.bit_number);
+        writer.write(&self.cur_bitplane);
+        writer.write(&self.prev_bitplane_bits);
+        writer.write(&self.out_mask);
+        writer.write(&self.out_plane_0);
+        writer.write(&self.out_plane_1);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.in_addr = reader.read()?;
+        self.in_bit_count = reader.read()?;
+        for generator in &mut self.bit_generators {
+            generator.mps_count = reader.read()?;
+            generator.lps_pending = reader.read()?;
+        }
+        for context in &mut self.contexts {
+            context.state = reader.read()?;
+            context.mps = reader.read()?;
+        }
+        self.bitplanes_info = reader.read()?;
+        self.context_bits_info = reader.read()?;
+        self.bit_number = reader.read()?;
+        self.cur_bitplane = reader.read()?;
+        self.prev_bitplane_bits = reader.read()?;
+        self.out_mask = reader.read()?;
+        self.out_plane_0 = reader.read()?;
+        self.out_plane_1 = reader.read()?;
+
+        if self.in_bit_count > 7
+            || self.bitplanes_info & !0xC0 != 0
+            || self.context_bits_info & !0x30 != 0
+            || self.cur_bitplane > 7
+            || self
+                .contexts
+                .iter()
+                .any(|context| context.state as usize >= EVOLUTION_TABLE.len() || context.mps > 1)
+        {
+            return Err(LoadError::InvalidData);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,634 @@
+mod decompressor;
+pub mod rtc;
+
+use super::{
+    map::{mirror, Map, ReadHandler, WriteHandler},
+    Cart,
+};
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    schedule::Timestamp,
+    Model,
+};
+use decompressor::Decompressor;
+use rtc::Rtc;
+
+/// The size of the program ROM at the start of the cartridge ROM; the rest of it is the data ROM,
+/// which is only accessible through the SPC7110's data port, decompressor and bank registers.
+pub const PROGRAM_ROM_SIZE: usize = 0x10_0000;
+
+// The SPC7110 runs off the master clock, so operation durations are given in master cycles
+const MUL_CYCLES: Timestamp = 30;
+const DIV_CYCLES: Timestamp = 40;
+const DECOMPRESSION_START_CYCLES: Timestamp = 20;
+
+/// The state of the SPC7110, a memory controller with a graphics decompressor, a data port with
+/// configurable address stepping, a 16-bit multiplier/divider, and bank registers for up to 7 MiB
+/// of data ROM. Some boards also contain an Epson RTC-4513 real-time clock.
+#[derive(Clone)]
+pub struct Spc7110 {
+    // Decompression unit
+    table_addr: u32,
+    table_index: u8,
+    dcu_offset_reg: u16,
+    dcu_skip: u8,
+    dcu_counter: u16,
+    dcu_control: u8,
+    dcu_ready_time: Option<Timestamp>,
+    dcu_mode: u8,
+    dcu_addr: u32,
+    dcu_tile: [u8; 32],
+    dcu_tile_offset: u8,
+    decompressor: Decompressor,
+
+    // Data port
+    data_port_value: u8,
+    data_offset: u32,
+    data_adjust: u16,
+    data_stride: u16,
+    data_control: u8,
+
+    // ALU
+    alu_regs: [u8; 0x10],
+    alu_signed: bool,
+    alu_busy_until: Timestamp,
+
+    // Memory control unit
+    ram_control: u8,
+    data_rom_banks: [u8; 3],
+    data_rom_size: u8,
+
+    rtc: Option<Rtc>,
+}
+
+/// Reads from the data ROM; the size register limits its addressable range to 1, 2, 4 or 8 MiB,
+/// with the upper half of the address space reading as 0 unless the biggest size is selected.
+fn read_data_rom(data_rom: &[u8], size_reg: u8, addr: u32) -> u8 {
+    let size = size_reg & 3;
+    if data_rom.is_empty() || (size != 3 && addr & 0x40_0000 != 0) {
+        return 0;
+    }
+    let offset = addr & ((0x10_0000 << size) - 1);
+    data_rom[mirror(offset, data_rom.len() as u32) as usize]
+}
+
+impl Spc7110 {
+    pub(super) fn new(has_rtc: bool) -> Self {
+        Spc7110 {
+            table_addr: 0,
+            table_index: 0,
+            dcu_offset_reg: 0,
+            dcu_skip: 0,
+            dcu_counter: 0,
+            dcu_control: 0,
+            dcu_ready_time: None,
+            dcu_mode: 0,
+            dcu_addr: 0,
+            dcu_tile: [0; 32],
+            dcu_tile_offset: 0,
+            decompressor: Decompressor::new(),
+
+            data_port_value: 0,
+            data_offset: 0,
+            data_adjust: 0,
+            data_stride: 0,
+            data_control: 0,
+
+            alu_regs: [0; 0x10],
+            alu_signed: false,
+            alu_busy_until: 0,
+
+            ram_control: 0,
+            data_rom_banks: [1, 2, 3],
+            data_rom_size: 0,
+
+            rtc: if has_rtc { Some(Rtc::new()) } else { None },
+        }
+    }
+
+    pub(super) fn setup(&mut self, model: Model, time: Timestamp) {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.setup(model, time);
+        }
+    }
+
+    pub(super) fn soft_reset(&mut self) {
+        let rtc = self.rtc.take();
+        *self = Spc7110::new(false);
+        self.rtc = rtc;
+        if let Some(rtc) = &mut self.rtc {
+            rtc.soft_reset();
+        }
+    }
+
+    #[inline]
+    pub fn rtc(&self) -> Option<&Rtc> {
+        self.rtc.as_ref()
+    }
+
+    #[inline]
+    pub(super) fn rtc_mut(&mut self) -> Option<&mut Rtc> {
+        self.rtc.as_mut()
+    }
+
+    #[inline]
+    pub fn ram_enabled(&self) -> bool {
+        self.ram_control & 0x80 != 0
+    }
+
+    #[inline]
+    pub fn data_rom_banks(&self) -> [u8; 3] {
+        self.data_rom_banks
+    }
+
+    /// Reads from the program or data ROM through the given quarter of the HiROM address space,
+    /// with the first one always containing the program ROM and the others being switchable.
+    fn read_rom(&self, program_rom: &[u8], data_rom: &[u8], quarter: u8, offset: u32) -> u8 {
+        if quarter == 0 {
+            if program_rom.is_empty() {
+                return 0;
+            }
+            program_rom[mirror(offset, program_rom.len() as u32) as usize]
+        } else {
+            let bank = (self.data_rom_banks[(quarter - 1) as usize] & 7) as u32;
+            read_data_rom(data_rom, self.data_rom_size, bank << 20 | offset)
+        }
+    }
+
+    // Decompression unit
+
+    fn start_decompression(&mut self, data_rom: &[u8], time: Timestamp) {
+        let size_reg = self.data_rom_size;
+        let mut read = |addr| read_data_rom(data_rom, size_reg, addr);
+
+        let entry_addr = self.table_addr.wrapping_add((self.table_index as u32) << 2);
+        self.dcu_mode = read(entry_addr);
+        self.dcu_addr = (read(entry_addr.wrapping_add(1)) as u32) << 16
+            | (read(entry_addr.wrapping_add(2)) as u32) << 8
+            | read(entry_addr.wrapping_add(3)) as u32;
+
+        self.dcu_ready_time = None;
+        if self.dcu_mode == 3 {
+            return;
+        }
+
+        self.decompressor
+            .start(self.dcu_mode, self.dcu_addr, &mut read);
+        self.decompressor.decode(&mut read);
+        // Optionally skip the given amount of rows before the first tile
+        if self.dcu_control & 2 != 0 {
+            for _ in 0..self.dcu_offset_reg {
+                self.decompressor.decode(&mut read);
+            }
+        }
+
+        self.dcu_tile_offset = 0;
+        self.dcu_ready_time = Some(time + DECOMPRESSION_START_CYCLES);
+    }
+
+    fn read_decompressed(&mut self, data_rom: &[u8], time: Timestamp) -> u8 {
+        self.dcu_counter = self.dcu_counter.wrapping_sub(1);
+        if !matches!(self.dcu_ready_time, Some(ready_time) if time >= ready_time) {
+            return 0;
+        }
+
+        if self.dcu_tile_offset == 0 {
+            let size_reg = self.data_rom_size;
+            let mut read = |addr| read_data_rom(data_rom, size_reg, addr);
+            let decompressor = &mut self.decompressor;
+            for row in 0..8 {
+                let result = decompressor.result();
+                match decompressor.bpp() {
+                    1 => self.dcu_tile[row] = result as u8,
+                    2 => {
+                        self.dcu_tile[row << 1] = result as u8;
+                        self.dcu_tile[row << 1 | 1] = (result >> 8) as u8;
+                    }
+                    _ => {
+                        self.dcu_tile[row << 1] = result as u8;
+                        self.dcu_tile[row << 1 | 1] = (result >> 8) as u8;
+                        self.dcu_tile[row << 1 | 16] = (result >> 16) as u8;
+                        self.dcu_tile[row << 1 | 17] = (result >> 24) as u8;
+                    }
+                }
+
+                // Rows can be spaced out by a custom stride, to decompress wider images
+                let stride = if self.dcu_control & 1 != 0 {
+                    self.dcu_skip
+                } else {
+                    1
+                };
+                for _ in 0..stride {
+                    decompressor.decode(&mut read);
+                }
+            }
+        }
+
+        let result = self.dcu_tile[self.dcu_tile_offset as usize];
+        self.dcu_tile_offset = (self.dcu_tile_offset + 1) & ((self.decompressor.bpp() << 3) - 1);
+        result
+    }
+
+    // Data port
+
+    fn update_data_port(&mut self, data_rom: &[u8]) {
+        let mut adjust = if self.data_control & 2 != 0 {
+            self.data_adjust as u32
+        } else {
+            0
+        };
+        if self.data_control & 8 != 0 {
+            adjust = adjust as i16 as u32;
+        }
+        self.data_port_value = read_data_rom(
+            data_rom,
+            self.data_rom_size,
+            self.data_offset.wrapping_add(adjust),
+        );
+    }
+
+    fn increment_data_port(&mut self, data_rom: &[u8]) {
+        let mut stride = if self.data_control & 1 != 0 {
+            self.data_stride as u32
+        } else {
+            1
+        };
+        if self.data_control & 4 != 0 {
+            stride = stride as i16 as u32;
+        }
+        if self.data_control & 0x10 != 0 {
+            self.data_adjust = self.data_adjust.wrapping_add(stride as u16);
+        } else {
+            self.data_offset = self.data_offset.wrapping_add(stride) & 0xFF_FFFF;
+        }
+        self.update_data_port(data_rom);
+    }
+
+    /// Adds the adjust value to the data offset if the data port is configured to do so when
+    /// the given trigger happens.
+    fn adjust_data_port(&mut self, data_rom: &[u8], trigger: u8) {
+        if self.data_control >> 5 != trigger {
+            return;
+        }
+        let mut adjust = self.data_adjust as u32;
+        if self.data_control & 8 != 0 {
+            adjust = adjust as i16 as u32;
+        }
+        self.data_offset = self.data_offset.wrapping_add(adjust) & 0xFF_FFFF;
+        self.update_data_port(data_rom);
+    }
+
+    // ALU
+
+    fn multiply(&mut self, time: Timestamp) {
+        let regs = &mut self.alu_regs;
+        let a = u16::from_le_bytes([regs[0], regs[1]]);
+        let b = u16::from_le_bytes([regs[4], regs[5]]);
+        let result = if self.alu_signed {
+            (a as i16 as i32 * b as i16 as i32) as u32
+        } else {
+            a as u32 * b as u32
+        };
+        regs[8..0xC].copy_from_slice(&result.to_le_bytes());
+        self.alu_busy_until = time + MUL_CYCLES;
+    }
+
+    fn divide(&mut self, time: Timestamp) {
+        let regs = &mut self.alu_regs;
+        let dividend = u32::from_le_bytes([regs[0], regs[1], regs[2], regs[3]]);
+        let divisor = u16::from_le_bytes([regs[6], regs[7]]);
+        // Dividing by 0 returns a quotient of 0 and the dividend as the remainder
+        let (quotient, remainder) = if divisor == 0 {
+            (0, dividend as u16)
+        } else if self.alu_signed {
+            let dividend = dividend as i32;
+            let divisor = divisor as i16 as i32;
+            (
+                dividend.wrapping_div(divisor) as u32,
+                dividend.wrapping_rem(divisor) as u16,
+            )
+        } else {
+            (
+                dividend / divisor as u32,
+                (dividend % divisor as u32) as u16,
+            )
+        };
+        regs[8..0xC].copy_from_slice(&quotient.to_le_bytes());
+        regs[0xC..0xE].copy_from_slice(&remainder.to_le_bytes());
+        self.alu_busy_until = time + DIV_CYCLES;
+    }
+
+    // I/O
+
+    fn read_io(&mut self, addr: u16, data_rom: &[u8], time: Timestamp) -> u8 {
+        match addr & 0x3F {
+            0x00 => self.read_decompressed(data_rom, time),
+            0x01 => self.table_addr as u8,
+            0x02 => (self.table_addr >> 8) as u8,
+            0x03 => (self.table_addr >> 16) as u8,
+            0x04 => self.table_index,
+            0x05 => self.dcu_offset_reg as u8,
+            0x06 => (self.dcu_offset_reg >> 8) as u8,
+            0x07 => self.dcu_skip,
+            0x09 => self.dcu_counter as u8,
+            0x0A => (self.dcu_counter >> 8) as u8,
+            0x0B => self.dcu_control,
+            0x0C => {
+                (matches!(self.dcu_ready_time, Some(ready_time) if time >= ready_time) as u8) << 7
+            }
+
+            0x10 => {
+                let value = self.data_port_value;
+                self.increment_data_port(data_rom);
+                value
+            }
+            0x11 => self.data_offset as u8,
+            0x12 => (self.data_offset >> 8) as u8,
+            0x13 => (self.data_offset >> 16) as u8,
+            0x14 => self.data_adjust as u8,
+            0x15 => (self.data_adjust >> 8) as u8,
+            0x16 => self.data_stride as u8,
+            0x17 => (self.data_stride >> 8) as u8,
+            0x18 => self.data_control,
+            0x1A => {
+                let value = self.data_port_value;
+                self.adjust_data_port(data_rom, 3);
+                value
+            }
+
+            0x20..=0x2D => self.alu_regs[(addr & 0xF) as usize],
+            0x2E => self.alu_signed as u8,
+            0x2F => ((time < self.alu_busy_until) as u8) << 7,
+
+            0x30 => self.ram_control,
+            0x31..=0x33 => self.data_rom_banks[(addr as usize & 0x3F) - 0x31],
+            0x34 => self.data_rom_size,
+
+            _ => 0,
+        }
+    }
+
+    fn write_io(&mut self, addr: u16, value: u8, data_rom: &[u8], time: Timestamp) {
+        match addr & 0x3F {
+            0x01 => self.table_addr = (self.table_addr & 0xFF_FF00) | value as u32,
+            0x02 => self.table_addr = (self.table_addr & 0xFF_00FF) | (value as u32) << 8,
+            0x03 => self.table_addr = (self.table_addr & 0x00_FFFF) | (value as u32) << 16,
+            0x04 => self.table_index = value,
+            0x05 => self.dcu_offset_reg = (self.dcu_offset_reg & 0xFF00) | value as u16,
+            0x06 => {
+                // Writing the high byte of the offset starts decompression
+                self.dcu_offset_reg = (self.dcu_offset_reg & 0xFF) | (value as u16) << 8;
+                self.start_decompression(data_rom, time);
+            }
+            0x07 => self.dcu_skip = value,
+            0x09 => self.dcu_counter = (self.dcu_counter & 0xFF00) | value as u16,
+            0x0A => self.dcu_counter = (self.dcu_counter & 0xFF) | (value as u16) << 8,
+            0x0B => self.dcu_control = value & 3,
+
+            0x11 => self.data_offset = (self.data_offset & 0xFF_FF00) | value as u32,
+            0x12 => self.data_offset = (self.data_offset & 0xFF_00FF) | (value as u32) << 8,
+            0x13 => {
+                self.data_offset = (self.data_offset & 0x00_FFFF) | (value as u32) << 16;
+                self.update_data_port(data_rom);
+            }
+            0x14 => {
+                self.data_adjust = (self.data_adjust & 0xFF00) | value as u16;
+                self.adjust_data_port(data_rom, 1);
+            }
+            0x15 => {
+                self.data_adjust = (self.data_adjust & 0xFF) | (value as u16) << 8;
+                self.adjust_data_port(data_rom, 2);
+            }
+            0x16 => self.data_stride = (self.data_stride & 0xFF00) | value as u16,
+            0x17 => self.data_stride = (self.data_stride & 0xFF) | (value as u16) << 8,
+            0x18 => {
+                self.data_control = value & 0x7F;
+                self.update_data_port(data_rom);
+            }
+
+            0x20..=0x24 | 0x26 => self.alu_regs[(addr & 0xF) as usize] = value,
+            0x25 => {
+                self.alu_regs[5] = value;
+                self.multiply(time);
+            }
+            0x27 => {
+                self.alu_regs[7] = value;
+                self.divide(time);
+            }
+            0x2E => self.alu_signed = value & 1 != 0,
+
+            0x30 => self.ram_control = value & 0x87,
+            0x31..=0x33 => self.data_rom_banks[(addr as usize & 0x3F) - 0x31] = value & 7,
+            0x34 => self.data_rom_size = value & 7,
+
+            _ => {}
+        }
+    }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.table_addr);
+        writer.write(&self.table_index);
+        writer.write(&self.dcu_offset_reg);
+        writer.write(&self.dcu_skip);
+        writer.write(&self.dcu_counter);
+        writer.write(&self.dcu_control);
+        writer.write(&self.dcu_ready_time);
+        writer.write(&self.dcu_mode);
+        writer.write(&self.dcu_addr);
+        writer.write(&self.dcu_tile);
+        writer.write(&self.dcu_tile_offset);
+        self.decompressor.save_state(writer);
+
+        writer.write(&self.data_port_value);
+        writer.write(&self.data_offset);
+        writer.write(&self.data_adjust);
+        writer.write(&self.data_stride);
+        writer.write(&self.data_control);
+
+        writer.write(&self.alu_regs);
+        writer.write(&self.alu_signed);
+        writer.write(&self.alu_busy_until);
+
+        writer.write(&self.ram_control);
+        writer.write(&self.data_rom_banks);
+        writer.write(&self.data_rom_size);
+
+        if let Some(rtc) = &self.rtc {
+            rtc.save_state(writer);
+        }
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.table_addr = reader.read()?;
+        self.table_index = reader.read()?;
+        self.dcu_offset_reg = reader.read()?;
+        self.dcu_skip = reader.read()?;
+        self.dcu_counter = reader.read()?;
+        self.dcu_control = reader.read()?;
+        self.dcu_ready_time = reader.read()?;
+        self.dcu_mode = reader.read()?;
+        self.dcu_addr = reader.read()?;
+        self.dcu_tile = reader.read()?;
+        self.dcu_tile_offset = reader.read()?;
+        self.decompressor.load_state(reader)?;
+
+        self.data_port_value = reader.read()?;
+        self.data_offset = reader.read()?;
+        self.data_adjust = reader.read()?;
+        self.data_stride = reader.read()?;
+        self.data_control = reader.read()?;
+
+        self.alu_regs = reader.read()?;
+        self.alu_signed = reader.read()?;
+        self.alu_busy_until = reader.read()?;
+
+        self.ram_control = reader.read()?;
+        self.data_rom_banks = reader.read()?;
+        self.data_rom_size = reader.read()?;
+
+        if self.dcu_tile_offset >= self.decompressor.bpp() << 3 {
+            return Err(LoadError::InvalidData);
+        }
+
+        if let Some(rtc) = &mut self.rtc {
+            rtc.load_state(reader)?;
+        }
+        Ok(())
+    }
+}
+
+impl Cart {
+    pub(super) fn map_spc7110(map: &mut Map, has_rtc: bool) {
+        // All handlers receive the unmodified bus address, as the ROM mapping depends on the
+        // SPC7110's registers
+        for banks in [(0x00, 0x3F), (0x80, 0xBF)] {
+            map.map::<true, true>(
+                Some(Self::handle_spc7110_io_read as ReadHandler),
+                Some(Self::handle_spc7110_io_write as WriteHandler),
+                banks,
+                (0x4800, 0x483F),
+                0,
+                1 << 24,
+                0,
+            );
+            if has_rtc {
+                map.map::<true, true>(
+                    Some(Self::handle_spc7110_rtc_read as ReadHandler),
+                    Some(Self::handle_spc7110_rtc_write as WriteHandler),
+                    banks,
+                    (0x4840, 0x4842),
+                    0,
+                    1 << 24,
+                    0,
+                );
+            }
+            map.map::<true, false>(
+                Some(Self::handle_spc7110_rom_read as ReadHandler),
+                None,
+                banks,
+                (0x8000, 0xFFFF),
+                0,
+                1 << 24,
+                0,
+            );
+        }
+        map.map::<true, false>(
+            Some(Self::handle_spc7110_rom_read as ReadHandler),
+            None,
+            (0xC0, 0xFF),
+            (0x0000, 0xFFFF),
+            0,
+            1 << 24,
+            0,
+        );
+        // Banks 50 and 58 mirror the decompressed data port and $4808 respectively
+        map.map::<true, true>(
+            Some(Self::handle_spc7110_io_read as ReadHandler),
+            Some(Self::handle_spc7110_io_write as WriteHandler),
+            (0x50, 0x50),
+            (0x0000, 0xFFFF),
+            0,
+            1 << 24,
+            0,
+        );
+        map.map::<true, true>(
+            Some(Self::handle_spc7110_io_read as ReadHandler),
+            Some(Self::handle_spc7110_io_write as WriteHandler),
+            (0x58, 0x58),
+            (0x0000, 0xFFFF),
+            0,
+            1 << 24,
+            0,
+        );
+    }
+
+    fn spc7110_parts(&mut self) -> (&mut Spc7110, &[u8], &[u8]) {
+        let (program_rom, data_rom) = self.rom[..].split_at(PROGRAM_ROM_SIZE.min(self.rom.len()));
+        match &mut self.coprocessor {
+            Some(super::Coprocessor::Spc7110(spc7110)) => (spc7110, program_rom, data_rom),
+            _ => unreachable!(),
+        }
+    }
+
+    fn handle_spc7110_io_read(&mut self, addr: u32) -> u8 {
+        let time = self.cur_time;
+        let reg = match addr >> 16 & 0x7F {
+            0x50 => 0x4800,
+            0x58 => 0x4808,
+            _ => addr as u16,
+        };
+        let (spc7110, _, data_rom) = self.spc7110_parts();
+        spc7110.read_io(reg, data_rom, time)
+    }
+
+    fn handle_spc7110_io_write(&mut self, addr: u32, value: u8) {
+        let time = self.cur_time;
+        let reg = match addr >> 16 & 0x7F {
+            0x50 => 0x4800,
+            0x58 => 0x4808,
+            _ => addr as u16,
+        };
+        let (spc7110, _, data_rom) = self.spc7110_parts();
+        spc7110.write_io(reg, value, data_rom, time);
+    }
+
+    fn handle_spc7110_rtc_read(&mut self, addr: u32) -> u8 {
+        let time = self.cur_time;
+        let rtc = self.spc7110_parts().0.rtc_mut().unwrap();
+        rtc.sync(time);
+        rtc.read_io(addr as u16)
+    }
+
+    fn handle_spc7110_rtc_write(&mut self, addr: u32, value: u8) {
+        let time = self.cur_time;
+        let rtc = self.spc7110_parts().0.rtc_mut().unwrap();
+        rtc.sync(time);
+        rtc.write_io(addr as u16, value);
+    }
+
+    fn handle_spc7110_rom_read(&mut self, addr: u32) -> u8 {
+        let bank = (addr >> 16) as u8;
+        let (spc7110, program_rom, data_rom) = self.spc7110_parts();
+        spc7110.read_rom(
+            program_rom,
+            data_rom,
+            bank >> 4 & 3,
+            ((bank & 0xF) as u32) << 16 | (addr & 0xFFFF),
+        )
+    }
+
+    pub(super) fn handle_spc7110_ram_read(&mut self, offset: u32) -> u8 {
+        if self.spc7110_parts().0.ram_enabled() {
+            self.ram[offset as usize]
+        } else {
+            0
+        }
+    }
+
+    pub(super) fn handle_spc7110_ram_write(&mut self, offset: u32, value: u8) {
+        if self.spc7110_parts().0.ram_enabled() {
+            self.ram_modified = true;
+            self.ram[offset as usize] = value;
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use crate::savestate::{LoadError, Reader, Writer};
+
+const HALF: u32 = 0x55;
+const MAX: u32 = 0xFF;
+
+#[derive(Clone, Copy)]
+struct ModelState {
+    probability: u8,
+    next_if_mps: u8,
+    next_if_lps: u8,
+}
+
+macro_rules! model_states {
+    ($(($probability: expr, $next_if_mps: expr, $next_if_lps: expr)),*$(,)?) => {
+        [$(ModelState {
+            probability: $probability,
+            next_if_mps: $next_if_mps,
+            next_if_lps: $next_if_lps,
+        }),*]
+    };
+}
+
+static EVOLUTION_TABLE: [ModelState; 53] = model_states![
+    (0x5A, 1, 1),
+    (0x25, 2, 6),
+    (0x11, 3, 8),
+    (0x08, 4, 10),
+    (0x03, 5, 12),
+    (0x01, 5, 15),
+    (0x5A, 7, 7),
+    (0x3F, 8, 19),
+    (0x2C, 9, 21),
+    (0x20, 10, 22),
+    (0x17, 11, 23),
+    (0x11, 12, 25),
+    (0x0C, 13, 26),
+    (0x09, 14, 28),
+    (0x07, 15, 29),
+    (0x05, 16, 31),
+    (0x04, 17, 32),
+    (0x03, 18, 34),
+    (0x02, 5, 35),
+    (0x5A, 20, 20),
+    (0x48, 21, 39),
+    (0x3A, 22, 40),
+    (0x2E, 23, 42),
+    (0x26, 24, 44),
+    (0x1F, 25, 45),
+    (0x19, 26, 46),
+    (0x15, 27, 25),
+    (0x11, 28, 26),
+    (0x0E, 29, 26),
+    (0x0B, 30, 27),
+    (0x09, 31, 28),
+    (0x08, 32, 29),
+    (0x07, 33, 30),
+    (0x05, 34, 31),
+    (0x04, 35, 33),
+    (0x04, 36, 33),
+    (0x03, 37, 34),
+    (0x02, 38, 35),
+    (0x02, 5, 36),
+    (0x58, 40, 39),
+    (0x4D, 41, 47),
+    (0x43, 42, 48),
+    (0x3B, 43, 49),
+    (0x34, 44, 50),
+    (0x2E, 45, 51),
+    (0x29, 46, 44),
+    (0x25, 24, 45),
+    (0x56, 48, 47),
+    (0x4F, 49, 47),
+    (0x47, 50, 48),
+    (0x41, 51, 49),
+    (0x3C, 52, 50),
+    (0x37, 43, 51),
+];
+
+#[derive(Clone, Copy, Default)]
+struct Context {
+    prediction: u8,
+    swap: bool,
+}
+
+/// Unpacks big-endian packed pixels, returning their odd bits in the lower half of the result and
+/// their even bits in the upper half.
+fn deinterleave(data: u64, bits: u32) -> u32 {
+    let mut data = data & ((1 << bits) - 1);
+    data = 0x5555_5555_5555_5555 & (data << bits | data >> 1);
+    data = 0x3333_3333_3333_3333 & (data | data >> 1);
+    data = 0x0F0F_0F0F_0F0F_0F0F & (data | data >> 2);
+    data = 0x00FF_00FF_00FF_00FF & (data | data >> 4);
+    data = 0x0000_FFFF_0000_FFFF & (data | data >> 8);
+    (data | data >> 16) as u32
+}
+
+/// Moves the given nibble to the front (lowest 4 bits) of a list of nibbles.
+fn move_to_front(list: u64, nibble: u64) -> u64 {
+    let mut mask = !0xF;
+    for shift in (0..64).step_by(4) {
+        if list >> shift & 0xF == nibble {
+            return (list & mask) | (list << 4 & !mask) | nibble;
+        }
+        mask <<= 4;
+    }
+    list
+}
+
+/// The SPC7110's data decompressor, which decodes 1, 2 or 4 bits per pixel graphics using an
+/// adaptive binary arithmetic coder, with contexts chosen based on the neighboring pixels.
+#[derive(Clone)]
+pub(super) struct Decompressor {
+    bpp: u8,
+    offset: u32,
+    bits: u8,
+    range: u32,
+    input: u32,
+    output: u32,
+    pixels: u64,
+    color_map: u64,
+    contexts: [[Context; 15]; 5],
+    result: u32,
+}
+
+impl Decompressor {
+    pub(super) fn new() -> Self {
+        Decompressor {
+            bpp: 1,
+            offset: 0,
+            bits: 8,
+            range: MAX + 1,
+            input: 0,
+            output: 0,
+            pixels: 0,
+            color_map: 0xFEDC_BA98_7654_3210,
+            contexts: [[Context::default(); 15]; 5],
+            result: 0,
+        }
+    }
+
+    #[inline]
+    pub(super) fn bpp(&self) -> u8 {
+        self.bpp
+    }
+
+    /// Returns the last 8 decoded pixels, as a planar row of tile data.
+    #[inline]
+    pub(super) fn result(&self) -> u32 {
+        self.result
+    }
+
+    /// Starts decompressing a stream at the given offset in the data ROM, with the given mode
+    /// selecting 1, 2 or 4 bits per pixel.
+    pub(super) fn start(&mut self, mode: u8, offset: u32, read: &mut impl FnMut(u32) -> u8) {
+        *self = Decompressor {
+            bpp: 1 << mode,
+            offset: offset.wrapping_add(2),
+            input: (read(offset) as u32) << 8 | read(offset.wrapping_add(1)) as u32,
+            ..Decompressor::new()
+        };
+    }
+
+    /// Decodes the next 8 pixels.
+    pub(super) fn decode(&mut self, read: &mut impl FnMut(u32) -> u8) {
+        let bpp = self.bpp as u32;
+        for pixel in 0..8 {
+            let mut map = self.color_map;
+            let mut diff = 0;
+
+            if bpp > 1 {
+                let (a, b, c) = if bpp == 2 {
+                    (
+                        self.pixels >> 2 & 3,
+                        self.pixels >> 14 & 3,
+                        self.pixels >> 16 & 3,
+                    )
+                } else {
+                    (
+                        self.pixels & 0xF,
+                        self.pixels >> 28 & 0xF,
+                        self.pixels >> 32 & 0xF,
+                    )
+                };
+
+                if a != b || b != c {
+                    let matching = a ^ b ^ c;
+                    diff = if matching == a {
+                        // B == C, A differs
+                        1
+                    } else if matching == b {
+                        // A == C, B differs
+                        2
+                    } else if matching == c {
+                        // A == B, C differs
+                        3
+                    } else {
+                        4
+                    };
+                }
+
+                self.color_map = move_to_front(self.color_map, a);
+
+                map = move_to_front(map, c);
+                map = move_to_front(map, b);
+                map = move_to_front(map, a);
+            }
+
+            for plane in 0..bpp {
+                let bit = if bpp > 1 {
+                    1 << plane
+                } else {
+                    1 << (pixel & 3)
+                };
+                let history = (bit - 1) & self.output;
+                let set = if plane >= 2 && history <= 1 {
+                    diff
+                } else {
+                    match bpp {
+                        1 => (pixel >= 4) as usize,
+                        2 => diff,
+                        _ => 0,
+                    }
+                };
+
+                let context = &mut self.contexts[set][(bit + history - 1) as usize];
+                let model = EVOLUTION_TABLE[context.prediction as usize];
+                let lps_offset = self.range - model.probability as u32;
+                let is_lps = self.input >= lps_offset << 8;
+
+                self.output = self.output << 1 | (is_lps ^ context.swap) as u32;
+
+                if is_lps {
+                    self.range -= lps_offset;
+                    self.input -= lps_offset << 8;
+                } else {
+                    self.range = lps_offset;
+                }
+
+                // Renormalize, updating the context's prediction if needed
+                while self.range <= MAX / 2 {
+                    context.prediction = if is_lps {
+                        model.next_if_lps
+                    } else {
+                        model.next_if_mps
+                    };
+
+                    self.range <<= 1;
+                    self.input <<= 1;
+
+                    self.bits -= 1;
+                    if self.bits == 0 {
+                        self.bits = 8;
+                        self.input += read(self.offset) as u32;
+                        self.offset = self.offset.wrapping_add(1);
+                    }
+                }
+
+                if is_lps && model.probability as u32 > HALF {
+                    context.swap = !context.swap;
+                }
+            }
+
+            let mut index = self.output & ((1 << bpp) - 1);
+            if bpp == 1 {
+                index ^= (self.pixels >> 15) as u32 & 1;
+            }
+
+            self.pixels = self.pixels << bpp | (map >> (4 * index) & ((1 << bpp) - 1));
+        }
+
+        self.result = match bpp {
+            1 => self.pixels as u32,
+            2 => deinterleave(self.pixels, 16),
+            _ => deinterleave(deinterleave(self.pixels, 32) as u64, 32),
+        };
+    }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.bpp);
+        writer.write(&self.offset);
+        writer.write(&self.bits);
+        writer.write(&self.range);
+        writer.write(&self.input);
+        writer.write(&self.output);
+        writer.write(&self.pixels);
+        writer.write(&self.color_map);
+        for set in &self.contexts {
+            for context in set {
+                writer.write(&context.prediction);
+                writer.write(&context.swap);
+            }
+        }
+        writer.write(&self.result);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.bpp = reader.read()?;
+        self.offset = reader.read()?;
+        self.bits = reader.read()?;
+        self.range = reader.read()?;
+        self.input = reader.read()?;
+        self.output = reader.read()?;
+        self.pixels = reader.read()?;
+        self.color_map = reader.read()?;
+        for set in &mut self.contexts {
+            for context in set {
+                context.prediction = reader.read()?;
+                context.swap = reader.read()?;
+            }
+        }
+        self.result = reader.read()?;
+
+        if !matches!(self.bpp, 1 | 2 | 4)
+            || !(1..=8).contains(&self.bits)
+            || self.range > MAX + 1
+            || self.contexts.iter().any(|set| {
+                set.iter()
+                    .any(|context| context.prediction as usize >= EVOLUTION_TABLE.len())
+            })
+        {
+            return Err(LoadError::InvalidData);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,552 @@
+use crate::{
+    cart::date_time::DateTime,
+    savestate::{LoadError, Reader, Writer},
+    schedule::Timestamp,
+    Model,
+};
+
+const CLOCK_FREQUENCY: u64 = 32_768;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Mode,
+    Seek,
+    Read,
+    Write,
+}
+
+/// The Epson RTC-4513 real-time clock, accessed serially through $4840-$4842.
+///
+/// Time is kept as BCD nibbles like on the real chip, and advances with emulated time while the
+/// game is running; when persisted, the host time is stored too, so that the clock can be caught up
+/// with the time elapsed while the emulator wasn't running.
+#[derive(Clone)]
+pub struct Rtc {
+    master_clock_frequency: u64,
+    cur_time: Timestamp,
+    sub_second_cycles: u64,
+    ready_time: Timestamp,
+
+    chip_select: u8,
+    state: State,
+    mdr: u8,
+    offset: u8,
+
+    // Stored as raw (and possibly invalid) BCD digits
+    second: u8,
+    minute: u8,
+    hour: u8,
+    day: u8,
+    month: u8,
+    year: u8,
+    weekday: u8,
+
+    battery_failure: bool,
+    resync: bool,
+    pm: bool,
+    day_ram: bool,
+    month_ram: u8,
+
+    hold: bool,
+    hold_tick: bool,
+    calendar: bool,
+    irq_flag: bool,
+    round_seconds: bool,
+    irq_mask: bool,
+    irq_duty: bool,
+    irq_period: u8,
+    pause: bool,
+    stop: bool,
+    is_24_hour: bool,
+    test: bool,
+}
+
+/// The length of the persistent RTC data returned by [`Rtc::data`].
+pub const DATA_LEN: usize = 0x20;
+
+fn bcd_inc(value: u8) -> u8 {
+    if value & 0xF >= 9 {
+        (value & 0xF0) + 0x10
+    } else {
+        value + 1
+    }
+}
+
+fn bcd_to_bin(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0xF)
+}
+
+impl Rtc {
+    pub(super) fn new() -> Self {
+        Rtc {
+            master_clock_frequency: 21_477_270,
+            cur_time: 0,
+            sub_second_cycles: 0,
+            ready_time: 0,
+
+            chip_select: 0,
+            state: State::Mode,
+            mdr: 0,
+            offset: 0,
+
+            second: 0,
+            minute: 0,
+            hour: 0,
+            day: 1,
+            month: 1,
+            year: 0,
+            weekday: 6,
+
+            battery_failure: true,
+            resync: false,
+            pm: false,
+            day_ram: false,
+            month_ram: 0,
+
+            hold: false,
+            hold_tick: false,
+            calendar: true,
+            irq_flag: false,
+            round_seconds: false,
+            irq_mask: true,
+            irq_duty: false,
+            irq_period: 0,
+            pause: false,
+            stop: false,
+            is_24_hour: true,
+            test: false,
+        }
+    }
+
+    pub(super) fn setup(&mut self, model: Model, time: Timestamp) {
+        self.master_clock_frequency = match model {
+            Model::Ntsc => 21_477_270,
+            Model::Pal => 21_281_370,
+        };
+        self.cur_time = time;
+    }
+
+    pub(super) fn soft_reset(&mut self) {
+        self.chip_select = 0;
+        self.state = State::Mode;
+        self.mdr = 0;
+        self.offset = 0;
+    }
+
+    /// Advances the clock to the given timestamp.
+    pub(crate) fn sync(&mut self, time: Timestamp) {
+        if time <= self.cur_time {
+            return;
+        }
+        let prev_cycles = self.sub_second_cycles;
+        self.sub_second_cycles += time - self.cur_time;
+        self.cur_time = time;
+        // The 1/64 s interrupt period is only checked here, the others are raised while ticking
+        let interval = self.master_clock_frequency / 64;
+        if self.irq_period == 0 && self.sub_second_cycles / interval != prev_cycles / interval {
+            self.raise_irq(0);
+        }
+        while self.sub_second_cycles >= self.master_clock_frequency {
+            self.sub_second_cycles -= self.master_clock_frequency;
+            self.tick();
+        }
+    }
+
+    fn raise_irq(&mut self, period: u8) {
+        if !self.stop && !self.pause && period == self.irq_period {
+            self.irq_flag = true;
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.stop || self.pause {
+            return;
+        }
+        if self.hold {
+            self.hold_tick = true;
+            return;
+        }
+        self.resync = true;
+        self.tick_second();
+    }
+
+    fn tick_second(&mut self) {
+        self.raise_irq(1);
+        if self.second >= 0x59 {
+            self.second = 0;
+            self.tick_minute();
+        } else {
+            self.second = bcd_inc(self.second);
+        }
+    }
+
+    fn tick_minute(&mut self) {
+        self.raise_irq(2);
+        if self.minute >= 0x59 {
+            self.minute = 0;
+            self.tick_hour();
+        } else {
+            self.minute = bcd_inc(self.minute);
+        }
+    }
+
+    fn tick_hour(&mut self) {
+        self.raise_irq(3);
+        if self.is_24_hour {
+            if self.hour >= 0x23 {
+                self.hour = 0;
+                self.tick_day();
+            } else {
+                self.hour = bcd_inc(self.hour);
+            }
+        } else if self.hour >= 0x11 {
+            self.hour = 0;
+            self.pm = !self.pm;
+            if !self.pm {
+                self.tick_day();
+            }
+        } else {
+            self.hour = bcd_inc(self.hour);
+        }
+    }
+
+    fn days_in_month(&self) -> u8 {
+        match bcd_to_bin(self.month) {
+            2 if bcd_to_bin(self.year) & 3 == 0 => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+
+    fn tick_day(&mut self) {
+        if !self.calendar {
+            return;
+        }
+        self.weekday = if self.weekday >= 6 {
+            0
+        } else {
+            self.weekday + 1
+        };
+        if bcd_to_bin(self.day) >= self.days_in_month() {
+            self.day = 1;
+            self.tick_month();
+        } else {
+            self.day = bcd_inc(self.day);
+        }
+    }
+
+    fn tick_month(&mut self) {
+        if self.month >= 0x12 {
+            self.month = 1;
+            self.year = if self.year >= 0x99 {
+                0
+            } else {
+                bcd_inc(self.year)
+            };
+        } else {
+            self.month = bcd_inc(self.month);
+        }
+    }
+
+    fn clear_hour_bits(&mut self) {
+        if self.is_24_hour {
+            self.pm = false;
+        } else {
+            self.hour &= 0x1F;
+        }
+    }
+
+    /// Sets the clock to the given date and time.
+    pub(crate) fn set_time(&mut self, date_time: &DateTime) {
+        let to_bcd = |value: u8| ((value / 10) << 4) | (value % 10);
+        self.second = to_bcd(date_time.second);
+        self.minute = to_bcd(date_time.minute);
+        if self.is_24_hour {
+            self.hour = to_bcd(date_time.hour);
+            self.pm = false;
+        } else {
+            self.hour = to_bcd(date_time.hour % 12);
+            self.pm = date_time.hour >= 12;
+        }
+        self.day = to_bcd(date_time.day);
+        self.month = to_bcd(date_time.month);
+        self.year = to_bcd(date_time.year.rem_euclid(100) as u8);
+        self.weekday = date_time.weekday;
+        self.sub_second_cycles = 0;
+        self.battery_failure = false;
+    }
+
+    fn reg(&self, index: u8) -> u8 {
+        let resync = (self.resync as u8) << 3;
+        match index & 0xF {
+            0 => self.second & 0xF,
+            1 => (self.second >> 4 & 7) | (self.battery_failure as u8) << 3,
+            2 => self.minute & 0xF,
+            3 => (self.minute >> 4 & 7) | resync,
+            4 => self.hour & 0xF,
+            5 => (self.hour >> 4 & 3) | (self.pm as u8) << 2 | resync,
+            6 => self.day & 0xF,
+            7 => (self.day >> 4 & 3) | (self.day_ram as u8) << 2 | resync,
+            8 => self.month & 0xF,
+            9 => (self.month >> 4 & 1) | self.month_ram << 1 | resync,
+            10 => self.year & 0xF,
+            11 => self.year >> 4,
+            12 => self.weekday | resync,
+            13 => {
+                self.hold as u8
+                    | (self.calendar as u8) << 1
+                    | ((self.irq_flag && !self.irq_mask) as u8) << 2
+                    | (self.round_seconds as u8) << 3
+            }
+            14 => self.irq_mask as u8 | (self.irq_duty as u8) << 1 | self.irq_period << 2,
+            _ => {
+                self.pause as u8
+                    | (self.stop as u8) << 1
+                    | (self.is_24_hour as u8) << 2
+                    | (self.test as u8) << 3
+            }
+        }
+    }
+
+    fn read_reg(&mut self, index: u8) -> u8 {
+        let value = self.reg(index);
+        if index & 0xF == 13 {
+            self.irq_flag = false;
+        }
+        value
+    }
+
+    fn write_reg(&mut self, index: u8, value: u8) {
+        match index & 0xF {
+            0 => self.second = (self.second & 0x70) | value,
+            1 => {
+                self.second = (self.second & 0xF) | (value & 7) << 4;
+                self.battery_failure = value & 8 != 0;
+            }
+            2 => self.minute = (self.minute & 0x70) | value,
+            3 => self.minute = (self.minute & 0xF) | (value & 7) << 4,
+            4 => self.hour = (self.hour & 0x30) | value,
+            5 => {
+                self.hour = (self.hour & 0xF) | (value & 3) << 4;
+                self.pm = value & 4 != 0;
+                self.clear_hour_bits();
+            }
+            6 => self.day = (self.day & 0x30) | value,
+            7 => {
+                self.day = (self.day & 0xF) | (value & 3) << 4;
+                self.day_ram = value & 4 != 0;
+            }
+            8 => self.month = (self.month & 0x10) | value,
+            9 => {
+                self.month = (self.month & 0xF) | (value & 1) << 4;
+                self.month_ram = value >> 1 & 3;
+            }
+            10 => self.year = (self.year & 0xF0) | value,
+            11 => self.year = (self.year & 0xF) | value << 4,
+            12 => self.weekday = value & 7,
+            13 => {
+                let was_held = self.hold;
+                self.hold = value & 1 != 0;
+                self.calendar = value & 2 != 0;
+                // The IRQ flag can't be set manually
+                self.round_seconds = value & 8 != 0;
+                if self.round_seconds {
+                    self.round_seconds = false;
+                    if self.second >= 0x30 {
+                        self.tick_minute();
+                    }
+                    self.second = 0;
+                }
+                // If a second passed while the clock was held, it's counted once it resumes
+                if was_held && !self.hold && self.hold_tick {
+                    self.hold_tick = false;
+                    self.tick_second();
+                }
+            }
+            14 => {
+                self.irq_mask = value & 1 != 0;
+                self.irq_duty = value & 2 != 0;
+                self.irq_period = value >> 2 & 3;
+            }
+            _ => {
+                self.pause = value & 1 != 0;
+                self.stop = value & 2 != 0;
+                self.is_24_hour = value & 4 != 0;
+                self.test = value & 8 != 0;
+                self.clear_hour_bits();
+                if self.pause {
+                    self.second = 0;
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn ready(&self) -> bool {
+        self.cur_time >= self.ready_time
+    }
+
+    fn start_wait(&mut self) {
+        // Each serial transfer takes 8 cycles of the RTC's 32.768 kHz clock
+        self.ready_time = self.cur_time + 8 * self.master_clock_frequency / CLOCK_FREQUENCY;
+    }
+
+    pub(super) fn read_io(&mut self, addr: u16) -> u8 {
+        match addr & 3 {
+            0 => self.chip_select,
+            1 => {
+                if self.chip_select != 1 || !self.ready() {
+                    return 0;
+                }
+                match self.state {
+                    State::Write => self.mdr,
+                    State::Read => {
+                        self.start_wait();
+                        let value = self.read_reg(self.offset);
+                        self.offset = (self.offset + 1) & 0xF;
+                        value
+                    }
+                    _ => 0,
+                }
+            }
+            2 => (self.ready() as u8) << 7,
+            _ => 0,
+        }
+    }
+
+    pub(super) fn write_io(&mut self, addr: u16, value: u8) {
+        let value = value & 0xF;
+        match addr & 3 {
+            0 => {
+                self.chip_select = value;
+                if value != 1 {
+                    self.state = State::Mode;
+                    self.mdr = 0;
+                    self.offset = 0;
+                    self.resync = false;
+                }
+                self.ready_time = self.cur_time;
+            }
+            1 => {
+                if self.chip_select != 1 || !self.ready() {
+                    return;
+                }
+                match self.state {
+                    State::Mode => {
+                        if value != 3 && value != 0xC {
+                            return;
+                        }
+                        self.state = State::Seek;
+                    }
+                    State::Seek => {
+                        self.state = if self.mdr == 3 {
+                            State::Write
+                        } else {
+                            State::Read
+                        };
+                        self.offset = value;
+                    }
+                    State::Write => {
+                        self.write_reg(self.offset, value);
+                        self.offset = (self.offset + 1) & 0xF;
+                    }
+                    State::Read => return,
+                }
+                self.start_wait();
+                self.mdr = value;
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the clock's persistent state, along with the given host time in seconds since the
+    /// Unix epoch.
+    pub fn data(&self, unix_time: u64) -> [u8; DATA_LEN] {
+        let mut result = [0; DATA_LEN];
+        for (i, byte) in result[..0x10].iter_mut().enumerate() {
+            *byte = self.reg(i as u8);
+        }
+        result[0x10..0x18].copy_from_slice(&unix_time.to_le_bytes());
+        result
+    }
+
+    /// Restores the persistent state returned by [`Rtc::data`], advancing the clock by the host
+    /// time elapsed since then. Returns whether the data was valid.
+    pub fn load_data(&mut self, data: &[u8], unix_time: u64) -> bool {
+        if data.len() != DATA_LEN {
+            return false;
+        }
+        // Control registers need to be restored last, as they affect how time registers are
+        // written
+        if data[..0x10].iter().any(|reg| *reg > 0xF) {
+            return false;
+        }
+        for (i, &value) in data[..0x10].iter().enumerate().rev() {
+            self.write_reg(i as u8, value);
+        }
+        self.hold = data[13] & 1 != 0;
+        self.irq_flag = data[13] & 4 != 0;
+        self.resync = data[3] & 8 != 0;
+
+        let mut elapsed =
+            unix_time.saturating_sub(u64::from_le_bytes(data[0x10..0x18].try_into().unwrap()));
+        if self.stop || self.pause {
+            return true;
+        }
+        while elapsed >= 60 * 60 * 24 {
+            self.tick_day();
+            elapsed -= 60 * 60 * 24;
+        }
+        for _ in 0..elapsed {
+            self.tick_second();
+        }
+        true
+    }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.cur_time);
+        writer.write(&self.sub_second_cycles);
+        writer.write(&self.ready_time);
+        writer.write(&self.chip_select);
+        writer.write(&(self.state as u8));
+        writer.write(&self.mdr);
+        writer.write(&self.offset);
+        writer.write(&self.hold_tick);
+        let mut regs = [0; 0x10];
+        for (i, reg) in regs.iter_mut().enumerate() {
+            *reg = self.reg(i as u8);
+        }
+        writer.write(&regs);
+        writer.write(&self.irq_flag);
+        writer.write(&self.resync);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.cur_time = reader.read()?;
+        self.sub_second_cycles = reader.read()?;
+        self.ready_time = reader.read()?;
+        self.chip_select = reader.read()?;
+        self.state = match reader.read::<u8>()? {
+            0 => State::Mode,
+            1 => State::Seek,
+            2 => State::Read,
+            3 => State::Write,
+            _ => return Err(LoadError::InvalidData),
+        };
+        self.mdr = reader.read()?;
+        self.offset = reader.read()?;
+        self.hold_tick = reader.read()?;
+        let regs: [u8; 0x10] = reader.read()?;
+        if regs.iter().any(|reg| *reg > 0xF) || self.offset > 0xF {
+            return Err(LoadError::InvalidData);
+        }
+        for (i, &value) in regs.iter().enumerate().rev() {
+            self.write_reg(i as u8, value);
+        }
+        self.hold = regs[13] & 1 != 0;
+        self.irq_flag = reader.read()?;
+        self.resync = reader.read()?;
+        Ok(())
+    }
+}
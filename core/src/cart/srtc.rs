@@ -0,0 +1,367 @@
+use super::{
+    date_time::{self, DateTime},
+    info,
+    map::{Map, ReadHandler, WriteHandler},
+    Cart,
+};
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    schedule::Timestamp,
+    Model,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Ready,
+    Command,
+    Read,
+    Write,
+}
+
+/// The length of the persistent RTC data returned by [`SRtc::data`].
+pub const DATA_LEN: usize = 0x18;
+
+// The number of time registers, followed by the (read-only) weekday
+const TIME_REGS: u8 = 12;
+
+/// The state of the Sharp S-RTC, a real-time clock accessed serially through $2800 (data reads)
+/// and $2801 (commands and data writes), one decimal digit at a time.
+///
+/// Years are counted starting from 1000. Like the SPC7110's RTC, time advances with emulated time
+/// while the game is running, and is caught up with the host time elapsed since it was last saved
+/// when loading its persistent state; it can also be set directly from the host's clock.
+#[derive(Clone)]
+pub struct SRtc {
+    master_clock_frequency: u64,
+    cur_time: Timestamp,
+    sub_second_cycles: u64,
+
+    state: State,
+    index: i8,
+
+    second: u8,
+    minute: u8,
+    hour: u8,
+    day: u8,
+    month: u8,
+    year: u16,
+    weekday: u8,
+}
+
+impl SRtc {
+    pub(super) fn new() -> Self {
+        SRtc {
+            master_clock_frequency: 21_477_270,
+            cur_time: 0,
+            sub_second_cycles: 0,
+
+            state: State::Read,
+            index: -1,
+
+            second: 0,
+            minute: 0,
+            hour: 0,
+            day: 1,
+            month: 1,
+            year: 0,
+            weekday: date_time::weekday(1000, 1, 1),
+        }
+    }
+
+    pub(super) fn setup(&mut self, model: Model, time: Timestamp) {
+        self.master_clock_frequency = match model {
+            Model::Ntsc => 21_477_270,
+            Model::Pal => 21_281_370,
+        };
+        self.cur_time = time;
+    }
+
+    pub(super) fn soft_reset(&mut self) {
+        self.state = State::Read;
+        self.index = -1;
+    }
+
+    /// Advances the clock to the given timestamp.
+    pub(super) fn sync(&mut self, time: Timestamp) {
+        if time <= self.cur_time {
+            return;
+        }
+        self.sub_second_cycles += time - self.cur_time;
+        self.cur_time = time;
+        while self.sub_second_cycles >= self.master_clock_frequency {
+            self.sub_second_cycles -= self.master_clock_frequency;
+            self.tick_second();
+        }
+    }
+
+    fn tick_second(&mut self) {
+        self.second += 1;
+        if self.second >= 60 {
+            self.second = 0;
+            self.tick_minute();
+        }
+    }
+
+    fn tick_minute(&mut self) {
+        self.minute += 1;
+        if self.minute >= 60 {
+            self.minute = 0;
+            self.tick_hour();
+        }
+    }
+
+    fn tick_hour(&mut self) {
+        self.hour += 1;
+        if self.hour >= 24 {
+            self.hour = 0;
+            self.tick_day();
+        }
+    }
+
+    fn tick_day(&mut self) {
+        self.weekday = (self.weekday + 1) % 7;
+        let days_in_month = date_time::days_in_month(1000 + self.year as i64, self.month);
+        if self.day >= days_in_month {
+            self.day = 1;
+            self.month += 1;
+            if self.month > 12 {
+                self.month = 1;
+                self.year = (self.year + 1) & 0xFFF;
+            }
+        } else {
+            self.day += 1;
+        }
+    }
+
+    /// Sets the clock to the given date and time.
+    pub(super) fn set_time(&mut self, date_time: &DateTime) {
+        self.second = date_time.second;
+        self.minute = date_time.minute;
+        self.hour = date_time.hour;
+        self.day = date_time.day;
+        self.month = date_time.month;
+        self.year = (date_time.year - 1000).clamp(0, 0xFFF) as u16;
+        self.weekday = date_time.weekday;
+        self.sub_second_cycles = 0;
+    }
+
+    fn reg(&self, index: u8) -> u8 {
+        match index {
+            0 => self.second % 10,
+            1 => self.second / 10,
+            2 => self.minute % 10,
+            3 => self.minute / 10,
+            4 => self.hour % 10,
+            5 => self.hour / 10,
+            6 => self.day % 10,
+            7 => self.day / 10,
+            8 => self.month,
+            9 => (self.year % 10) as u8,
+            10 => (self.year / 10 % 10) as u8,
+            11 => (self.year / 100) as u8,
+            _ => self.weekday,
+        }
+    }
+
+    fn write_reg(&mut self, index: u8, value: u8) {
+        match index {
+            0 => self.second = self.second / 10 * 10 + value,
+            1 => self.second = value * 10 + self.second % 10,
+            2 => self.minute = self.minute / 10 * 10 + value,
+            3 => self.minute = value * 10 + self.minute % 10,
+            4 => self.hour = self.hour / 10 * 10 + value,
+            5 => self.hour = value * 10 + self.hour % 10,
+            6 => self.day = self.day / 10 * 10 + value,
+            7 => self.day = value * 10 + self.day % 10,
+            8 => self.month = value,
+            9 => self.year = self.year / 10 * 10 + value as u16,
+            10 => self.year = self.year / 100 * 100 + value as u16 * 10 + self.year % 10,
+            11 => self.year = value as u16 * 100 + self.year % 100,
+            _ => self.weekday = value % 7,
+        }
+    }
+
+    /// Recalculates the day of the week after the date was written, as the chip does
+    /// automatically.
+    fn update_weekday(&mut self) {
+        self.weekday = date_time::weekday(
+            1000 + self.year as i64,
+            self.month.clamp(1, 12),
+            self.day.clamp(1, 31),
+        );
+    }
+
+    fn read_data(&mut self) -> u8 {
+        if self.state != State::Read {
+            return 0;
+        }
+        // Reads are framed by 0xF nibbles before the first and after the last register
+        if self.index < 0 || self.index > TIME_REGS as i8 {
+            self.index = if self.index < 0 { 0 } else { -1 };
+            return 0xF;
+        }
+        let value = self.reg(self.index as u8);
+        self.index += 1;
+        value
+    }
+
+    fn write_command(&mut self, value: u8) {
+        let value = value & 0xF;
+        match value {
+            0xD => {
+                self.state = State::Read;
+                self.index = -1;
+            }
+            0xE => self.state = State::Command,
+            0xF => {}
+            _ => match self.state {
+                State::Command => {
+                    if value == 0 {
+                        self.state = State::Write;
+                        self.index = 0;
+                    } else {
+                        self.state = State::Ready;
+                        self.index = -1;
+                        if value == 4 {
+                            self.second = 0;
+                            self.minute = 0;
+                            self.hour = 0;
+                            self.day = 0;
+                            self.month = 0;
+                            self.year = 0;
+                            self.weekday = 0;
+                        }
+                    }
+                }
+                State::Write if (0..TIME_REGS as i8).contains(&self.index) => {
+                    self.write_reg(self.index as u8, value);
+                    self.index += 1;
+                    if self.index == TIME_REGS as i8 {
+                        self.update_weekday();
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+
+    /// Returns the clock's persistent state, along with the given host time in seconds since the
+    /// Unix epoch.
+    pub fn data(&self, unix_time: u64) -> [u8; DATA_LEN] {
+        let mut result = [0; DATA_LEN];
+        for (i, byte) in result[..=TIME_REGS as usize].iter_mut().enumerate() {
+            *byte = self.reg(i as u8);
+        }
+        result[0x10..0x18].copy_from_slice(&unix_time.to_le_bytes());
+        result
+    }
+
+    /// Restores the persistent state returned by [`SRtc::data`], advancing the clock by the host
+    /// time elapsed since then. Returns whether the data was valid.
+    pub fn load_data(&mut self, data: &[u8], unix_time: u64) -> bool {
+        if data.len() != DATA_LEN || data[..=TIME_REGS as usize].iter().any(|&reg| reg > 0xF) {
+            return false;
+        }
+        for (i, &value) in data[..=TIME_REGS as usize].iter().enumerate() {
+            self.write_reg(i as u8, value);
+        }
+
+        let mut elapsed =
+            unix_time.saturating_sub(u64::from_le_bytes(data[0x10..0x18].try_into().unwrap()));
+        while elapsed >= 60 * 60 * 24 {
+            self.tick_day();
+            elapsed -= 60 * 60 * 24;
+        }
+        for _ in 0..elapsed {
+            self.tick_second();
+        }
+        true
+    }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.cur_time);
+        writer.write(&self.sub_second_cycles);
+        writer.write(&(self.state as u8));
+        writer.write(&self.index);
+        writer.write(&self.second);
+        writer.write(&self.minute);
+        writer.write(&self.hour);
+        writer.write(&self.day);
+        writer.write(&self.month);
+        writer.write(&self.year);
+        writer.write(&self.weekday);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.cur_time = reader.read()?;
+        self.sub_second_cycles = reader.read()?;
+        self.state = match reader.read::<u8>()? {
+            0 => State::Ready,
+            1 => State::Command,
+            2 => State::Read,
+            3 => State::Write,
+            _ => return Err(LoadError::InvalidData),
+        };
+        self.index = reader.read()?;
+        self.second = reader.read()?;
+        self.minute = reader.read()?;
+        self.hour = reader.read()?;
+        self.day = reader.read()?;
+        self.month = reader.read()?;
+        self.year = reader.read()?;
+        self.weekday = reader.read()?;
+        if !(-1..=TIME_REGS as i8 + 1).contains(&self.index) {
+            return Err(LoadError::InvalidData);
+        }
+        Ok(())
+    }
+}
+
+impl Cart {
+    pub(super) fn map_srtc(map: &mut Map, io_map: &info::Map) {
+        // The S-RTC's two registers are smaller than a page, so the whole page they're in is
+        // mapped, with the lowest address bit selecting between them
+        let page_mask = (Map::PAGE_SIZE - 1) as u16;
+        for region in io_map {
+            for addr_range in &region.address_ranges {
+                map.map::<true, true>(
+                    Some(Self::handle_srtc_read as ReadHandler),
+                    Some(Self::handle_srtc_write as WriteHandler),
+                    addr_range.banks,
+                    (
+                        addr_range.addrs.0 & !page_mask,
+                        addr_range.addrs.1 | page_mask,
+                    ),
+                    0,
+                    1 << 24,
+                    0,
+                );
+            }
+        }
+    }
+
+    fn synced_srtc(&mut self) -> &mut SRtc {
+        let time = self.cur_time;
+        match &mut self.coprocessor {
+            Some(super::Coprocessor::SRtc(srtc)) => {
+                srtc.sync(time);
+                srtc
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn handle_srtc_read(&mut self, addr: u32) -> u8 {
+        if addr & 1 == 0 {
+            self.synced_srtc().read_data()
+        } else {
+            0
+        }
+    }
+
+    fn handle_srtc_write(&mut self, addr: u32, value: u8) {
+        if addr & 1 != 0 {
+            self.synced_srtc().write_command(value);
+        }
+    }
+}
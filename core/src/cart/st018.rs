@@ -0,0 +1,522 @@
+mod instrs;
+
+use super::{
+    info,
+    map::{Map, ReadHandler, WriteHandler},
+    Cart,
+};
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    schedule::Timestamp,
+    utils::BoxedByteSlice,
+    Model,
+};
+
+pub const PROGRAM_ROM_SIZE: usize = 0x2_0000;
+pub const DATA_ROM_SIZE: usize = 0x8000;
+
+const PROGRAM_RAM_SIZE: usize = 0x4000;
+const FREQUENCY: u128 = 21_477_270;
+// The amount of cycles the chip takes to become ready after being released from reset
+const BOOT_CYCLES: u32 = 0x1_0000;
+
+// CPSR bits
+const PSR_N: u32 = 1 << 31;
+const PSR_Z: u32 = 1 << 30;
+const PSR_C: u32 = 1 << 29;
+const PSR_V: u32 = 1 << 28;
+const PSR_I: u32 = 1 << 7;
+const PSR_F: u32 = 1 << 6;
+const PSR_MODE_MASK: u32 = 0x1F;
+
+const MODE_SUPERVISOR: u32 = 0x13;
+const MODE_UNDEFINED: u32 = 0x1B;
+
+/// The register banks of the ARM's processor modes. The 26-bit modes share the banks of their
+/// 32-bit counterparts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bank {
+    User,
+    Fiq,
+    Irq,
+    Supervisor,
+    Abort,
+    Undefined,
+}
+
+impl Bank {
+    pub fn for_mode(mode: u32) -> Self {
+        match mode & 0xF {
+            1 => Bank::Fiq,
+            2 => Bank::Irq,
+            3 => Bank::Supervisor,
+            7 => Bank::Abort,
+            0xB => Bank::Undefined,
+            _ => Bank::User,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Regs {
+    /// The registers visible in the current mode. Between instructions, R15 holds the address of
+    /// the last fetched instruction, which is 8 bytes past the next one to be executed.
+    pub gprs: [u32; 16],
+    pub cpsr: u32,
+    /// The SPSRs of all modes, indexed by [`Bank`]; the user mode entry is unused.
+    pub spsrs: [u32; 6],
+    /// The copies of R8-R12 not currently visible, for FIQ mode and all other modes respectively.
+    pub r8_12_fiq: [u32; 5],
+    pub r8_12_other: [u32; 5],
+    /// The copies of R13 and R14 for all modes, indexed by [`Bank`]; the current mode's entry is
+    /// only updated when switching away from it.
+    pub r13_14: [[u32; 2]; 6],
+}
+
+impl Regs {
+    fn new() -> Self {
+        Regs {
+            gprs: [0; 16],
+            cpsr: MODE_SUPERVISOR | PSR_I | PSR_F,
+            spsrs: [0; 6],
+            r8_12_fiq: [0; 5],
+            r8_12_other: [0; 5],
+            r13_14: [[0; 2]; 6],
+        }
+    }
+
+    #[inline]
+    pub fn bank(&self) -> Bank {
+        Bank::for_mode(self.cpsr)
+    }
+
+    #[inline]
+    pub fn spsr(&self) -> u32 {
+        // Reading the SPSR from user mode returns the CPSR
+        match self.bank() {
+            Bank::User => self.cpsr,
+            bank => self.spsrs[bank as usize],
+        }
+    }
+
+    fn set_spsr(&mut self, value: u32) {
+        let bank = self.bank();
+        if bank != Bank::User {
+            self.spsrs[bank as usize] = value;
+        }
+    }
+
+    /// Writes to the CPSR, swapping in the banked registers of the new mode if it changed.
+    fn set_cpsr(&mut self, value: u32) {
+        let prev_bank = self.bank();
+        let new_bank = Bank::for_mode(value);
+        self.cpsr = value;
+        if new_bank == prev_bank {
+            return;
+        }
+        self.r13_14[prev_bank as usize].copy_from_slice(&self.gprs[13..15]);
+        if prev_bank == Bank::Fiq {
+            self.r8_12_fiq.copy_from_slice(&self.gprs[8..13]);
+            self.gprs[8..13].copy_from_slice(&self.r8_12_other);
+        } else if new_bank == Bank::Fiq {
+            self.r8_12_other.copy_from_slice(&self.gprs[8..13]);
+            self.gprs[8..13].copy_from_slice(&self.r8_12_fiq);
+        }
+        self.gprs[13..15].copy_from_slice(&self.r13_14[new_bank as usize]);
+    }
+
+    /// Reads one of the user mode registers, regardless of the current mode.
+    fn user_reg(&self, i: usize) -> u32 {
+        match (i, self.bank()) {
+            (8..=12, Bank::Fiq) => self.r8_12_other[i - 8],
+            (13 | 14, bank) if bank != Bank::User => self.r13_14[Bank::User as usize][i - 13],
+            _ => self.gprs[i],
+        }
+    }
+
+    fn set_user_reg(&mut self, i: usize, value: u32) {
+        match (i, self.bank()) {
+            (8..=12, Bank::Fiq) => self.r8_12_other[i - 8] = value,
+            (13 | 14, bank) if bank != Bank::User => {
+                self.r13_14[Bank::User as usize][i - 13] = value;
+            }
+            _ => self.gprs[i] = value,
+        }
+    }
+
+    fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.gprs);
+        writer.write(&self.cpsr);
+        writer.write(&self.spsrs);
+        writer.write(&self.r8_12_fiq);
+        writer.write(&self.r8_12_other);
+        for r13_14 in &self.r13_14 {
+            writer.write(r13_14);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.gprs = reader.read()?;
+        self.cpsr = reader.read()?;
+        self.spsrs = reader.read()?;
+        self.r8_12_fiq = reader.read()?;
+        self.r8_12_other = reader.read()?;
+        for r13_14 in &mut self.r13_14 {
+            *r13_14 = reader.read()?;
+        }
+        Ok(())
+    }
+}
+
+/// A one-byte mailbox between the ARM and the main CPU.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Latch {
+    ready: bool,
+    data: u8,
+}
+
+impl Latch {
+    fn take(&mut self) -> u8 {
+        if self.ready {
+            self.ready = false;
+            self.data
+        } else {
+            0
+        }
+    }
+
+    fn set(&mut self, value: u8) {
+        self.ready = true;
+        self.data = value;
+    }
+}
+
+/// The state of the Seta ST018, an ARM6 core running at 21.47727 MHz with its own program and data
+/// ROMs and 16 KiB of program RAM, used as a shogi AI.
+///
+/// Its ROMs aren't part of the cartridge ROM dump, and need to be supplied separately. The ARM
+/// has no access to the cartridge memories; the main CPU communicates with it through a pair of
+/// one-byte mailboxes and a status register at $3800-$3804, before every access to which the ARM
+/// is caught up to the main CPU.
+#[derive(Clone)]
+pub struct St018 {
+    regs: Regs,
+    pipeline: [u32; 2],
+    cur_cycle: u64,
+    master_clock_frequency: u128,
+    program_rom: BoxedByteSlice,
+    data_rom: BoxedByteSlice,
+    program_ram: BoxedByteSlice,
+
+    arm_to_cpu: Latch,
+    cpu_to_arm: Latch,
+    signal: bool,
+    ready: bool,
+    reset_held: bool,
+    boot_cycles_left: u32,
+    timer: u32,
+    timer_latch: u32,
+}
+
+fn to_boxed(bytes: &[u8]) -> BoxedByteSlice {
+    let mut result = BoxedByteSlice::new_zeroed(bytes.len());
+    result[..].copy_from_slice(bytes);
+    result
+}
+
+impl St018 {
+    /// Creates an ST018 from its program and data ROMs, returning `None` if their sizes are
+    /// invalid.
+    pub(super) fn new(program_rom: &[u8], data_rom: &[u8]) -> Option<Self> {
+        if program_rom.len() != PROGRAM_ROM_SIZE || data_rom.len() != DATA_ROM_SIZE {
+            return None;
+        }
+        let mut result = St018 {
+            regs: Regs::new(),
+            pipeline: [0; 2],
+            cur_cycle: 0,
+            master_clock_frequency: 21_477_270,
+            program_rom: to_boxed(program_rom),
+            data_rom: to_boxed(data_rom),
+            program_ram: BoxedByteSlice::new_zeroed(PROGRAM_RAM_SIZE),
+
+            arm_to_cpu: Latch::default(),
+            cpu_to_arm: Latch::default(),
+            signal: false,
+            ready: false,
+            reset_held: false,
+            boot_cycles_left: 0,
+            timer: 0,
+            timer_latch: 0,
+        };
+        result.reset();
+        Some(result)
+    }
+
+    fn reset(&mut self) {
+        self.regs = Regs::new();
+        self.reload_pipeline(0);
+        self.arm_to_cpu = Latch::default();
+        self.cpu_to_arm = Latch::default();
+        self.signal = false;
+        self.ready = false;
+        self.boot_cycles_left = BOOT_CYCLES;
+        self.timer = 0;
+        self.timer_latch = 0;
+    }
+
+    pub(super) fn setup(&mut self, model: Model, time: Timestamp) {
+        self.master_clock_frequency = match model {
+            Model::Ntsc => 21_477_270,
+            Model::Pal => 21_281_370,
+        };
+        self.cur_cycle = self.cycle_for_time(time);
+    }
+
+    pub(super) fn soft_reset(&mut self) {
+        self.reset_held = false;
+        self.reset();
+    }
+
+    #[inline]
+    pub fn regs(&self) -> &Regs {
+        &self.regs
+    }
+
+    #[inline]
+    pub fn program_ram(&self) -> &BoxedByteSlice {
+        &self.program_ram
+    }
+
+    #[inline]
+    pub fn ready(&self) -> bool {
+        self.ready
+    }
+
+    fn cycle_for_time(&self, time: Timestamp) -> u64 {
+        (time as u128 * FREQUENCY / self.master_clock_frequency) as u64
+    }
+
+    pub(super) fn run_until(&mut self, end_time: Timestamp) {
+        let end_cycle = self.cycle_for_time(end_time);
+        while self.cur_cycle < end_cycle {
+            if self.reset_held {
+                self.cur_cycle = end_cycle;
+            } else if !self.ready {
+                let cycles = (end_cycle - self.cur_cycle).min(self.boot_cycles_left as u64);
+                self.cur_cycle += cycles;
+                self.boot_cycles_left -= cycles as u32;
+                self.ready = self.boot_cycles_left == 0;
+            } else {
+                self.run_instr();
+            }
+        }
+    }
+
+    fn add_cycles(&mut self, cycles: u32) {
+        self.cur_cycle += cycles as u64;
+        self.timer = self.timer.saturating_sub(cycles);
+    }
+
+    fn status(&self) -> u8 {
+        self.arm_to_cpu.ready as u8
+            | (self.signal as u8) << 2
+            | (self.cpu_to_arm.ready as u8) << 3
+            | (self.ready as u8) << 7
+    }
+
+    // Every bus access, including instruction fetches, takes one cycle; the unmapped regions return
+    // the last fetched instruction.
+
+    fn read_word(&mut self, addr: u32) -> u32 {
+        self.add_cycles(1);
+        let addr = addr & !3;
+        match addr >> 29 {
+            0 => self.program_rom.read_le((addr & 0x1_FFFC) as usize),
+            2 => self.read_io(addr) as u32,
+            3 => 0x4040_4001,
+            5 => self.data_rom.read_le((addr & 0x7FFC) as usize),
+            7 => self.program_ram.read_le((addr & 0x3FFC) as usize),
+            _ => self.pipeline[1],
+        }
+    }
+
+    fn read_byte(&mut self, addr: u32) -> u8 {
+        self.add_cycles(1);
+        match addr >> 29 {
+            0 => self.program_rom[(addr & 0x1_FFFF) as usize],
+            2 => self.read_io(addr),
+            3 => (0x4040_4001_u32 >> ((addr & 3) << 3)) as u8,
+            5 => self.data_rom[(addr & 0x7FFF) as usize],
+            7 => self.program_ram[(addr & 0x3FFF) as usize],
+            _ => (self.pipeline[1] >> ((addr & 3) << 3)) as u8,
+        }
+    }
+
+    fn write_word(&mut self, addr: u32, value: u32) {
+        self.add_cycles(1);
+        let addr = addr & !3;
+        match addr >> 29 {
+            2 => self.write_io(addr, value as u8),
+            7 => self.program_ram.write_le((addr & 0x3FFC) as usize, value),
+            _ => {}
+        }
+    }
+
+    fn write_byte(&mut self, addr: u32, value: u8) {
+        self.add_cycles(1);
+        match addr >> 29 {
+            2 => self.write_io(addr, value),
+            7 => self.program_ram[(addr & 0x3FFF) as usize] = value,
+            _ => {}
+        }
+    }
+
+    fn read_io(&mut self, addr: u32) -> u8 {
+        match addr & 0x3F {
+            0x10 => self.cpu_to_arm.take(),
+            0x20 => self.status(),
+            _ => 0,
+        }
+    }
+
+    fn write_io(&mut self, addr: u32, value: u8) {
+        match addr & 0x3F {
+            0x00 => self.arm_to_cpu.set(value),
+            0x10 => self.signal = true,
+            0x20 => self.timer_latch = (self.timer_latch & 0xFF_FF00) | value as u32,
+            0x24 => self.timer_latch = (self.timer_latch & 0xFF_00FF) | (value as u32) << 8,
+            0x28 => self.timer_latch = (self.timer_latch & 0x00_FFFF) | (value as u32) << 16,
+            0x2C => self.timer = self.timer_latch,
+            _ => {}
+        }
+    }
+
+    /// Refills the pipeline starting from the given address, after a branch or a write to R15.
+    fn reload_pipeline(&mut self, addr: u32) {
+        let addr = addr & !3;
+        self.pipeline[0] = self.read_word(addr);
+        self.pipeline[1] = self.read_word(addr.wrapping_add(4));
+        self.regs.gprs[15] = addr.wrapping_add(4);
+    }
+
+    fn run_instr(&mut self) {
+        // The next instruction is fetched while the current one executes, so R15 reads as the
+        // current instruction's address plus 8
+        self.regs.gprs[15] = self.regs.gprs[15].wrapping_add(4);
+        let instr = self.pipeline[0];
+        self.pipeline[0] = self.pipeline[1];
+        self.pipeline[1] = self.read_word(self.regs.gprs[15]);
+        self.execute(instr);
+    }
+
+    fn read_s_cpu_io(&mut self, addr: u16) -> u8 {
+        match addr & 0xFF06 {
+            0x3800 => self.arm_to_cpu.take(),
+            0x3802 => {
+                self.signal = false;
+                0
+            }
+            0x3804 => self.status(),
+            _ => 0,
+        }
+    }
+
+    fn write_s_cpu_io(&mut self, addr: u16, value: u8) {
+        match addr & 0xFF06 {
+            0x3802 => self.cpu_to_arm.set(value),
+            0x3804 => {
+                // The ARM is reset on the rising edge of bit 0, and held in reset while it's set
+                let reset_held = value & 1 != 0;
+                if reset_held && !self.reset_held {
+                    self.reset();
+                }
+                self.reset_held = reset_held;
+            }
+            _ => {}
+        }
+    }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        self.regs.save_state(writer);
+        writer.write(&self.pipeline);
+        writer.write(&self.cur_cycle);
+        writer.write_bytes(&self.program_ram[..]);
+
+        writer.write(&self.arm_to_cpu.ready);
+        writer.write(&self.arm_to_cpu.data);
+        writer.write(&self.cpu_to_arm.ready);
+        writer.write(&self.cpu_to_arm.data);
+        writer.write(&self.signal);
+        writer.write(&self.ready);
+        writer.write(&self.reset_held);
+        writer.write(&self.boot_cycles_left);
+        writer.write(&self.timer);
+        writer.write(&self.timer_latch);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.regs.load_state(reader)?;
+        self.pipeline = reader.read()?;
+        self.cur_cycle = reader.read()?;
+        reader.read_bytes(&mut self.program_ram[..])?;
+
+        self.arm_to_cpu.ready = reader.read()?;
+        self.arm_to_cpu.data = reader.read()?;
+        self.cpu_to_arm.ready = reader.read()?;
+        self.cpu_to_arm.data = reader.read()?;
+        self.signal = reader.read()?;
+        self.ready = reader.read()?;
+        self.reset_held = reader.read()?;
+        self.boot_cycles_left = reader.read()?;
+        self.timer = reader.read()?;
+        self.timer_latch = reader.read()?;
+        if self.boot_cycles_left > BOOT_CYCLES || self.timer_latch > 0xFF_FFFF {
+            return Err(LoadError::InvalidData);
+        }
+        Ok(())
+    }
+}
+
+impl Cart {
+    pub(super) fn map_st018(map: &mut Map, io_map: &info::Map) {
+        // The ST018's registers are smaller than a page, so the whole page they're in is mapped,
+        // with the handlers decoding the unmodified bus address
+        let page_mask = (Map::PAGE_SIZE - 1) as u16;
+        for region in io_map {
+            for addr_range in &region.address_ranges {
+                map.map::<true, true>(
+                    Some(Self::handle_st018_read as ReadHandler),
+                    Some(Self::handle_st018_write as WriteHandler),
+                    addr_range.banks,
+                    (
+                        addr_range.addrs.0 & !page_mask,
+                        addr_range.addrs.1 | page_mask,
+                    ),
+                    0,
+                    1 << 24,
+                    0,
+                );
+            }
+        }
+    }
+
+    fn synced_st018(&mut self) -> &mut St018 {
+        let time = self.cur_time;
+        match &mut self.coprocessor {
+            Some(super::Coprocessor::St018(st018)) => {
+                st018.run_until(time);
+                st018
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn handle_st018_read(&mut self, addr: u32) -> u8 {
+        self.synced_st018().read_s_cpu_io(addr as u16)
+    }
+
+    fn handle_st018_write(&mut self, addr: u32, value: u8) {
+        self.synced_st018().write_s_cpu_io(addr as u16, value);
+    }
+}
@@ -0,0 +1,538 @@
+use super::{
+    Bank, St018, MODE_SUPERVISOR, MODE_UNDEFINED, PSR_C, PSR_I, PSR_MODE_MASK, PSR_N, PSR_V, PSR_Z,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ShiftTy {
+    Lsl,
+    Lsr,
+    Asr,
+    Ror,
+}
+
+impl ShiftTy {
+    fn from_instr(instr: u32) -> Self {
+        match instr >> 5 & 3 {
+            0 => ShiftTy::Lsl,
+            1 => ShiftTy::Lsr,
+            2 => ShiftTy::Asr,
+            _ => ShiftTy::Ror,
+        }
+    }
+}
+
+/// Shifts a value by an amount encoded in the instruction, where an amount of 0 selects special
+/// operations for all shifts but LSL; returns the result along with the shifter's carry output.
+fn shift_imm(ty: ShiftTy, value: u32, amount: u32, carry: bool) -> (u32, bool) {
+    match (ty, amount) {
+        (ShiftTy::Lsl, 0) => (value, carry),
+        (ShiftTy::Lsl, _) => (value << amount, value >> (32 - amount) & 1 != 0),
+        (ShiftTy::Lsr, 0) => (0, value >> 31 != 0),
+        (ShiftTy::Lsr, _) => (value >> amount, value >> (amount - 1) & 1 != 0),
+        (ShiftTy::Asr, 0) => (((value as i32) >> 31) as u32, value >> 31 != 0),
+        (ShiftTy::Asr, _) => (
+            ((value as i32) >> amount) as u32,
+            value >> (amount - 1) & 1 != 0,
+        ),
+        // RRX
+        (ShiftTy::Ror, 0) => ((carry as u32) << 31 | value >> 1, value & 1 != 0),
+        (ShiftTy::Ror, _) => (value.rotate_right(amount), value >> (amount - 1) & 1 != 0),
+    }
+}
+
+/// Shifts a value by the bottom byte of a register, which can exceed 31.
+fn shift_reg(ty: ShiftTy, value: u32, amount: u32, carry: bool) -> (u32, bool) {
+    let amount = amount & 0xFF;
+    if amount == 0 {
+        return (value, carry);
+    }
+    match ty {
+        ShiftTy::Lsl => match amount {
+            1..=31 => (value << amount, value >> (32 - amount) & 1 != 0),
+            32 => (0, value & 1 != 0),
+            _ => (0, false),
+        },
+        ShiftTy::Lsr => match amount {
+            1..=31 => (value >> amount, value >> (amount - 1) & 1 != 0),
+            32 => (0, value >> 31 != 0),
+            _ => (0, false),
+        },
+        ShiftTy::Asr => {
+            if amount < 32 {
+                (
+                    ((value as i32) >> amount) as u32,
+                    value >> (amount - 1) & 1 != 0,
+                )
+            } else {
+                (((value as i32) >> 31) as u32, value >> 31 != 0)
+            }
+        }
+        ShiftTy::Ror => {
+            let amount = amount & 31;
+            if amount == 0 {
+                (value, value >> 31 != 0)
+            } else {
+                (value.rotate_right(amount), value >> (amount - 1) & 1 != 0)
+            }
+        }
+    }
+}
+
+/// Returns `a + b + carry`, along with the resulting carry and overflow flags.
+fn add_with_carry(a: u32, b: u32, carry: bool) -> (u32, bool, bool) {
+    let result = a as u64 + b as u64 + carry as u64;
+    let result_32 = result as u32;
+    (
+        result_32,
+        result >> 32 != 0,
+        !(a ^ b) & (a ^ result_32) & 1 << 31 != 0,
+    )
+}
+
+impl St018 {
+    #[inline]
+    fn carry(&self) -> bool {
+        self.regs.cpsr & PSR_C != 0
+    }
+
+    fn set_nz(&mut self, value: u32) {
+        self.regs.cpsr = (self.regs.cpsr & !(PSR_N | PSR_Z))
+            | (value & PSR_N)
+            | if value == 0 { PSR_Z } else { 0 };
+    }
+
+    fn set_c(&mut self, value: bool) {
+        self.regs.cpsr = (self.regs.cpsr & !PSR_C) | if value { PSR_C } else { 0 };
+    }
+
+    fn set_v(&mut self, value: bool) {
+        self.regs.cpsr = (self.regs.cpsr & !PSR_V) | if value { PSR_V } else { 0 };
+    }
+
+    fn condition_passed(&self, cond: u32) -> bool {
+        let cpsr = self.regs.cpsr;
+        let n = cpsr & PSR_N != 0;
+        let z = cpsr & PSR_Z != 0;
+        let c = cpsr & PSR_C != 0;
+        let v = cpsr & PSR_V != 0;
+        match cond {
+            0x0 => z,
+            0x1 => !z,
+            0x2 => c,
+            0x3 => !c,
+            0x4 => n,
+            0x5 => !n,
+            0x6 => v,
+            0x7 => !v,
+            0x8 => c && !z,
+            0x9 => !c || z,
+            0xA => n == v,
+            0xB => n != v,
+            0xC => !z && n == v,
+            0xD => z || n != v,
+            0xE => true,
+            // NV
+            _ => false,
+        }
+    }
+
+    /// Writes to a register, refilling the pipeline if it's R15.
+    fn write_reg(&mut self, i: u32, value: u32) {
+        if i == 15 {
+            self.reload_pipeline(value);
+        } else {
+            self.regs.gprs[i as usize] = value;
+        }
+    }
+
+    fn enter_exception(&mut self, vector: u32, mode: u32) {
+        let cpsr = self.regs.cpsr;
+        self.regs.set_cpsr((cpsr & !PSR_MODE_MASK) | mode | PSR_I);
+        self.regs.set_spsr(cpsr);
+        // The return address is the one of the next instruction
+        self.regs.gprs[14] = self.regs.gprs[15].wrapping_sub(4);
+        self.reload_pipeline(vector);
+    }
+
+    fn undefined(&mut self) {
+        self.add_cycles(1);
+        self.enter_exception(0x04, MODE_UNDEFINED);
+    }
+
+    pub(super) fn execute(&mut self, instr: u32) {
+        if !self.condition_passed(instr >> 28) {
+            return;
+        }
+        match instr >> 25 & 7 {
+            0 => {
+                if instr & 0xF0 == 0x90 {
+                    if instr & 0x0FC0_0000 == 0 {
+                        self.mul(instr);
+                    } else if instr & 0x0FB0_0F00 == 0x0100_0000 {
+                        self.swp(instr);
+                    } else {
+                        self.undefined();
+                    }
+                } else if instr & 0x90 == 0x90 {
+                    // Halfword and signed transfers were only introduced in ARMv4
+                    self.undefined();
+                } else if instr & 0x0190_0000 == 0x0100_0000 {
+                    self.psr_transfer(instr);
+                } else {
+                    let (op2, carry) = self.reg_operand(instr);
+                    self.data_proc(instr, op2, carry, instr & 0x10 != 0);
+                }
+            }
+            1 => {
+                if instr & 0x0190_0000 == 0x0100_0000 {
+                    self.psr_transfer(instr);
+                } else {
+                    let (op2, carry) = self.imm_operand(instr);
+                    self.data_proc(instr, op2, carry, false);
+                }
+            }
+            2 => self.single_transfer(instr),
+            3 => {
+                if instr & 0x10 != 0 {
+                    self.undefined();
+                } else {
+                    self.single_transfer(instr);
+                }
+            }
+            4 => self.block_transfer(instr),
+            5 => self.branch(instr),
+            // There are no coprocessors attached, so all of their instructions are undefined
+            6 => self.undefined(),
+            _ => {
+                if instr & 1 << 24 != 0 {
+                    self.add_cycles(1);
+                    self.enter_exception(0x08, MODE_SUPERVISOR);
+                } else {
+                    self.undefined();
+                }
+            }
+        }
+    }
+
+    fn imm_operand(&self, instr: u32) -> (u32, bool) {
+        let rotate = (instr >> 8 & 0xF) << 1;
+        let value = (instr & 0xFF).rotate_right(rotate);
+        if rotate == 0 {
+            (value, self.carry())
+        } else {
+            (value, value >> 31 != 0)
+        }
+    }
+
+    /// Computes a shifted register operand; shifts by a register take an extra internal cycle,
+    /// during which R15 is incremented again.
+    fn reg_operand(&mut self, instr: u32) -> (u32, bool) {
+        let ty = ShiftTy::from_instr(instr);
+        let rm = instr as usize & 0xF;
+        if instr & 0x10 != 0 {
+            self.add_cycles(1);
+            let mut value = self.regs.gprs[rm];
+            if rm == 15 {
+                value = value.wrapping_add(4);
+            }
+            let amount = self.regs.gprs[instr as usize >> 8 & 0xF];
+            shift_reg(ty, value, amount, self.carry())
+        } else {
+            shift_imm(ty, self.regs.gprs[rm], instr >> 7 & 0x1F, self.carry())
+        }
+    }
+
+    fn data_proc(&mut self, instr: u32, op2: u32, shifter_carry: bool, reg_shift: bool) {
+        let opcode = instr >> 21 & 0xF;
+        let set_flags = instr & 1 << 20 != 0;
+        let rn = instr >> 16 & 0xF;
+        let rd = instr >> 12 & 0xF;
+        let mut op1 = self.regs.gprs[rn as usize];
+        if rn == 15 && reg_shift {
+            op1 = op1.wrapping_add(4);
+        }
+
+        let carry = self.carry();
+        let (result, arith_flags) = match opcode {
+            0x0 | 0x8 => (op1 & op2, None),
+            0x1 | 0x9 => (op1 ^ op2, None),
+            0x2 | 0xA => {
+                let (result, c, v) = add_with_carry(op1, !op2, true);
+                (result, Some((c, v)))
+            }
+            0x3 => {
+                let (result, c, v) = add_with_carry(op2, !op1, true);
+                (result, Some((c, v)))
+            }
+            0x4 | 0xB => {
+                let (result, c, v) = add_with_carry(op1, op2, false);
+                (result, Some((c, v)))
+            }
+            0x5 => {
+                let (result, c, v) = add_with_carry(op1, op2, carry);
+                (result, Some((c, v)))
+            }
+            0x6 => {
+                let (result, c, v) = add_with_carry(op1, !op2, carry);
+                (result, Some((c, v)))
+            }
+            0x7 => {
+                let (result, c, v) = add_with_carry(op2, !op1, carry);
+                (result, Some((c, v)))
+            }
+            0xC => (op1 | op2, None),
+            0xD => (op2, None),
+            0xE => (op1 & !op2, None),
+            _ => (!op2, None),
+        };
+        let writes_result = !(0x8..=0xB).contains(&opcode);
+
+        if set_flags {
+            if rd == 15 && writes_result {
+                // Returning from an exception restores the CPSR from the current mode's SPSR
+                let spsr = self.regs.spsr();
+                self.regs.set_cpsr(spsr);
+            } else {
+                self.set_nz(result);
+                match arith_flags {
+                    Some((c, v)) => {
+                        self.set_c(c);
+                        self.set_v(v);
+                    }
+                    None => self.set_c(shifter_carry),
+                }
+            }
+        }
+        if writes_result {
+            self.write_reg(rd, result);
+        }
+    }
+
+    fn psr_transfer(&mut self, instr: u32) {
+        let use_spsr = instr & 1 << 22 != 0;
+        if instr & 1 << 21 == 0 {
+            // MRS
+            let value = if use_spsr {
+                self.regs.spsr()
+            } else {
+                self.regs.cpsr
+            };
+            self.regs.gprs[instr as usize >> 12 & 0xF] = value;
+            return;
+        }
+
+        // MSR, which can update the flags and control bytes separately
+        let value = if instr & 1 << 25 != 0 {
+            self.imm_operand(instr).0
+        } else {
+            self.regs.gprs[instr as usize & 0xF]
+        };
+        let mut mask = 0;
+        if instr & 1 << 19 != 0 {
+            mask |= 0xFF00_0000;
+        }
+        if instr & 1 << 16 != 0 && self.regs.bank() != Bank::User {
+            mask |= 0xFF;
+        }
+        if use_spsr {
+            let spsr = self.regs.spsr();
+            self.regs.set_spsr((spsr & !mask) | (value & mask));
+        } else {
+            let cpsr = self.regs.cpsr;
+            self.regs.set_cpsr((cpsr & !mask) | (value & mask));
+        }
+    }
+
+    fn mul(&mut self, instr: u32) {
+        let rd = instr as usize >> 16 & 0xF;
+        let rn = instr as usize >> 12 & 0xF;
+        let rs = instr as usize >> 8 & 0xF;
+        let rm = instr as usize & 0xF;
+        let multiplier = self.regs.gprs[rs];
+        let mut result = self.regs.gprs[rm].wrapping_mul(multiplier);
+        // The multiplier is processed 8 bits at a time, terminating early when the remaining ones
+        // are all zeros or all ones
+        let mut cycles = 1;
+        for shift in [8, 16, 24] {
+            let remaining = (multiplier as i32) >> shift;
+            if remaining == 0 || remaining == -1 {
+                break;
+            }
+            cycles += 1;
+        }
+        if instr & 1 << 21 != 0 {
+            result = result.wrapping_add(self.regs.gprs[rn]);
+            cycles += 1;
+        }
+        self.add_cycles(cycles);
+        if instr & 1 << 20 != 0 {
+            self.set_nz(result);
+        }
+        if rd != 15 {
+            self.regs.gprs[rd] = result;
+        }
+    }
+
+    fn swp(&mut self, instr: u32) {
+        let addr = self.regs.gprs[instr as usize >> 16 & 0xF];
+        let rd = instr >> 12 & 0xF;
+        let value = self.regs.gprs[instr as usize & 0xF];
+        let prev = if instr & 1 << 22 != 0 {
+            let prev = self.read_byte(addr) as u32;
+            self.write_byte(addr, value as u8);
+            prev
+        } else {
+            let prev = self.read_word(addr).rotate_right((addr & 3) << 3);
+            self.write_word(addr, value);
+            prev
+        };
+        self.add_cycles(1);
+        self.write_reg(rd, prev);
+    }
+
+    fn single_transfer(&mut self, instr: u32) {
+        let pre_index = instr & 1 << 24 != 0;
+        let up = instr & 1 << 23 != 0;
+        let byte = instr & 1 << 22 != 0;
+        let writeback = !pre_index || instr & 1 << 21 != 0;
+        let load = instr & 1 << 20 != 0;
+        let rn = instr >> 16 & 0xF;
+        let rd = instr >> 12 & 0xF;
+
+        let offset = if instr & 1 << 25 != 0 {
+            shift_imm(
+                ShiftTy::from_instr(instr),
+                self.regs.gprs[instr as usize & 0xF],
+                instr >> 7 & 0x1F,
+                self.carry(),
+            )
+            .0
+        } else {
+            instr & 0xFFF
+        };
+        let base = self.regs.gprs[rn as usize];
+        let offset_addr = if up {
+            base.wrapping_add(offset)
+        } else {
+            base.wrapping_sub(offset)
+        };
+        let addr = if pre_index { offset_addr } else { base };
+
+        if load {
+            let value = if byte {
+                self.read_byte(addr) as u32
+            } else {
+                // Misaligned word loads rotate the word containing the addressed byte
+                self.read_word(addr).rotate_right((addr & 3) << 3)
+            };
+            self.add_cycles(1);
+            // The loaded value takes priority over the written back base address
+            if writeback {
+                self.write_reg(rn, offset_addr);
+            }
+            self.write_reg(rd, value);
+        } else {
+            let mut value = self.regs.gprs[rd as usize];
+            if rd == 15 {
+                value = value.wrapping_add(4);
+            }
+            if byte {
+                self.write_byte(addr, value as u8);
+            } else {
+                self.write_word(addr, value);
+            }
+            if writeback {
+                self.write_reg(rn, offset_addr);
+            }
+        }
+    }
+
+    fn block_transfer(&mut self, instr: u32) {
+        let pre_index = instr & 1 << 24 != 0;
+        let up = instr & 1 << 23 != 0;
+        let psr_or_user = instr & 1 << 22 != 0;
+        let writeback = instr & 1 << 21 != 0;
+        let load = instr & 1 << 20 != 0;
+        let rn = instr >> 16 & 0xF;
+        let reg_list = instr as u16;
+        if reg_list == 0 {
+            return;
+        }
+
+        // Registers are always transferred in ascending order starting from the lowest address
+        let len = reg_list.count_ones() * 4;
+        let base = self.regs.gprs[rn as usize];
+        let (mut addr, new_base) = if up {
+            (base, base.wrapping_add(len))
+        } else {
+            (base.wrapping_sub(len), base.wrapping_sub(len))
+        };
+        if pre_index == up {
+            addr = addr.wrapping_add(4);
+        }
+
+        let loads_pc = load && reg_list & 1 << 15 != 0;
+        // With the S bit set, user mode registers are transferred instead, unless R15 is being
+        // loaded, in which case the CPSR is restored from the SPSR
+        let user_regs = psr_or_user && !loads_pc;
+
+        if load {
+            if writeback {
+                self.write_reg(rn, new_base);
+            }
+            let mut pc = None;
+            for i in 0..16 {
+                if reg_list & 1 << i == 0 {
+                    continue;
+                }
+                let value = self.read_word(addr);
+                addr = addr.wrapping_add(4);
+                if i == 15 {
+                    pc = Some(value);
+                } else if user_regs {
+                    self.regs.set_user_reg(i, value);
+                } else {
+                    self.regs.gprs[i] = value;
+                }
+            }
+            self.add_cycles(1);
+            if let Some(pc) = pc {
+                if psr_or_user {
+                    let spsr = self.regs.spsr();
+                    self.regs.set_cpsr(spsr);
+                }
+                self.reload_pipeline(pc);
+            }
+        } else {
+            // The base register is written back after the first register is stored
+            let mut first = true;
+            for i in 0..16 {
+                if reg_list & 1 << i == 0 {
+                    continue;
+                }
+                let value = if i == 15 {
+                    self.regs.gprs[15].wrapping_add(4)
+                } else if user_regs {
+                    self.regs.user_reg(i)
+                } else {
+                    self.regs.gprs[i]
+                };
+                self.write_word(addr, value);
+                addr = addr.wrapping_add(4);
+                if first && writeback {
+                    self.regs.gprs[rn as usize] = new_base;
+                }
+                first = false;
+            }
+        }
+    }
+
+    fn branch(&mut self, instr: u32) {
+        let offset = ((instr << 8) as i32 >> 6) as u32;
+        let pc = self.regs.gprs[15];
+        if instr & 1 << 24 != 0 {
+            self.regs.gprs[14] = pc.wrapping_sub(4);
+        }
+        self.reload_pipeline(pc.wrapping_add(offset));
+    }
+}
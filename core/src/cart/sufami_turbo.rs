@@ -0,0 +1,14 @@
+use crate::utils::ByteSlice;
+
+const HEADER_MAGIC: &[u8; 14] = b"BANDAI SFC-ADX";
+
+/// Returns the size of the save RAM contained in the Sufami Turbo mini-cart with the given ROM, or
+/// `None` if the ROM doesn't have a valid mini-cart header.
+pub fn ram_size(rom: ByteSlice) -> Option<u32> {
+    let header = rom[..].get(..0x38)?;
+    if &header[..HEADER_MAGIC.len()] != HEADER_MAGIC {
+        return None;
+    }
+    // The size is specified in units of 2 KiB
+    Some((header[0x37] as u32) << 11)
+}
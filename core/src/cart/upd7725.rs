@@ -0,0 +1,698 @@
+use super::{
+    info,
+    map::{reduce, Map, ReadHandler, WriteHandler},
+    Cart,
+};
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    schedule::Timestamp,
+    Model,
+};
+
+const MAX_STACK_LEN: usize = 16;
+
+// SR bits
+const STATUS_RQM: u16 = 1 << 15;
+const STATUS_DRS: u16 = 1 << 12;
+const STATUS_DRC: u16 = 1 << 10;
+const STATUS_READ_ONLY_MASK: u16 = 0x907C;
+
+/// The chip variants sharing this core, which differ only in their memory and stack sizes (and in
+/// the uPD96050's ability to jump across the two halves of its program ROM).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+    Upd7725,
+    Upd96050,
+}
+
+impl Variant {
+    /// The size in bytes of the program ROM, containing little-endian 24-bit words.
+    pub const fn program_rom_size(self) -> usize {
+        match self {
+            Variant::Upd7725 => 0x1800,
+            Variant::Upd96050 => 0xC000,
+        }
+    }
+
+    /// The size in bytes of the data ROM, containing little-endian 16-bit words.
+    pub const fn data_rom_size(self) -> usize {
+        match self {
+            Variant::Upd7725 => 0x800,
+            Variant::Upd96050 => 0x1000,
+        }
+    }
+
+    const fn data_ram_words(self) -> usize {
+        match self {
+            Variant::Upd7725 => 0x100,
+            Variant::Upd96050 => 0x800,
+        }
+    }
+
+    const fn stack_len(self) -> usize {
+        match self {
+            Variant::Upd7725 => 4,
+            Variant::Upd96050 => 16,
+        }
+    }
+
+    const fn default_frequency(self) -> u32 {
+        match self {
+            Variant::Upd7725 => 7_600_000,
+            Variant::Upd96050 => 11_000_000,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Flags {
+    pub ov0: bool,
+    pub ov1: bool,
+    pub z: bool,
+    pub c: bool,
+    pub s0: bool,
+    pub s1: bool,
+}
+
+impl Flags {
+    fn to_raw(self) -> u8 {
+        self.ov0 as u8
+            | (self.ov1 as u8) << 1
+            | (self.z as u8) << 2
+            | (self.c as u8) << 3
+            | (self.s0 as u8) << 4
+            | (self.s1 as u8) << 5
+    }
+
+    fn from_raw(value: u8) -> Self {
+        Flags {
+            ov0: value & 1 != 0,
+            ov1: value & 1 << 1 != 0,
+            z: value & 1 << 2 != 0,
+            c: value & 1 << 3 != 0,
+            s0: value & 1 << 4 != 0,
+            s1: value & 1 << 5 != 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Regs {
+    pub pc: u16,
+    pub rp: u16,
+    pub dp: u16,
+    pub sp: u8,
+    pub stack: [u16; MAX_STACK_LEN],
+    pub k: u16,
+    pub l: u16,
+    pub m: u16,
+    pub n: u16,
+    pub a: u16,
+    pub b: u16,
+    pub flags_a: Flags,
+    pub flags_b: Flags,
+    pub tr: u16,
+    pub trb: u16,
+    pub sr: u16,
+    pub dr: u16,
+    pub si: u16,
+    pub so: u16,
+}
+
+/// The state of the NEC uPD7725, a 16-bit fixed-point DSP running at 7.6 MHz, used (with different
+/// firmware) as the DSP-1, DSP-2, DSP-3 and DSP-4, or of its bigger sibling, the uPD96050, used as
+/// the ST010 and ST011.
+///
+/// Their program and data ROMs aren't part of the cartridge ROM dump, and need to be supplied
+/// separately; the main CPU communicates with them through their DR (data) and SR (status)
+/// registers (and, for the uPD96050, by directly accessing its data RAM), before every access to
+/// which they're caught up to the main CPU.
+#[derive(Clone)]
+pub struct Upd7725 {
+    variant: Variant,
+    regs: Regs,
+    cur_cycle: u64,
+    frequency: u128,
+    master_clock_frequency: u128,
+    program_rom: Box<[u32]>,
+    data_rom: Box<[u16]>,
+    data_ram: Box<[u16]>,
+    select_mask: u32,
+}
+
+impl Upd7725 {
+    /// Creates a uPD7725 or uPD96050 from its program and data ROMs (containing little-endian
+    /// 24-bit and 16-bit words respectively), returning `None` if their sizes are invalid.
+    ///
+    /// `frequency` overrides the chip's clock frequency, in Hz, for boards that use a non-standard
+    /// oscillator.
+    pub(super) fn new(
+        variant: Variant,
+        program_rom: &[u8],
+        data_rom: &[u8],
+        select_mask: u32,
+        frequency: Option<u32>,
+    ) -> Option<Self> {
+        if program_rom.len() != variant.program_rom_size()
+            || data_rom.len() != variant.data_rom_size()
+        {
+            return None;
+        }
+        let mut result = Upd7725 {
+            variant,
+            regs: Regs::default(),
+            cur_cycle: 0,
+            frequency: frequency.unwrap_or_else(|| variant.default_frequency()) as u128,
+            master_clock_frequency: 21_477_270,
+            program_rom: program_rom
+                .chunks_exact(3)
+                .map(|bytes| bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16)
+                .collect(),
+            data_rom: data_rom
+                .chunks_exact(2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                .collect(),
+            data_ram: vec![0; variant.data_ram_words()].into_boxed_slice(),
+            select_mask,
+        };
+        result.reset();
+        Some(result)
+    }
+
+    fn reset(&mut self) {
+        self.regs = Regs::default();
+    }
+
+    pub(super) fn setup(&mut self, model: Model, time: Timestamp) {
+        self.master_clock_frequency = match model {
+            Model::Ntsc => 21_477_270,
+            Model::Pal => 21_281_370,
+        };
+        self.cur_cycle = self.cycle_for_time(time);
+    }
+
+    pub(super) fn soft_reset(&mut self) {
+        self.reset();
+    }
+
+    #[inline]
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    #[inline]
+    pub fn regs(&self) -> &Regs {
+        &self.regs
+    }
+
+    #[inline]
+    pub fn data_ram(&self) -> &[u16] {
+        &self.data_ram
+    }
+
+    #[inline]
+    fn pc_mask(&self) -> u16 {
+        (self.program_rom.len() - 1) as u16
+    }
+
+    #[inline]
+    fn rp_mask(&self) -> u16 {
+        (self.data_rom.len() - 1) as u16
+    }
+
+    #[inline]
+    fn dp_mask(&self) -> u16 {
+        (self.data_ram.len() - 1) as u16
+    }
+
+    #[inline]
+    fn sp_mask(&self) -> u8 {
+        (self.variant.stack_len() - 1) as u8
+    }
+
+    fn cycle_for_time(&self, time: Timestamp) -> u64 {
+        (time as u128 * self.frequency / self.master_clock_frequency) as u64
+    }
+
+    pub(super) fn run_until(&mut self, end_time: Timestamp) {
+        let end_cycle = self.cycle_for_time(end_time);
+        while self.cur_cycle < end_cycle {
+            self.run_instr();
+            self.cur_cycle += 1;
+        }
+    }
+
+    fn run_instr(&mut self) {
+        let opcode = self.program_rom[self.regs.pc as usize];
+        self.regs.pc = (self.regs.pc + 1) & self.pc_mask();
+        match opcode >> 22 & 3 {
+            0 => self.run_op(opcode),
+            1 => {
+                // RT: an OP followed by a return
+                self.run_op(opcode);
+                self.regs.sp = (self.regs.sp.wrapping_sub(1)) & self.sp_mask();
+                self.regs.pc = self.regs.stack[self.regs.sp as usize];
+            }
+            2 => self.run_jp(opcode),
+            _ => self.run_ld(opcode),
+        }
+        let product = self.regs.k as i16 as i32 * self.regs.l as i16 as i32;
+        self.regs.m = (product >> 15) as u16;
+        self.regs.n = (product << 1) as u16;
+    }
+
+    fn run_op(&mut self, opcode: u32) {
+        let p_select = opcode >> 20 & 3;
+        let alu_op = opcode >> 16 & 0xF;
+        let use_b = opcode & 1 << 15 != 0;
+        let dp_low_op = opcode >> 13 & 3;
+        let dp_high_xor = (opcode >> 9 & 0xF) as u16;
+        let decrement_rp = opcode & 1 << 8 != 0;
+        let src = opcode >> 4 & 0xF;
+        let dst = opcode & 0xF;
+
+        let regs = &mut self.regs;
+        let idb = match src {
+            0 => regs.trb,
+            1 => regs.a,
+            2 => regs.b,
+            3 => regs.tr,
+            4 => regs.dp,
+            5 => regs.rp,
+            6 => self.data_rom[regs.rp as usize],
+            7 => 0x8000 - regs.flags_a.s1 as u16,
+            8 => {
+                regs.sr |= STATUS_RQM;
+                regs.dr
+            }
+            9 => regs.dr,
+            10 => regs.sr,
+            11 | 12 => regs.si,
+            13 => regs.k,
+            14 => regs.l,
+            _ => self.data_ram[regs.dp as usize],
+        };
+
+        if alu_op != 0 {
+            let mut p = match p_select {
+                0 => self.data_ram[regs.dp as usize],
+                1 => idb,
+                2 => regs.m,
+                _ => regs.n,
+            };
+            // The carry used by ADC and SBB comes from the other accumulator's flags
+            let (q, mut flags, carry) = if use_b {
+                (regs.b, regs.flags_b, regs.flags_a.c)
+            } else {
+                (regs.a, regs.flags_a, regs.flags_b.c)
+            };
+
+            let result = match alu_op {
+                1 => q | p,
+                2 => q & p,
+                3 => q ^ p,
+                4 => q.wrapping_sub(p),
+                5 => q.wrapping_add(p),
+                6 => q.wrapping_sub(p).wrapping_sub(carry as u16),
+                7 => q.wrapping_add(p).wrapping_add(carry as u16),
+                8 => {
+                    p = 1;
+                    q.wrapping_sub(1)
+                }
+                9 => {
+                    p = 1;
+                    q.wrapping_add(1)
+                }
+                10 => !q,
+                11 => q >> 1 | (q & 0x8000),
+                12 => q << 1 | carry as u16,
+                13 => q << 2 | 3,
+                14 => q << 4 | 0xF,
+                _ => q.swap_bytes(),
+            };
+
+            match alu_op {
+                4..=9 => {
+                    if alu_op & 1 != 0 {
+                        flags.ov0 = (q ^ result) & (p ^ result) & 0x8000 != 0;
+                        flags.c = result < q;
+                    } else {
+                        flags.ov0 = (q ^ result) & (q ^ p) & 0x8000 != 0;
+                        flags.c = result > q;
+                    }
+                    // OV1 tracks whether the accumulator overflowed an odd number of times over
+                    // consecutive operations
+                    flags.ov1 = if flags.ov0 && flags.ov1 {
+                        flags.s0 == flags.s1
+                    } else {
+                        flags.ov0 || flags.ov1
+                    };
+                }
+                11 => {
+                    flags.c = q & 1 != 0;
+                    flags.ov0 = false;
+                    flags.ov1 = false;
+                }
+                12 => {
+                    flags.c = q >> 15 != 0;
+                    flags.ov0 = false;
+                    flags.ov1 = false;
+                }
+                _ => {
+                    flags.c = false;
+                    flags.ov0 = false;
+                    flags.ov1 = false;
+                }
+            }
+            flags.s0 = result & 0x8000 != 0;
+            flags.z = result == 0;
+            if !flags.ov1 {
+                flags.s1 = flags.s0;
+            }
+
+            if use_b {
+                regs.b = result;
+                regs.flags_b = flags;
+            } else {
+                regs.a = result;
+                regs.flags_a = flags;
+            }
+        }
+
+        self.run_ld((idb as u32) << 6 | dst);
+
+        let rp_mask = self.rp_mask();
+        let regs = &mut self.regs;
+        if dst != 4 {
+            match dp_low_op {
+                1 => regs.dp = (regs.dp & 0xF0) | (regs.dp.wrapping_add(1) & 0xF),
+                2 => regs.dp = (regs.dp & 0xF0) | (regs.dp.wrapping_sub(1) & 0xF),
+                3 => regs.dp &= 0xF0,
+                _ => {}
+            }
+            regs.dp ^= dp_high_xor << 4;
+        }
+        if dst != 5 && decrement_rp {
+            regs.rp = regs.rp.wrapping_sub(1) & rp_mask;
+        }
+    }
+
+    fn run_jp(&mut self, opcode: u32) {
+        let branch = opcode >> 13 & 0x1FF;
+        let pc_mask = self.pc_mask();
+        let sp_mask = self.sp_mask();
+        let regs = &mut self.regs;
+        // The lowest 2 bits select one of the 2 KiB-word banks in the current half of the
+        // program ROM, which only exist on the uPD96050
+        let mut target =
+            (regs.pc & 0x2000 | (opcode as u16 & 3) << 11 | (opcode >> 2 & 0x7FF) as u16) & pc_mask;
+        let (a, b) = (regs.flags_a, regs.flags_b);
+        let taken = match branch {
+            0x000 => {
+                regs.pc = regs.so & pc_mask;
+                return;
+            }
+            0x080 => !a.c,
+            0x082 => a.c,
+            0x084 => !b.c,
+            0x086 => b.c,
+            0x088 => !a.z,
+            0x08A => a.z,
+            0x08C => !b.z,
+            0x08E => b.z,
+            0x090 => !a.ov0,
+            0x092 => a.ov0,
+            0x094 => !b.ov0,
+            0x096 => b.ov0,
+            0x098 => !a.ov1,
+            0x09A => a.ov1,
+            0x09C => !b.ov1,
+            0x09E => b.ov1,
+            0x0A0 => !a.s0,
+            0x0A2 => a.s0,
+            0x0A4 => !b.s0,
+            0x0A6 => b.s0,
+            0x0A8 => !a.s1,
+            0x0AA => a.s1,
+            0x0AC => !b.s1,
+            0x0AE => b.s1,
+            0x0B0 => regs.dp & 0xF == 0,
+            0x0B1 => regs.dp & 0xF != 0,
+            0x0B2 => regs.dp & 0xF == 0xF,
+            0x0B3 => regs.dp & 0xF != 0xF,
+            // The serial interface isn't connected, so SI and SO are never acknowledged
+            0x0B4 | 0x0B8 => true,
+            0x0B6 | 0x0BA => false,
+            0x0BC => regs.sr & STATUS_RQM == 0,
+            0x0BE => regs.sr & STATUS_RQM != 0,
+            // Long jumps and calls can also switch to the other half of the program ROM
+            0x100 | 0x101 | 0x140 | 0x141 => {
+                target = if branch & 1 != 0 {
+                    target | 0x2000
+                } else {
+                    target & !0x2000
+                } & pc_mask;
+                if branch & 0x40 != 0 {
+                    regs.stack[regs.sp as usize] = regs.pc;
+                    regs.sp = (regs.sp + 1) & sp_mask;
+                }
+                true
+            }
+            _ => false,
+        };
+        if taken {
+            regs.pc = target;
+        }
+    }
+
+    fn run_ld(&mut self, opcode: u32) {
+        let value = (opcode >> 6) as u16;
+        let dp_mask = self.dp_mask();
+        let rp_mask = self.rp_mask();
+        let regs = &mut self.regs;
+        match opcode & 0xF {
+            0 => {}
+            1 => regs.a = value,
+            2 => regs.b = value,
+            3 => regs.tr = value,
+            4 => regs.dp = value & dp_mask,
+            5 => regs.rp = value & rp_mask,
+            6 => {
+                regs.dr = value;
+                regs.sr |= STATUS_RQM;
+            }
+            7 => {
+                regs.sr = (regs.sr & STATUS_READ_ONLY_MASK) | (value & !STATUS_READ_ONLY_MASK);
+            }
+            8 => regs.so = value.reverse_bits(),
+            9 => regs.so = value,
+            10 => regs.k = value,
+            11 => {
+                regs.k = value;
+                regs.l = self.data_rom[regs.rp as usize];
+            }
+            12 => {
+                regs.l = value;
+                regs.k = self.data_ram[(regs.dp | 0x40) as usize];
+            }
+            13 => regs.l = value,
+            14 => regs.trb = value,
+            _ => self.data_ram[regs.dp as usize] = value,
+        }
+    }
+
+    fn read_dr(&mut self) -> u8 {
+        let regs = &mut self.regs;
+        if regs.sr & STATUS_DRC != 0 {
+            // 8-bit transfers
+            regs.sr &= !STATUS_RQM;
+            regs.dr as u8
+        } else if regs.sr & STATUS_DRS == 0 {
+            regs.sr |= STATUS_DRS;
+            regs.dr as u8
+        } else {
+            regs.sr &= !(STATUS_RQM | STATUS_DRS);
+            (regs.dr >> 8) as u8
+        }
+    }
+
+    fn write_dr(&mut self, value: u8) {
+        let regs = &mut self.regs;
+        if regs.sr & STATUS_DRC != 0 {
+            regs.sr &= !STATUS_RQM;
+            regs.dr = (regs.dr & 0xFF00) | value as u16;
+        } else if regs.sr & STATUS_DRS == 0 {
+            regs.sr |= STATUS_DRS;
+            regs.dr = (regs.dr & 0xFF00) | value as u16;
+        } else {
+            regs.sr &= !(STATUS_RQM | STATUS_DRS);
+            regs.dr = (value as u16) << 8 | (regs.dr & 0xFF);
+        }
+    }
+
+    /// Reads a byte from the uPD96050's data RAM, whose 16-bit words are exposed to the main CPU as
+    /// little-endian byte pairs.
+    fn read_data_ram(&self, addr: u32) -> u8 {
+        let word = self.data_ram[(addr >> 1) as usize & (self.data_ram.len() - 1)];
+        (word >> ((addr & 1) << 3)) as u8
+    }
+
+    fn write_data_ram(&mut self, addr: u32, value: u8) {
+        let index = (addr >> 1) as usize & (self.data_ram.len() - 1);
+        let word = &mut self.data_ram[index];
+        *word = if addr & 1 != 0 {
+            (value as u16) << 8 | (*word & 0xFF)
+        } else {
+            (*word & 0xFF00) | value as u16
+        };
+    }
+
+    /// Returns whether a main CPU access to the given address targets SR instead of DR, based on
+    /// the lowest address bit not covered by the board's map mask.
+    #[inline]
+    fn selects_sr(&self, addr: u32) -> bool {
+        reduce(addr, self.select_mask) & 1 != 0
+    }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        let regs = &self.regs;
+        writer.write(&self.cur_cycle);
+        writer.write(&regs.pc);
+        writer.write(&regs.rp);
+        writer.write(&regs.dp);
+        writer.write(&regs.sp);
+        writer.write(&regs.stack);
+        writer.write(&regs.k);
+        writer.write(&regs.l);
+        writer.write(&regs.m);
+        writer.write(&regs.n);
+        writer.write(&regs.a);
+        writer.write(&regs.b);
+        writer.write(&regs.flags_a.to_raw());
+        writer.write(&regs.flags_b.to_raw());
+        writer.write(&regs.tr);
+        writer.write(&regs.trb);
+        writer.write(&regs.sr);
+        writer.write(&regs.dr);
+        writer.write(&regs.si);
+        writer.write(&regs.so);
+        for word in self.data_ram.iter() {
+            writer.write(word);
+        }
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        let regs = &mut self.regs;
+        self.cur_cycle = reader.read()?;
+        regs.pc = reader.read()?;
+        regs.rp = reader.read()?;
+        regs.dp = reader.read()?;
+        regs.sp = reader.read()?;
+        regs.stack = reader.read()?;
+        regs.k = reader.read()?;
+        regs.l = reader.read()?;
+        regs.m = reader.read()?;
+        regs.n = reader.read()?;
+        regs.a = reader.read()?;
+        regs.b = reader.read()?;
+        regs.flags_a = Flags::from_raw(reader.read()?);
+        regs.flags_b = Flags::from_raw(reader.read()?);
+        regs.tr = reader.read()?;
+        regs.trb = reader.read()?;
+        regs.sr = reader.read()?;
+        regs.dr = reader.read()?;
+        regs.si = reader.read()?;
+        regs.so = reader.read()?;
+        for word in self.data_ram.iter_mut() {
+            *word = reader.read()?;
+        }
+
+        let regs = &self.regs;
+        if regs.pc as usize >= self.program_rom.len()
+            || regs.rp as usize >= self.data_rom.len()
+            || regs.dp as usize >= self.data_ram.len()
+            || regs.sp as usize >= self.variant.stack_len()
+            || regs
+                .stack
+                .iter()
+                .any(|&pc| pc as usize >= self.program_rom.len())
+        {
+            return Err(LoadError::InvalidData);
+        }
+        Ok(())
+    }
+}
+
+impl Cart {
+    pub(super) fn map_upd7725(
+        map: &mut Map,
+        io_map: &info::Map,
+        data_ram_map: &info::Map,
+        variant: Variant,
+    ) {
+        // The handlers receive the unmodified bus address, to select between DR and SR
+        for region in io_map {
+            for addr_range in &region.address_ranges {
+                map.map::<true, true>(
+                    Some(Self::handle_upd7725_read as ReadHandler),
+                    Some(Self::handle_upd7725_write as WriteHandler),
+                    addr_range.banks,
+                    addr_range.addrs,
+                    0,
+                    1 << 24,
+                    0,
+                );
+            }
+        }
+        let data_ram_size = (variant.data_ram_words() << 1) as u32;
+        for region in data_ram_map {
+            for addr_range in &region.address_ranges {
+                map.map::<true, true>(
+                    Some(Self::handle_upd7725_data_ram_read as ReadHandler),
+                    Some(Self::handle_upd7725_data_ram_write as WriteHandler),
+                    addr_range.banks,
+                    addr_range.addrs,
+                    0,
+                    data_ram_size,
+                    region.mask,
+                );
+            }
+        }
+    }
+
+    fn synced_upd7725(&mut self) -> &mut Upd7725 {
+        let time = self.cur_time;
+        match &mut self.coprocessor {
+            Some(super::Coprocessor::Upd7725(upd7725)) => {
+                upd7725.run_until(time);
+                upd7725
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn handle_upd7725_read(&mut self, addr: u32) -> u8 {
+        let upd7725 = self.synced_upd7725();
+        if upd7725.selects_sr(addr) {
+            (upd7725.regs.sr >> 8) as u8
+        } else {
+            upd7725.read_dr()
+        }
+    }
+
+    fn handle_upd7725_write(&mut self, addr: u32, value: u8) {
+        let upd7725 = self.synced_upd7725();
+        // SR is read-only from the main CPU's side
+        if !upd7725.selects_sr(addr) {
+            upd7725.write_dr(value);
+        }
+    }
+
+    fn handle_upd7725_data_ram_read(&mut self, offset: u32) -> u8 {
+        self.synced_upd7725().read_data_ram(offset)
+    }
+
+    fn handle_upd7725_data_ram_write(&mut self, offset: u32, value: u8) {
+        self.synced_upd7725().write_data_ram(offset, value);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::Wram;
+use core::fmt::{self, Display};
+use std::error::Error as StdError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// A Game Genie code, which substitutes the value read from an address.
+    GameGenie,
+    /// A Pro Action Replay code (or a raw `AAAAAA=VV` one), which substitutes the value read from
+    /// an address and also writes it to WRAM every frame.
+    ProActionReplay,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Code {
+    pub kind: Kind,
+    pub addr: u32,
+    pub value: u8,
+    /// The value that has to be read from the address for it to be substituted, if any.
+    pub compare: Option<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    UnknownFormat,
+    InvalidCharacter(char),
+}
+
+impl StdError for DecodeError {}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownFormat => f.write_str("Unknown cheat code format"),
+            Self::InvalidCharacter(char) => {
+                write!(f, "Invalid character in cheat code: {:?}", char)
+            }
+        }
+    }
+}
+
+fn parse_hex(digits: &str) -> Result<u32, DecodeError> {
+    digits.chars().try_fold(0, |acc, char| {
+        char.to_digit(16)
+            .map(|digit| acc << 4 | digit)
+            .ok_or(DecodeError::InvalidCharacter(char))
+    })
+}
+
+fn decode_game_genie(digits: &str) -> Result<Code, DecodeError> {
+    // Game Genie codes use their own ordering of hex digits
+    const ALPHABET: &[u8; 16] = b"DF4709156BC8A23E";
+    let value = digits.chars().try_fold(0_u32, |acc, char| {
+        ALPHABET
+            .iter()
+            .position(|&c| c as char == char.to_ascii_uppercase())
+            .map(|digit| acc << 4 | digit as u32)
+            .ok_or(DecodeError::InvalidCharacter(char))
+    })?;
+    let scrambled = value & 0xFF_FFFF;
+    let addr = (scrambled & 0x00_3C00) << 10
+        | (scrambled & 0x00_003C) << 14
+        | (scrambled & 0xF0_0000) >> 8
+        | (scrambled & 0x00_0003) << 10
+        | (scrambled & 0x00_C000) >> 6
+        | (scrambled & 0x0F_0000) >> 12
+        | (scrambled & 0x00_03C0) >> 6;
+    Ok(Code {
+        kind: Kind::GameGenie,
+        addr,
+        value: (value >> 24) as u8,
+        compare: None,
+    })
+}
+
+/// Decodes a single cheat code, in one of the following formats:
+/// - `XXXX-XXXX`: Game Genie
+/// - `BBAAAAVV` or `BBAAAA:VV`: Pro Action Replay
+/// - `BBAAAA=VV` or `BBAAAA=CC?VV`: raw address and value, optionally with a value to compare
+///   against
+pub fn decode(code: &str) -> Result<Code, DecodeError> {
+    let code = code.trim();
+    if let Some((addr, value)) = code.split_once('=') {
+        if addr.len() != 6 {
+            return Err(DecodeError::UnknownFormat);
+        }
+        let (compare, value) = match value.split_once('?') {
+            Some((compare, value)) if compare.len() == 2 => {
+                (Some(parse_hex(compare)? as u8), value)
+            }
+            Some(_) => return Err(DecodeError::UnknownFormat),
+            None => (None, value),
+        };
+        if value.len() != 2 {
+            return Err(DecodeError::UnknownFormat);
+        }
+        return Ok(Code {
+            kind: Kind::ProActionReplay,
+            addr: parse_hex(addr)?,
+            value: parse_hex(value)? as u8,
+            compare,
+        });
+    }
+
+    match code.as_bytes() {
+        [_, _, _, _, b'-', _, _, _, _] => decode_game_genie(&code.replacen('-', "", 1)),
+        [_, _, _, _, _, _, b':', _, _] => {
+            let value = parse_hex(&code.replacen(':', "", 1))?;
+            Ok(Code {
+                kind: Kind::ProActionReplay,
+                addr: value >> 8,
+                value: value as u8,
+                compare: None,
+            })
+        }
+        [_, _, _, _, _, _, _, _] => {
+            let value = parse_hex(code)?;
+            Ok(Code {
+                kind: Kind::ProActionReplay,
+                addr: value >> 8,
+                value: value as u8,
+                compare: None,
+            })
+        }
+        _ => Err(DecodeError::UnknownFormat),
+    }
+}
+
+/// Maps WRAM mirrors to their address in banks $7E-$7F, leaving other addresses untouched.
+fn canonicalize_addr(addr: u32) -> u32 {
+    let bank = (addr >> 16) as u8;
+    if addr & 0xE000 == 0 && matches!(bank, 0x00..=0x3F | 0x80..=0xBF) {
+        0x7E_0000 | (addr & 0x1FFF)
+    } else {
+        addr & 0xFF_FFFF
+    }
+}
+
+/// The set of cheat codes currently applied to the S-CPU bus.
+#[derive(Clone)]
+pub struct Cheats {
+    codes: Vec<Code>,
+}
+
+impl Cheats {
+    pub(crate) fn new() -> Self {
+        Cheats { codes: Vec::new() }
+    }
+
+    #[inline]
+    pub fn codes(&self) -> &[Code] {
+        &self.codes
+    }
+
+    pub fn set_codes(&mut self, codes: Vec<Code>) {
+        self.codes = codes
+            .into_iter()
+            .map(|code| Code {
+                addr: canonicalize_addr(code.addr),
+                ..code
+            })
+            .collect();
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// Returns the value that should be read from the given address in place of `value`, if any
+    /// code applies to it.
+    pub(crate) fn substitute(&self, addr: u32, value: u8) -> Option<u8> {
+        let addr = canonicalize_addr(addr);
+        self.codes
+            .iter()
+            .find(|code| code.addr == addr && code.compare.unwrap_or(value) == value)
+            .map(|code| code.value)
+    }
+
+    /// Writes the values of all Pro Action Replay codes targeting WRAM, as the original device
+    /// does once per frame.
+    pub(crate) fn apply_wram_writes(&self, wram: &mut Wram) {
+        for code in &self.codes {
+            if code.kind == Kind::ProActionReplay
+                && code.compare.is_none()
+                && matches!(code.addr >> 16, 0x7E | 0x7F)
+            {
+                wram.contents[code.addr as usize & 0x1_FFFF] = code.value;
+            }
+        }
+    }
+}
@@ -1,9 +1,18 @@
 pub mod empty;
 pub mod joypad;
+pub mod justifier;
+pub mod mouse;
+pub mod multitap;
+pub mod super_scope;
 
-use crate::schedule::{self, event_slots, Schedule, Timestamp};
+use crate::{
+    cpu::bus::AccessType,
+    savestate::{LoadError, Reader, Writer},
+    schedule::{self, event_slots, Schedule, Timestamp},
+};
 use empty::Empty;
 use joypad::Joypad;
+use multitap::Multitap;
 use std::any::Any;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -14,14 +23,48 @@ pub enum Event {
 
 pub trait Device: Any {
     fn as_any(&mut self) -> &mut dyn Any;
-    fn auto_read(&mut self) -> u16;
+
+    /// Updates the latch line, which is shared by both ports and driven by bit 0 of $4016.
+    fn set_latch(&mut self, value: bool);
+
+    /// Returns the current state of the D0 and D1 data lines in bits 0 and 1.
+    fn data(&self) -> u8;
+
+    /// Handles a pulse on the port's clock line, sent after every read from its serial data
+    /// register ($4016 or $4017) and 16 times during joypad auto-read.
+    fn clock(&mut self);
+
+    /// Updates the I/O line, driven by bit 6 (port 1) or 7 (port 2) of WRIO ($4201).
+    fn set_io(&mut self, value: bool);
+
+    /// Returns the level the device drives the I/O line to; as the line is open-collector, it
+    /// reads low if either the device or WRIO pulls it low.
+    fn io(&self) -> bool {
+        true
+    }
+
+    /// Returns the screen-space position at which the device detects the CRT beam, if any; when
+    /// the beam reaches it, the device pulls its I/O line low, which for port 2 latches the PPU's
+    /// H/V counters.
+    fn beam_target(&self) -> Option<[u16; 2]> {
+        None
+    }
+
+    /// Notifies the device that the beam reached its target (or, with `false`, that the scanline
+    /// it was on ended), to drive the I/O line accordingly.
+    fn set_beam_detected(&mut self, _value: bool) {}
+
+    fn save_state(&self, writer: &mut Writer);
+    fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError>;
 }
 
 pub struct Controllers {
     last_auto_read: Option<Timestamp>,
     joypad_auto_read_enabled: bool,
     joypad_auto_read_busy: bool,
-    pub devices: [Box<dyn Device>; 4],
+    latch: bool,
+    wrio: u8,
+    pub devices: [Box<dyn Device>; 2],
     pub auto_read_results: [u16; 4],
 }
 
@@ -35,12 +78,9 @@ impl Controllers {
             last_auto_read: None,
             joypad_auto_read_enabled: false,
             joypad_auto_read_busy: false,
-            devices: [
-                Box::new(Joypad::new()),
-                Box::new(Empty::new()),
-                Box::new(Empty::new()),
-                Box::new(Empty::new()),
-            ],
+            latch: false,
+            wrio: 0xFF,
+            devices: [Box::new(Joypad::new()), Box::new(Empty::new())],
             auto_read_results: [0; 4],
         }
     }
@@ -50,9 +90,7 @@ impl Controllers {
             Event::StartAutoRead => {
                 if self.joypad_auto_read_enabled {
                     self.joypad_auto_read_busy = true;
-                }
-                for i in 0..4 {
-                    self.auto_read_results[i] = self.devices[i].auto_read();
+                    self.auto_read();
                 }
                 schedule.set_event(
                     event_slots::CONTROLLERS,
@@ -70,6 +108,64 @@ impl Controllers {
         }
     }
 
+    /// Replaces the device connected to the given port, bringing the new one's latch and I/O
+    /// lines in sync with the current state.
+    pub fn connect_device(&mut self, port: usize, mut device: Box<dyn Device>) {
+        device.set_latch(self.latch);
+        device.set_io(self.wrio & 0x40 << port != 0);
+        self.devices[port] = device;
+    }
+
+    /// Returns the joypad assigned to the given player; players are numbered in port order, with
+    /// a multitap providing four of them.
+    pub fn joypad_mut(&mut self, mut player: usize) -> Option<&mut Joypad> {
+        for device in &mut self.devices {
+            let device = device.as_any();
+            if device.is::<Joypad>() {
+                if player == 0 {
+                    return device.downcast_mut::<Joypad>();
+                }
+                player -= 1;
+            } else if device.is::<Multitap>() {
+                if player < 4 {
+                    return device
+                        .downcast_mut::<Multitap>()
+                        .map(|multitap| &mut multitap.pads[player]);
+                }
+                player -= 4;
+            }
+        }
+        None
+    }
+
+    fn auto_read(&mut self) {
+        for device in &mut self.devices {
+            device.set_latch(true);
+            device.set_latch(self.latch);
+        }
+        self.auto_read_results = [0; 4];
+        for _ in 0..16 {
+            for (i, device) in self.devices.iter_mut().enumerate() {
+                let data = device.data();
+                self.auto_read_results[i] = self.auto_read_results[i] << 1 | (data & 1) as u16;
+                self.auto_read_results[i + 2] =
+                    self.auto_read_results[i + 2] << 1 | (data >> 1 & 1) as u16;
+                device.clock();
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn beam_target(&self) -> Option<[u16; 2]> {
+        // Only port 2's I/O line is connected to the PPU's external latch pin
+        self.devices[1].beam_target()
+    }
+
+    #[inline]
+    pub(crate) fn set_beam_detected(&mut self, value: bool) {
+        self.devices[1].set_beam_detected(value);
+    }
+
     pub(crate) fn last_auto_read(&self) -> Option<Timestamp> {
         self.last_auto_read
     }
@@ -88,4 +184,75 @@ impl Controllers {
     pub fn joypad_auto_read_busy(&self) -> bool {
         self.joypad_auto_read_busy
     }
+
+    #[inline]
+    pub fn latch(&self) -> bool {
+        self.latch
+    }
+
+    #[inline]
+    pub fn set_latch(&mut self, value: bool) {
+        self.latch = value;
+        for device in &mut self.devices {
+            device.set_latch(value);
+        }
+    }
+
+    #[inline]
+    pub fn read_serial_data<A: AccessType>(&mut self, port: usize) -> u8 {
+        let device = &mut self.devices[port];
+        let result = device.data() & 3;
+        if A::SIDE_EFFECTS {
+            device.clock();
+        }
+        result
+    }
+
+    #[inline]
+    pub fn wrio(&self) -> u8 {
+        self.wrio
+    }
+
+    #[inline]
+    pub fn set_wrio(&mut self, value: u8) {
+        self.wrio = value;
+        self.devices[0].set_io(value & 0x40 != 0);
+        self.devices[1].set_io(value & 0x80 != 0);
+    }
+
+    #[inline]
+    pub fn rdio(&self) -> u8 {
+        let mut result = self.wrio;
+        for (port, device) in self.devices.iter().enumerate() {
+            if !device.io() {
+                result &= !(0x40 << port);
+            }
+        }
+        result
+    }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.last_auto_read);
+        writer.write(&self.joypad_auto_read_enabled);
+        writer.write(&self.joypad_auto_read_busy);
+        writer.write(&self.latch);
+        writer.write(&self.wrio);
+        for device in &self.devices {
+            writer.write_section(|writer| device.save_state(writer));
+        }
+        writer.write(&self.auto_read_results);
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.last_auto_read = reader.read()?;
+        self.joypad_auto_read_enabled = reader.read()?;
+        self.joypad_auto_read_busy = reader.read()?;
+        self.latch = reader.read()?;
+        self.wrio = reader.read()?;
+        for device in &mut self.devices {
+            reader.read_section(|reader| device.load_state(reader))?;
+        }
+        self.auto_read_results = reader.read()?;
+        Ok(())
+    }
 }
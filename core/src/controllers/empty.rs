@@ -1,4 +1,5 @@
 use super::Device;
+use crate::savestate::{LoadError, Reader, Writer};
 
 pub struct Empty {}
 
@@ -19,7 +20,19 @@ impl Device for Empty {
         self
     }
 
-    fn auto_read(&mut self) -> u16 {
+    fn set_latch(&mut self, _value: bool) {}
+
+    fn data(&self) -> u8 {
         0
     }
+
+    fn clock(&mut self) {}
+
+    fn set_io(&mut self, _value: bool) {}
+
+    fn save_state(&self, _writer: &mut Writer) {}
+
+    fn load_state(&mut self, _reader: &mut Reader) -> Result<(), LoadError> {
+        Ok(())
+    }
 }
@@ -1,4 +1,5 @@
 use super::Device;
+use crate::savestate::{LoadError, Reader, Writer};
 
 bitflags::bitflags! {
     pub struct Keys: u16 {
@@ -19,12 +20,16 @@ bitflags::bitflags! {
 
 pub struct Joypad {
     pub pressed_keys: Keys,
+    latched: bool,
+    shift_reg: u16,
 }
 
 impl Joypad {
     pub fn new() -> Self {
         Joypad {
             pressed_keys: Keys::empty(),
+            latched: false,
+            shift_reg: 0,
         }
     }
 
@@ -44,7 +49,42 @@ impl Device for Joypad {
         self
     }
 
-    fn auto_read(&mut self) -> u16 {
-        self.pressed_keys.bits()
+    fn set_latch(&mut self, value: bool) {
+        // The shift register is continuously reloaded while the latch line is high
+        if value || self.latched {
+            self.shift_reg = self.pressed_keys.bits();
+        }
+        self.latched = value;
+    }
+
+    fn data(&self) -> u8 {
+        let shift_reg = if self.latched {
+            self.pressed_keys.bits()
+        } else {
+            self.shift_reg
+        };
+        (shift_reg >> 15) as u8
+    }
+
+    fn clock(&mut self) {
+        if !self.latched {
+            // Once all bits have been shifted out, the data line stays high
+            self.shift_reg = self.shift_reg << 1 | 1;
+        }
+    }
+
+    fn set_io(&mut self, _value: bool) {}
+
+    fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.pressed_keys.bits());
+        writer.write(&self.latched);
+        writer.write(&self.shift_reg);
+    }
+
+    fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.pressed_keys = Keys::from_bits_truncate(reader.read()?);
+        self.latched = reader.read()?;
+        self.shift_reg = reader.read()?;
+        Ok(())
     }
 }
@@ -0,0 +1,116 @@
+use super::Device;
+use crate::savestate::{LoadError, Reader, Writer};
+
+/// A single Konami Justifier, which has to be connected to port 2 to be able to latch the PPU's
+/// H/V counters.
+///
+/// The Justifier reports the state of two guns, with a second one optionally chained to the first
+/// one, and only lets one of them latch the counters at a time, switching to the other one on
+/// every latch; as a second gun is never connected here, the counters are only latched every
+/// other time.
+pub struct Justifier {
+    /// The screen-space position the gun is aimed at, or `None` if it's pointed away from the
+    /// screen.
+    pub aim: Option<[u16; 2]>,
+    pub trigger_pressed: bool,
+    pub start_pressed: bool,
+    second_gun_active: bool,
+    latched: bool,
+    shift_reg: u32,
+    beam_detected: bool,
+}
+
+impl Justifier {
+    pub fn new() -> Self {
+        Justifier {
+            aim: None,
+            trigger_pressed: false,
+            start_pressed: false,
+            second_gun_active: false,
+            latched: false,
+            shift_reg: 0,
+            beam_detected: false,
+        }
+    }
+
+    fn report(&self) -> u32 {
+        // The first 24 bits contain the device's signature
+        0x000E_5500
+            | (self.trigger_pressed as u32) << 7
+            | (self.start_pressed as u32) << 5
+            | (self.second_gun_active as u32) << 3
+    }
+}
+
+impl Default for Justifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Device for Justifier {
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn set_latch(&mut self, value: bool) {
+        if self.latched && !value {
+            self.second_gun_active = !self.second_gun_active;
+            self.shift_reg = self.report();
+        }
+        self.latched = value;
+    }
+
+    fn data(&self) -> u8 {
+        if self.latched {
+            0
+        } else {
+            (self.shift_reg >> 31) as u8
+        }
+    }
+
+    fn clock(&mut self) {
+        if !self.latched {
+            self.shift_reg = self.shift_reg << 1 | 1;
+        }
+    }
+
+    fn set_io(&mut self, _value: bool) {}
+
+    fn io(&self) -> bool {
+        !self.beam_detected
+    }
+
+    fn beam_target(&self) -> Option<[u16; 2]> {
+        if self.second_gun_active {
+            None
+        } else {
+            self.aim
+        }
+    }
+
+    fn set_beam_detected(&mut self, value: bool) {
+        self.beam_detected = value;
+    }
+
+    fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.aim);
+        writer.write(&self.trigger_pressed);
+        writer.write(&self.start_pressed);
+        writer.write(&self.second_gun_active);
+        writer.write(&self.latched);
+        writer.write(&self.shift_reg);
+        writer.write(&self.beam_detected);
+    }
+
+    fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.aim = reader.read()?;
+        self.trigger_pressed = reader.read()?;
+        self.start_pressed = reader.read()?;
+        self.second_gun_active = reader.read()?;
+        self.latched = reader.read()?;
+        self.shift_reg = reader.read()?;
+        self.beam_detected = reader.read()?;
+        Ok(())
+    }
+}
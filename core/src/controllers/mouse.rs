@@ -0,0 +1,134 @@
+use super::Device;
+use crate::savestate::{LoadError, Reader, Writer};
+
+pub struct Mouse {
+    pub left_pressed: bool,
+    pub right_pressed: bool,
+    delta_x: i32,
+    delta_y: i32,
+    speed: u8,
+    latched: bool,
+    shift_reg: u32,
+}
+
+impl Mouse {
+    pub fn new() -> Self {
+        Mouse {
+            left_pressed: false,
+            right_pressed: false,
+            delta_x: 0,
+            delta_y: 0,
+            speed: 0,
+            latched: false,
+            shift_reg: 0,
+        }
+    }
+
+    /// Accumulates relative motion (with positive values going right and down), which will be
+    /// reported and cleared on the next latch.
+    pub fn move_by(&mut self, x: i32, y: i32) {
+        self.delta_x = self.delta_x.saturating_add(x);
+        self.delta_y = self.delta_y.saturating_add(y);
+    }
+
+    /// Returns the accumulated relative motion that hasn't been reported yet.
+    #[inline]
+    pub fn motion(&self) -> [i32; 2] {
+        [self.delta_x, self.delta_y]
+    }
+
+    #[inline]
+    pub fn set_motion(&mut self, [x, y]: [i32; 2]) {
+        self.delta_x = x;
+        self.delta_y = y;
+    }
+
+    #[inline]
+    pub fn speed(&self) -> u8 {
+        self.speed
+    }
+
+    fn encode_motion(&self, value: i32) -> u32 {
+        let magnitude = value.unsigned_abs().min(0x7F);
+        let magnitude = match self.speed {
+            0 => magnitude,
+            1 => magnitude * 3 / 2,
+            _ => magnitude * 2,
+        };
+        magnitude.min(0x7F) | ((value < 0) as u32) << 7
+    }
+
+    fn report(&mut self) -> u32 {
+        let result = (self.right_pressed as u32) << 23
+            | (self.left_pressed as u32) << 22
+            | (self.speed as u32) << 20
+            | 1 << 16
+            | self.encode_motion(self.delta_y) << 8
+            | self.encode_motion(self.delta_x);
+        self.delta_x = 0;
+        self.delta_y = 0;
+        result
+    }
+}
+
+impl Default for Mouse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Device for Mouse {
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn set_latch(&mut self, value: bool) {
+        if self.latched && !value {
+            self.shift_reg = self.report();
+        }
+        self.latched = value;
+    }
+
+    fn data(&self) -> u8 {
+        if self.latched {
+            0
+        } else {
+            (self.shift_reg >> 31) as u8
+        }
+    }
+
+    fn clock(&mut self) {
+        if self.latched {
+            // Clocking the mouse while it's latched cycles through the three sensitivity settings
+            self.speed = (self.speed + 1) % 3;
+        } else {
+            self.shift_reg = self.shift_reg << 1 | 1;
+        }
+    }
+
+    fn set_io(&mut self, _value: bool) {}
+
+    fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.left_pressed);
+        writer.write(&self.right_pressed);
+        writer.write(&self.delta_x);
+        writer.write(&self.delta_y);
+        writer.write(&self.speed);
+        writer.write(&self.latched);
+        writer.write(&self.shift_reg);
+    }
+
+    fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.left_pressed = reader.read()?;
+        self.right_pressed = reader.read()?;
+        self.delta_x = reader.read()?;
+        self.delta_y = reader.read()?;
+        self.speed = reader.read()?;
+        if self.speed > 2 {
+            return Err(LoadError::InvalidData);
+        }
+        self.latched = reader.read()?;
+        self.shift_reg = reader.read()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use super::{joypad::Joypad, Device};
+use crate::savestate::{LoadError, Reader, Writer};
+
+/// A Super Multitap adapter with four joypads connected; the I/O line selects whether pads 1
+/// and 2 (high) or 3 and 4 (low) are connected to the D0 and D1 data lines.
+pub struct Multitap {
+    pub pads: [Joypad; 4],
+    latched: bool,
+    io: bool,
+}
+
+impl Multitap {
+    pub fn new() -> Self {
+        Multitap {
+            pads: [Joypad::new(), Joypad::new(), Joypad::new(), Joypad::new()],
+            latched: false,
+            io: true,
+        }
+    }
+
+    #[inline]
+    fn selected_pads(&self) -> usize {
+        if self.io {
+            0
+        } else {
+            2
+        }
+    }
+}
+
+impl Default for Multitap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Device for Multitap {
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn set_latch(&mut self, value: bool) {
+        self.latched = value;
+        for pad in &mut self.pads {
+            pad.set_latch(value);
+        }
+    }
+
+    fn data(&self) -> u8 {
+        let i = self.selected_pads();
+        // While latched, D1 is held high, which is how games detect the presence of a multitap
+        let d1 = if self.latched {
+            1
+        } else {
+            self.pads[i + 1].data() & 1
+        };
+        self.pads[i].data() & 1 | d1 << 1
+    }
+
+    fn clock(&mut self) {
+        let i = self.selected_pads();
+        self.pads[i].clock();
+        self.pads[i + 1].clock();
+    }
+
+    fn set_io(&mut self, value: bool) {
+        self.io = value;
+    }
+
+    fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.latched);
+        writer.write(&self.io);
+        for pad in &self.pads {
+            pad.save_state(writer);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.latched = reader.read()?;
+        self.io = reader.read()?;
+        for pad in &mut self.pads {
+            pad.load_state(reader)?;
+        }
+        Ok(())
+    }
+}
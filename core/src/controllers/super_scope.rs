@@ -0,0 +1,146 @@
+use super::Device;
+use crate::savestate::{LoadError, Reader, Writer};
+
+/// A Nintendo Super Scope, which has to be connected to port 2 to be able to latch the PPU's H/V
+/// counters.
+pub struct SuperScope {
+    /// The screen-space position the scope is aimed at, or `None` if it's pointed away from the
+    /// screen.
+    pub aim: Option<[u16; 2]>,
+    pub trigger_pressed: bool,
+    pub cursor_pressed: bool,
+    pub turbo_pressed: bool,
+    pub pause_pressed: bool,
+    turbo: bool,
+    prev_turbo_pressed: bool,
+    trigger_locked: bool,
+    prev_pause_pressed: bool,
+    latched: bool,
+    shift_reg: u16,
+    beam_detected: bool,
+}
+
+impl SuperScope {
+    pub fn new() -> Self {
+        SuperScope {
+            aim: None,
+            trigger_pressed: false,
+            cursor_pressed: false,
+            turbo_pressed: false,
+            pause_pressed: false,
+            turbo: false,
+            prev_turbo_pressed: false,
+            trigger_locked: false,
+            prev_pause_pressed: false,
+            latched: false,
+            shift_reg: 0,
+            beam_detected: false,
+        }
+    }
+
+    /// Returns the state of the turbo switch, which is toggled on every press of the turbo button.
+    #[inline]
+    pub fn turbo(&self) -> bool {
+        self.turbo
+    }
+
+    fn report(&mut self) -> u16 {
+        if self.turbo_pressed && !self.prev_turbo_pressed {
+            self.turbo = !self.turbo;
+        }
+        self.prev_turbo_pressed = self.turbo_pressed;
+
+        // Outside of turbo mode, the trigger only fires once per press
+        let trigger = self.trigger_pressed && (self.turbo || !self.trigger_locked);
+        self.trigger_locked = self.trigger_pressed;
+
+        let pause = self.pause_pressed && !self.prev_pause_pressed;
+        self.prev_pause_pressed = self.pause_pressed;
+
+        let offscreen = self.aim.is_none();
+        ((trigger && !offscreen) as u16) << 15
+            | (self.cursor_pressed as u16) << 14
+            | (self.turbo as u16) << 13
+            | (pause as u16) << 12
+            | (offscreen as u16) << 9
+            | 0xFF
+    }
+}
+
+impl Default for SuperScope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Device for SuperScope {
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn set_latch(&mut self, value: bool) {
+        if self.latched && !value {
+            self.shift_reg = self.report();
+        }
+        self.latched = value;
+    }
+
+    fn data(&self) -> u8 {
+        if self.latched {
+            0
+        } else {
+            (self.shift_reg >> 15) as u8
+        }
+    }
+
+    fn clock(&mut self) {
+        if !self.latched {
+            self.shift_reg = self.shift_reg << 1 | 1;
+        }
+    }
+
+    fn set_io(&mut self, _value: bool) {}
+
+    fn io(&self) -> bool {
+        !self.beam_detected
+    }
+
+    fn beam_target(&self) -> Option<[u16; 2]> {
+        self.aim
+    }
+
+    fn set_beam_detected(&mut self, value: bool) {
+        self.beam_detected = value;
+    }
+
+    fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.aim);
+        writer.write(&self.trigger_pressed);
+        writer.write(&self.cursor_pressed);
+        writer.write(&self.turbo_pressed);
+        writer.write(&self.pause_pressed);
+        writer.write(&self.turbo);
+        writer.write(&self.prev_turbo_pressed);
+        writer.write(&self.trigger_locked);
+        writer.write(&self.prev_pause_pressed);
+        writer.write(&self.latched);
+        writer.write(&self.shift_reg);
+        writer.write(&self.beam_detected);
+    }
+
+    fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.aim = reader.read()?;
+        self.trigger_pressed = reader.read()?;
+        self.cursor_pressed = reader.read()?;
+        self.turbo_pressed = reader.read()?;
+        self.pause_pressed = reader.read()?;
+        self.turbo = reader.read()?;
+        self.prev_turbo_pressed = reader.read()?;
+        self.trigger_locked = reader.read()?;
+        self.prev_pause_pressed = reader.read()?;
+        self.latched = reader.read()?;
+        self.shift_reg = reader.read()?;
+        self.beam_detected = reader.read()?;
+        Ok(())
+    }
+}
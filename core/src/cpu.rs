@@ -1,4 +1,7 @@
-use crate::emu::Emu;
+use crate::{
+    emu::Emu,
+    savestate::{LoadError, Reader, Writer},
+};
 
 pub mod bus;
 pub mod dma;
@@ -10,7 +13,7 @@ pub mod regs;
 mod common;
 #[cfg(feature = "disasm")]
 pub mod disasm;
-mod interpreter;
+pub(crate) mod interpreter;
 
 use math::Math;
 use regs::Regs;
@@ -55,4 +58,24 @@ impl Cpu {
     pub(crate) fn run_until_next_event(emu: &mut Emu) {
         interpreter::run_until_next_event(emu)
     }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        self.regs.save_state(writer);
+        writer.write(&self.mdr);
+        writer.write(&self.stopped);
+        self.irqs.save_state(writer);
+        self.math.save_state(writer);
+        self.dmac.save_state(writer);
+        self.bus_timings.save_state(writer);
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.regs.load_state(reader)?;
+        self.mdr = reader.read()?;
+        self.stopped = reader.read()?;
+        self.irqs.load_state(reader)?;
+        self.math.load_state(reader)?;
+        self.dmac.load_state(reader)?;
+        self.bus_timings.load_state(reader)
+    }
 }
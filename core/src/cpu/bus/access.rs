@@ -49,9 +49,8 @@ fn read_a_io<A: AccessType>(emu: &mut Emu, addr: u32) -> u8 {
     }
 
     match addr & 0x3FF {
-        // TODO: Manual controller reading
-        0x016 => return emu.cpu.mdr & 0xFC,
-        0x017 => return 0x1C | (emu.cpu.mdr & 0xE0),
+        0x016 => return emu.controllers.read_serial_data::<A>(0) | (emu.cpu.mdr & 0xFC),
+        0x017 => return emu.controllers.read_serial_data::<A>(1) | 0x1C | (emu.cpu.mdr & 0xE0),
         0x210 => return emu.ppu.read_nmi_flag::<A>().0 | (emu.cpu.mdr & 0x70),
         0x211 => {
             return emu
@@ -66,6 +65,7 @@ fn read_a_io<A: AccessType>(emu: &mut Emu, addr: u32) -> u8 {
                 | emu.controllers.joypad_auto_read_busy() as u8
                 | (emu.cpu.mdr & 0x3E);
         }
+        0x213 => return emu.controllers.rdio(),
         0x214 => return emu.cpu.math.div_quotient() as u8,
         0x215 => return (emu.cpu.math.div_quotient() >> 8) as u8,
         0x216 => return emu.cpu.math.mul_result_div_remainder() as u8,
@@ -118,6 +118,7 @@ fn write_a_io<A: AccessType>(emu: &mut Emu, addr: u32, value: u8) {
     }
 
     match addr & 0x3FF {
+        0x016 => return emu.controllers.set_latch(value & 1 != 0),
         0x200 => {
             emu.controllers.set_joypad_auto_read_enabled(value & 1 != 0);
             return emu.ppu.set_irq_control(
@@ -127,6 +128,13 @@ fn write_a_io<A: AccessType>(emu: &mut Emu, addr: u32, value: u8) {
                 &mut emu.schedule,
             );
         }
+        0x201 => {
+            // A high-to-low transition on the port 2 I/O line latches the H/V counters
+            if emu.controllers.wrio() & !value & 0x80 != 0 {
+                emu.ppu.latch_hv_counters(emu.schedule.cur_time);
+            }
+            return emu.controllers.set_wrio(value);
+        }
         0x202 => return emu.cpu.math.multiplicand = value,
         0x203 => {
             emu.cpu.math.multiplier = value;
@@ -260,6 +268,15 @@ pub fn read_b_io<A: AccessType>(emu: &mut Emu, addr: u8) -> u8 {
             return emu.apu.spc700.apu_to_cpu[addr as usize & 3];
         }
         0x80 => return emu.wram.read_data::<A>(),
+        0x88..=0x9F => {
+            if let Some(value) = emu
+                .satellaview
+                .as_ref()
+                .and_then(|satellaview| satellaview.read(addr))
+            {
+                return value;
+            }
+        }
         _ => {}
     }
 
@@ -384,6 +401,13 @@ pub fn write_b_io<A: AccessType>(emu: &mut Emu, addr: u8, value: u8) {
                 .wram
                 .set_addr((emu.wram.cur_addr() & !(0xFF << 16)) | (value as u32) << 16)
         }
+        0x88..=0x9F => {
+            if let Some(satellaview) = &mut emu.satellaview {
+                if satellaview.write(addr, value) {
+                    return;
+                }
+            }
+        }
         _ => {}
     }
 
@@ -400,7 +424,7 @@ pub fn write_b_io<A: AccessType>(emu: &mut Emu, addr: u8, value: u8) {
     }
 }
 
-pub fn read<A: AccessType>(emu: &mut Emu, addr: u32) -> u8 {
+fn read_bus<A: AccessType>(emu: &mut Emu, addr: u32) -> u8 {
     macro_rules! update_mdr {
         ($value: expr$(,)?) => {
             if A::SIDE_EFFECTS {
@@ -419,6 +443,13 @@ pub fn read<A: AccessType>(emu: &mut Emu, addr: u32) -> u8 {
             // WRAM system area mirror
             0x00..=0x1F => return update_mdr!(emu.wram.contents[addr as usize & 0x1FFF]),
 
+            // MSU-1
+            0x20 if addr & 0xF8 == 0 => {
+                if let Some(msu1) = &mut emu.msu1 {
+                    return update_mdr!(msu1.read::<A>(addr as u16));
+                }
+            }
+
             // Bus B I/O
             0x21 => {
                 return update_mdr!(if A::IS_DMA {
@@ -442,7 +473,8 @@ pub fn read<A: AccessType>(emu: &mut Emu, addr: u32) -> u8 {
         _ => {}
     }
 
-    if let Some(result) = emu.cart.read_data(addr) {
+    if let Some(result) = emu.cart.read_data(addr, emu.schedule.cur_time) {
+        emu.update_cart_irq();
         return update_mdr!(result);
     }
 
@@ -459,6 +491,23 @@ pub fn read<A: AccessType>(emu: &mut Emu, addr: u32) -> u8 {
     emu.cpu.mdr
 }
 
+#[inline]
+pub fn read<A: AccessType>(emu: &mut Emu, addr: u32) -> u8 {
+    let result = read_bus::<A>(emu, addr);
+    if emu.cheats.is_empty() {
+        return result;
+    }
+    match emu.cheats.substitute(addr, result) {
+        Some(value) => {
+            if A::SIDE_EFFECTS {
+                emu.cpu.mdr = value;
+            }
+            value
+        }
+        None => result,
+    }
+}
+
 #[allow(clippy::needless_return)] // With logging disabled, the return is detected as needless
 pub fn write<A: AccessType>(emu: &mut Emu, addr: u32, value: u8) {
     if !A::IS_DMA {
@@ -472,6 +521,13 @@ pub fn write<A: AccessType>(emu: &mut Emu, addr: u32, value: u8) {
             // WRAM system area mirror
             0x00..=0x1F => return emu.wram.contents[addr as usize & 0x1FFF] = value,
 
+            // MSU-1
+            0x20 if addr & 0xF8 == 0 => {
+                if let Some(msu1) = &mut emu.msu1 {
+                    return msu1.write(addr as u16, value);
+                }
+            }
+
             // Bus B I/O
             0x21 if !A::IS_DMA => return write_b_io::<A>(emu, addr as u8, value),
 
@@ -492,7 +548,12 @@ pub fn write<A: AccessType>(emu: &mut Emu, addr: u32, value: u8) {
         _ => {}
     }
 
-    if emu.cart.write_data(addr, value).is_some() {
+    if emu
+        .cart
+        .write_data(addr, value, emu.schedule.cur_time)
+        .is_some()
+    {
+        emu.update_cart_irq();
         return;
     }
 
@@ -1,4 +1,7 @@
-use crate::utils::zeroed_box;
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    utils::zeroed_box,
+};
 
 pub struct Timings {
     values: Box<[u8; Self::ENTRIES]>,
@@ -64,4 +67,13 @@ impl Timings {
         self.set((0x80, 0xBF), (0x8000, 0xFFFF), cycles);
         self.set((0xC0, 0xFF), (0x0000, 0xFFFF), cycles);
     }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.fastrom_enabled);
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.set_fastrom_enabled(reader.read()?);
+        Ok(())
+    }
 }
@@ -1,6 +1,10 @@
 use super::bus;
 use crate::utils::bitfield_debug;
-use crate::{emu::Emu, schedule::Schedule};
+use crate::{
+    emu::Emu,
+    savestate::{LoadError, Reader, Writer},
+    schedule::Schedule,
+};
 
 mod bounded {
     use crate::utils::bounded_int;
@@ -68,6 +72,33 @@ impl Channel {
     pub fn gp_addr_step(&self) -> i8 {
         self.gp_addr_step
     }
+
+    fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.control.0);
+        writer.write(&self.h_do_transfer);
+        writer.write(&self.b_addr);
+        writer.write(&self.gp_a_addr_h_table_start_addr);
+        writer.write(&self.gp_a_bank_h_table_bank);
+        writer.write(&self.gp_byte_counter_h_indirect_addr);
+        writer.write(&self.h_indirect_bank);
+        writer.write(&self.h_cur_table_addr);
+        writer.write(&self.h_line_counter);
+        writer.write(&self.unused);
+    }
+
+    fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.set_control(ChannelControl(reader.read()?));
+        self.h_do_transfer = reader.read()?;
+        self.b_addr = reader.read()?;
+        self.gp_a_addr_h_table_start_addr = reader.read()?;
+        self.gp_a_bank_h_table_bank = reader.read()?;
+        self.gp_byte_counter_h_indirect_addr = reader.read()?;
+        self.h_indirect_bank = reader.read()?;
+        self.h_cur_table_addr = reader.read()?;
+        self.h_line_counter = reader.read()?;
+        self.unused = reader.read()?;
+        Ok(())
+    }
 }
 
 pub struct Controller {
@@ -333,6 +364,9 @@ impl Controller {
             emu.cpu.dmac.cur_channel = None;
             emu.cpu.dmac.select_next_channel();
         } else {
+            // Some cartridge chips alter the data read by specific channels, such as the S-DD1's
+            // decompressor
+            emu.cart.set_gp_dma_channel(Some(i.get()));
             while emu.schedule.cur_time < emu.schedule.next_event_time() {
                 transfer!(
                     channel,
@@ -346,6 +380,7 @@ impl Controller {
                         channel.gp_byte_counter_h_indirect_addr =
                             channel.gp_byte_counter_h_indirect_addr.wrapping_sub(1);
                         if channel.gp_byte_counter_h_indirect_addr == 0 {
+                            emu.cart.finish_gp_dma(i.get());
                             emu.cpu.dmac.gp_requested &= !(1 << i.get());
                             emu.cpu.dmac.cur_channel = None;
                             emu.cpu.dmac.select_next_channel();
@@ -354,6 +389,34 @@ impl Controller {
                     },
                 );
             }
+            emu.cart.set_gp_dma_channel(None);
         }
     }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        for channel in &self.channels {
+            channel.save_state(writer);
+        }
+        writer.write(&self.gp_requested);
+        writer.write(&self.h_enabled);
+        writer.write(&self.h_frame_enabled);
+        writer.write(&self.h_requested);
+        writer.write(&self.cur_channel.map(|i| i.get()));
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        for channel in &mut self.channels {
+            channel.load_state(reader)?;
+        }
+        self.gp_requested = reader.read()?;
+        self.h_enabled = reader.read()?;
+        self.h_frame_enabled = reader.read()?;
+        self.h_requested = reader.read()?;
+        self.cur_channel = match reader.read::<Option<u8>>()? {
+            Some(i) if i < 8 => Some(Index::new(i)),
+            Some(_) => return Err(LoadError::InvalidData),
+            None => None,
+        };
+        Ok(())
+    }
 }
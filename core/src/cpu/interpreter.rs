@@ -12,9 +12,103 @@ use other::*;
 mod transfers;
 use transfers::*;
 
-use super::dma;
-use crate::emu::Emu;
+use super::{bus, dma, regs::Regs};
+use crate::{emu::Emu, schedule::Timestamp};
 use common::jump_to_exc_vector;
+use core::marker::PhantomData;
+
+/// The state a 65C816 core operates on, so that the same interpreter can run both the main CPU and
+/// coprocessors based on it (i.e. the SA-1).
+pub(crate) trait Core {
+    #[cfg(feature = "log")]
+    fn logger(&self) -> &slog::Logger;
+    fn regs(&self) -> &Regs;
+    fn regs_mut(&mut self) -> &mut Regs;
+    fn add_io_cycles(&mut self, cycles: u8);
+    fn read_8(&mut self, addr: u32) -> u8;
+    fn write_8(&mut self, addr: u32, value: u8);
+    fn set_irqs_enabled(&mut self, value: bool);
+    fn wait_for_exception(&mut self);
+    fn stop(&mut self);
+}
+
+impl Core for Emu {
+    #[cfg(feature = "log")]
+    #[inline]
+    fn logger(&self) -> &slog::Logger {
+        &self.cpu.logger
+    }
+
+    #[inline]
+    fn regs(&self) -> &Regs {
+        &self.cpu.regs
+    }
+
+    #[inline]
+    fn regs_mut(&mut self) -> &mut Regs {
+        &mut self.cpu.regs
+    }
+
+    #[inline]
+    fn add_io_cycles(&mut self, cycles: u8) {
+        self.schedule.cur_time += cycles as Timestamp * 6;
+    }
+
+    #[inline]
+    fn read_8(&mut self, addr: u32) -> u8 {
+        let cycles = self.cpu.bus_timings.get(addr);
+        let result = bus::read::<bus::CpuAccess>(self, addr);
+        self.schedule.cur_time += cycles as Timestamp;
+        result
+    }
+
+    #[inline]
+    fn write_8(&mut self, addr: u32, value: u8) {
+        let cycles = self.cpu.bus_timings.get(addr);
+        bus::write::<bus::CpuAccess>(self, addr, value);
+        self.schedule.cur_time += cycles as Timestamp;
+    }
+
+    #[inline]
+    fn set_irqs_enabled(&mut self, value: bool) {
+        self.cpu.irqs.set_irqs_enabled(value, &mut self.schedule);
+    }
+
+    fn wait_for_exception(&mut self) {
+        self.cpu.irqs.set_waiting_for_exception(true);
+        if self.cpu.irqs.waiting_for_exception() {
+            self.schedule.set_target_to_cur();
+        }
+    }
+
+    fn stop(&mut self) {
+        self.cpu.stopped = true;
+        self.schedule.set_target_to_cur();
+    }
+}
+
+struct InstrTable<C: Core>(PhantomData<C>);
+
+impl<C: Core> InstrTable<C> {
+    const TABLE: [fn(&mut C); 0x800] = include!(concat!(env!("OUT_DIR"), "/instr_table_65c816.rs"));
+}
+
+/// Runs a single instruction on the given core.
+#[inline]
+pub(crate) fn run_instr<C: Core>(core: &mut C) {
+    let table = &InstrTable::<C>::TABLE;
+    let instr = consume_imm::<u8>(core);
+    unsafe { table.get_unchecked(instr as usize | core.regs().psw_lut_base() as usize)(core) };
+}
+
+/// Pushes the current return address and flags to the stack and jumps to the given exception
+/// handler, as done when an interrupt is taken.
+pub(crate) fn handle_interrupt<C: Core>(core: &mut C, handler_pc: u16) {
+    push(core, core.regs().code_bank());
+    push(core, core.regs().pc);
+    push(core, core.regs().psw.0);
+    jump_to_exc_handler(core, handler_pc);
+}
 
 pub fn soft_reset(emu: &mut Emu) {
     emu.cpu.stopped = false;
@@ -32,9 +126,6 @@ pub fn soft_reset(emu: &mut Emu) {
     jump_to_exc_vector(emu, 0xFFFC);
 }
 
-static INSTR_TABLE: [fn(&mut Emu); 0x800] =
-    include!(concat!(env!("OUT_DIR"), "/instr_table_65c816.rs"));
-
 #[inline]
 pub fn run_until_next_event(emu: &mut Emu) {
     while emu.schedule.cur_time < emu.schedule.next_event_time() {
@@ -60,12 +151,7 @@ pub fn run_until_next_event(emu: &mut Emu) {
                 jump_to_exc_vector(emu, 0xFFEE);
             }
             while emu.schedule.cur_time < emu.schedule.target_time {
-                let instr = consume_imm::<u8>(emu);
-                unsafe {
-                    INSTR_TABLE.get_unchecked(instr as usize | emu.cpu.regs.psw_lut_base() as usize)(
-                        emu,
-                    )
-                };
+                run_instr(emu);
             }
         }
     }
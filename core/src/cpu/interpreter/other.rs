@@ -1,127 +1,119 @@
 use super::common::{add_io_cycles, consume_imm, jump_to_exc_vector, pull, push};
-use crate::{cpu::regs::Psw, emu::Emu};
+use super::Core;
+use crate::cpu::regs::Psw;
 
-pub(super) fn sec(emu: &mut Emu) {
-    emu.cpu.regs.psw.set_carry(true);
+pub(super) fn sec(emu: &mut impl Core) {
+    emu.regs_mut().psw.set_carry(true);
 }
 
-pub(super) fn sed(emu: &mut Emu) {
-    emu.cpu
-        .regs
-        .set_psw(emu.cpu.regs.psw.with_decimal_mode(true));
+pub(super) fn sed(emu: &mut impl Core) {
+    let psw = emu.regs().psw.with_decimal_mode(true);
+    emu.regs_mut().set_psw(psw);
 }
 
-pub(super) fn sei(emu: &mut Emu) {
-    emu.cpu.regs.psw.set_irqs_disabled(true);
-    emu.cpu.irqs.set_irqs_enabled(false, &mut emu.schedule);
+pub(super) fn sei(emu: &mut impl Core) {
+    emu.regs_mut().psw.set_irqs_disabled(true);
+    emu.set_irqs_enabled(false);
 }
 
-pub(super) fn clc(emu: &mut Emu) {
-    emu.cpu.regs.psw.set_carry(false);
+pub(super) fn clc(emu: &mut impl Core) {
+    emu.regs_mut().psw.set_carry(false);
 }
 
-pub(super) fn cld(emu: &mut Emu) {
-    emu.cpu
-        .regs
-        .set_psw(emu.cpu.regs.psw.with_decimal_mode(false));
+pub(super) fn cld(emu: &mut impl Core) {
+    let psw = emu.regs().psw.with_decimal_mode(false);
+    emu.regs_mut().set_psw(psw);
 }
 
-pub(super) fn cli(emu: &mut Emu) {
-    emu.cpu.regs.psw.set_irqs_disabled(false);
-    emu.cpu.irqs.set_irqs_enabled(true, &mut emu.schedule);
+pub(super) fn cli(emu: &mut impl Core) {
+    emu.regs_mut().psw.set_irqs_disabled(false);
+    emu.set_irqs_enabled(true);
 }
 
-pub(super) fn clv(emu: &mut Emu) {
-    emu.cpu.regs.psw.set_overflow(false);
+pub(super) fn clv(emu: &mut impl Core) {
+    emu.regs_mut().psw.set_overflow(false);
 }
 
-pub(super) fn sep(emu: &mut Emu) {
+pub(super) fn sep(emu: &mut impl Core) {
     let mask = consume_imm::<u8>(emu);
-    emu.cpu.regs.set_psw(Psw(emu.cpu.regs.psw.0 | mask));
-    emu.cpu
-        .irqs
-        .set_irqs_enabled(!emu.cpu.regs.psw.irqs_disabled(), &mut emu.schedule);
+    let psw = Psw(emu.regs().psw.0 | mask);
+    emu.regs_mut().set_psw(psw);
+    emu.set_irqs_enabled(!emu.regs().psw.irqs_disabled());
     add_io_cycles(emu, 1);
 }
 
-pub(super) fn rep(emu: &mut Emu) {
+pub(super) fn rep(emu: &mut impl Core) {
     let mask = consume_imm::<u8>(emu);
-    emu.cpu.regs.set_psw(Psw(emu.cpu.regs.psw.0 & !mask));
-    emu.cpu
-        .irqs
-        .set_irqs_enabled(!emu.cpu.regs.psw.irqs_disabled(), &mut emu.schedule);
+    let psw = Psw(emu.regs().psw.0 & !mask);
+    emu.regs_mut().set_psw(psw);
+    emu.set_irqs_enabled(!emu.regs().psw.irqs_disabled());
     add_io_cycles(emu, 1);
 }
 
-pub(super) fn xce(emu: &mut Emu) {
-    let new_value = emu.cpu.regs.psw.carry();
-    emu.cpu.regs.psw.set_carry(emu.cpu.regs.emulation_mode());
-    emu.cpu.regs.set_emulation_mode::<false>(new_value);
+pub(super) fn xce(emu: &mut impl Core) {
+    let new_value = emu.regs().psw.carry();
+    let emulation_mode = emu.regs().emulation_mode();
+    emu.regs_mut().psw.set_carry(emulation_mode);
+    emu.regs_mut().set_emulation_mode::<false>(new_value);
 }
 
-pub(super) fn rti(emu: &mut Emu) {
+pub(super) fn rti(emu: &mut impl Core) {
     add_io_cycles(emu, 2);
     let new_psw = pull::<u8>(emu);
     let new_pc = pull::<u16>(emu);
     let new_code_bank = pull::<u8>(emu);
-    emu.cpu.regs.set_psw(Psw(new_psw));
-    emu.cpu
-        .irqs
-        .set_irqs_enabled(!emu.cpu.regs.psw.irqs_disabled(), &mut emu.schedule);
-    emu.cpu.regs.pc = new_pc;
-    emu.cpu.regs.set_code_bank(new_code_bank);
+    emu.regs_mut().set_psw(Psw(new_psw));
+    emu.set_irqs_enabled(!emu.regs().psw.irqs_disabled());
+    emu.regs_mut().pc = new_pc;
+    emu.regs_mut().set_code_bank(new_code_bank);
 }
 
-pub(super) fn brk(emu: &mut Emu) {
+pub(super) fn brk(emu: &mut impl Core) {
     #[cfg(feature = "log")]
     slog::info!(
-        emu.cpu.logger,
+        emu.logger(),
         "BRK encountered @ {:#08X}",
-        emu.cpu.regs.pc.wrapping_sub(1) as u32 | emu.cpu.regs.code_bank_base()
+        emu.regs().pc.wrapping_sub(1) as u32 | emu.regs().code_bank_base()
     );
     let _signature = consume_imm::<u8>(emu);
-    push(emu, emu.cpu.regs.code_bank());
-    push(emu, emu.cpu.regs.pc);
-    push(emu, emu.cpu.regs.psw.0);
+    push(emu, emu.regs().code_bank());
+    push(emu, emu.regs().pc);
+    push(emu, emu.regs().psw.0);
     jump_to_exc_vector(emu, 0xFFE6);
 }
 
-pub(super) fn nop(emu: &mut Emu) {
+pub(super) fn nop(emu: &mut impl Core) {
     add_io_cycles(emu, 1);
 }
 
-pub(super) fn wai(emu: &mut Emu) {
-    emu.cpu.irqs.set_waiting_for_exception(true);
-    if emu.cpu.irqs.waiting_for_exception() {
-        emu.schedule.set_target_to_cur();
-    }
+pub(super) fn wai(emu: &mut impl Core) {
+    emu.wait_for_exception();
 }
 
-pub(super) fn cop(emu: &mut Emu) {
+pub(super) fn cop(emu: &mut impl Core) {
     let _signature = consume_imm::<u8>(emu);
-    push(emu, emu.cpu.regs.code_bank());
-    push(emu, emu.cpu.regs.pc);
-    push(emu, emu.cpu.regs.psw.0);
+    push(emu, emu.regs().code_bank());
+    push(emu, emu.regs().pc);
+    push(emu, emu.regs().psw.0);
     jump_to_exc_vector(emu, 0xFFE4);
 }
 
-pub(super) fn stp(emu: &mut Emu) {
+pub(super) fn stp(emu: &mut impl Core) {
     #[cfg(feature = "log")]
     slog::warn!(
-        emu.cpu.logger,
+        emu.logger(),
         "STP encountered @ {:#08X}",
-        emu.cpu.regs.pc.wrapping_sub(1) as u32 | emu.cpu.regs.code_bank_base()
+        emu.regs().pc.wrapping_sub(1) as u32 | emu.regs().code_bank_base()
     );
-    emu.cpu.stopped = true;
-    emu.schedule.set_target_to_cur();
+    emu.stop();
 }
 
-pub(super) fn wdm(emu: &mut Emu) {
+pub(super) fn wdm(emu: &mut impl Core) {
     #[cfg(feature = "log")]
     slog::warn!(
-        emu.cpu.logger,
+        emu.logger(),
         "WDM encountered @ {:#08X}",
-        emu.cpu.regs.pc.wrapping_sub(1) as u32 | emu.cpu.regs.code_bank_base()
+        emu.regs().pc.wrapping_sub(1) as u32 | emu.regs().code_bank_base()
     );
     let _dummy = consume_imm::<u8>(emu);
 }
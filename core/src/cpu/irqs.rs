@@ -1,12 +1,16 @@
 // TODO: IRQ delay emulation, especially interacting with WAI and DMAs
 
-use crate::schedule::Schedule;
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    schedule::Schedule,
+};
 
 #[derive(Debug)]
 pub struct Irqs {
     irqs_enabled: bool,
     waiting_for_exception: bool,
     hv_timer_irq_requested: bool,
+    cart_irq_requested: bool,
     processing_irq: bool,
     processing_nmi: bool,
 }
@@ -17,6 +21,7 @@ impl Irqs {
             irqs_enabled: true,
             waiting_for_exception: false,
             hv_timer_irq_requested: false,
+            cart_irq_requested: false,
             processing_irq: false,
             processing_nmi: false,
         }
@@ -28,7 +33,8 @@ impl Irqs {
     }
 
     fn update_irqs(&mut self, schedule: &mut Schedule) {
-        self.processing_irq = self.hv_timer_irq_requested && self.irqs_enabled;
+        self.processing_irq =
+            (self.hv_timer_irq_requested || self.cart_irq_requested) && self.irqs_enabled;
         if self.processing_irq {
             schedule.set_target_to_cur();
         }
@@ -47,7 +53,8 @@ impl Irqs {
 
     #[inline]
     pub fn set_waiting_for_exception(&mut self, value: bool) {
-        self.waiting_for_exception = value && !(self.processing_nmi || self.hv_timer_irq_requested);
+        self.waiting_for_exception = value
+            && !(self.processing_nmi || self.hv_timer_irq_requested || self.cart_irq_requested);
     }
 
     #[inline]
@@ -62,6 +69,18 @@ impl Irqs {
         self.update_irqs(schedule);
     }
 
+    #[inline]
+    pub fn cart_irq_requested(&self) -> bool {
+        self.cart_irq_requested
+    }
+
+    #[inline]
+    pub fn set_cart_irq_requested(&mut self, value: bool, schedule: &mut Schedule) {
+        self.cart_irq_requested = value;
+        self.waiting_for_exception &= !value;
+        self.update_irqs(schedule);
+    }
+
     #[inline]
     pub fn processing_irq(&self) -> bool {
         self.processing_irq
@@ -82,4 +101,23 @@ impl Irqs {
     pub(super) fn acknowledge_nmi(&mut self) {
         self.processing_nmi = false;
     }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.irqs_enabled);
+        writer.write(&self.waiting_for_exception);
+        writer.write(&self.hv_timer_irq_requested);
+        writer.write(&self.cart_irq_requested);
+        writer.write(&self.processing_irq);
+        writer.write(&self.processing_nmi);
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.irqs_enabled = reader.read()?;
+        self.waiting_for_exception = reader.read()?;
+        self.hv_timer_irq_requested = reader.read()?;
+        self.cart_irq_requested = reader.read()?;
+        self.processing_irq = reader.read()?;
+        self.processing_nmi = reader.read()?;
+        Ok(())
+    }
 }
@@ -1,5 +1,7 @@
 // TODO: Timings
 
+use crate::savestate::{LoadError, Reader, Writer};
+
 pub struct Math {
     pub multiplicand: u8,
     pub multiplier: u8,
@@ -46,4 +48,23 @@ impl Math {
             self.mul_result_div_remainder = self.dividend % self.divisor as u16;
         }
     }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.multiplicand);
+        writer.write(&self.multiplier);
+        writer.write(&self.dividend);
+        writer.write(&self.divisor);
+        writer.write(&self.div_quotient);
+        writer.write(&self.mul_result_div_remainder);
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.multiplicand = reader.read()?;
+        self.multiplier = reader.read()?;
+        self.dividend = reader.read()?;
+        self.divisor = reader.read()?;
+        self.div_quotient = reader.read()?;
+        self.mul_result_div_remainder = reader.read()?;
+        Ok(())
+    }
 }
@@ -1,4 +1,7 @@
-use crate::utils::bitfield_debug;
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    utils::bitfield_debug,
+};
 
 bitfield_debug!(
     #[derive(Clone, Copy, PartialEq, Eq)]
@@ -110,4 +113,31 @@ impl Regs {
     pub(crate) fn data_bank_base(&self) -> u32 {
         self.data_bank_base
     }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.a);
+        writer.write(&self.x);
+        writer.write(&self.y);
+        writer.write(&self.sp);
+        writer.write(&self.pc);
+        writer.write(&self.direct_page_offset);
+        writer.write(&self.psw.0);
+        writer.write(&self.emulation_mode);
+        writer.write(&self.code_bank);
+        writer.write(&self.data_bank);
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.a = reader.read()?;
+        self.x = reader.read()?;
+        self.y = reader.read()?;
+        self.sp = reader.read()?;
+        self.pc = reader.read()?;
+        self.direct_page_offset = reader.read()?;
+        self.set_psw(Psw(reader.read()?));
+        self.emulation_mode = reader.read()?;
+        self.set_code_bank(reader.read()?);
+        self.set_data_bank(reader.read()?);
+        Ok(())
+    }
 }
@@ -1,9 +1,13 @@
 use crate::{
     apu::{dsp, Apu},
-    cart::Cart,
+    cart::{Cart, Coprocessor},
+    cheats::Cheats,
     controllers::Controllers,
     cpu::Cpu,
+    msu1::Msu1,
     ppu::Ppu,
+    satellaview::BaseUnit,
+    savestate::{LoadError, Reader, Writer},
     schedule::{Event, Schedule},
     Model, Wram,
 };
@@ -16,17 +20,33 @@ pub struct Emu {
     pub ppu: Ppu,
     pub cart: Cart,
     pub controllers: Controllers,
+    /// The MSU-1 expansion, if the game uses it; can be connected after creating the emulator.
+    pub msu1: Option<Msu1>,
+    /// The Satellaview base unit, connected automatically for BS-X cartridges.
+    pub satellaview: Option<BaseUnit>,
+    /// The active cheat codes; not part of savestates.
+    pub cheats: Cheats,
 }
 
 impl Emu {
     pub fn new(
         model: Model,
-        cart: Cart,
+        mut cart: Cart,
         audio_backend: Box<dyn dsp::Backend>,
         audio_sample_chunk_len: usize,
         #[cfg(feature = "log")] logger: &slog::Logger,
     ) -> Self {
         let mut schedule = Schedule::new();
+        cart.setup(
+            model,
+            &mut schedule,
+            #[cfg(feature = "log")]
+            logger,
+        );
+        let satellaview = match cart.coprocessor() {
+            Some(Coprocessor::Mcc(_)) => Some(BaseUnit::new()),
+            _ => None,
+        };
         let mut emu = Emu {
             cpu: Cpu::new(
                 #[cfg(feature = "log")]
@@ -44,6 +64,9 @@ impl Emu {
             ppu: Ppu::new(model, &mut schedule),
             cart,
             controllers: Controllers::new(&mut schedule),
+            msu1: None,
+            satellaview,
+            cheats: Cheats::new(),
             schedule,
         };
         emu.soft_reset();
@@ -53,6 +76,13 @@ impl Emu {
     pub fn soft_reset(&mut self) {
         // TODO: Reset other components
         self.apu.soft_reset();
+        self.cart.soft_reset();
+        if let Some(msu1) = &mut self.msu1 {
+            msu1.reset();
+        }
+        if let Some(satellaview) = &mut self.satellaview {
+            satellaview.reset();
+        }
         Cpu::soft_reset(self);
     }
 
@@ -71,10 +101,88 @@ impl Emu {
                         self.controllers
                             .handle_event(event, time, &mut self.schedule)
                     }
-                    Event::UpdateApu => self.apu.handle_update(time, &mut self.schedule),
+                    Event::UpdateApu => {
+                        let cart_sample = self
+                            .msu1
+                            .as_mut()
+                            .map_or([0; 2], |msu1| msu1.output_sample());
+                        self.apu
+                            .handle_update(time, &mut self.schedule, cart_sample);
+                    }
+                    Event::Cart => {
+                        self.cart.handle_event(time, &mut self.schedule);
+                        self.update_cart_irq();
+                    }
                 }
             }
         }
         self.ppu.frame_finished = false;
+        if !self.cheats.is_empty() {
+            self.cheats.apply_wram_writes(&mut self.wram);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn update_cart_irq(&mut self) {
+        let requested = self.cart.irq_requested();
+        if requested != self.cpu.irqs.cart_irq_requested() {
+            self.cpu
+                .irqs
+                .set_cart_irq_requested(requested, &mut self.schedule);
+        }
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        self.schedule.save_state(&mut writer);
+        self.cpu.save_state(&mut writer);
+        self.wram.save_state(&mut writer);
+        self.apu.save_state(&mut writer);
+        self.ppu.save_state(&mut writer);
+        self.cart.save_state(&mut writer);
+        self.controllers.save_state(&mut writer);
+        writer.write_section(|writer| {
+            if let Some(msu1) = &self.msu1 {
+                msu1.save_state(writer);
+            }
+        });
+        writer.write_section(|writer| {
+            if let Some(satellaview) = &self.satellaview {
+                satellaview.save_state(writer);
+            }
+        });
+        writer.finish()
+    }
+
+    fn load_state_unchecked(&mut self, mut reader: Reader) -> Result<(), LoadError> {
+        self.schedule.load_state(&mut reader)?;
+        self.cpu.load_state(&mut reader)?;
+        self.wram.load_state(&mut reader)?;
+        self.apu.load_state(&mut reader)?;
+        self.ppu.load_state(&mut reader)?;
+        self.cart.load_state(&mut reader)?;
+        self.controllers.load_state(&mut reader)?;
+        reader.read_section(|reader| match &mut self.msu1 {
+            Some(msu1) => msu1.load_state(reader),
+            None => Ok(()),
+        })?;
+        reader.read_section(|reader| match &mut self.satellaview {
+            Some(satellaview) => satellaview.load_state(reader),
+            None => Ok(()),
+        })?;
+        reader.finish()
+    }
+
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), LoadError> {
+        let reader = Reader::new(state)?;
+        // Components are loaded in place, so a failure halfway through would leave the emulator in
+        // an inconsistent state; restore the previous one in that case.
+        let prev_state = self.save_state();
+        let result = self.load_state_unchecked(reader);
+        if result.is_err() {
+            self.load_state_unchecked(Reader::new(&prev_state).unwrap())
+                .expect("Couldn't restore previous emulator state");
+        }
+        result
     }
 }
@@ -6,10 +6,15 @@ use serde::{Deserialize, Serialize};
 pub extern crate emu_utils as utils;
 
 pub mod cart;
+pub mod cheats;
 pub mod controllers;
 pub mod cpu;
 pub mod emu;
+pub mod movie;
+pub mod msu1;
 pub mod ppu;
+pub mod satellaview;
+pub mod savestate;
 pub mod schedule;
 mod wram;
 pub use wram::Wram;
@@ -0,0 +1,727 @@
+use crate::{
+    cheats::{self, Code},
+    controllers::{
+        joypad::{Joypad, Keys},
+        justifier::Justifier,
+        mouse::Mouse,
+        multitap::Multitap,
+        super_scope::SuperScope,
+        Device,
+    },
+    emu::Emu,
+    ppu::FB_WIDTH,
+    savestate, Model,
+};
+use core::fmt::{self, Display};
+use std::error::Error as StdError;
+
+const MAGIC: [u8; 4] = *b"NSMV";
+pub const VERSION: u32 = 2;
+
+// The host time used both when storing and when restoring RTC state, so that the clock doesn't
+// advance depending on when the movie is played back
+const RTC_UNIX_TIME: u64 = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    UnexpectedEnd,
+    InvalidData,
+}
+
+impl StdError for LoadError {}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported movie version {} (expected {})",
+                version, VERSION
+            ),
+            _ => f.write_str(match self {
+                Self::InvalidMagic => "Not a movie file",
+                Self::UnexpectedEnd => "Unexpected end of movie data",
+                Self::InvalidData => "Invalid movie data",
+                Self::UnsupportedVersion(_) => unreachable!(),
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StartError {
+    SaveRamSizeMismatch,
+    SaveState(savestate::LoadError),
+}
+
+impl StdError for StartError {}
+
+impl Display for StartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SaveRamSizeMismatch => {
+                f.write_str("The movie's save RAM doesn't match the cart's save RAM size")
+            }
+            Self::SaveState(err) => write!(f, "Couldn't load the movie's save state: {}", err),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reset {
+    Soft,
+    Hard,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceKind {
+    None,
+    Joypad,
+    Multitap,
+    Mouse,
+    SuperScope,
+    Justifier,
+}
+
+/// The input given to the device connected to a port during a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortInput {
+    None,
+    Joypad(Keys),
+    Multitap([Keys; 4]),
+    Mouse {
+        /// The relative motion that hadn't been reported yet at the start of the frame.
+        motion: [i32; 2],
+        left_pressed: bool,
+        right_pressed: bool,
+    },
+    SuperScope {
+        aim: Option<[u16; 2]>,
+        trigger_pressed: bool,
+        cursor_pressed: bool,
+        turbo_pressed: bool,
+        pause_pressed: bool,
+    },
+    Justifier {
+        aim: Option<[u16; 2]>,
+        trigger_pressed: bool,
+        start_pressed: bool,
+    },
+}
+
+impl PortInput {
+    pub fn kind(&self) -> DeviceKind {
+        match self {
+            PortInput::None => DeviceKind::None,
+            PortInput::Joypad(_) => DeviceKind::Joypad,
+            PortInput::Multitap(_) => DeviceKind::Multitap,
+            PortInput::Mouse { .. } => DeviceKind::Mouse,
+            PortInput::SuperScope { .. } => DeviceKind::SuperScope,
+            PortInput::Justifier { .. } => DeviceKind::Justifier,
+        }
+    }
+
+    fn capture(device: &mut dyn Device) -> Self {
+        let device = device.as_any();
+        if let Some(joypad) = device.downcast_ref::<Joypad>() {
+            PortInput::Joypad(joypad.pressed_keys)
+        } else if let Some(multitap) = device.downcast_ref::<Multitap>() {
+            PortInput::Multitap([0, 1, 2, 3].map(|i| multitap.pads[i].pressed_keys))
+        } else if let Some(mouse) = device.downcast_ref::<Mouse>() {
+            PortInput::Mouse {
+                motion: mouse.motion(),
+                left_pressed: mouse.left_pressed,
+                right_pressed: mouse.right_pressed,
+            }
+        } else if let Some(super_scope) = device.downcast_ref::<SuperScope>() {
+            PortInput::SuperScope {
+                aim: super_scope.aim,
+                trigger_pressed: super_scope.trigger_pressed,
+                cursor_pressed: super_scope.cursor_pressed,
+                turbo_pressed: super_scope.turbo_pressed,
+                pause_pressed: super_scope.pause_pressed,
+            }
+        } else if let Some(justifier) = device.downcast_ref::<Justifier>() {
+            PortInput::Justifier {
+                aim: justifier.aim,
+                trigger_pressed: justifier.trigger_pressed,
+                start_pressed: justifier.start_pressed,
+            }
+        } else {
+            PortInput::None
+        }
+    }
+
+    fn apply(&self, device: &mut dyn Device) {
+        let device = device.as_any();
+        match *self {
+            PortInput::None => {}
+            PortInput::Joypad(keys) => {
+                if let Some(joypad) = device.downcast_mut::<Joypad>() {
+                    joypad.modify_keys(keys, !keys);
+                }
+            }
+            PortInput::Multitap(keys) => {
+                if let Some(multitap) = device.downcast_mut::<Multitap>() {
+                    for (pad, keys) in multitap.pads.iter_mut().zip(keys) {
+                        pad.modify_keys(keys, !keys);
+                    }
+                }
+            }
+            PortInput::Mouse {
+                motion,
+                left_pressed,
+                right_pressed,
+            } => {
+                if let Some(mouse) = device.downcast_mut::<Mouse>() {
+                    mouse.set_motion(motion);
+                    mouse.left_pressed = left_pressed;
+                    mouse.right_pressed = right_pressed;
+                }
+            }
+            PortInput::SuperScope {
+                aim,
+                trigger_pressed,
+                cursor_pressed,
+                turbo_pressed,
+                pause_pressed,
+            } => {
+                if let Some(super_scope) = device.downcast_mut::<SuperScope>() {
+                    super_scope.aim = aim;
+                    super_scope.trigger_pressed = trigger_pressed;
+                    super_scope.cursor_pressed = cursor_pressed;
+                    super_scope.turbo_pressed = turbo_pressed;
+                    super_scope.pause_pressed = pause_pressed;
+                }
+            }
+            PortInput::Justifier {
+                aim,
+                trigger_pressed,
+                start_pressed,
+            } => {
+                if let Some(justifier) = device.downcast_mut::<Justifier>() {
+                    justifier.aim = aim;
+                    justifier.trigger_pressed = trigger_pressed;
+                    justifier.start_pressed = start_pressed;
+                }
+            }
+        }
+    }
+
+    fn save(&self, output: &mut Vec<u8>) {
+        fn write_aim(output: &mut Vec<u8>, aim: Option<[u16; 2]>) {
+            match aim {
+                Some([x, y]) => {
+                    output.push(1);
+                    output.extend_from_slice(&x.to_le_bytes());
+                    output.extend_from_slice(&y.to_le_bytes());
+                }
+                None => output.push(0),
+            }
+        }
+
+        output.push(self.kind() as u8);
+        match *self {
+            PortInput::None => {}
+            PortInput::Joypad(keys) => output.extend_from_slice(&keys.bits().to_le_bytes()),
+            PortInput::Multitap(keys) => {
+                for keys in keys {
+                    output.extend_from_slice(&keys.bits().to_le_bytes());
+                }
+            }
+            PortInput::Mouse {
+                motion,
+                left_pressed,
+                right_pressed,
+            } => {
+                for delta in motion {
+                    output.extend_from_slice(&delta.to_le_bytes());
+                }
+                output.push(left_pressed as u8 | (right_pressed as u8) << 1);
+            }
+            PortInput::SuperScope {
+                aim,
+                trigger_pressed,
+                cursor_pressed,
+                turbo_pressed,
+                pause_pressed,
+            } => {
+                write_aim(output, aim);
+                output.push(
+                    trigger_pressed as u8
+                        | (cursor_pressed as u8) << 1
+                        | (turbo_pressed as u8) << 2
+                        | (pause_pressed as u8) << 3,
+                );
+            }
+            PortInput::Justifier {
+                aim,
+                trigger_pressed,
+                start_pressed,
+            } => {
+                write_aim(output, aim);
+                output.push(trigger_pressed as u8 | (start_pressed as u8) << 1);
+            }
+        }
+    }
+
+    fn load(reader: &mut Reader) -> Result<Self, LoadError> {
+        fn read_aim(reader: &mut Reader) -> Result<Option<[u16; 2]>, LoadError> {
+            Ok(match reader.u8()? {
+                0 => None,
+                1 => Some([reader.u16()?, reader.u16()?]),
+                _ => return Err(LoadError::InvalidData),
+            })
+        }
+
+        Ok(match reader.device_kind()? {
+            DeviceKind::None => PortInput::None,
+            DeviceKind::Joypad => PortInput::Joypad(Keys::from_bits_truncate(reader.u16()?)),
+            DeviceKind::Multitap => {
+                let mut keys = [Keys::empty(); 4];
+                for keys in &mut keys {
+                    *keys = Keys::from_bits_truncate(reader.u16()?);
+                }
+                PortInput::Multitap(keys)
+            }
+            DeviceKind::Mouse => {
+                let motion = [reader.u32()? as i32, reader.u32()? as i32];
+                let buttons = reader.u8()?;
+                PortInput::Mouse {
+                    motion,
+                    left_pressed: buttons & 1 != 0,
+                    right_pressed: buttons & 2 != 0,
+                }
+            }
+            DeviceKind::SuperScope => {
+                let aim = read_aim(reader)?;
+                let buttons = reader.u8()?;
+                PortInput::SuperScope {
+                    aim,
+                    trigger_pressed: buttons & 1 != 0,
+                    cursor_pressed: buttons & 2 != 0,
+                    turbo_pressed: buttons & 4 != 0,
+                    pause_pressed: buttons & 8 != 0,
+                }
+            }
+            DeviceKind::Justifier => {
+                let aim = read_aim(reader)?;
+                let buttons = reader.u8()?;
+                PortInput::Justifier {
+                    aim,
+                    trigger_pressed: buttons & 1 != 0,
+                    start_pressed: buttons & 2 != 0,
+                }
+            }
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum StartCondition {
+    /// A power-on with the given save RAM contents and, for carts that have one, RTC state.
+    PowerOn {
+        save_ram: Vec<u8>,
+        rtc: Option<Vec<u8>>,
+    },
+    SaveState(Vec<u8>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Frame {
+    /// The reset performed right before the frame, if any.
+    pub reset: Option<Reset>,
+    /// The input given to the device connected to each port during the frame.
+    pub ports: [PortInput; 2],
+    /// The hash of WRAM and of the framebuffer at the end of the frame, used to detect desyncs.
+    pub hash: u64,
+}
+
+/// A recording of the input given to the emulator on every frame, starting from either a power-on
+/// or a savestate.
+///
+/// The devices connected to the ports and the active cheats can't change during a movie, as they
+/// affect how the input is interpreted.
+#[derive(Clone, Debug)]
+pub struct Movie {
+    pub model: Model,
+    pub rom_sha256: [u8; 32],
+    pub devices: [DeviceKind; 2],
+    pub cheats: Vec<Code>,
+    pub start: StartCondition,
+    pub frames: Vec<Frame>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], LoadError> {
+        if self.data.len() < len {
+            return Err(LoadError::UnexpectedEnd);
+        }
+        let (result, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(result)
+    }
+
+    fn u8(&mut self) -> Result<u8, LoadError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, LoadError> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, LoadError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, LoadError> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn byte_vec(&mut self) -> Result<Vec<u8>, LoadError> {
+        let len = self.u32()? as usize;
+        Ok(self.bytes(len)?.to_vec())
+    }
+
+    fn device_kind(&mut self) -> Result<DeviceKind, LoadError> {
+        Ok(match self.u8()? {
+            0 => DeviceKind::None,
+            1 => DeviceKind::Joypad,
+            2 => DeviceKind::Multitap,
+            3 => DeviceKind::Mouse,
+            4 => DeviceKind::SuperScope,
+            5 => DeviceKind::Justifier,
+            _ => return Err(LoadError::InvalidData),
+        })
+    }
+}
+
+fn write_byte_vec(output: &mut Vec<u8>, bytes: &[u8]) {
+    output.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    output.extend_from_slice(bytes);
+}
+
+impl Movie {
+    pub fn save(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        result.extend_from_slice(&MAGIC);
+        result.extend_from_slice(&VERSION.to_le_bytes());
+        result.push(match self.model {
+            Model::Ntsc => 0,
+            Model::Pal => 1,
+        });
+        result.extend_from_slice(&self.rom_sha256);
+        for device in self.devices {
+            result.push(device as u8);
+        }
+        result.extend_from_slice(&(self.cheats.len() as u32).to_le_bytes());
+        for code in &self.cheats {
+            result.push(match code.kind {
+                cheats::Kind::GameGenie => 0,
+                cheats::Kind::ProActionReplay => 1,
+            });
+            result.extend_from_slice(&code.addr.to_le_bytes());
+            result.push(code.value);
+            match code.compare {
+                Some(compare) => result.extend_from_slice(&[1, compare]),
+                None => result.push(0),
+            }
+        }
+        match &self.start {
+            StartCondition::PowerOn { save_ram, rtc } => {
+                result.push(0);
+                write_byte_vec(&mut result, save_ram);
+                match rtc {
+                    Some(rtc) => {
+                        result.push(1);
+                        write_byte_vec(&mut result, rtc);
+                    }
+                    None => result.push(0),
+                }
+            }
+            StartCondition::SaveState(state) => {
+                result.push(1);
+                write_byte_vec(&mut result, state);
+            }
+        }
+        result.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for frame in &self.frames {
+            result.push(match frame.reset {
+                None => 0,
+                Some(Reset::Soft) => 1,
+                Some(Reset::Hard) => 2,
+            });
+            for input in &frame.ports {
+                input.save(&mut result);
+            }
+            result.extend_from_slice(&frame.hash.to_le_bytes());
+        }
+        result
+    }
+
+    pub fn load(data: &[u8]) -> Result<Self, LoadError> {
+        let mut reader = Reader { data };
+        if reader.bytes(4).map_err(|_| LoadError::InvalidMagic)? != MAGIC {
+            return Err(LoadError::InvalidMagic);
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(LoadError::UnsupportedVersion(version));
+        }
+        let model = match reader.u8()? {
+            0 => Model::Ntsc,
+            1 => Model::Pal,
+            _ => return Err(LoadError::InvalidData),
+        };
+        let rom_sha256 = reader.bytes(32)?.try_into().unwrap();
+        let devices = [reader.device_kind()?, reader.device_kind()?];
+        let cheat_count = reader.u32()? as usize;
+        let mut cheats = Vec::with_capacity(cheat_count.min(reader.data.len()));
+        for _ in 0..cheat_count {
+            let kind = match reader.u8()? {
+                0 => cheats::Kind::GameGenie,
+                1 => cheats::Kind::ProActionReplay,
+                _ => return Err(LoadError::InvalidData),
+            };
+            let addr = reader.u32()?;
+            let value = reader.u8()?;
+            let compare = match reader.u8()? {
+                0 => None,
+                1 => Some(reader.u8()?),
+                _ => return Err(LoadError::InvalidData),
+            };
+            cheats.push(Code {
+                kind,
+                addr,
+                value,
+                compare,
+            });
+        }
+        let start = match reader.u8()? {
+            0 => {
+                let save_ram = reader.byte_vec()?;
+                let rtc = match reader.u8()? {
+                    0 => None,
+                    1 => Some(reader.byte_vec()?),
+                    _ => return Err(LoadError::InvalidData),
+                };
+                StartCondition::PowerOn { save_ram, rtc }
+            }
+            1 => StartCondition::SaveState(reader.byte_vec()?),
+            _ => return Err(LoadError::InvalidData),
+        };
+        let frame_count = reader.u32()? as usize;
+        let mut frames = Vec::with_capacity(frame_count.min(reader.data.len()));
+        for _ in 0..frame_count {
+            let reset = match reader.u8()? {
+                0 => None,
+                1 => Some(Reset::Soft),
+                2 => Some(Reset::Hard),
+                _ => return Err(LoadError::InvalidData),
+            };
+            let ports = [PortInput::load(&mut reader)?, PortInput::load(&mut reader)?];
+            frames.push(Frame {
+                reset,
+                ports,
+                hash: reader.u64()?,
+            });
+        }
+        Ok(Movie {
+            model,
+            rom_sha256,
+            devices,
+            cheats,
+            start,
+            frames,
+        })
+    }
+}
+
+/// Hashes the contents of WRAM and of the framebuffer, which diverge quickly (and visibly, in the
+/// latter's case) once playback stops matching the recording.
+pub fn frame_hash(emu: &Emu) -> u64 {
+    // FNV-1a, applied to 32-bit words for the framebuffer
+    const PRIME: u64 = 0x100_0000_01B3;
+    let mut hash = 0xCBF2_9CE4_8422_2325_u64;
+    for &byte in emu.wram.contents.iter() {
+        hash = (hash ^ byte as u64).wrapping_mul(PRIME);
+    }
+    // Only the part of the framebuffer that was rendered to is hashed, as the rest could still
+    // contain stale data from before a savestate was loaded
+    let fb_width = emu.ppu.fb_width();
+    for line in emu
+        .ppu
+        .framebuffer
+        .0
+        .chunks(FB_WIDTH)
+        .take(emu.ppu.fb_height())
+    {
+        for &pixel in &line[..fb_width] {
+            hash = (hash ^ pixel as u64).wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
+
+fn capture_ports(emu: &mut Emu) -> [PortInput; 2] {
+    [0, 1].map(|port| PortInput::capture(&mut *emu.controllers.devices[port]))
+}
+
+pub struct Recorder {
+    movie: Movie,
+    pending_reset: Option<Reset>,
+}
+
+impl Recorder {
+    /// Starts recording a movie; if `from_power_on` is set, the emulator should have just been
+    /// powered on, and only its save RAM and RTC state are stored instead of a full savestate.
+    pub fn new(emu: &mut Emu, model: Model, rom_sha256: [u8; 32], from_power_on: bool) -> Self {
+        let devices = capture_ports(emu).map(|input| input.kind());
+        Recorder {
+            movie: Movie {
+                model,
+                rom_sha256,
+                devices,
+                cheats: emu.cheats.codes().to_vec(),
+                start: if from_power_on {
+                    StartCondition::PowerOn {
+                        save_ram: emu.cart.ram().to_vec(),
+                        rtc: emu.cart.rtc_data(RTC_UNIX_TIME),
+                    }
+                } else {
+                    StartCondition::SaveState(emu.save_state())
+                },
+                frames: Vec::new(),
+            },
+            pending_reset: None,
+        }
+    }
+
+    #[inline]
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    /// Records a reset, to be performed before the next frame on playback.
+    pub fn record_reset(&mut self, reset: Reset) {
+        // A hard reset supersedes any previous soft one
+        if self.pending_reset != Some(Reset::Hard) {
+            self.pending_reset = Some(reset);
+        }
+    }
+
+    /// Records the input for the frame that's about to be emulated.
+    pub fn start_frame(&mut self, emu: &mut Emu) {
+        let ports = capture_ports(emu);
+        self.movie.frames.push(Frame {
+            reset: self.pending_reset.take(),
+            ports,
+            hash: 0,
+        });
+    }
+
+    /// Records the resulting hash for the frame that was just emulated.
+    pub fn frame_finished(&mut self, emu: &Emu) {
+        if let Some(frame) = self.movie.frames.last_mut() {
+            frame.hash = frame_hash(emu);
+        }
+    }
+
+    pub fn finish(self) -> Movie {
+        self.movie
+    }
+}
+
+pub struct Playback {
+    movie: Movie,
+    pos: usize,
+    desync_frame: Option<usize>,
+}
+
+impl Playback {
+    /// Starts playing back a movie, bringing the emulator into its starting condition and enabling
+    /// its cheats. The movie's devices should already be connected and, if it starts from a
+    /// power-on, the emulator should have just been powered on.
+    pub fn new(movie: Movie, emu: &mut Emu) -> Result<Self, StartError> {
+        match &movie.start {
+            StartCondition::PowerOn { save_ram, rtc } => {
+                if save_ram.len() != emu.cart.ram().len() {
+                    return Err(StartError::SaveRamSizeMismatch);
+                }
+                emu.cart.modify_ram(|ram| ram.copy_from_slice(save_ram));
+                if let Some(rtc) = rtc {
+                    emu.cart.load_rtc_data(rtc, RTC_UNIX_TIME);
+                }
+            }
+            StartCondition::SaveState(state) => {
+                emu.load_state(state).map_err(StartError::SaveState)?;
+            }
+        }
+        emu.cheats.set_codes(movie.cheats.clone());
+        Ok(Playback {
+            movie,
+            pos: 0,
+            desync_frame: None,
+        })
+    }
+
+    #[inline]
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    #[inline]
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    #[inline]
+    pub fn finished(&self) -> bool {
+        self.pos >= self.movie.frames.len()
+    }
+
+    /// Returns the index of the first frame whose hash didn't match the recorded one, if any.
+    #[inline]
+    pub fn desync_frame(&self) -> Option<usize> {
+        self.desync_frame
+    }
+
+    /// Returns the reset that has to be performed before the next frame, if any.
+    pub fn next_reset(&self) -> Option<Reset> {
+        self.movie
+            .frames
+            .get(self.pos)
+            .and_then(|frame| frame.reset)
+    }
+
+    /// Gives the connected devices the input recorded for the next frame; this should be called
+    /// after performing its reset.
+    pub fn apply_input(&mut self, emu: &mut Emu) {
+        let frame = match self.movie.frames.get(self.pos) {
+            Some(frame) => frame,
+            None => return,
+        };
+        for (input, device) in frame.ports.iter().zip(&mut emu.controllers.devices) {
+            input.apply(&mut **device);
+        }
+    }
+
+    /// Checks the hash of the frame that was just emulated, returning its index if it's the first
+    /// one not to match the recording.
+    pub fn frame_finished(&mut self, emu: &Emu) -> Option<usize> {
+        let frame = self.movie.frames.get(self.pos)?;
+        let pos = self.pos;
+        self.pos += 1;
+        if self.desync_frame.is_none() && frame_hash(emu) != frame.hash {
+            self.desync_frame = Some(pos);
+            return Some(pos);
+        }
+        None
+    }
+}
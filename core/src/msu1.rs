@@ -0,0 +1,324 @@
+use crate::{
+    apu::dsp::Sample,
+    cpu::bus::AccessType,
+    savestate::{LoadError, Reader, Writer},
+};
+use std::io::{Read, Seek, SeekFrom};
+
+/// A seekable byte stream containing one of the MSU-1's files.
+pub trait Stream: Read + Seek {}
+
+impl<T: Read + Seek> Stream for T {}
+
+/// Provides the MSU-1's data file and audio tracks, either from the host's filesystem (where
+/// they're usually stored as `<rom>.msu` and `<rom>-<track>.pcm` respectively) or from memory.
+pub trait Files {
+    fn open_data(&mut self) -> Option<Box<dyn Stream>>;
+    fn open_track(&mut self, track: u16) -> Option<Box<dyn Stream>>;
+}
+
+const REVISION: u8 = 2;
+const IDENTIFIER: [u8; 6] = *b"S-MSU1";
+
+// Audio tracks start with the `MSU1` magic and a 32-bit loop point, followed by 16-bit stereo
+// samples at 44.1 kHz
+const TRACK_MAGIC: [u8; 4] = *b"MSU1";
+const TRACK_HEADER_LEN: u64 = 8;
+const TRACK_SAMPLE_RATE: u32 = 44_100;
+const DSP_SAMPLE_RATE: u32 = 32_000;
+
+struct Track {
+    stream: Box<dyn Stream>,
+    loop_offset: u64,
+}
+
+/// The state of the MSU-1, a cartridge expansion that streams data and CD-quality audio from
+/// files, mapped at $2000-$2007.
+///
+/// File accesses complete instantly, so the data and audio busy flags are never set.
+pub struct Msu1 {
+    files: Box<dyn Files>,
+    data: Option<Box<dyn Stream>>,
+    data_seek_offset: u32,
+    data_offset: u32,
+    data_latch: u8,
+
+    track: Option<Track>,
+    track_index_write: u16,
+    track_index: u16,
+    track_missing: bool,
+    track_offset: u64,
+    volume: u8,
+    playing: bool,
+    repeat: bool,
+
+    resample_counter: u32,
+    prev_sample: [Sample; 2],
+    cur_sample: [Sample; 2],
+}
+
+impl Msu1 {
+    pub fn new(mut files: Box<dyn Files>) -> Self {
+        let data = files.open_data();
+        let mut result = Msu1 {
+            files,
+            data,
+            data_seek_offset: 0,
+            data_offset: 0,
+            data_latch: 0,
+
+            track: None,
+            track_index_write: 0,
+            track_index: 0,
+            track_missing: false,
+            track_offset: 0,
+            volume: 0,
+            playing: false,
+            repeat: false,
+
+            resample_counter: 0,
+            prev_sample: [0; 2],
+            cur_sample: [0; 2],
+        };
+        result.reset();
+        result
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.data_seek_offset = 0;
+        self.seek_data(0);
+        self.track = None;
+        self.track_index_write = 0;
+        self.track_index = 0;
+        self.track_missing = false;
+        self.track_offset = 0;
+        self.volume = 0;
+        self.playing = false;
+        self.repeat = false;
+        self.resample_counter = 0;
+        self.prev_sample = [0; 2];
+        self.cur_sample = [0; 2];
+    }
+
+    #[inline]
+    pub fn data_offset(&self) -> u32 {
+        self.data_offset
+    }
+
+    #[inline]
+    pub fn track_index(&self) -> u16 {
+        self.track_index
+    }
+
+    #[inline]
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    #[inline]
+    pub fn playing(&self) -> bool {
+        self.playing
+    }
+
+    #[inline]
+    pub fn repeat(&self) -> bool {
+        self.repeat
+    }
+
+    pub fn status(&self) -> u8 {
+        (self.repeat as u8) << 5
+            | (self.playing as u8) << 4
+            | (self.track_missing as u8) << 3
+            | REVISION
+    }
+
+    /// Moves the data read position, fetching the byte at the new one.
+    fn seek_data(&mut self, offset: u32) {
+        self.data_offset = offset;
+        self.data_latch = 0;
+        if let Some(data) = &mut self.data {
+            let mut byte = [0];
+            if data.seek(SeekFrom::Start(offset as u64)).is_ok()
+                && data.read_exact(&mut byte).is_ok()
+            {
+                self.data_latch = byte[0];
+            }
+        }
+    }
+
+    fn read_data<A: AccessType>(&mut self) -> u8 {
+        let result = self.data_latch;
+        if A::SIDE_EFFECTS {
+            self.data_offset = self.data_offset.wrapping_add(1);
+            let mut byte = [0];
+            self.data_latch = match &mut self.data {
+                Some(data) => match data.read_exact(&mut byte) {
+                    Ok(()) => byte[0],
+                    Err(_) => 0,
+                },
+                None => 0,
+            };
+        }
+        result
+    }
+
+    /// Opens the given audio track and moves to the given byte offset inside it, returning `None`
+    /// if it's missing or invalid.
+    fn open_track(&mut self, index: u16, offset: u64) -> Option<Track> {
+        let mut stream = self.files.open_track(index)?;
+        let mut header = [0; TRACK_HEADER_LEN as usize];
+        stream.read_exact(&mut header).ok()?;
+        if header[..4] != TRACK_MAGIC {
+            return None;
+        }
+        let loop_point = u32::from_le_bytes(header[4..].try_into().unwrap());
+        stream.seek(SeekFrom::Start(offset)).ok()?;
+        Some(Track {
+            stream,
+            loop_offset: TRACK_HEADER_LEN + ((loop_point as u64) << 2),
+        })
+    }
+
+    fn load_track(&mut self) {
+        self.track_index = self.track_index_write;
+        self.track = self.open_track(self.track_index, TRACK_HEADER_LEN);
+        self.track_missing = self.track.is_none();
+        self.track_offset = TRACK_HEADER_LEN;
+        self.playing = false;
+        self.repeat = false;
+    }
+
+    pub(crate) fn read<A: AccessType>(&mut self, addr: u16) -> u8 {
+        match addr & 7 {
+            0 => self.status(),
+            1 => self.read_data::<A>(),
+            i => IDENTIFIER[i as usize - 2],
+        }
+    }
+
+    pub(crate) fn write(&mut self, addr: u16, value: u8) {
+        match addr & 7 {
+            i @ 0..=3 => {
+                let shift = i << 3;
+                self.data_seek_offset =
+                    (self.data_seek_offset & !(0xFF << shift)) | (value as u32) << shift;
+                if i == 3 {
+                    self.seek_data(self.data_seek_offset);
+                }
+            }
+            4 => self.track_index_write = (self.track_index_write & 0xFF00) | value as u16,
+            5 => {
+                self.track_index_write = (self.track_index_write & 0xFF) | (value as u16) << 8;
+                self.load_track();
+            }
+            6 => self.volume = value,
+            _ => {
+                if self.track.is_some() {
+                    self.playing = value & 1 != 0;
+                    self.repeat = value & 2 != 0;
+                }
+            }
+        }
+    }
+
+    /// Reads the next 44.1 kHz sample of the current track, looping or stopping playback at its
+    /// end.
+    fn read_track_sample(&mut self) -> [Sample; 2] {
+        let track = match &mut self.track {
+            Some(track) if self.playing => track,
+            _ => return [0; 2],
+        };
+        let mut bytes = [0; 4];
+        if track.stream.read_exact(&mut bytes).is_err() {
+            let looped = self.repeat
+                && track
+                    .stream
+                    .seek(SeekFrom::Start(track.loop_offset))
+                    .is_ok()
+                && track.stream.read_exact(&mut bytes).is_ok();
+            if !looped {
+                self.playing = false;
+                return [0; 2];
+            }
+            self.track_offset = track.loop_offset;
+        }
+        self.track_offset += 4;
+        [
+            i16::from_le_bytes([bytes[0], bytes[1]]),
+            i16::from_le_bytes([bytes[2], bytes[3]]),
+        ]
+    }
+
+    /// Returns the audio output for the current DSP sample, to be mixed with the DSP's own.
+    pub(crate) fn output_sample(&mut self) -> [Sample; 2] {
+        // Track samples are consumed at 44.1 kHz, interpolating linearly between the two most
+        // recent ones depending on how far into the current one the DSP sample falls
+        self.resample_counter += TRACK_SAMPLE_RATE;
+        while self.resample_counter >= DSP_SAMPLE_RATE {
+            self.resample_counter -= DSP_SAMPLE_RATE;
+            self.prev_sample = self.cur_sample;
+            self.cur_sample = self.read_track_sample();
+        }
+        let mut result = [0; 2];
+        for (i, result) in result.iter_mut().enumerate() {
+            let prev = self.prev_sample[i] as i32;
+            let cur = self.cur_sample[i] as i32;
+            let sample =
+                prev + (cur - prev) * self.resample_counter as i32 / DSP_SAMPLE_RATE as i32;
+            *result = (sample * self.volume as i32 / 0xFF) as Sample;
+        }
+        result
+    }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.data_seek_offset);
+        writer.write(&self.data_offset);
+        writer.write(&self.track.is_some());
+        writer.write(&self.track_index_write);
+        writer.write(&self.track_index);
+        writer.write(&self.track_missing);
+        writer.write(&self.track_offset);
+        writer.write(&self.volume);
+        writer.write(&self.playing);
+        writer.write(&self.repeat);
+        writer.write(&self.resample_counter);
+        writer.write(&self.prev_sample);
+        writer.write(&self.cur_sample);
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.data_seek_offset = reader.read()?;
+        let data_offset = reader.read()?;
+        let track_loaded = reader.read::<bool>()?;
+        self.track_index_write = reader.read()?;
+        self.track_index = reader.read()?;
+        self.track_missing = reader.read()?;
+        self.track_offset = reader.read()?;
+        self.volume = reader.read()?;
+        self.playing = reader.read()?;
+        self.repeat = reader.read()?;
+        self.resample_counter = reader.read()?;
+        self.prev_sample = reader.read()?;
+        self.cur_sample = reader.read()?;
+        if self.resample_counter >= DSP_SAMPLE_RATE
+            || (track_loaded && self.track_offset < TRACK_HEADER_LEN)
+        {
+            return Err(LoadError::InvalidData);
+        }
+
+        // Files are reopened at the saved positions; if a track can't be found anymore, it's
+        // treated as missing
+        self.seek_data(data_offset);
+        self.track = if track_loaded {
+            self.open_track(self.track_index, self.track_offset)
+        } else {
+            None
+        };
+        if track_loaded && self.track.is_none() {
+            self.track_missing = true;
+            self.playing = false;
+            self.repeat = false;
+        }
+        Ok(())
+    }
+}
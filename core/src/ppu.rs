@@ -17,6 +17,7 @@ pub use windows_math::*;
 use crate::{
     cpu::{bus::AccessType, dma, Irqs},
     emu::Emu,
+    savestate::{LoadError, Reader, Writer},
     schedule::{self, event_slots, Schedule, Timestamp},
     utils::{bitfield_debug, zeroed_box, Zero},
     Model,
@@ -42,6 +43,9 @@ const SCANLINE_CYCLES: u16 = 1364;
 const SCANLINES_NTSC: u16 = 262;
 const SCANLINES_PAL: u16 = 312;
 
+// H counter value at which the first visible pixel of a scanline is output
+const BEAM_TARGET_H_OFFSET: u16 = 22;
+
 #[repr(C, align(64))]
 #[derive(Clone)]
 pub struct Framebuffer(pub [u32; FB_WIDTH * FB_HEIGHT]);
@@ -120,6 +124,7 @@ pub enum Event {
     RequestVBlankNmi,
     ReloadOamAddr,
     EndScanline,
+    ExternalLatch,
 }
 
 #[repr(C, align(32))]
@@ -382,6 +387,24 @@ impl Ppu {
                     time,
                     &mut emu.schedule,
                 );
+                // Light guns release their I/O line at the end of the scanline the beam was
+                // detected on
+                emu.controllers.set_beam_detected(false);
+                if let Some([x, y]) = emu.controllers.beam_target() {
+                    if x < VIEW_WIDTH as u16
+                        && y < emu.ppu.view_height as u16
+                        && y + 1 == new_v_counter
+                    {
+                        emu.schedule.set_event(
+                            event_slots::PPU_EXTERNAL_LATCH,
+                            schedule::Event::Ppu(Event::ExternalLatch),
+                        );
+                        emu.schedule.schedule_event(
+                            event_slots::PPU_EXTERNAL_LATCH,
+                            time + ((x + BEAM_TARGET_H_OFFSET) * DOT_CYCLES) as Timestamp,
+                        );
+                    }
+                }
                 if new_v_counter == emu.ppu.counters.v_display_end() {
                     emu.ppu.hv_status.set_vblank(true);
                     emu.ppu.frame_finished = true;
@@ -435,6 +458,16 @@ impl Ppu {
                     emu.ppu.oam.reload_cur_byte_addr();
                 }
             }
+
+            // H=beam target X + 22, V=beam target Y + 1
+            Event::ExternalLatch => {
+                emu.controllers.set_beam_detected(true);
+                // The I/O line can only be pulled low by the device if WRIO isn't already
+                // driving it low
+                if emu.controllers.wrio() & 0x80 != 0 {
+                    emu.ppu.latch_hv_counters(time);
+                }
+            }
         }
     }
 
@@ -487,6 +520,10 @@ impl Ppu {
         let result = self.status78.0 | (self.ppu2_mdr & 0x20);
         if A::SIDE_EFFECTS {
             self.ppu2_mdr = result;
+            // Reading STAT78 acknowledges the latch and resets the OPHCT/OPVCT high byte
+            // flip-flops
+            self.status78.set_external_latch_flag(false);
+            self.latched_counters.reset_read_high();
         }
         Status78(result)
     }
@@ -583,4 +620,145 @@ impl Ppu {
             self.status78.set_interlace_field(false);
         }
     }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.frame_finished);
+        for pixel in self.framebuffer.0.iter() {
+            writer.write(pixel);
+        }
+
+        writer.write(&(self.fb_height as u32));
+        writer.write(&(self.view_height as u32));
+        writer.write(&self.prev_line_fb_x_shift);
+        writer.write(&self.drawing_fb_x_shift);
+        writer.write(&self.fb_x_shift);
+
+        writer.write(&self.ppu1_mdr);
+        writer.write(&self.ppu2_mdr);
+
+        self.vram.save_state(writer);
+        self.oam.save_state(writer);
+        self.palette.save_state(writer);
+
+        writer.write(&self.status77.0);
+        writer.write(&self.status78.0);
+        writer.write(&self.hv_status.0);
+
+        self.counters.save_state(writer);
+        self.latched_counters.save_state(writer);
+
+        writer.write(&self.vblank_nmi_enabled);
+        writer.write(&self.nmi_flag.0);
+
+        writer.write(&self.display_control_0.0);
+        writer.write(&self.master_brightness);
+        writer.write(&self.display_control_1.0);
+        writer.write(&self.enabled_main_screen_layers);
+        writer.write(&self.enabled_sub_screen_layers);
+
+        self.mode7.save_state(writer);
+
+        writer.write(&self.color_math_control_a.0);
+        writer.write(&self.color_math_control_b.0);
+        writer.write(&self.sub_backdrop_color);
+
+        writer.write(&self.window_ranges);
+        for areas in &self.win12_areas {
+            writer.write(&areas.0);
+        }
+        for masks in &self.win12_masks {
+            writer.write(&masks.0);
+        }
+        writer.write(&self.win_disabled_layer_masks);
+
+        writer.write(&self.mosaic_control.0);
+        writer.write(&self.mosaic_remaining_lines);
+
+        for bg in &self.bgs {
+            bg.save_state(writer);
+        }
+        writer.write(&self.bg_char_control_12.0);
+        writer.write(&self.bg_char_control_34.0);
+        writer.write(&self.bg_scroll_prev_1);
+        writer.write(&self.bg_scroll_prev_2);
+
+        writer.write(&self.bg_mode_control.0);
+
+        writer.write(&self.obj_control.0);
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.frame_finished = reader.read()?;
+        for pixel in self.framebuffer.0.iter_mut() {
+            *pixel = reader.read()?;
+        }
+
+        self.fb_height = reader.read::<u32>()? as usize;
+        self.view_height = reader.read::<u32>()? as usize;
+        if self.fb_height > FB_HEIGHT
+            || !matches!(self.view_height, VIEW_HEIGHT_NTSC | VIEW_HEIGHT_PAL)
+        {
+            return Err(LoadError::InvalidData);
+        }
+        self.prev_line_fb_x_shift = reader.read()?;
+        self.drawing_fb_x_shift = reader.read()?;
+        self.fb_x_shift = reader.read()?;
+
+        self.ppu1_mdr = reader.read()?;
+        self.ppu2_mdr = reader.read()?;
+
+        self.vram.load_state(reader)?;
+        self.oam.load_state(reader)?;
+        self.palette.load_state(reader)?;
+
+        self.status77 = Status77(reader.read()?);
+        self.status78 = Status78(reader.read()?);
+        self.hv_status = HvStatus(reader.read()?);
+
+        self.counters.load_state(reader)?;
+        self.latched_counters.load_state(reader)?;
+
+        self.vblank_nmi_enabled = reader.read()?;
+        self.nmi_flag = NmiFlag(reader.read()?);
+
+        // The setters for these registers have side effects on other state, so they can't be
+        // used here
+        self.display_control_0 = DisplayControl0(reader.read()?);
+        self.master_brightness = reader.read()?;
+        self.display_control_1 = DisplayControl1(reader.read()?);
+        self.enabled_main_screen_layers = reader.read()?;
+        self.enabled_sub_screen_layers = reader.read()?;
+
+        self.mode7.load_state(reader)?;
+
+        self.set_color_math_control_a(ColorMathControlA(reader.read()?));
+        self.set_color_math_control_b(ColorMathControlB(reader.read()?));
+        self.sub_backdrop_color = reader.read()?;
+
+        self.window_ranges = reader.read()?;
+        self.set_win12_areas_bg_12(LayerWin12Areas(reader.read()?));
+        self.set_win12_areas_bg_34(LayerWin12Areas(reader.read()?));
+        self.set_win12_areas_obj_math(LayerWin12Areas(reader.read()?));
+        self.set_win12_masks_bgs(LayerWin12Masks(reader.read()?));
+        self.set_win12_masks_obj_math(LayerWin12Masks(reader.read()?));
+        self.win_disabled_layer_masks = reader.read()?;
+
+        self.set_mosaic_control(MosaicControl(reader.read()?));
+        self.mosaic_remaining_lines = reader.read()?;
+
+        for bg in &mut self.bgs {
+            bg.load_state(reader)?;
+        }
+        self.set_bg_char_control_12(BgCharControl(reader.read()?));
+        self.set_bg_char_control_34(BgCharControl(reader.read()?));
+        self.bg_scroll_prev_1 = reader.read()?;
+        self.bg_scroll_prev_2 = reader.read()?;
+
+        self.bg_mode_control = BgModeControl(reader.read()?);
+        self.bg_mode = BgMode::new(self.bg_mode_control.bg_mode());
+        self.bg_tile_size_mask = self.bg_mode_control.bg_tile_size_mask();
+
+        self.set_obj_control(ObjControl(reader.read()?));
+        Ok(())
+    }
 }
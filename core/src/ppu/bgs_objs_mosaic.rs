@@ -1,5 +1,8 @@
 use super::Ppu;
-use crate::utils::bitfield_debug;
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    utils::bitfield_debug,
+};
 
 mod bounded {
     use crate::utils::bounded_int;
@@ -102,6 +105,19 @@ impl Bg {
     pub fn y_scroll(&self) -> u16 {
         self.y_scroll
     }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.screen_control.0);
+        writer.write(&self.x_scroll);
+        writer.write(&self.y_scroll);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.set_screen_control(BgScreenControl(reader.read()?));
+        self.x_scroll = reader.read()?;
+        self.y_scroll = reader.read()?;
+        Ok(())
+    }
 }
 
 impl Ppu {
@@ -1,6 +1,7 @@
 use super::{SCANLINES_NTSC, SCANLINES_PAL, SCANLINE_CYCLES, VIEW_HEIGHT_NTSC, VIEW_HEIGHT_PAL};
 use crate::{
     cpu::{bus::AccessType, Irqs},
+    savestate::{LoadError, Reader, Writer},
     schedule::{event_slots, Event, Schedule, Timestamp},
     utils::bitfield_debug,
     Model,
@@ -230,4 +231,44 @@ impl Counters {
         self.v_counter_last_change_time = time;
         self.update_hv_irq(time, schedule);
     }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.v_counter_last_change_time);
+        writer.write(&self.v_counter);
+        writer.write(&self.v_display_end);
+        writer.write(&self.v_end);
+        writer.write(&self.h_irq_end_cycles);
+        writer.write(&self.h_end_cycles);
+        writer.write(&self.v_timer_value);
+        writer.write(&self.h_timer_value);
+        writer.write(&self.scheduled_hv_irq_time);
+        writer.write(&match self.hv_irq_mode {
+            HvIrqMode::None => 0_u8,
+            HvIrqMode::VMatch => 1,
+            HvIrqMode::HMatch => 2,
+            HvIrqMode::VMatchHMatch => 3,
+        });
+        writer.write(&self.hv_timer_irq_flag.0);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.v_counter_last_change_time = reader.read()?;
+        self.v_counter = reader.read()?;
+        self.v_display_end = reader.read()?;
+        self.v_end = reader.read()?;
+        self.h_irq_end_cycles = reader.read()?;
+        self.h_end_cycles = reader.read()?;
+        self.v_timer_value = reader.read()?;
+        self.h_timer_value = reader.read()?;
+        self.scheduled_hv_irq_time = reader.read()?;
+        self.hv_irq_mode = match reader.read::<u8>()? {
+            0 => HvIrqMode::None,
+            1 => HvIrqMode::VMatch,
+            2 => HvIrqMode::HMatch,
+            3 => HvIrqMode::VMatchHMatch,
+            _ => return Err(LoadError::InvalidData),
+        };
+        self.hv_timer_irq_flag = HvTimerIrqFlag(reader.read()?);
+        Ok(())
+    }
 }
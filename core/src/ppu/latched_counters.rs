@@ -1,5 +1,9 @@
 use super::Ppu;
-use crate::{cpu::bus::AccessType, schedule::Timestamp};
+use crate::{
+    cpu::bus::AccessType,
+    savestate::{LoadError, Reader, Writer},
+    schedule::Timestamp,
+};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct LatchedCounters {
@@ -51,6 +55,11 @@ impl LatchedCounters {
         }
     }
 
+    #[inline]
+    pub(super) fn reset_read_high(&mut self) {
+        self.read_high = 0;
+    }
+
     #[inline]
     pub fn h_counter_high_read(&self) -> bool {
         self.read_high & 1 != 0
@@ -60,6 +69,19 @@ impl LatchedCounters {
     pub fn v_counter_high_read(&self) -> bool {
         self.read_high & 2 != 0
     }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.h_counter);
+        writer.write(&self.v_counter);
+        writer.write(&self.read_high);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.h_counter = reader.read()?;
+        self.v_counter = reader.read()?;
+        self.read_high = reader.read()?;
+        Ok(())
+    }
 }
 
 impl Ppu {
@@ -68,6 +90,7 @@ impl Ppu {
         self.latched_counters.h_counter = self.counters.h_dot(time);
         self.latched_counters.v_counter = self.counters.v_counter();
         self.latched_counters.read_high = 0;
+        self.status78.set_external_latch_flag(true);
     }
 
     #[inline]
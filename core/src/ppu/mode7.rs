@@ -1,5 +1,9 @@
 use super::Ppu;
-use crate::{schedule::Timestamp, utils::bitfield_debug};
+use crate::{
+    savestate::{LoadError, Reader, Writer},
+    schedule::Timestamp,
+    utils::bitfield_debug,
+};
 
 bitfield_debug! {
     #[derive(Clone, Copy, PartialEq, Eq)]
@@ -34,6 +38,23 @@ impl Mode7 {
         self.control
     }
 
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.old);
+        writer.write(&self.control.0);
+        writer.write(&self.params);
+        writer.write(&self.scroll);
+        writer.write(&self.center);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.old = reader.read()?;
+        self.control = Mode7Control(reader.read()?);
+        self.params = reader.read()?;
+        self.scroll = reader.read()?;
+        self.center = reader.read()?;
+        Ok(())
+    }
+
     pub(super) fn origin(&self) -> [i16; 2] {
         fn mask_to_1c00(value: i16) -> i16 {
             if value < 0 {
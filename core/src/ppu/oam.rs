@@ -1,5 +1,9 @@
 use super::Ppu;
-use crate::{cpu::bus::AccessType, utils::bitfield_debug};
+use crate::{
+    cpu::bus::AccessType,
+    savestate::{LoadError, Reader, Writer},
+    utils::bitfield_debug,
+};
 
 bitfield_debug! {
     #[derive(Clone, Copy, PartialEq, Eq)]
@@ -97,6 +101,41 @@ impl Oam {
     pub fn next_first_sprite(&self) -> u8 {
         self.next_first_sprite
     }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        for obj in self.contents.iter() {
+            writer.write(&obj.x_coord);
+            writer.write(&obj.y_coord);
+            writer.write(&obj.tile_number);
+            writer.write(&obj.pal_number);
+            writer.write(&obj.bg_prio);
+            writer.write(&obj.attrs.0);
+        }
+        writer.write(&self.cur_byte_addr);
+        writer.write(&self.reload_addr);
+        writer.write(&self.write_latch);
+        writer.write(&self.start_prio_at_cur_sprite);
+        writer.write(&self.next_first_sprite);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        for obj in self.contents.iter_mut() {
+            *obj = Obj {
+                x_coord: reader.read()?,
+                y_coord: reader.read()?,
+                tile_number: reader.read()?,
+                pal_number: reader.read()?,
+                bg_prio: reader.read()?,
+                attrs: Attrs(reader.read()?),
+            };
+        }
+        self.cur_byte_addr = reader.read()?;
+        self.reload_addr = reader.read()?;
+        self.write_latch = reader.read()?;
+        self.start_prio_at_cur_sprite = reader.read()?;
+        self.next_first_sprite = reader.read()?;
+        Ok(())
+    }
 }
 
 impl Ppu {
@@ -1,6 +1,7 @@
 use super::Ppu;
 use crate::{
     cpu::bus::AccessType,
+    savestate::{LoadError, Reader, Writer},
     utils::{bitfield_debug, zeroed_box},
 };
 
@@ -49,6 +50,21 @@ impl Palette {
     pub fn set_word_addr(&mut self, value: u8) {
         self.cur_addr = value;
     }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&*self.contents);
+        writer.write(&self.write_latch);
+        writer.write(&self.second_access);
+        writer.write(&self.cur_addr);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        *self.contents = reader.read()?;
+        self.write_latch = reader.read()?;
+        self.second_access = reader.read()?;
+        self.cur_addr = reader.read()?;
+        Ok(())
+    }
 }
 
 impl Ppu {
@@ -1,5 +1,6 @@
 use super::{oam, BgIndex, Ppu, FB_WIDTH, VIEW_WIDTH};
 use crate::utils::bitfield_debug;
+use core::ops::Range;
 
 bitfield_debug! {
     #[derive(Clone, Copy, PartialEq, Eq)]
@@ -334,9 +335,8 @@ impl Ppu {
                     self,
                     BgIndex::new(2),
                 ),
-                2 | 4 | 6 => {
-                    // todo!("draw offset-per-tile");
-                }
+                // In modes 2, 4 and 6, BG3 isn't displayed and its tilemap is used to hold
+                // offset-per-tile data for BG1 and BG2 instead (see `draw_bg_text`)
                 _ => {}
             }
         }
@@ -545,11 +545,101 @@ impl Ppu {
         );
     }
 
+    fn bg3_offset_per_tile_entry(&self, x: u16, y: u16) -> u16 {
+        let bg3 = &self.bgs[2];
+        let mut addr = bg3
+            .screen_base_words
+            .wrapping_add((y & 0x1F) << 5 | (x & 0x1F));
+        if bg3.screen_size & 1 != 0 {
+            addr = addr.wrapping_add((x & 0x20) << 5);
+        }
+        if bg3.screen_size & 2 != 0 {
+            addr = addr.wrapping_add((y & 0x20) << (5 + (bg3.screen_size & 1)));
+        }
+        self.vram.contents.read_le::<u16>((addr << 1) as usize)
+    }
+
     fn draw_bg_text<const COLOR_SIZE: u16, const X_SHIFT: u8, const Y_SHIFT: u8>(
         &mut self,
         bg_index: BgIndex,
     ) {
         let bg = &self.bgs[bg_index.get() as usize];
+        let (x_scroll, y_scroll) = (bg.x_scroll, bg.y_scroll);
+        let mut mosaic_state = (1, ScreenPixel(0));
+
+        let bg_mode = self.bg_mode.get();
+        if !matches!(bg_mode, 2 | 4 | 6) {
+            self.draw_bg_text_span::<COLOR_SIZE, X_SHIFT, Y_SHIFT>(
+                bg_index,
+                x_scroll,
+                y_scroll,
+                0..VIEW_WIDTH << self.fb_x_shift as u8,
+                &mut mosaic_state,
+            );
+            return;
+        }
+
+        // Offset-per-tile: BG3's tilemap contains per-column scroll values for BG1 and BG2. The
+        // first (possibly partial) column on the screen is never affected; the others each use
+        // the entry in BG3's scrolled tilemap row (and the row below for vertical offsets, except
+        // in mode 4, where a single entry selects either direction using bit 15). Bits 13 and 14
+        // select whether the entry applies to BG1 and BG2 respectively.
+        let fine_x_scroll = x_scroll & 7;
+        let opt_apply_mask = 0x2000 << bg_index.get();
+        let opt_base_x = self.bgs[2].x_scroll >> 3;
+        let opt_y = self.bgs[2].y_scroll >> 3;
+
+        let mut column_start_x = 0;
+        for column_i in 0..=VIEW_WIDTH >> 3 {
+            let column_end_x = (((column_i + 1) << 3) - fine_x_scroll as usize).min(VIEW_WIDTH);
+            if column_start_x == column_end_x {
+                break;
+            }
+
+            let (mut column_x_scroll, mut column_y_scroll) = (x_scroll, y_scroll);
+            if column_i != 0 {
+                let opt_x = opt_base_x.wrapping_add(column_i as u16 - 1);
+                let h_entry = self.bg3_offset_per_tile_entry(opt_x, opt_y);
+                if bg_mode == 4 {
+                    if h_entry & opt_apply_mask != 0 {
+                        if h_entry & 1 << 15 != 0 {
+                            column_y_scroll = h_entry & 0x3FF;
+                        } else {
+                            column_x_scroll = (h_entry & 0x3F8) | fine_x_scroll;
+                        }
+                    }
+                } else {
+                    let v_entry = self.bg3_offset_per_tile_entry(opt_x, opt_y.wrapping_add(1));
+                    if h_entry & opt_apply_mask != 0 {
+                        column_x_scroll = (h_entry & 0x3F8) | fine_x_scroll;
+                    }
+                    if v_entry & opt_apply_mask != 0 {
+                        column_y_scroll = v_entry & 0x3FF;
+                    }
+                }
+            }
+
+            self.draw_bg_text_span::<COLOR_SIZE, X_SHIFT, Y_SHIFT>(
+                bg_index,
+                column_x_scroll,
+                column_y_scroll,
+                column_start_x << self.fb_x_shift as u8..column_end_x << self.fb_x_shift as u8,
+                &mut mosaic_state,
+            );
+            column_start_x = column_end_x;
+        }
+    }
+
+    #[inline(always)]
+    fn draw_bg_text_span<const COLOR_SIZE: u16, const X_SHIFT: u8, const Y_SHIFT: u8>(
+        &mut self,
+        bg_index: BgIndex,
+        x_scroll: u16,
+        y_scroll: u16,
+        line_x_range: Range<usize>,
+        (mosaic_counter, pixel): &mut (usize, ScreenPixel),
+    ) {
+        let bg = &self.bgs[bg_index.get() as usize];
 
         let mosaic_size = if self.bg_mosaic_mask & 1 << bg_index.get() != 0 {
             self.mosaic_remaining_lines.1 as usize
@@ -562,11 +652,11 @@ impl Ppu {
             y = y << self.display_control_1.interlacing() as u8
                 | self.status78.interlace_field() as u16;
         }
-        y = y.wrapping_add(bg.y_scroll);
+        y = y.wrapping_add(y_scroll);
         y -= y % mosaic_size as u16;
-        let start_x = bg.x_scroll << self.fb_x_shift as u8;
+        let start_x = (x_scroll << self.fb_x_shift as u8).wrapping_add(line_x_range.start as u16);
+        let span_width = line_x_range.end - line_x_range.start;
 
-        let fb_width = VIEW_WIDTH << self.fb_x_shift as u8;
         let tile_size_x_shift = 3 + X_SHIFT;
         let tile_size_y_shift = 3 + Y_SHIFT;
 
@@ -599,23 +689,21 @@ impl Ppu {
             )
         };
 
+        let tile_size_x_mask = (1 << tile_size_x_shift) - 1;
+        let start_x_off_in_tile = start_x as usize & tile_size_x_mask;
+
         let mut tile_data = [0; 65];
-        let mut tiles_len = 0;
+        let tiles_len = ((start_x_off_in_tile + span_width - 1) >> tile_size_x_shift) + 1;
         {
             let fetch_x_mask = 0x1F | wide_x_mask;
             let mut fetch_x = start_x >> tile_size_x_shift & fetch_x_mask;
-            let end_fetch_x = (start_x + (fb_width - 1) as u16) >> tile_size_x_shift & fetch_x_mask;
-            loop {
-                tile_data[tiles_len] = self.vram.contents.read_le::<u16>(
+            for tile in &mut tile_data[..tiles_len] {
+                *tile = self.vram.contents.read_le::<u16>(
                     (line_screen_base_words
                         .wrapping_add(fetch_x & 0x1F)
                         .wrapping_add((fetch_x & wide_x_mask) << 5)
                         << 1) as usize,
                 );
-                tiles_len += 1;
-                if tiles_len != 1 && fetch_x == end_fetch_x {
-                    break;
-                }
                 fetch_x = (fetch_x + 1) & fetch_x_mask;
             }
         }
@@ -624,20 +712,16 @@ impl Ppu {
         let y_off_in_tile_row = y & tile_y_mask;
 
         let common_pixel_attrs = ScreenPixel(0).with_color_math_mask(1 << bg_index.get());
-        let tile_size_x_mask = (1 << tile_size_x_shift) - 1;
-        let start_x_off_in_tile = start_x as usize & tile_size_x_mask;
 
         let mut first = true;
-        let mut mosaic_counter = 1;
-        let mut pixel = ScreenPixel(0);
         let mut tile_pixels = [ScreenPixel(0); 16];
-        let mut start_tile_x_half = (start_x as usize & tile_size_x_mask) >> 3 & 1;
+        let mut start_tile_x_half = start_x_off_in_tile >> 3 & 1;
 
-        for (line_x, line_pixel) in self.bg_line_pixels[bg_index.get() as usize].0[..fb_width]
+        for (span_x, line_pixel) in self.bg_line_pixels[bg_index.get() as usize].0[line_x_range]
             .iter_mut()
             .enumerate()
         {
-            let tiles_x = start_x_off_in_tile + line_x;
+            let tiles_x = start_x_off_in_tile + span_x;
             if tiles_x & tile_size_x_mask == 0 || first {
                 first = false;
 
@@ -666,7 +750,9 @@ impl Ppu {
                     0
                 };
 
-                let end_tile_x_half = if tile_size_x_shift == 4 && line_x + 8 < fb_width {
+                let end_tile_x_half = if tile_size_x_shift == 4
+                    && (tiles_x & tile_size_x_mask) + (span_width - span_x) > 8
+                {
                     2
                 } else {
                     1
@@ -701,12 +787,12 @@ impl Ppu {
                 }
                 start_tile_x_half = 0;
             }
-            mosaic_counter -= 1;
-            if mosaic_counter == 0 {
-                mosaic_counter = mosaic_size;
-                pixel = tile_pixels[tiles_x & tile_size_x_mask];
+            *mosaic_counter -= 1;
+            if *mosaic_counter == 0 {
+                *mosaic_counter = mosaic_size;
+                *pixel = tile_pixels[tiles_x & tile_size_x_mask];
             }
-            *line_pixel = pixel;
+            *line_pixel = *pixel;
         }
     }
 
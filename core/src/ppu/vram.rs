@@ -1,6 +1,7 @@
 use super::Ppu;
 use crate::{
     cpu::bus::AccessType,
+    savestate::{LoadError, Reader, Writer},
     utils::{bitfield_debug, zeroed_box, Bytes},
 };
 
@@ -92,6 +93,23 @@ impl Vram {
         self.cpu_written_addr = (self.cpu_written_addr & 0xFF) | (value as u16) << 8;
         self.reset_cur_word_addr();
     }
+
+    pub(super) fn save_state(&self, writer: &mut Writer) {
+        writer.write_bytes(&self.contents[..]);
+        writer.write(&self.increment_control.0);
+        writer.write(&self.read_latch);
+        writer.write(&self.cpu_written_addr);
+        writer.write(&self.cur_word_addr);
+    }
+
+    pub(super) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        reader.read_bytes(&mut self.contents[..])?;
+        self.set_increment_control(IncrementControl(reader.read()?));
+        self.read_latch = reader.read()?;
+        self.cpu_written_addr = reader.read()?;
+        self.cur_word_addr = reader.read()?;
+        Ok(())
+    }
 }
 
 impl Ppu {
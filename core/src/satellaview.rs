@@ -0,0 +1,97 @@
+use crate::savestate::{LoadError, Reader, Writer};
+
+/// The registers of the Satellaview, the BS-X receiver unit connected to the console's expansion
+/// port, mapped at $2188-$219F on bus B.
+///
+/// No satellite broadcasts are emulated: both data streams always report empty queues, which lets
+/// the BS-X BIOS boot to its town screen as if there were no signal.
+#[derive(Clone)]
+pub struct BaseUnit {
+    stream_channels: [u16; 2],
+    led_control: u8,
+    control: u8,
+    serial: [u8; 2],
+}
+
+impl BaseUnit {
+    pub(crate) fn new() -> Self {
+        let mut result = BaseUnit {
+            stream_channels: [0; 2],
+            led_control: 0,
+            control: 0,
+            serial: [0; 2],
+        };
+        result.reset();
+        result
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.stream_channels = [0; 2];
+        self.led_control = 0;
+        self.control = 0x80;
+        self.serial = [0; 2];
+    }
+
+    #[inline]
+    pub fn stream_channels(&self) -> [u16; 2] {
+        self.stream_channels
+    }
+
+    #[inline]
+    pub fn led_control(&self) -> u8 {
+        self.led_control
+    }
+
+    /// Reads from the given bus B address, returning `None` for unused ones (which read open bus).
+    pub(crate) fn read(&self, addr: u8) -> Option<u8> {
+        Some(match addr {
+            0x88 | 0x8E => self.stream_channels[(addr >= 0x8E) as usize] as u8,
+            0x89 | 0x8F => (self.stream_channels[(addr >= 0x8E) as usize] >> 8) as u8,
+            // Queue sizes, prefixes, data and status for both streams, which never receive any
+            // packets
+            0x8A..=0x8D | 0x90..=0x93 => 0,
+            0x94 => self.led_control,
+            // Always report the receiver as ready
+            0x96 => 0x10,
+            0x97 => self.control,
+            0x98 | 0x99 => self.serial[addr as usize & 1],
+            _ => return None,
+        })
+    }
+
+    /// Writes to the given bus B address, returning whether it's mapped to a register.
+    pub(crate) fn write(&mut self, addr: u8, value: u8) -> bool {
+        match addr {
+            0x88 | 0x8E => {
+                let channel = &mut self.stream_channels[(addr >= 0x8E) as usize];
+                *channel = (*channel & 0xFF00) | value as u16;
+            }
+            0x89 | 0x8F => {
+                let channel = &mut self.stream_channels[(addr >= 0x8E) as usize];
+                *channel = (*channel & 0x00FF) | (value as u16) << 8;
+            }
+            // Writes to the stream registers only reset their (always empty) queues
+            0x8A..=0x8D | 0x90..=0x93 => {}
+            0x94 => self.led_control = value,
+            0x97 => self.control = value,
+            0x98 | 0x99 => self.serial[addr as usize & 1] = value,
+            _ => return false,
+        }
+        true
+    }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.stream_channels);
+        writer.write(&self.led_control);
+        writer.write(&self.control);
+        writer.write(&self.serial);
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.stream_channels = reader.read()?;
+        self.led_control = reader.read()?;
+        self.control = reader.read()?;
+        self.serial = reader.read()?;
+        Ok(())
+    }
+}
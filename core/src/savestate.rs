@@ -0,0 +1,228 @@
+use core::fmt::{self, Display};
+use std::error::Error;
+
+const MAGIC: [u8; 4] = *b"NESS";
+pub const VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    UnexpectedEnd,
+    InvalidData,
+    ModelMismatch,
+    CartMismatch,
+}
+
+impl Error for LoadError {}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported save state version {} (expected {})",
+                version, VERSION
+            ),
+            _ => f.write_str(match self {
+                Self::InvalidMagic => "Not a save state file",
+                Self::UnexpectedEnd => "Unexpected end of save state data",
+                Self::InvalidData => "Invalid save state data",
+                Self::ModelMismatch => "Save state was created for a different console model",
+                Self::CartMismatch => "Save state was created for a different cart",
+                Self::UnsupportedVersion(_) => unreachable!(),
+            }),
+        }
+    }
+}
+
+pub trait Value: Sized {
+    fn write(&self, writer: &mut Writer);
+    fn read(reader: &mut Reader) -> Result<Self, LoadError>;
+}
+
+macro_rules! impl_value_for_ints {
+    ($($ty: ty),*) => {
+        $(
+            impl Value for $ty {
+                #[inline]
+                fn write(&self, writer: &mut Writer) {
+                    writer.write_bytes(&self.to_le_bytes());
+                }
+
+                #[inline]
+                fn read(reader: &mut Reader) -> Result<Self, LoadError> {
+                    let mut bytes = [0; core::mem::size_of::<$ty>()];
+                    reader.read_bytes(&mut bytes)?;
+                    Ok(<$ty>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+impl_value_for_ints!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl Value for bool {
+    #[inline]
+    fn write(&self, writer: &mut Writer) {
+        writer.write(&(*self as u8));
+    }
+
+    #[inline]
+    fn read(reader: &mut Reader) -> Result<Self, LoadError> {
+        match reader.read::<u8>()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(LoadError::InvalidData),
+        }
+    }
+}
+
+impl<T: Value> Value for Option<T> {
+    fn write(&self, writer: &mut Writer) {
+        match self {
+            Some(value) => {
+                writer.write(&true);
+                writer.write(value);
+            }
+            None => writer.write(&false),
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, LoadError> {
+        Ok(if reader.read::<bool>()? {
+            Some(reader.read()?)
+        } else {
+            None
+        })
+    }
+}
+
+impl<A: Value, B: Value> Value for (A, B) {
+    fn write(&self, writer: &mut Writer) {
+        writer.write(&self.0);
+        writer.write(&self.1);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, LoadError> {
+        Ok((reader.read()?, reader.read()?))
+    }
+}
+
+impl<T: Value + Copy + Default, const LEN: usize> Value for [T; LEN] {
+    fn write(&self, writer: &mut Writer) {
+        for value in self {
+            writer.write(value);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, LoadError> {
+        let mut result = [T::default(); LEN];
+        for value in &mut result {
+            *value = reader.read()?;
+        }
+        Ok(result)
+    }
+}
+
+pub struct Writer {
+    buffer: Vec<u8>,
+}
+
+impl Writer {
+    pub(crate) fn new() -> Self {
+        let mut writer = Writer { buffer: Vec::new() };
+        writer.write_bytes(&MAGIC);
+        writer.write(&VERSION);
+        writer
+    }
+
+    #[inline]
+    pub fn write<T: Value>(&mut self, value: &T) {
+        value.write(self);
+    }
+
+    #[inline]
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Writes the data produced by `f` prefixed by its length, so that it can be read back as a
+    /// self-contained section (used for parts of the state whose layout depends on runtime
+    /// configuration, such as connected controller devices).
+    pub fn write_section(&mut self, f: impl FnOnce(&mut Writer)) {
+        let len_pos = self.buffer.len();
+        self.write(&0_u32);
+        f(self);
+        let len = (self.buffer.len() - len_pos - 4) as u32;
+        self.buffer[len_pos..len_pos + 4].copy_from_slice(&len.to_le_bytes());
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Result<Self, LoadError> {
+        let mut reader = Reader { data };
+        let mut magic = [0; 4];
+        reader
+            .read_bytes(&mut magic)
+            .map_err(|_| LoadError::InvalidMagic)?;
+        if magic != MAGIC {
+            return Err(LoadError::InvalidMagic);
+        }
+        let version = reader.read::<u32>()?;
+        if version != VERSION {
+            return Err(LoadError::UnsupportedVersion(version));
+        }
+        Ok(reader)
+    }
+
+    #[inline]
+    pub fn read<T: Value>(&mut self) -> Result<T, LoadError> {
+        T::read(self)
+    }
+
+    pub fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<(), LoadError> {
+        if self.data.len() < bytes.len() {
+            return Err(LoadError::UnexpectedEnd);
+        }
+        let (contents, rest) = self.data.split_at(bytes.len());
+        bytes.copy_from_slice(contents);
+        self.data = rest;
+        Ok(())
+    }
+
+    /// Reads a section written by [`Writer::write_section`], failing if `f` doesn't consume it
+    /// entirely.
+    pub fn read_section(
+        &mut self,
+        f: impl FnOnce(&mut Reader) -> Result<(), LoadError>,
+    ) -> Result<(), LoadError> {
+        let len = self.read::<u32>()? as usize;
+        if self.data.len() < len {
+            return Err(LoadError::UnexpectedEnd);
+        }
+        let (contents, rest) = self.data.split_at(len);
+        let mut section_reader = Reader { data: contents };
+        f(&mut section_reader)?;
+        section_reader.finish()?;
+        self.data = rest;
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> Result<(), LoadError> {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err(LoadError::InvalidData)
+        }
+    }
+}
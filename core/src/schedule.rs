@@ -1,5 +1,6 @@
 use crate::{
     controllers, ppu,
+    savestate::{LoadError, Reader, Writer},
     utils::{
         bounded_int,
         schedule::{self, RawTimestamp},
@@ -14,6 +15,7 @@ pub enum Event {
     HvIrq,
     Controllers(controllers::Event),
     UpdateApu,
+    Cart,
 }
 
 impl Default for Event {
@@ -28,9 +30,11 @@ pub mod event_slots {
         super::EventSlotIndex,
         PPU,
         PPU_OTHER,
+        PPU_EXTERNAL_LATCH,
         HV_IRQ,
         CONTROLLERS,
-        APU
+        APU,
+        CART
     );
 }
 pub const EVENT_SLOTS: usize = event_slots::LEN;
@@ -52,11 +56,79 @@ impl From<EventSlotIndex> for usize {
     }
 }
 
+impl Event {
+    fn save_state(&self, writer: &mut Writer) {
+        let (kind, sub_kind) = match self {
+            Event::Ppu(event) => (
+                0_u8,
+                match event {
+                    ppu::Event::StartHDraw => 0_u8,
+                    ppu::Event::StartHBlank => 1,
+                    ppu::Event::ReloadHdmas => 2,
+                    ppu::Event::StartHdmas => 3,
+                    ppu::Event::RequestVBlankNmi => 4,
+                    ppu::Event::ReloadOamAddr => 5,
+                    ppu::Event::EndScanline => 6,
+                    ppu::Event::ExternalLatch => 7,
+                },
+            ),
+            Event::HvIrq => (1, 0),
+            Event::Controllers(event) => (
+                2,
+                match event {
+                    controllers::Event::StartAutoRead => 0,
+                    controllers::Event::EndAutoRead => 1,
+                },
+            ),
+            Event::UpdateApu => (3, 0),
+            Event::Cart => (4, 0),
+        };
+        writer.write(&kind);
+        writer.write(&sub_kind);
+    }
+
+    fn load_state(reader: &mut Reader) -> Result<Self, LoadError> {
+        let kind = reader.read::<u8>()?;
+        let sub_kind = reader.read::<u8>()?;
+        Ok(match (kind, sub_kind) {
+            (0, sub_kind) => Event::Ppu(match sub_kind {
+                0 => ppu::Event::StartHDraw,
+                1 => ppu::Event::StartHBlank,
+                2 => ppu::Event::ReloadHdmas,
+                3 => ppu::Event::StartHdmas,
+                4 => ppu::Event::RequestVBlankNmi,
+                5 => ppu::Event::ReloadOamAddr,
+                6 => ppu::Event::EndScanline,
+                7 => ppu::Event::ExternalLatch,
+                _ => return Err(LoadError::InvalidData),
+            }),
+            (1, 0) => Event::HvIrq,
+            (2, 0) => Event::Controllers(controllers::Event::StartAutoRead),
+            (2, 1) => Event::Controllers(controllers::Event::EndAutoRead),
+            (3, 0) => Event::UpdateApu,
+            (4, 0) => Event::Cart,
+            _ => return Err(LoadError::InvalidData),
+        })
+    }
+}
+
+// Mirrors the contents of the underlying schedule's slots, as they're not otherwise observable;
+// `seq` records the order in which events were scheduled, so that events scheduled for the same
+// time fire in the same order after loading a save state.
+#[derive(Clone, Copy, Debug, Default)]
+struct SlotState {
+    event: Event,
+    time: Option<Timestamp>,
+    seq: u64,
+}
+
 pub struct Schedule {
     pub(crate) cur_time: Timestamp,
     pub(crate) last_poll_time: Timestamp,
     pub(crate) target_time: Timestamp,
     pub(crate) schedule: schedule::Schedule<Timestamp, Event, EventSlotIndex, EVENT_SLOTS>,
+    slots: [SlotState; EVENT_SLOTS],
+    next_seq: u64,
 }
 
 impl Schedule {
@@ -66,6 +138,8 @@ impl Schedule {
             last_poll_time: 0,
             target_time: 0,
             schedule: schedule::Schedule::new(),
+            slots: [SlotState::default(); EVENT_SLOTS],
+            next_seq: 0,
         }
     }
 
@@ -91,10 +165,15 @@ impl Schedule {
 
     pub(crate) fn set_event(&mut self, slot_index: EventSlotIndex, event: Event) {
         self.schedule.set_event(slot_index, event);
+        self.slots[usize::from(slot_index)].event = event;
     }
 
     pub(crate) fn schedule_event(&mut self, slot_index: EventSlotIndex, time: Timestamp) {
         self.schedule.schedule(slot_index, time);
+        let slot = &mut self.slots[usize::from(slot_index)];
+        slot.time = Some(time);
+        slot.seq = self.next_seq;
+        self.next_seq += 1;
         if time < self.target_time {
             self.target_time = time;
         }
@@ -102,13 +181,64 @@ impl Schedule {
 
     pub(crate) fn cancel_event(&mut self, slot_index: EventSlotIndex) {
         self.schedule.cancel(slot_index);
+        self.slots[usize::from(slot_index)].time = None;
     }
 
     pub(crate) fn pop_pending_event(&mut self) -> Option<(Event, Timestamp)> {
-        self.schedule.pop_pending_event(self.cur_time)
+        let result = self.schedule.pop_pending_event(self.cur_time);
+        if let Some((event, time)) = result {
+            if let Some(slot) = self
+                .slots
+                .iter_mut()
+                .find(|slot| slot.event == event && slot.time == Some(time))
+            {
+                slot.time = None;
+            }
+        }
+        result
     }
 
     pub(crate) fn set_target_to_cur(&mut self) {
         self.target_time = self.cur_time;
     }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        writer.write(&self.cur_time);
+        writer.write(&self.last_poll_time);
+        writer.write(&self.target_time);
+        for slot in &self.slots {
+            slot.event.save_state(writer);
+            writer.write(&slot.time);
+            writer.write(&slot.seq);
+        }
+        writer.write(&self.next_seq);
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        self.cur_time = reader.read()?;
+        self.last_poll_time = reader.read()?;
+        self.target_time = reader.read()?;
+        for slot in &mut self.slots {
+            *slot = SlotState {
+                event: Event::load_state(reader)?,
+                time: reader.read()?,
+                seq: reader.read()?,
+            };
+        }
+        self.next_seq = reader.read()?;
+
+        self.schedule = schedule::Schedule::new();
+        let mut scheduled = Vec::with_capacity(EVENT_SLOTS);
+        for (i, slot) in self.slots.iter().enumerate() {
+            self.schedule.set_event(EventSlotIndex::from(i), slot.event);
+            if let Some(time) = slot.time {
+                scheduled.push((time, slot.seq, i));
+            }
+        }
+        scheduled.sort_unstable();
+        for (time, _, i) in scheduled {
+            self.schedule.schedule(EventSlotIndex::from(i), time);
+        }
+        Ok(())
+    }
 }
@@ -1,5 +1,6 @@
 use crate::{
     cpu::bus::AccessType,
+    savestate::{LoadError, Reader, Writer},
     utils::{zeroed_box, Bytes},
 };
 
@@ -46,4 +47,15 @@ impl Wram {
         self.contents[self.cur_addr.get() as usize] = value;
         self.cur_addr = Address::new((self.cur_addr.get() + 1) & 0x1_FFFF);
     }
+
+    pub(crate) fn save_state(&self, writer: &mut Writer) {
+        writer.write_bytes(&self.contents[..]);
+        writer.write(&self.cur_addr.get());
+    }
+
+    pub(crate) fn load_state(&mut self, reader: &mut Reader) -> Result<(), LoadError> {
+        reader.read_bytes(&mut self.contents[..])?;
+        self.set_addr(reader.read()?);
+        Ok(())
+    }
 }
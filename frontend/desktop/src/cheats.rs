@@ -0,0 +1,184 @@
+use super::config::Cheat;
+use imgui::{StyleColor, Ui, Window};
+use ness_core::cheats::{self, Code, DecodeError};
+use rfd::FileDialog;
+use std::fs;
+
+static CHEAT_FILE_EXTENSIONS: &[&str] = &["cht"];
+
+// The size of each entry in Snes9x's old binary cheat files
+const SNES9X_ENTRY_LEN: usize = 28;
+
+fn decode_all(code: &str) -> impl Iterator<Item = Result<Code, DecodeError>> + '_ {
+    code.split('+').map(cheats::decode)
+}
+
+/// Decodes the codes of all enabled cheats, skipping invalid ones.
+pub fn active_codes(cheats: &[Cheat]) -> Vec<Code> {
+    cheats
+        .iter()
+        .filter(|cheat| cheat.enabled)
+        .flat_map(|cheat| decode_all(&cheat.code).filter_map(Result::ok))
+        .collect()
+}
+
+fn parse_cht_text(text: &str) -> Vec<Cheat> {
+    let mut result: Vec<Cheat> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line == "cheat" {
+            result.push(Cheat {
+                description: String::new(),
+                code: String::new(),
+                enabled: false,
+            });
+            continue;
+        }
+        let cheat = match result.last_mut() {
+            Some(cheat) => cheat,
+            None => continue,
+        };
+        if line == "enable" {
+            cheat.enabled = true;
+        } else if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim().trim_matches('"');
+            match key {
+                // bsnes uses "description", Snes9x uses "name"
+                "description" | "name" => cheat.description = value.to_string(),
+                "code" => cheat.code = value.to_string(),
+                _ => {}
+            }
+        }
+    }
+    result.retain(|cheat| !cheat.code.is_empty());
+    result
+}
+
+fn parse_cht_snes9x_binary(data: &[u8]) -> Vec<Cheat> {
+    data.chunks_exact(SNES9X_ENTRY_LEN)
+        .map(|entry| {
+            let addr = u32::from_le_bytes([entry[2], entry[3], entry[4], 0]);
+            let name = &entry[8..];
+            let name_len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            Cheat {
+                description: String::from_utf8_lossy(&name[..name_len]).into_owned(),
+                code: format!("{:06X}={:02X}", addr, entry[1]),
+                // Bit 2 of the flags marks disabled cheats
+                enabled: entry[0] & 4 == 0,
+            }
+        })
+        .collect()
+}
+
+/// Parses a `.cht` file, either in the text format used by bsnes and recent Snes9x versions or in
+/// the binary one used by older Snes9x versions.
+pub fn parse_cht(data: &[u8]) -> Option<Vec<Cheat>> {
+    match std::str::from_utf8(data) {
+        Ok(text) if text.trim_start().starts_with("cheat") => Some(parse_cht_text(text)),
+        _ if !data.is_empty() && data.len() % SNES9X_ENTRY_LEN == 0 => {
+            Some(parse_cht_snes9x_binary(data))
+        }
+        _ => None,
+    }
+}
+
+pub struct Manager {
+    new_description: String,
+    new_code: String,
+    error: Option<String>,
+}
+
+impl Manager {
+    pub fn new() -> Self {
+        Manager {
+            new_description: String::new(),
+            new_code: String::new(),
+            error: None,
+        }
+    }
+
+    /// Draws the cheat manager window, returning whether the cheat list was modified.
+    pub fn draw(&mut self, ui: &Ui, cheats: &mut Vec<Cheat>, opened: &mut bool) -> bool {
+        let mut changed = false;
+        Window::new("Cheats").opened(opened).build(ui, || {
+            let mut removed = None;
+            for (i, cheat) in cheats.iter_mut().enumerate() {
+                let _id = ui.push_id(&format!("cheat{}", i));
+                changed |= ui.checkbox("##enabled", &mut cheat.enabled);
+                ui.same_line();
+                if ui.small_button("Remove") {
+                    removed = Some(i);
+                }
+                ui.same_line();
+                if cheat.description.is_empty() {
+                    ui.text(&cheat.code);
+                } else {
+                    ui.text(&cheat.description);
+                    ui.same_line();
+                    ui.text_colored(ui.style_color(StyleColor::TextDisabled), &cheat.code);
+                }
+            }
+            if let Some(i) = removed {
+                cheats.remove(i);
+                changed = true;
+            }
+            if cheats.is_empty() {
+                ui.text_colored(ui.style_color(StyleColor::TextDisabled), "No cheats");
+            }
+
+            ui.separator();
+
+            ui.input_text("Description", &mut self.new_description)
+                .build();
+            ui.input_text("Code", &mut self.new_code).build();
+            if ui.button("Add") {
+                let code = self.new_code.trim();
+                match decode_all(code).find_map(Result::err) {
+                    Some(err) => self.error = Some(format!("{}.", err)),
+                    None => {
+                        cheats.push(Cheat {
+                            description: self.new_description.trim().to_string(),
+                            code: code.to_string(),
+                            enabled: true,
+                        });
+                        self.new_description.clear();
+                        self.new_code.clear();
+                        self.error = None;
+                        changed = true;
+                    }
+                }
+            }
+            ui.same_line();
+            if ui.button("Import...") {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("Cheat file", CHEAT_FILE_EXTENSIONS)
+                    .pick_file()
+                {
+                    match fs::read(&path) {
+                        Ok(data) => match parse_cht(&data) {
+                            Some(imported) => {
+                                cheats.extend(imported);
+                                changed = true;
+                            }
+                            None => {
+                                error!(
+                                    "Couldn't import cheats",
+                                    "{}: unknown cheat file format.",
+                                    path.display()
+                                );
+                            }
+                        },
+                        Err(err) => {
+                            error!("Couldn't read cheat file", "{}.", err);
+                        }
+                    }
+                }
+            }
+
+            if let Some(error) = &self.error {
+                ui.text_colored([1.0, 0.4, 0.4, 1.0], error);
+            }
+        });
+        changed
+    }
+}
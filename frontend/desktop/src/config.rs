@@ -2,18 +2,24 @@ mod saves;
 
 use super::{
     audio,
+    input::trigger::Trigger,
     utils::{config_base, data_base},
 };
 use ness_core::{
-    cart::info::header::{Header as CartHeader, Region},
+    cart::info::{
+        header::{Header as CartHeader, Region},
+        SaveRamLayout,
+    },
     Model,
 };
+pub use saves::slot_cart_save_path;
 use saves::{save_path, SavePathConfig};
 use serde::{Deserialize, Serialize};
 use std::{
     fmt, fs, io,
     path::{Path, PathBuf},
 };
+use winit::event::VirtualKeyCode;
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -30,6 +36,17 @@ pub enum ModelConfig {
     Pal,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ControllerDevice {
+    None,
+    Joypad,
+    Multitap,
+    Mouse,
+    SuperScope,
+    Justifier,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
 pub struct Global {
@@ -39,6 +56,7 @@ pub struct Global {
     pub audio_interp_method: audio::InterpMethod,
     pub pause_on_launch: bool,
     pub autosave_interval_ms: f32,
+    pub controller_devices: [ControllerDevice; 2],
 
     pub save_dir_path: PathBuf,
 
@@ -50,6 +68,15 @@ pub struct Global {
     pub logging_kind: LoggingKind,
     pub window_size: (u32, u32),
     pub imgui_config_path: Option<PathBuf>,
+
+    /// Whether to take rewind snapshots; as they involve serializing the whole emulator state,
+    /// they're only taken when rewinding is enabled.
+    pub rewind_enabled: bool,
+    /// The number of frames between rewind snapshots.
+    pub rewind_interval: u32,
+    /// The maximum amount of memory used by rewind snapshots, in MiB.
+    pub rewind_memory_budget_mib: u32,
+    pub rewind_trigger: Option<Trigger>,
 }
 
 impl Default for Global {
@@ -63,6 +90,7 @@ impl Default for Global {
             audio_interp_method: audio::InterpMethod::Nearest,
             pause_on_launch: false,
             autosave_interval_ms: 1000.0,
+            controller_devices: [ControllerDevice::Joypad, ControllerDevice::Joypad],
 
             save_dir_path: data_base.join("saves"),
 
@@ -74,6 +102,11 @@ impl Default for Global {
             logging_kind: LoggingKind::Imgui,
             window_size: (1300, 800),
             imgui_config_path: Some(config_base.join("imgui.ini")),
+
+            rewind_enabled: false,
+            rewind_interval: 1,
+            rewind_memory_budget_mib: 64,
+            rewind_trigger: Some(Trigger::KeyCode(VirtualKeyCode::Back)),
         }
     }
 }
@@ -87,8 +120,13 @@ pub struct Game {
     pub audio_interp_method: Option<audio::InterpMethod>,
     pub pause_on_launch: Option<bool>,
     pub autosave_interval_ms: Option<f32>,
+    pub controller_devices: Option<[ControllerDevice; 2]>,
 
     pub save_path: Option<SavePathConfig>,
+    /// Overrides the guessed save RAM layout for carts that aren't in the database.
+    pub save_ram_layout: Option<SaveRamLayout>,
+
+    pub cheats: Vec<Cheat>,
 }
 
 impl Default for Game {
@@ -100,12 +138,25 @@ impl Default for Game {
             audio_interp_method: None,
             pause_on_launch: None,
             autosave_interval_ms: None,
+            controller_devices: None,
 
             save_path: Some(SavePathConfig::GlobalSingle),
+            save_ram_layout: None,
+
+            cheats: Vec::new(),
         }
     }
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Cheat {
+    pub description: String,
+    /// One or more codes, separated by `+`.
+    pub code: String,
+    pub enabled: bool,
+}
+
 #[derive(Debug)]
 pub enum Error {
     Io(io::Error),
@@ -233,8 +284,12 @@ pub struct LaunchConfig {
     pub audio_interp_method: RuntimeModifiable<audio::InterpMethod>,
     pub pause_on_launch: bool,
     pub autosave_interval_ms: RuntimeModifiable<f32>,
+    pub controller_devices: RuntimeModifiable<[ControllerDevice; 2]>,
     pub audio_sample_chunk_size: u32,
     pub cur_save_path: Option<PathBuf>,
+    pub rewind_enabled: bool,
+    pub rewind_interval: u32,
+    pub rewind_memory_budget: usize,
 }
 
 #[derive(Debug)]
@@ -330,6 +385,7 @@ pub fn launch_config(
     let audio_interp_method = runtime_modifiable!(audio_interp_method);
     let pause_on_launch = plain_setting!(pause_on_launch);
     let autosave_interval_ms = runtime_modifiable!(autosave_interval_ms);
+    let controller_devices = runtime_modifiable!(controller_devices);
 
     let cur_save_path = save_path(
         &global_config.save_dir_path,
@@ -344,7 +400,11 @@ pub fn launch_config(
         audio_interp_method,
         pause_on_launch,
         autosave_interval_ms,
+        controller_devices,
         audio_sample_chunk_size: global_config.audio_sample_chunk_size,
         cur_save_path,
+        rewind_enabled: global_config.rewind_enabled,
+        rewind_interval: global_config.rewind_interval,
+        rewind_memory_budget: (global_config.rewind_memory_budget_mib as usize) << 20,
     })
 }
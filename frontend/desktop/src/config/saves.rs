@@ -59,6 +59,16 @@ pub fn save_path(
     }
 }
 
+/// Returns the save file path for a slot cart (i.e. a Sufami Turbo mini-cart or BS Memory pack),
+/// placed next to the main game's save file and suffixed with the slot cart's name.
+pub fn slot_cart_save_path(game_save_path: &Path, slot_cart_name: &OsStr) -> PathBuf {
+    let mut path = game_save_path.with_extension("").into_os_string();
+    path.push(".");
+    path.push(slot_cart_name);
+    path.push(".sav");
+    PathBuf::from(path)
+}
+
 pub fn make_multi_slot(
     prev_path: &Path,
     base_dir: &Path,
@@ -12,8 +12,10 @@ mod spc_memory;
 pub use spc_memory::SpcMemory;
 mod spc_disasm;
 pub use spc_disasm::SpcDisasm;
+mod ram_search;
+pub use ram_search::RamSearch;
 
-use super::ui::window::Window;
+use super::{config::Cheat, ui::window::Window};
 use fxhash::FxHashMap;
 use imgui::MenuItem;
 use ness_core::emu::Emu;
@@ -407,8 +409,19 @@ macro_rules! declare_structs {
 declare_structs!(
     singleton cpu_state, CpuState, ToggleCpuStateUpdates, UpdateCpuStateEmuState;
     singleton spc_state, SpcState, ToggleSpcStateUpdates, UpdateSpcStateEmuState;
+    singleton ram_search, RamSearch, ToggleRamSearchUpdates, UpdateRamSearchEmuState;
     instanceable cpu_memory, CpuMemory, ToggleCpuMemoryUpdates, UpdateCpuMemoryEmuState;
     instanceable cpu_disasm, CpuDisasm, ToggleCpuDisasmUpdates, UpdateCpuDisasmEmuState;
     instanceable spc_memory, SpcMemory, ToggleSpcMemoryUpdates, UpdateSpcMemoryEmuState;
     instanceable spc_disasm, SpcDisasm, ToggleSpcDisasmUpdates, UpdateSpcDisasmEmuState;
 );
+
+impl UiState {
+    /// Returns the cheats created from the RAM search view since the last call.
+    pub fn take_new_cheats(&mut self) -> Vec<Cheat> {
+        match &mut self.ram_search {
+            Some((ram_search, _)) => ram_search.take_new_cheats(),
+            None => Vec::new(),
+        }
+    }
+}
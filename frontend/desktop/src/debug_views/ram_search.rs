@@ -0,0 +1,386 @@
+use super::{FrameDataSlot, View};
+use crate::{config::Cheat, ui::window::Window};
+use imgui::{ChildWindow, StyleColor};
+use ness_core::emu::Emu;
+
+// Only this many candidates are listed, to keep the view responsive at the start of a search
+const MAX_LISTED_CANDIDATES: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Region {
+    Wram,
+    SaveRam,
+}
+
+impl Region {
+    fn format_offset(self, offset: u32) -> String {
+        match self {
+            Region::Wram => format!("{:06X}", 0x7E_0000 + offset),
+            Region::SaveRam => format!("SRAM:{:05X}", offset),
+        }
+    }
+}
+
+static REGIONS: [Region; 2] = [Region::Wram, Region::SaveRam];
+static REGION_NAMES: [&str; 2] = ["WRAM", "Save RAM"];
+static WIDTH_NAMES: [&str; 3] = ["8-bit", "16-bit", "24-bit"];
+static COMPARISON_NAMES: [&str; 4] = ["Equal to", "Not equal to", "Greater than", "Less than"];
+static OPERAND_NAMES: [&str; 2] = ["Previous value", "Constant"];
+
+/// A value that's written back to memory every frame, as long as the view is open.
+#[derive(Clone, Copy, Debug)]
+pub struct FrozenValue {
+    region: Region,
+    offset: u32,
+    width: usize,
+    value: u32,
+}
+
+#[derive(Clone)]
+pub struct EmuState {
+    frozen: Vec<FrozenValue>,
+}
+
+pub struct RamContents {
+    wram: Vec<u8>,
+    save_ram: Vec<u8>,
+}
+
+impl RamContents {
+    fn region(&self, region: Region) -> &[u8] {
+        match region {
+            Region::Wram => &self.wram,
+            Region::SaveRam => &self.save_ram,
+        }
+    }
+}
+
+fn read_value(data: &[u8], offset: u32, width: usize) -> Option<u32> {
+    let bytes = data.get(offset as usize..offset as usize + width)?;
+    Some(
+        bytes
+            .iter()
+            .rev()
+            .fold(0, |acc, byte| acc << 8 | *byte as u32),
+    )
+}
+
+fn parse_constant(input: &str) -> Option<u32> {
+    let input = input.trim();
+    if let Some(hex) = input.strip_prefix('$').or_else(|| input.strip_prefix("0x")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        input.parse().ok()
+    }
+}
+
+pub struct RamSearch {
+    region: usize,
+    width: usize,
+    comparison: usize,
+    operand: usize,
+    constant_input: String,
+    error: Option<&'static str>,
+    contents: RamContents,
+    /// The contents of the searched region at the time of the last search step.
+    prev_contents: Option<Vec<u8>>,
+    candidates: Vec<u32>,
+    frozen: Vec<FrozenValue>,
+    new_cheats: Vec<Cheat>,
+}
+
+impl RamSearch {
+    fn cur_region(&self) -> Region {
+        REGIONS[self.region]
+    }
+
+    fn value_width(&self) -> usize {
+        self.width + 1
+    }
+
+    /// Returns the cheats created from search results since the last call.
+    pub fn take_new_cheats(&mut self) -> Vec<Cheat> {
+        std::mem::take(&mut self.new_cheats)
+    }
+
+    fn start_search(&mut self) {
+        let data = self.contents.region(self.cur_region());
+        let width = self.value_width();
+        self.candidates = (0..(data.len() + 1).saturating_sub(width) as u32).collect();
+        self.prev_contents = Some(data.to_vec());
+        self.error = None;
+    }
+
+    fn filter(&mut self) {
+        let prev_contents = match &self.prev_contents {
+            Some(prev_contents) => prev_contents,
+            None => return,
+        };
+        let constant = if self.operand == 1 {
+            match parse_constant(&self.constant_input) {
+                Some(constant) => Some(constant),
+                None => {
+                    self.error = Some("Invalid constant");
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+        let data = self.contents.region(self.cur_region());
+        let width = self.value_width();
+        let comparison = self.comparison;
+        self.candidates.retain(|&offset| {
+            let (value, operand) = match (
+                read_value(data, offset, width),
+                constant.or_else(|| read_value(prev_contents, offset, width)),
+            ) {
+                (Some(value), Some(operand)) => (value, operand),
+                _ => return false,
+            };
+            match comparison {
+                0 => value == operand,
+                1 => value != operand,
+                2 => value > operand,
+                _ => value < operand,
+            }
+        });
+        self.prev_contents = Some(data.to_vec());
+        self.error = None;
+    }
+
+    fn add_cheat(&mut self, offset: u32, value: u32) {
+        let width = self.value_width();
+        let code = (0..width as u32)
+            .map(|i| {
+                format!(
+                    "{:06X}={:02X}",
+                    0x7E_0000 + offset + i,
+                    value >> (i * 8) & 0xFF
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("+");
+        self.new_cheats.push(Cheat {
+            description: format!("RAM search: {:06X}", 0x7E_0000 + offset),
+            code,
+            enabled: true,
+        });
+    }
+}
+
+impl View for RamSearch {
+    const NAME: &'static str = "RAM search";
+
+    type FrameData = RamContents;
+    type EmuState = EmuState;
+
+    fn new(_window: &mut Window) -> Self {
+        RamSearch {
+            region: 0,
+            width: 0,
+            comparison: 0,
+            operand: 0,
+            constant_input: String::new(),
+            error: None,
+            contents: RamContents {
+                wram: Vec::new(),
+                save_ram: Vec::new(),
+            },
+            prev_contents: None,
+            candidates: Vec::new(),
+            frozen: Vec::new(),
+            new_cheats: Vec::new(),
+        }
+    }
+
+    fn destroy(self, _window: &mut Window) {}
+
+    fn emu_state(&self) -> Self::EmuState {
+        EmuState {
+            frozen: self.frozen.clone(),
+        }
+    }
+
+    fn prepare_frame_data<'a, S: FrameDataSlot<'a, Self::FrameData>>(
+        emu_state: &Self::EmuState,
+        emu: &mut Emu,
+        frame_data: S,
+    ) {
+        for frozen in &emu_state.frozen {
+            let bytes = frozen.value.to_le_bytes();
+            let range = frozen.offset as usize..frozen.offset as usize + frozen.width;
+            match frozen.region {
+                Region::Wram => {
+                    if let Some(dst) = emu.wram.contents.get_mut(range) {
+                        dst.copy_from_slice(&bytes[..frozen.width]);
+                    }
+                }
+                Region::SaveRam => {
+                    // Avoid marking the save RAM as modified every frame
+                    if matches!(
+                        emu.cart.ram().get(range.clone()),
+                        Some(cur) if cur != &bytes[..frozen.width]
+                    ) {
+                        emu.cart
+                            .modify_ram(|ram| ram[range].copy_from_slice(&bytes[..frozen.width]));
+                    }
+                }
+            }
+        }
+
+        let frame_data = frame_data.get_or_insert_with(|| RamContents {
+            wram: Vec::new(),
+            save_ram: Vec::new(),
+        });
+        frame_data.wram.clear();
+        frame_data.wram.extend_from_slice(&emu.wram.contents[..]);
+        frame_data.save_ram.clear();
+        frame_data.save_ram.extend_from_slice(&emu.cart.ram()[..]);
+    }
+
+    fn update_from_frame_data(&mut self, frame_data: &Self::FrameData, _window: &mut Window) {
+        self.contents.wram.clear();
+        self.contents.wram.extend_from_slice(&frame_data.wram);
+        self.contents.save_ram.clear();
+        self.contents
+            .save_ram
+            .extend_from_slice(&frame_data.save_ram);
+    }
+
+    fn customize_window<'a, T: AsRef<str>>(
+        &mut self,
+        _ui: &imgui::Ui,
+        window: imgui::Window<'a, T>,
+    ) -> imgui::Window<'a, T> {
+        window.size([360.0, 480.0], imgui::Condition::FirstUseEver)
+    }
+
+    fn render(
+        &mut self,
+        ui: &imgui::Ui,
+        window: &mut Window,
+        _emu_running: bool,
+    ) -> Option<Self::EmuState> {
+        let mut emu_state_changed = false;
+
+        if ui.combo_simple_string("Region", &mut self.region, &REGION_NAMES) {
+            self.candidates.clear();
+            self.prev_contents = None;
+        }
+        ui.combo_simple_string("Width", &mut self.width, &WIDTH_NAMES);
+        ui.combo_simple_string("Comparison", &mut self.comparison, &COMPARISON_NAMES);
+        ui.combo_simple_string("Operand", &mut self.operand, &OPERAND_NAMES);
+        if self.operand == 1 {
+            ui.input_text("Constant", &mut self.constant_input).build();
+        }
+
+        if ui.button("New search") {
+            self.start_search();
+        }
+        ui.same_line();
+        if self.prev_contents.is_some() && ui.button("Filter") {
+            self.filter();
+        }
+        if let Some(error) = self.error {
+            ui.same_line();
+            ui.text_colored([1.0, 0.4, 0.4, 1.0], error);
+        }
+
+        ui.separator();
+
+        let region = self.cur_region();
+        let width = self.value_width();
+        let mut freeze = None;
+        let mut cheat = None;
+        ui.text(&format!("{} candidates", self.candidates.len()));
+        ChildWindow::new("##candidates")
+            .movable(false)
+            .size([0.0, -ui.frame_height_with_spacing() * 6.0])
+            .build(ui, || {
+                let _mono_font = ui.push_font(window.mono_font);
+                let data = self.contents.region(region);
+                let prev_data = self.prev_contents.as_deref().unwrap_or(&[]);
+                for &offset in self.candidates.iter().take(MAX_LISTED_CANDIDATES) {
+                    let value = match read_value(data, offset, width) {
+                        Some(value) => value,
+                        None => continue,
+                    };
+                    let _id = ui.push_id(&offset.to_string());
+                    ui.text(&format!(
+                        "{}: {:0width$X}",
+                        region.format_offset(offset),
+                        value,
+                        width = width * 2,
+                    ));
+                    if let Some(prev_value) = read_value(prev_data, offset, width) {
+                        ui.same_line();
+                        ui.text_colored(
+                            ui.style_color(StyleColor::TextDisabled),
+                            &format!("(was {:0width$X})", prev_value, width = width * 2),
+                        );
+                    }
+                    ui.same_line();
+                    if ui.small_button("Freeze") {
+                        freeze = Some((offset, value));
+                    }
+                    // Cheats apply to bus addresses, which are only known for WRAM
+                    if region == Region::Wram {
+                        ui.same_line();
+                        if ui.small_button("Cheat") {
+                            cheat = Some((offset, value));
+                        }
+                    }
+                }
+                if self.candidates.len() > MAX_LISTED_CANDIDATES {
+                    ui.text_colored(
+                        ui.style_color(StyleColor::TextDisabled),
+                        &format!("{} more...", self.candidates.len() - MAX_LISTED_CANDIDATES),
+                    );
+                }
+            });
+
+        if let Some((offset, value)) = freeze {
+            self.frozen
+                .retain(|frozen| !(frozen.region == region && frozen.offset == offset));
+            self.frozen.push(FrozenValue {
+                region,
+                offset,
+                width,
+                value,
+            });
+            emu_state_changed = true;
+        }
+        if let Some((offset, value)) = cheat {
+            self.add_cheat(offset, value);
+        }
+
+        ui.separator();
+
+        ui.text("Frozen values");
+        let mut unfrozen = None;
+        for (i, frozen) in self.frozen.iter().enumerate() {
+            let _id = ui.push_id(&format!("frozen{}", i));
+            ui.text(&format!(
+                "{}: {:0width$X}",
+                frozen.region.format_offset(frozen.offset),
+                frozen.value,
+                width = frozen.width * 2,
+            ));
+            ui.same_line();
+            if ui.small_button("Unfreeze") {
+                unfrozen = Some(i);
+            }
+        }
+        if let Some(i) = unfrozen {
+            self.frozen.remove(i);
+            emu_state_changed = true;
+        }
+
+        if emu_state_changed {
+            Some(self.emu_state())
+        } else {
+            None
+        }
+    }
+}
@@ -1,10 +1,33 @@
 #[cfg(feature = "debug-views")]
 use super::debug_views;
-use super::{audio, config::LaunchConfig, input, triple_buffer, FrameData};
-use ness_core::{apu::dsp::DummyBackend as DummyAudioBackend, cart::Cart, emu::Emu, Model};
+use super::{
+    audio,
+    config::{ControllerDevice, LaunchConfig},
+    input, msu1, rewind, triple_buffer,
+    utils::{rtc_path, unix_time},
+    FrameData,
+};
+use ness_core::{
+    apu::dsp::DummyBackend as DummyAudioBackend,
+    cart::Cart,
+    cheats::Code,
+    controllers::{
+        empty::Empty,
+        joypad::{Joypad, Keys},
+        justifier::Justifier,
+        mouse::Mouse,
+        multitap::Multitap,
+        super_scope::SuperScope,
+        Device,
+    },
+    emu::Emu,
+    movie::{self, Movie, StartCondition},
+    msu1::Msu1,
+    Model,
+};
 use parking_lot::RwLock;
 use std::{
-    fs, hint,
+    fs, hint, mem,
     path::PathBuf,
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -21,9 +44,26 @@ pub struct SharedState {
 
 pub enum Message {
     UpdateInput(input::Changes),
+    UpdateMouse(input::MouseChanges),
+    UpdateLightGun(input::LightGunState),
     UpdateSavePath(Option<PathBuf>),
     UpdateAudioSampleChunkSize(u32),
     UpdateAudioSync(bool),
+    UpdateControllerDevices([ControllerDevice; 2]),
+    UpdateCheats(Vec<Code>),
+    /// Enables or disables rewind snapshots, taken every given number of frames and kept within the
+    /// given memory budget in bytes.
+    UpdateRewind(bool, u32, usize),
+    /// Starts or stops stepping backwards through the rewind history.
+    Rewind(bool),
+    /// Starts recording a movie to the given path, either from a power-on (if the flag is set) or
+    /// from the current state.
+    RecordMovie(PathBuf, bool),
+    PlayMovie(PathBuf),
+    /// Stops recording or playing back a movie, saving it in the former case.
+    StopMovie,
+    SaveState(PathBuf),
+    LoadState(PathBuf),
     #[cfg(feature = "debug-views")]
     DebugViews(debug_views::Message),
     SoftReset,
@@ -31,9 +71,50 @@ pub enum Message {
     Stop,
 }
 
+fn create_controller_device(device: ControllerDevice) -> Box<dyn Device> {
+    match device {
+        ControllerDevice::None => Box::new(Empty::new()),
+        ControllerDevice::Joypad => Box::new(Joypad::new()),
+        ControllerDevice::Multitap => Box::new(Multitap::new()),
+        ControllerDevice::Mouse => Box::new(Mouse::new()),
+        ControllerDevice::SuperScope => Box::new(SuperScope::new()),
+        ControllerDevice::Justifier => Box::new(Justifier::new()),
+    }
+}
+
+fn connect_controller_devices(emu: &mut Emu, devices: [ControllerDevice; 2]) {
+    for (port, device) in devices.into_iter().enumerate() {
+        emu.controllers
+            .connect_device(port, create_controller_device(device));
+    }
+}
+
+fn controller_device(kind: movie::DeviceKind) -> ControllerDevice {
+    match kind {
+        movie::DeviceKind::None => ControllerDevice::None,
+        movie::DeviceKind::Joypad => ControllerDevice::Joypad,
+        movie::DeviceKind::Multitap => ControllerDevice::Multitap,
+        movie::DeviceKind::Mouse => ControllerDevice::Mouse,
+        movie::DeviceKind::SuperScope => ControllerDevice::SuperScope,
+        movie::DeviceKind::Justifier => ControllerDevice::Justifier,
+    }
+}
+
+fn update_joypad_keys(emu: &mut Emu, pressed_keys: &[Keys; input::PLAYERS]) {
+    for (player, &keys) in pressed_keys.iter().enumerate() {
+        if let Some(joypad) = emu.controllers.joypad_mut(player) {
+            joypad.pressed_keys = keys;
+        }
+    }
+}
+
 pub(super) fn main(
     config: LaunchConfig,
     cart: Cart,
+    slot_save_paths: Vec<Option<PathBuf>>,
+    msu1_files: Option<msu1::Files>,
+    rom_sha256: [u8; 32],
+    mut cheats: Vec<Code>,
     audio_tx_data: Option<audio::SenderData>,
     mut frame_tx: triple_buffer::Sender<FrameData>,
     message_rx: crossbeam_channel::Receiver<Message>,
@@ -51,6 +132,12 @@ pub(super) fn main(
         #[cfg(feature = "log")]
         &logger,
     );
+    emu.msu1 = msu1_files.clone().map(|files| Msu1::new(Box::new(files)));
+    emu.cheats.set_codes(cheats.clone());
+    let mut controller_devices = config.controller_devices.value;
+    connect_controller_devices(&mut emu, controller_devices);
+    // The devices that are actually connected, which are the movie's ones while one is active
+    let mut connected_devices = controller_devices;
 
     let frame_interval = match config.model {
         Model::Ntsc => Duration::from_nanos(1_000_000_000 / 60),
@@ -77,21 +164,206 @@ pub(super) fn main(
             {
                 emu.cart.mark_ram_flushed();
             }
+            // The RTC keeps running even if the RAM wasn't modified, so always save it
+            if let Some(rtc_data) = emu.cart.rtc_data(unix_time()) {
+                let _ = fs::write(rtc_path($save_path), rtc_data);
+            }
+        };
+    }
+
+    // Slot carts (i.e. Sufami Turbo mini-carts and BS Memory packs) have their own save files,
+    // derived from the main cart's save path when the game was started
+    macro_rules! save_slot_carts {
+        () => {
+            for (i, save_path) in slot_save_paths.iter().enumerate() {
+                if let (Some(save_path), Some(slot_cart)) = (save_path, emu.cart.slot_cart_mut(i)) {
+                    // BS Memory packs have no RAM, and save their rewritten flash memory instead
+                    let create_parent_dir = || {
+                        save_path
+                            .parent()
+                            .map(|parent| fs::create_dir_all(parent).is_ok())
+                            .unwrap_or(true)
+                    };
+                    if slot_cart.rom_modified()
+                        && create_parent_dir()
+                        && fs::write(save_path, &slot_cart.rom()[..]).is_ok()
+                    {
+                        slot_cart.mark_rom_flushed();
+                    }
+                    if slot_cart.ram_modified()
+                        && !slot_cart.ram().is_empty()
+                        && create_parent_dir()
+                        && fs::write(save_path, &slot_cart.ram()[..]).is_ok()
+                    {
+                        slot_cart.mark_ram_flushed();
+                    }
+                }
+            }
         };
     }
 
     #[cfg(feature = "debug-views")]
     let mut debug_views = debug_views::EmuState::new();
 
+    let mut pressed_keys = [Keys::empty(); input::PLAYERS];
+
+    let mut rewind_history = if config.rewind_enabled {
+        Some(rewind::History::new(
+            config.rewind_interval,
+            config.rewind_memory_budget,
+        ))
+    } else {
+        None
+    };
+    let mut rewinding = false;
+
+    // States from before a reset or a jump elsewhere can't be rewound to
+    macro_rules! clear_rewind_history {
+        () => {
+            if let Some(rewind_history) = &mut rewind_history {
+                rewind_history.clear();
+            }
+        };
+    }
+
+    // The recorded movie and its path
+    let mut movie_recorder: Option<(movie::Recorder, PathBuf)> = None;
+    let mut movie_playback: Option<movie::Playback> = None;
+    // The save RAM contents, whether they were flushed and the RTC state from before movie playback
+    // replaced them
+    let mut pre_movie_save: Option<(Vec<u8>, bool, Option<Vec<u8>>)> = None;
+
+    // Movies run with their own save RAM and RTC, which shouldn't end up in the save files
+    macro_rules! flush_saves {
+        () => {
+            if movie_recorder.is_none() && movie_playback.is_none() {
+                if let Some(save_path) = &cur_save_path {
+                    save!(save_path);
+                }
+                save_slot_carts!();
+            }
+        };
+    }
+
+    macro_rules! hard_reset {
+        () => {
+            flush_saves!();
+            // The save RAM and the RTC are battery-backed, so they should keep their contents
+            // across power cycles; the same host time is used to save and restore the RTC's state
+            // so that it doesn't skip ahead
+            let ram = emu.cart.ram()[..].to_vec();
+            let ram_modified = emu.cart.ram_modified();
+            let now = unix_time();
+            let rtc_data = emu.cart.rtc_data(now);
+            let cheat_codes = emu.cheats.codes().to_vec();
+
+            emu = Emu::new(
+                config.model,
+                cart.clone(),
+                match &audio_tx_data {
+                    Some(data) => Box::new(audio::Sender::new(data, config.sync_to_audio.value)),
+                    None => Box::new(DummyAudioBackend),
+                },
+                emu.apu.dsp.sample_chunk_len,
+                #[cfg(feature = "log")]
+                &logger,
+            );
+            emu.cart.modify_ram(|new_ram| new_ram.copy_from_slice(&ram));
+            if !ram_modified {
+                emu.cart.mark_ram_flushed();
+            }
+            if let Some(rtc_data) = rtc_data {
+                emu.cart.load_rtc_data(&rtc_data, now);
+            }
+            emu.msu1 = msu1_files.clone().map(|files| Msu1::new(Box::new(files)));
+            emu.cheats.set_codes(cheat_codes);
+            connect_controller_devices(&mut emu, connected_devices);
+            update_joypad_keys(&mut emu, &pressed_keys);
+            clear_rewind_history!();
+        };
+    }
+
+    macro_rules! connect_devices {
+        ($devices: expr) => {
+            for (port, (device, new_device)) in
+                connected_devices.iter_mut().zip($devices).enumerate()
+            {
+                if *device != new_device {
+                    *device = new_device;
+                    emu.controllers
+                        .connect_device(port, create_controller_device(new_device));
+                }
+            }
+        };
+    }
+
+    // Restores the save RAM, RTC, devices and cheats that were replaced or locked by a movie
+    macro_rules! restore_movie_setup {
+        () => {
+            if let Some((ram, ram_modified, rtc_data)) = pre_movie_save.take() {
+                emu.cart.modify_ram(|cur_ram| cur_ram.copy_from_slice(&ram));
+                if !ram_modified {
+                    emu.cart.mark_ram_flushed();
+                }
+                if let Some(rtc_data) = rtc_data {
+                    emu.cart.load_rtc_data(&rtc_data, unix_time());
+                }
+            }
+            connect_devices!(controller_devices);
+            emu.cheats.set_codes(cheats.clone());
+            update_joypad_keys(&mut emu, &pressed_keys);
+        };
+    }
+
+    macro_rules! finish_movie_recording {
+        () => {
+            if let Some((recorder, path)) = movie_recorder.take() {
+                if let Err(err) = fs::write(&path, recorder.finish().save()) {
+                    error!("Couldn't save movie", "Couldn't write movie file: {}", err);
+                }
+            }
+        };
+    }
+
     'outer: loop {
         for message in message_rx.try_iter() {
             match message {
                 Message::UpdateInput(changes) => {
-                    if let Some(joypad) = emu.controllers.devices[0]
-                        .as_any()
-                        .downcast_mut::<ness_core::controllers::joypad::Joypad>()
-                    {
-                        joypad.modify_keys(changes.pressed, changes.released);
+                    for (i, keys) in pressed_keys.iter_mut().enumerate() {
+                        *keys = (*keys | changes.pressed[i]) & !changes.released[i];
+                    }
+                    // During movie playback, the keys only come from the movie
+                    if movie_playback.is_none() {
+                        update_joypad_keys(&mut emu, &pressed_keys);
+                    }
+                }
+
+                Message::UpdateMouse(changes) => {
+                    if movie_playback.is_none() {
+                        for device in &mut emu.controllers.devices {
+                            if let Some(mouse) = device.as_any().downcast_mut::<Mouse>() {
+                                mouse.move_by(changes.delta[0], changes.delta[1]);
+                                mouse.left_pressed = changes.left_pressed;
+                                mouse.right_pressed = changes.right_pressed;
+                            }
+                        }
+                    }
+                }
+                Message::UpdateLightGun(light_gun) => {
+                    if movie_playback.is_none() {
+                        for device in &mut emu.controllers.devices {
+                            let device = device.as_any();
+                            if let Some(super_scope) = device.downcast_mut::<SuperScope>() {
+                                super_scope.aim = light_gun.aim;
+                                super_scope.trigger_pressed = light_gun.trigger_pressed;
+                                super_scope.cursor_pressed = light_gun.cursor_pressed;
+                                super_scope.pause_pressed = light_gun.pause_pressed;
+                            } else if let Some(justifier) = device.downcast_mut::<Justifier>() {
+                                justifier.aim = light_gun.aim;
+                                justifier.trigger_pressed = light_gun.trigger_pressed;
+                                justifier.start_pressed = light_gun.cursor_pressed;
+                            }
+                        }
                     }
                 }
 
@@ -110,33 +382,161 @@ pub(super) fn main(
                     }
                 }
 
+                // While a movie is active, device and cheat changes only take effect once it stops
+                Message::UpdateControllerDevices(new_devices) => {
+                    controller_devices = new_devices;
+                    if movie_recorder.is_none() && movie_playback.is_none() {
+                        connect_devices!(controller_devices);
+                        update_joypad_keys(&mut emu, &pressed_keys);
+                    }
+                }
+
+                Message::UpdateCheats(new_cheats) => {
+                    cheats = new_cheats;
+                    if movie_recorder.is_none() && movie_playback.is_none() {
+                        emu.cheats.set_codes(cheats.clone());
+                    }
+                }
+
+                Message::UpdateRewind(enabled, interval, memory_budget) => {
+                    rewind_history = if enabled {
+                        Some(rewind::History::new(interval, memory_budget))
+                    } else {
+                        None
+                    };
+                    if rewinding {
+                        rewinding = false;
+                        update_joypad_keys(&mut emu, &pressed_keys);
+                    }
+                }
+
+                Message::Rewind(new_rewinding) => {
+                    // Rewinding would break the movie's continuity
+                    rewinding = new_rewinding
+                        && rewind_history.is_some()
+                        && movie_recorder.is_none()
+                        && movie_playback.is_none();
+                    // Restored states contain the joypad keys that were pressed at the time
+                    if !rewinding {
+                        update_joypad_keys(&mut emu, &pressed_keys);
+                    }
+                }
+
+                Message::RecordMovie(path, from_power_on) => {
+                    finish_movie_recording!();
+                    movie_playback = None;
+                    restore_movie_setup!();
+                    rewinding = false;
+                    if from_power_on {
+                        hard_reset!();
+                    }
+                    movie_recorder = Some((
+                        movie::Recorder::new(&mut emu, config.model, rom_sha256, from_power_on),
+                        path,
+                    ));
+                }
+
+                Message::PlayMovie(path) => {
+                    let movie = match fs::read(&path) {
+                        Ok(data) => match Movie::load(&data) {
+                            Ok(movie) => movie,
+                            Err(err) => {
+                                error!("Couldn't load movie", "{}.", err);
+                                continue;
+                            }
+                        },
+                        Err(err) => {
+                            error!("Couldn't load movie", "Couldn't read movie file: {}", err);
+                            continue;
+                        }
+                    };
+                    if movie.model != config.model || movie.rom_sha256 != rom_sha256 {
+                        error!(
+                            "Couldn't load movie",
+                            "The movie was recorded for a different game or console model."
+                        );
+                        continue;
+                    }
+                    finish_movie_recording!();
+                    movie_playback = None;
+                    restore_movie_setup!();
+                    rewinding = false;
+                    clear_rewind_history!();
+                    if matches!(movie.start, StartCondition::PowerOn { .. }) {
+                        hard_reset!();
+                    }
+                    pre_movie_save = Some((
+                        emu.cart.ram()[..].to_vec(),
+                        emu.cart.ram_modified(),
+                        emu.cart.rtc_data(unix_time()),
+                    ));
+                    // Savestates can only be loaded with the same devices connected
+                    connect_devices!(movie.devices.map(controller_device));
+                    match movie::Playback::new(movie, &mut emu) {
+                        Ok(playback) => movie_playback = Some(playback),
+                        Err(err) => {
+                            restore_movie_setup!();
+                            error!("Couldn't load movie", "{}.", err);
+                        }
+                    }
+                }
+
+                Message::StopMovie => {
+                    finish_movie_recording!();
+                    movie_playback = None;
+                    restore_movie_setup!();
+                }
+
+                Message::SaveState(path) => {
+                    if let Err(err) = fs::write(&path, emu.save_state()) {
+                        error!(
+                            "Couldn't save state",
+                            "Couldn't write save state file: {}", err
+                        );
+                    }
+                }
+
+                Message::LoadState(path) => match fs::read(&path) {
+                    Ok(state) => {
+                        if let Err(err) = emu.load_state(&state) {
+                            error!("Couldn't load state", "{}.", err);
+                        } else {
+                            clear_rewind_history!();
+                            if movie_playback.is_none() {
+                                // Keep the keys that are currently held instead of the ones that
+                                // were held when the state was saved
+                                update_joypad_keys(&mut emu, &pressed_keys);
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        error!(
+                            "Couldn't load state",
+                            "Couldn't read save state file: {}", err
+                        );
+                    }
+                },
+
                 #[cfg(feature = "debug-views")]
                 Message::DebugViews(message) => {
                     debug_views.handle_message(message);
                 }
 
+                // During movie playback, resets only come from the movie
+                Message::SoftReset | Message::HardReset if movie_playback.is_some() => {}
+
                 Message::SoftReset => {
                     emu.soft_reset();
+                    if let Some((recorder, _)) = &mut movie_recorder {
+                        recorder.record_reset(movie::Reset::Soft);
+                    }
                 }
 
                 Message::HardReset => {
-                    if let Some(save_path) = &cur_save_path {
-                        save!(save_path);
+                    hard_reset!();
+                    if let Some((recorder, _)) = &mut movie_recorder {
+                        recorder.record_reset(movie::Reset::Hard);
                     }
-
-                    emu = Emu::new(
-                        config.model,
-                        cart.clone(),
-                        match &audio_tx_data {
-                            Some(data) => {
-                                Box::new(audio::Sender::new(data, config.sync_to_audio.value))
-                            }
-                            None => Box::new(DummyAudioBackend),
-                        },
-                        emu.apu.dsp.sample_chunk_len,
-                        #[cfg(feature = "log")]
-                        &logger,
-                    );
                 }
 
                 Message::Stop => {
@@ -150,7 +550,54 @@ pub(super) fn main(
         let frame = frame_tx.start();
 
         if playing {
-            emu.run_frame();
+            if rewinding {
+                if let Some(state) = rewind_history.as_mut().and_then(rewind::History::pop) {
+                    if emu.load_state(&state).is_ok() {
+                        // Run a frame to display the restored state, with audio muted
+                        let audio_backend =
+                            mem::replace(&mut emu.apu.dsp.backend, Box::new(DummyAudioBackend));
+                        emu.run_frame();
+                        emu.apu.dsp.backend = audio_backend;
+                    }
+                }
+            } else {
+                if let Some(playback) = &mut movie_playback {
+                    match playback.next_reset() {
+                        Some(movie::Reset::Soft) => emu.soft_reset(),
+                        Some(movie::Reset::Hard) => {
+                            hard_reset!();
+                        }
+                        None => {}
+                    }
+                    playback.apply_input(&mut emu);
+                }
+                if let Some((recorder, _)) = &mut movie_recorder {
+                    recorder.start_frame(&mut emu);
+                }
+
+                emu.run_frame();
+
+                if let Some((recorder, _)) = &mut movie_recorder {
+                    recorder.frame_finished(&emu);
+                }
+                if let Some(playback) = &mut movie_playback {
+                    if let Some(frame) = playback.frame_finished(&emu) {
+                        error!(
+                            "Movie desync",
+                            "Movie playback desynced at frame {}: the emulator's state doesn't \
+                             match the recorded one.",
+                            frame
+                        );
+                    }
+                    if playback.finished() {
+                        movie_playback = None;
+                        restore_movie_setup!();
+                    }
+                }
+                if let Some(rewind_history) = &mut rewind_history {
+                    rewind_history.frame_finished(&emu);
+                }
+            }
         }
         frame.fb.0.copy_from_slice(&emu.ppu.framebuffer.0);
         frame.view_height = emu.ppu.view_height();
@@ -172,12 +619,10 @@ pub(super) fn main(
 
         frame_tx.finish();
 
-        if let Some(save_path) = &cur_save_path {
-            let now = Instant::now();
-            if now - last_save_flush_time >= *shared_state.autosave_interval.read() {
-                last_save_flush_time = now;
-                save!(save_path);
-            }
+        let now = Instant::now();
+        if now - last_save_flush_time >= *shared_state.autosave_interval.read() {
+            last_save_flush_time = now;
+            flush_saves!();
         }
 
         if !playing || shared_state.limit_framerate.load(Ordering::Relaxed) {
@@ -199,9 +644,10 @@ pub(super) fn main(
         }
     }
 
-    if let Some(save_path) = &cur_save_path {
-        save!(save_path);
-    }
+    finish_movie_recording!();
+    movie_playback = None;
+    restore_movie_setup!();
+    flush_saves!();
 
     frame_tx
 }
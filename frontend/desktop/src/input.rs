@@ -6,12 +6,34 @@ pub use keymap::Keymap;
 
 use super::config::Config;
 use ness_core::controllers::joypad::Keys as EmuKeys;
-use winit::event::{ElementState, Event, ScanCode, VirtualKeyCode, WindowEvent};
+use winit::{
+    event::{DeviceEvent, ElementState, Event, MouseButton, ScanCode, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+pub const PLAYERS: usize = 5;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Changes {
-    pub pressed: EmuKeys,
-    pub released: EmuKeys,
+    pub pressed: [EmuKeys; PLAYERS],
+    pub released: [EmuKeys; PLAYERS],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MouseChanges {
+    pub delta: [i32; 2],
+    pub left_pressed: bool,
+    pub right_pressed: bool,
+}
+
+/// The state of a light gun, aimed using the host cursor's position over the emulated screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LightGunState {
+    pub aim: Option<[u16; 2]>,
+    pub trigger_pressed: bool,
+    /// Mapped to the Super Scope's cursor button and the Justifier's start button.
+    pub cursor_pressed: bool,
+    pub pause_pressed: bool,
 }
 
 type PressedKey = (Option<VirtualKeyCode>, ScanCode);
@@ -19,7 +41,11 @@ type PressedKey = (Option<VirtualKeyCode>, ScanCode);
 pub struct State {
     pressed_keys: Vec<PressedKey>,
     pub keymap: Config<Keymap>,
-    pressed_emu_keys: EmuKeys,
+    pressed_emu_keys: [EmuKeys; PLAYERS],
+    mouse_captured: bool,
+    mouse_delta: [f64; 2],
+    mouse_buttons: [bool; 2],
+    prev_mouse_buttons: [bool; 2],
 }
 
 impl State {
@@ -27,10 +53,37 @@ impl State {
         State {
             pressed_keys: vec![],
             keymap,
-            pressed_emu_keys: EmuKeys::empty(),
+            pressed_emu_keys: [EmuKeys::empty(); PLAYERS],
+            mouse_captured: false,
+            mouse_delta: [0.0; 2],
+            mouse_buttons: [false; 2],
+            prev_mouse_buttons: [false; 2],
         }
     }
 
+    #[inline]
+    pub fn mouse_captured(&self) -> bool {
+        self.mouse_captured
+    }
+
+    /// Grabs and hides the host cursor so that its motion can be forwarded to emulated mice, or
+    /// releases it.
+    pub fn set_mouse_captured(&mut self, window: &Window, value: bool) {
+        if value == self.mouse_captured {
+            return;
+        }
+        if value && window.set_cursor_grab(true).is_err() {
+            return;
+        }
+        if !value {
+            let _ = window.set_cursor_grab(false);
+            self.mouse_delta = [0.0; 2];
+            self.mouse_buttons = [false; 2];
+        }
+        window.set_cursor_visible(!value);
+        self.mouse_captured = value;
+    }
+
     pub fn process_event<T: 'static>(&mut self, event: &Event<T>, catch_new: bool) {
         if let Event::WindowEvent { event, .. } = event {
             match event {
@@ -48,23 +101,76 @@ impl State {
                         self.pressed_keys.push(key);
                     }
                 }
-                WindowEvent::Focused(false) => self.pressed_keys.clear(),
+                WindowEvent::MouseInput { state, button, .. } if self.mouse_captured => {
+                    let i = match button {
+                        MouseButton::Left => 0,
+                        MouseButton::Right => 1,
+                        _ => return,
+                    };
+                    self.mouse_buttons[i] = *state == ElementState::Pressed;
+                }
+                WindowEvent::Focused(false) => {
+                    self.pressed_keys.clear();
+                    self.mouse_buttons = [false; 2];
+                }
                 _ => {}
             }
+        } else if let Event::DeviceEvent {
+            event: DeviceEvent::MouseMotion { delta },
+            ..
+        } = event
+        {
+            if self.mouse_captured {
+                self.mouse_delta[0] += delta.0;
+                self.mouse_delta[1] += delta.1;
+            }
         }
     }
 
+    /// Returns whether the given trigger (not mapped to any emulated key) is currently activated.
+    pub fn trigger_activated(&self, trigger: &trigger::Trigger) -> bool {
+        trigger.activated(&self.pressed_keys)
+    }
+
+    pub fn drain_mouse_changes(&mut self) -> Option<MouseChanges> {
+        let delta = [
+            self.mouse_delta[0].trunc() as i32,
+            self.mouse_delta[1].trunc() as i32,
+        ];
+        if delta == [0; 2] && self.mouse_buttons == self.prev_mouse_buttons {
+            return None;
+        }
+        self.mouse_delta[0] -= delta[0] as f64;
+        self.mouse_delta[1] -= delta[1] as f64;
+        self.prev_mouse_buttons = self.mouse_buttons;
+        Some(MouseChanges {
+            delta,
+            left_pressed: self.mouse_buttons[0],
+            right_pressed: self.mouse_buttons[1],
+        })
+    }
+
     pub fn drain_changes(&mut self) -> Option<Changes> {
-        let mut new_pressed_emu_keys = EmuKeys::empty();
-        for (&emu_key, trigger) in &self.keymap.contents.0 {
-            new_pressed_emu_keys.set(emu_key, trigger.activated(&self.pressed_keys));
+        let mut new_pressed_emu_keys = [EmuKeys::empty(); PLAYERS];
+        for (new_pressed_emu_keys, keymap) in
+            new_pressed_emu_keys.iter_mut().zip(&self.keymap.contents.0)
+        {
+            for (&emu_key, trigger) in &keymap.0 {
+                new_pressed_emu_keys.set(emu_key, trigger.activated(&self.pressed_keys));
+            }
         }
 
         if new_pressed_emu_keys != self.pressed_emu_keys {
-            let pressed = new_pressed_emu_keys & !self.pressed_emu_keys;
-            let released = self.pressed_emu_keys & !new_pressed_emu_keys;
+            let mut changes = Changes {
+                pressed: [EmuKeys::empty(); PLAYERS],
+                released: [EmuKeys::empty(); PLAYERS],
+            };
+            for i in 0..PLAYERS {
+                changes.pressed[i] = new_pressed_emu_keys[i] & !self.pressed_emu_keys[i];
+                changes.released[i] = self.pressed_emu_keys[i] & !new_pressed_emu_keys[i];
+            }
             self.pressed_emu_keys = new_pressed_emu_keys;
-            Some(Changes { pressed, released })
+            Some(changes)
         } else {
             None
         }
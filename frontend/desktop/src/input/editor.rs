@@ -1,10 +1,11 @@
-use super::{trigger::Trigger, PressedKey, State as InputState};
+use super::{trigger::Trigger, PressedKey, State as InputState, PLAYERS};
 use imgui::{StyleColor, Ui, Window};
 use ness_core::controllers::joypad::Keys;
 use winit::event::{ElementState, Event, WindowEvent};
 
 #[derive(Default)]
 pub struct Editor {
+    player: usize,
     current_key: Option<Keys>,
     pressed_keys: Vec<PressedKey>,
 }
@@ -24,6 +25,8 @@ static KEYS: &[(Keys, &str)] = &[
     (Keys::DOWN, "Down"),
 ];
 
+static PLAYER_NAMES: [&str; PLAYERS] = ["Player 1", "Player 2", "Player 3", "Player 4", "Player 5"];
+
 impl Editor {
     pub fn new() -> Self {
         Self::default()
@@ -35,6 +38,11 @@ impl Editor {
                 self.pressed_keys.clear();
             }
 
+            if ui.combo_simple_string("Player", &mut self.player, &PLAYER_NAMES) {
+                self.current_key = None;
+            }
+
+            let keymap = &input_state.keymap.contents.0[self.player].0;
             ui.columns(2, "input", true);
             for &(key, name) in KEYS {
                 let id = format!("{}:", name);
@@ -47,7 +55,10 @@ impl Editor {
                         StyleColor::Button,
                         ui.style_color(StyleColor::ButtonActive),
                     ))
-                } else if input_state.keymap.contents.0[&key].activated(&self.pressed_keys) {
+                } else if keymap
+                    .get(&key)
+                    .map_or(false, |trigger| trigger.activated(&self.pressed_keys))
+                {
                     Some(ui.push_style_color(
                         StyleColor::Button,
                         ui.style_color(StyleColor::ButtonHovered),
@@ -56,7 +67,10 @@ impl Editor {
                     None
                 };
 
-                if ui.button(&input_state.keymap.contents.0[&key].to_string()) {
+                let trigger_str = keymap
+                    .get(&key)
+                    .map_or_else(|| "-".to_string(), |trigger| trigger.to_string());
+                if ui.button(&trigger_str) {
                     ui.set_keyboard_focus_here();
                     self.current_key = Some(key);
                 }
@@ -93,14 +107,11 @@ impl Editor {
             }
 
             if let Some(current_key) = self.current_key.take() {
+                let keymap = &mut input_state.keymap.contents.0[self.player].0;
                 if let Some(key_code) = input.virtual_keycode {
-                    input_state
-                        .keymap
-                        .contents
-                        .0
-                        .insert(current_key, Trigger::KeyCode(key_code));
+                    keymap.insert(current_key, Trigger::KeyCode(key_code));
                 } else {
-                    input_state.keymap.contents.0.remove(&current_key);
+                    keymap.remove(&current_key);
                 }
             }
         }
@@ -1,10 +1,13 @@
-use super::trigger::{self, Trigger};
+use super::{
+    trigger::{self, Trigger},
+    PLAYERS,
+};
 use core::fmt;
 use fxhash::FxHashMap;
 use ness_core::controllers::joypad::Keys;
 use serde::{
-    de::{MapAccess, Visitor},
-    ser::SerializeMap,
+    de::{value::MapAccessDeserializer, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeSeq},
     Deserialize, Deserializer, Serialize, Serializer,
 };
 use winit::event::VirtualKeyCode;
@@ -24,12 +27,16 @@ static KEY_IDENTS: &[(Keys, &str)] = &[
     (Keys::DOWN, "down"),
 ];
 
+#[derive(Clone, Debug, Default)]
+pub struct PlayerKeymap(pub FxHashMap<Keys, Trigger>);
+
 #[derive(Clone, Debug)]
-pub struct Keymap(pub FxHashMap<Keys, Trigger>);
+pub struct Keymap(pub [PlayerKeymap; PLAYERS]);
 
 impl Default for Keymap {
     fn default() -> Self {
-        Keymap(
+        let mut players: [PlayerKeymap; PLAYERS] = Default::default();
+        players[0] = PlayerKeymap(
             [
                 (Keys::A, Trigger::KeyCode(VirtualKeyCode::X)),
                 (Keys::B, Trigger::KeyCode(VirtualKeyCode::Z)),
@@ -55,11 +62,12 @@ impl Default for Keymap {
             ]
             .into_iter()
             .collect(),
-        )
+        );
+        Keymap(players)
     }
 }
 
-impl Serialize for Keymap {
+impl Serialize for PlayerKeymap {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let mut map = serializer.serialize_map(Some(self.0.len()))?;
         for (key, value) in &self.0 {
@@ -71,12 +79,12 @@ impl Serialize for Keymap {
     }
 }
 
-impl<'de> Deserialize<'de> for Keymap {
+impl<'de> Deserialize<'de> for PlayerKeymap {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         struct MapVisitor;
 
         impl<'de> Visitor<'de> for MapVisitor {
-            type Value = Keymap;
+            type Value = PlayerKeymap;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
                 formatter.write_str("a map of key identifiers to triggers")
@@ -94,10 +102,55 @@ impl<'de> Deserialize<'de> for Keymap {
                     }
                 }
 
-                Ok(Keymap(map))
+                Ok(PlayerKeymap(map))
             }
         }
 
         deserializer.deserialize_map(MapVisitor)
     }
 }
+
+impl Serialize for Keymap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(PLAYERS))?;
+        for player in &self.0 {
+            seq.serialize_element(player)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Keymap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeymapVisitor;
+
+        impl<'de> Visitor<'de> for KeymapVisitor {
+            type Value = Keymap;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a list of per-player keymaps")
+            }
+
+            // Keymaps used to only contain the first player's key identifiers
+            fn visit_map<M: MapAccess<'de>>(self, access: M) -> Result<Self::Value, M::Error> {
+                let mut players: [PlayerKeymap; PLAYERS] = Default::default();
+                players[0] = PlayerKeymap::deserialize(MapAccessDeserializer::new(access))?;
+                Ok(Keymap(players))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+                let mut players: [PlayerKeymap; PLAYERS] = Default::default();
+                let mut i = 0;
+                while let Some(player) = access.next_element::<PlayerKeymap>()? {
+                    if i < PLAYERS {
+                        players[i] = player;
+                    }
+                    i += 1;
+                }
+                Ok(Keymap(players))
+            }
+        }
+
+        deserializer.deserialize_any(KeymapVisitor)
+    }
+}
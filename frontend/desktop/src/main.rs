@@ -4,10 +4,13 @@
 mod utils;
 
 mod audio;
+mod cheats;
 mod config;
 #[cfg(feature = "debug-views")]
 mod debug_views;
 mod input;
+mod msu1;
+mod rewind;
 mod triple_buffer;
 
 mod emu;
@@ -0,0 +1,42 @@
+use ness_core::msu1::{self, Stream};
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+/// Provides MSU-1 files stored next to the ROM as `<rom>.msu` and `<rom>-<track>.pcm`.
+#[derive(Clone)]
+pub struct Files {
+    base_path: PathBuf,
+}
+
+impl Files {
+    /// Returns the MSU-1 files for the given ROM, if it has a data file next to it.
+    pub fn for_rom(rom_path: &Path) -> Option<Self> {
+        let base_path = rom_path.with_extension("");
+        if base_path.with_extension("msu").is_file() {
+            Some(Files { base_path })
+        } else {
+            None
+        }
+    }
+
+    fn open(&self, suffix: &str) -> Option<Box<dyn Stream>> {
+        let mut path = self.base_path.clone().into_os_string();
+        path.push(suffix);
+        File::open(path)
+            .ok()
+            .map(|file| Box::new(BufReader::new(file)) as Box<dyn Stream>)
+    }
+}
+
+impl msu1::Files for Files {
+    fn open_data(&mut self) -> Option<Box<dyn Stream>> {
+        self.open(".msu")
+    }
+
+    fn open_track(&mut self, track: u16) -> Option<Box<dyn Stream>> {
+        self.open(&format!("-{}.pcm", track))
+    }
+}
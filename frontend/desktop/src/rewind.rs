@@ -0,0 +1,149 @@
+use ness_core::emu::Emu;
+use std::collections::VecDeque;
+
+fn write_var_int(output: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        output.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+fn read_var_int(input: &[u8], pos: &mut usize) -> usize {
+    let mut result = 0;
+    let mut shift = 0;
+    loop {
+        let byte = input[*pos];
+        *pos += 1;
+        result |= (byte as usize & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return result;
+        }
+        shift += 7;
+    }
+}
+
+// Literal runs are only interrupted by at least this many unchanged bytes, as shorter gaps take
+// more space to encode than to copy
+const MIN_ZERO_RUN_LEN: usize = 4;
+
+/// Encodes the differences needed to reconstruct `old` from `new`, as alternating runs of
+/// unchanged bytes and of bytes XORed with `new`'s.
+fn encode_delta(new: &[u8], old: &[u8]) -> Vec<u8> {
+    let xor = |i: usize| old[i] ^ new.get(i).copied().unwrap_or(0);
+    let mut result = Vec::new();
+    result.extend_from_slice(&(old.len() as u32).to_le_bytes());
+    let mut pos = 0;
+    while pos < old.len() {
+        let zeros_start = pos;
+        while pos < old.len() && xor(pos) == 0 {
+            pos += 1;
+        }
+        write_var_int(&mut result, pos - zeros_start);
+
+        let literal_start = pos;
+        let mut zero_run_len = 0;
+        while pos < old.len() && zero_run_len < MIN_ZERO_RUN_LEN {
+            if xor(pos) == 0 {
+                zero_run_len += 1;
+            } else {
+                zero_run_len = 0;
+            }
+            pos += 1;
+        }
+        pos -= zero_run_len;
+        write_var_int(&mut result, pos - literal_start);
+        result.extend((literal_start..pos).map(xor));
+    }
+    result
+}
+
+fn decode_delta(new: &[u8], delta: &[u8]) -> Vec<u8> {
+    let old_len = u32::from_le_bytes(delta[..4].try_into().unwrap()) as usize;
+    let mut result = new[..old_len.min(new.len())].to_vec();
+    result.resize(old_len, 0);
+    let mut delta_pos = 4;
+    let mut pos = 0;
+    while pos < old_len {
+        pos += read_var_int(delta, &mut delta_pos);
+        let literal_len = read_var_int(delta, &mut delta_pos);
+        for (byte, xor) in result[pos..pos + literal_len]
+            .iter_mut()
+            .zip(&delta[delta_pos..delta_pos + literal_len])
+        {
+            *byte ^= xor;
+        }
+        pos += literal_len;
+        delta_pos += literal_len;
+    }
+    result
+}
+
+/// A history of savestates taken every few frames, to step back through when rewinding.
+///
+/// Only the most recent state is stored in full, while each older one is delta-compressed against
+/// its successor; the oldest states are discarded once the history exceeds its memory budget.
+pub struct History {
+    interval: u32,
+    memory_budget: usize,
+    frames_since_snapshot: u32,
+    latest: Option<Vec<u8>>,
+    deltas: VecDeque<Vec<u8>>,
+    deltas_size: usize,
+}
+
+impl History {
+    pub fn new(interval: u32, memory_budget: usize) -> Self {
+        History {
+            interval: interval.max(1),
+            memory_budget,
+            frames_since_snapshot: 0,
+            latest: None,
+            deltas: VecDeque::new(),
+            deltas_size: 0,
+        }
+    }
+
+    /// Notifies the history that a frame was emulated, taking a snapshot if needed.
+    pub fn frame_finished(&mut self, emu: &Emu) {
+        self.frames_since_snapshot += 1;
+        if self.frames_since_snapshot >= self.interval {
+            self.frames_since_snapshot = 0;
+            self.push(emu.save_state());
+        }
+    }
+
+    fn push(&mut self, state: Vec<u8>) {
+        if let Some(prev) = self.latest.take() {
+            let delta = encode_delta(&state, &prev);
+            self.deltas_size += delta.len();
+            self.deltas.push_back(delta);
+        }
+        while self.deltas_size + state.len() > self.memory_budget {
+            match self.deltas.pop_front() {
+                Some(delta) => self.deltas_size -= delta.len(),
+                None => break,
+            }
+        }
+        self.latest = Some(state);
+    }
+
+    /// Discards all states, i.e. after the emulation jumped to an unrelated point.
+    pub fn clear(&mut self) {
+        self.frames_since_snapshot = 0;
+        self.latest = None;
+        self.deltas.clear();
+        self.deltas_size = 0;
+    }
+
+    /// Removes the most recent state from the history and returns it.
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        let latest = self.latest.take()?;
+        if let Some(delta) = self.deltas.pop_back() {
+            self.deltas_size -= delta.len();
+            self.latest = Some(decode_delta(&latest, &delta));
+        }
+        self.frames_since_snapshot = 0;
+        Some(latest)
+    }
+}
@@ -7,10 +7,10 @@ pub mod window;
 #[cfg(feature = "debug-views")]
 use super::debug_views;
 use super::{
-    audio,
+    audio, cheats,
     config::{self, Config, LaunchConfig, LoggingKind},
-    emu, input, triple_buffer,
-    utils::{config_base, scale_to_fit},
+    emu, input, msu1, triple_buffer,
+    utils::{config_base, rtc_path, scale_to_fit, unix_time},
     FrameData,
 };
 use ness_core::{
@@ -24,8 +24,9 @@ use rfd::FileDialog;
 use std::time::SystemTime;
 use std::{
     env,
+    ffi::OsString,
     fs::{self, File},
-    io::{self, Read, Seek, SeekFrom},
+    io::{self, Read},
     num::NonZeroU32,
     path::Path,
     sync::{
@@ -86,6 +87,10 @@ struct UiState {
     screen_focused: bool,
     input: input::State,
     input_editor: Option<input::Editor>,
+    rewinding: bool,
+    cheat_manager: Option<cheats::Manager>,
+    controller_devices: config::RuntimeModifiable<[config::ControllerDevice; 2]>,
+    light_gun: input::LightGunState,
 
     audio_channel: Option<audio::Channel>,
     audio_volume: f32,
@@ -124,6 +129,98 @@ struct UiState {
 }
 
 static ALLOWED_ROM_EXTENSIONS: &[&str] = &["sfc", "smc", "bin"];
+static SUFAMI_TURBO_ROM_EXTENSIONS: &[&str] = &["st", "sfc", "smc", "bin"];
+static BS_MEMORY_ROM_EXTENSIONS: &[&str] = &["bs", "sfc", "smc", "bin"];
+static SAVE_STATE_EXTENSIONS: &[&str] = &["state"];
+static MOVIE_EXTENSIONS: &[&str] = &["movie"];
+static PATCH_EXTENSIONS: &[&str] = &["bps", "ups", "ips"];
+
+/// Reads a ROM file, removing its copier header and de-interleaving it if needed.
+fn read_rom(path: &Path) -> io::Result<BoxedByteSlice> {
+    Ok(cart::rom::load(&fs::read(path)?).rom)
+}
+
+/// Reads a save RAM file, returning `None` if it doesn't exist or couldn't be read.
+fn read_save_ram(path: &Path) -> Option<BoxedByteSlice> {
+    match File::open(path) {
+        Ok(mut ram_file) => {
+            let ram_len = ram_file
+                .metadata()
+                .expect("Couldn't get save RAM file metadata")
+                .len()
+                .next_power_of_two() as usize;
+            let mut ram = BoxedByteSlice::new_zeroed(ram_len);
+            ram_file
+                .read_exact(&mut ram[..])
+                .expect("Couldn't read save RAM file");
+            Some(ram)
+        }
+        Err(err) => match err.kind() {
+            io::ErrorKind::NotFound => None,
+            err => {
+                error!("Couldn't read save RAM file", "{:?}.", err);
+                None
+            }
+        },
+    }
+}
+
+/// The files of a cartridge inserted into one of the board's slots; for BS Memory packs, the save
+/// file contains the rewritten flash memory contents.
+struct SlotCartFiles {
+    ty: cart::info::SlotType,
+    rom: BoxedByteSlice,
+    name: OsString,
+}
+
+/// Loads coprocessor firmware from the given directory, either from separate program and data ROM
+/// files (only the latter of which is used by the Cx4) or from a single file containing the
+/// uPD7725, uPD96050 or ST018 program ROM followed by its data ROM.
+fn load_firmware(dir: &Path, name: &str) -> cart::Firmware {
+    let read = |file_name: String| fs::read(dir.join(file_name)).ok();
+    let to_boxed = |bytes: &[u8]| {
+        let mut result = BoxedByteSlice::new_zeroed(bytes.len());
+        result[..].copy_from_slice(bytes);
+        result
+    };
+    let program_rom = read(format!("{}.program.rom", name));
+    let data_rom = read(format!("{}.data.rom", name));
+    if program_rom.is_some() || data_rom.is_some() {
+        return cart::Firmware {
+            program_rom: program_rom.as_deref().map(to_boxed),
+            data_rom: data_rom.as_deref().map(to_boxed),
+        };
+    }
+    let rom_sizes = [
+        (
+            cart::upd7725::Variant::Upd7725.program_rom_size(),
+            cart::upd7725::Variant::Upd7725.data_rom_size(),
+        ),
+        (
+            cart::upd7725::Variant::Upd96050.program_rom_size(),
+            cart::upd7725::Variant::Upd96050.data_rom_size(),
+        ),
+        #[cfg(feature = "st018")]
+        (cart::st018::PROGRAM_ROM_SIZE, cart::st018::DATA_ROM_SIZE),
+    ];
+    let program_rom_size = |len: usize| {
+        rom_sizes
+            .iter()
+            .find(|(program_rom_size, data_rom_size)| len == program_rom_size + data_rom_size)
+            .map(|(program_rom_size, _)| *program_rom_size)
+    };
+    match read(format!("{}.rom", name)) {
+        Some(combined) if program_rom_size(combined.len()).is_some() => {
+            let (program_rom, data_rom) =
+                combined.split_at(program_rom_size(combined.len()).unwrap());
+            cart::Firmware {
+                program_rom: Some(to_boxed(program_rom)),
+                data_rom: Some(to_boxed(data_rom)),
+            }
+        }
+        _ => cart::Firmware::default(),
+    }
+}
 
 impl UiState {
     fn send_message(&self, msg: emu::Message) {
@@ -139,31 +236,41 @@ impl UiState {
             return;
         }
 
-        let rom = {
-            let mut rom_file = File::open(path).expect("Couldn't load the specified ROM file");
-            let mut rom_len = rom_file
-                .metadata()
-                .expect("Couldn't get ROM file metadata")
-                .len() as usize;
-            if rom_len & 0x200 != 0 {
-                rom_len -= 0x200;
-                rom_file
-                    .seek(SeekFrom::Start(0x200))
-                    .expect("Couldn't seek in ROM file");
+        let mut rom = read_rom(path).expect("Couldn't load the specified ROM file");
+
+        // Patches stored next to the ROM with the same name are applied automatically, before the
+        // cart is looked up in the database
+        if let Some(patch_path) = PATCH_EXTENSIONS
+            .iter()
+            .map(|extension| path.with_extension(extension))
+            .find(|patch_path| patch_path.is_file())
+        {
+            let patch_result = fs::read(&patch_path)
+                .map_err(|err| err.to_string())
+                .and_then(|patch| {
+                    cart::patch::apply(&rom[..], &patch).map_err(|err| err.to_string())
+                });
+            match patch_result {
+                Ok(patched_rom) => rom = patched_rom,
+                Err(err) => {
+                    error!("Couldn't apply patch", "{}: {}.", patch_path.display(), err);
+                    return;
+                }
             }
-            let mut rom = BoxedByteSlice::new_zeroed(rom_len);
-            rom_file
-                .read_exact(&mut rom[..])
-                .expect("Couldn't read ROM file");
-            rom
-        };
+        }
 
-        let (cart_info, cart_header, cart_info_source) = cart::info::Info::new(
+        let (mut cart_info, cart_header, cart_info_source) = match cart::info::Info::new(
             rom.as_byte_slice(),
             self.cart_db
                 .as_ref()
                 .map(|db| (db, <sha2::Sha256 as sha2::Digest>::digest(&rom[..]).into())),
-        );
+        ) {
+            Ok(result) => result,
+            Err(err) => {
+                error!("Unsupported cartridge", "{}.", err);
+                return;
+            }
+        };
 
         match cart_info_source {
             cart::info::Source::Db => {}
@@ -177,6 +284,72 @@ impl UiState {
             }
         }
 
+        #[cfg(not(feature = "st018"))]
+        if cart_info.coprocessor == Some(cart::info::Coprocessor::St018) {
+            error!(
+                "Unsupported coprocessor",
+                "This game uses the ST018 coprocessor, support for which wasn't enabled in this \
+                 build; rebuild with the `st018` feature to play it."
+            );
+            return;
+        }
+
+        let firmware = if let Some(firmware_name) = &cart_info.firmware_name {
+            let firmware = load_firmware(path.parent().unwrap_or(Path::new(".")), firmware_name);
+            if firmware.program_rom.is_none() && firmware.data_rom.is_none() {
+                error!(
+                    "Missing coprocessor firmware",
+                    "Couldn't find the `{0}` firmware; place either `{0}.program.rom` and \
+                     `{0}.data.rom`, or a combined `{0}.rom`, next to the ROM file.",
+                    firmware_name
+                );
+                return;
+            }
+            firmware
+        } else {
+            cart::Firmware::default()
+        };
+
+        // The Sufami Turbo adaptor's mini-carts and BS Memory packs are separate ROM images, each
+        // with its own save file
+        let mut slot_cart_files = Vec::with_capacity(cart_info.slots.len());
+        for (i, slot) in cart_info.slots.iter().enumerate() {
+            let (title, filter_name, extensions) = match slot.ty {
+                cart::info::SlotType::SufamiTurbo => (
+                    format!(
+                        "Select the Sufami Turbo cart for slot {}",
+                        (b'A' + i as u8) as char
+                    ),
+                    "Sufami Turbo ROM file",
+                    SUFAMI_TURBO_ROM_EXTENSIONS,
+                ),
+                cart::info::SlotType::BsMemory => (
+                    "Select the BS Memory pack".to_string(),
+                    "BS Memory ROM file",
+                    BS_MEMORY_ROM_EXTENSIONS,
+                ),
+                _ => {
+                    slot_cart_files.push(None);
+                    continue;
+                }
+            };
+            let slot_path = FileDialog::new()
+                .set_title(&title)
+                .add_filter(filter_name, extensions)
+                .pick_file();
+            slot_cart_files.push(slot_path.and_then(|slot_path| match read_rom(&slot_path) {
+                Ok(rom) => Some(SlotCartFiles {
+                    ty: slot.ty,
+                    rom,
+                    name: slot_path.file_stem().unwrap_or_default().to_os_string(),
+                }),
+                Err(err) => {
+                    error!("Couldn't load slot cart", "{}.", err);
+                    None
+                }
+            }));
+        }
+
         let game_title = cart_info
             .title
             .as_deref()
@@ -193,6 +366,12 @@ impl UiState {
             &game_title,
         );
 
+        if cart_info_source != cart::info::Source::Db {
+            if let Some(layout) = game_config.contents.save_ram_layout {
+                cart_info.set_save_ram_layout(layout);
+            }
+        }
+
         match config::launch_config(
             &self.global_config.contents,
             &game_config.contents,
@@ -200,7 +379,16 @@ impl UiState {
             &game_title,
         ) {
             Ok(launch_config) => {
-                self.start(launch_config, game_title, game_config, rom, cart_info);
+                self.start(
+                    launch_config,
+                    game_title,
+                    game_config,
+                    rom,
+                    cart_info,
+                    firmware,
+                    slot_cart_files,
+                    msu1::Files::for_rom(path),
+                );
             }
             Err(errors) => {
                 config_error!(
@@ -222,6 +410,9 @@ impl UiState {
         game_config: Config<config::Game>,
         rom: BoxedByteSlice,
         cart_info: cart::info::Info,
+        firmware: cart::Firmware,
+        slot_cart_files: Vec<Option<SlotCartFiles>>,
+        msu1_files: Option<msu1::Files>,
     ) {
         self.stop();
 
@@ -235,11 +426,14 @@ impl UiState {
             self.presence_updated = true;
         }
 
+        let cheats = cheats::active_codes(&game_config.contents.cheats);
         self.game_title = Some(game_title);
         self.game_config = Some(game_config);
 
         self.limit_framerate = config.limit_framerate;
+        self.rewinding = false;
         self.sync_to_audio = config.sync_to_audio;
+        self.controller_devices = config.controller_devices;
 
         if let Some(channel) = &mut self.audio_channel {
             channel
@@ -247,43 +441,67 @@ impl UiState {
                 .set_interp(config.audio_interp_method.value.create_interp());
         }
 
-        let ram = if let Some(path) = config.cur_save_path.as_deref() {
-            match File::open(&path) {
-                Ok(mut ram_file) => {
-                    let ram_len = ram_file
-                        .metadata()
-                        .expect("Couldn't get save RAM file metadata")
-                        .len()
-                        .next_power_of_two() as usize;
-                    let mut ram = BoxedByteSlice::new_zeroed(ram_len);
-                    ram_file
-                        .read_exact(&mut ram[..])
-                        .expect("Couldn't read save RAM file");
-                    Some(ram)
+        let rom_sha256: [u8; 32] = <sha2::Sha256 as sha2::Digest>::digest(&rom[..]).into();
+
+        let ram = config
+            .cur_save_path
+            .as_deref()
+            .and_then(read_save_ram)
+            .unwrap_or_else(|| BoxedByteSlice::new_zeroed(cart_info.ram_size as usize));
+
+        // Slot carts' save files sit next to the main game's, and are disabled along with it
+        let (slot_carts, slot_save_paths): (Vec<_>, Vec<_>) = slot_cart_files
+            .into_iter()
+            .map(|files| match files {
+                Some(files) => {
+                    let save_path = config
+                        .cur_save_path
+                        .as_deref()
+                        .map(|path| config::slot_cart_save_path(path, &files.name));
+                    let save_data = save_path.as_deref().and_then(read_save_ram);
+                    let slot_cart = if files.ty == cart::info::SlotType::BsMemory {
+                        // Rewritten flash contents take the place of the original image
+                        let rom = save_data
+                            .filter(|flash| flash.len() == files.rom.len())
+                            .unwrap_or(files.rom);
+                        cart::SlotCart::new(rom, BoxedByteSlice::new_zeroed(0))
+                    } else {
+                        let ram = save_data.unwrap_or_else(|| {
+                            BoxedByteSlice::new_zeroed(
+                                cart::sufami_turbo::ram_size(files.rom.as_byte_slice()).unwrap_or(0)
+                                    as usize,
+                            )
+                        });
+                        cart::SlotCart::new(files.rom, ram)
+                    };
+                    (Some(slot_cart), save_path)
                 }
-                Err(err) => match err.kind() {
-                    io::ErrorKind::NotFound => None,
-                    err => {
-                        error!("Couldn't read save RAM file", "{:?}.", err);
-                        None
-                    }
-                },
-            }
-        } else {
-            None
-        }
-        .unwrap_or_else(|| BoxedByteSlice::new_zeroed(cart_info.ram_size as usize));
+                None => (None, None),
+            })
+            .unzip();
 
-        let cart = if let Some(cart) = cart::Cart::new(rom, ram, &cart_info) {
-            cart
-        } else {
-            error!(
-                "Cart creation error",
-                "Couldn't create cart from the specified ROM and save RAM files."
-            );
-            return;
+        let mut cart = match cart::Cart::new(rom, ram, &cart_info, firmware, slot_carts) {
+            Ok(cart) => cart,
+            Err(err) => {
+                error!("Couldn't create cart", "{}.", err);
+                return;
+            }
         };
 
+        // Without any saved state, the cart's RTC starts out synchronized with the host's clock
+        match config
+            .cur_save_path
+            .as_deref()
+            .and_then(|path| fs::read(rtc_path(path)).ok())
+        {
+            Some(rtc_data) => {
+                if !cart.load_rtc_data(&rtc_data, unix_time()) {
+                    error!("Couldn't read RTC file", "The RTC state file is invalid.");
+                }
+            }
+            None => cart.set_rtc_time(unix_time()),
+        }
+
         #[cfg(feature = "log")]
         let logger = self.logger.clone();
 
@@ -307,6 +525,10 @@ impl UiState {
                     emu::main(
                         config,
                         cart,
+                        slot_save_paths,
+                        msu1_files,
+                        rom_sha256,
+                        cheats,
                         audio_tx_data,
                         frame_tx,
                         message_rx,
@@ -358,6 +580,33 @@ impl UiState {
     }
 }
 
+fn light_gun_state(
+    ui: &imgui::Ui,
+    screen_rect: Option<([f32; 2], [f32; 2])>,
+    view_height: usize,
+    screen_focused: bool,
+) -> input::LightGunState {
+    let io = ui.io();
+    let aim = screen_rect.and_then(|([x_base, y_base], [width, height])| {
+        let x = (io.mouse_pos[0] - x_base) / width;
+        let y = (io.mouse_pos[1] - y_base) / height;
+        if (0.0..1.0).contains(&x) && (0.0..1.0).contains(&y) {
+            Some([
+                (x * VIEW_WIDTH as f32) as u16,
+                (y * view_height as f32) as u16,
+            ])
+        } else {
+            None
+        }
+    });
+    input::LightGunState {
+        aim,
+        trigger_pressed: screen_focused && io.mouse_down[0],
+        cursor_pressed: screen_focused && io.mouse_down[1],
+        pause_pressed: screen_focused && io.mouse_down[2],
+    }
+}
+
 fn clear_fb_texture(id: imgui::TextureId, window: &mut window::Window) {
     let mut data = zeroed_box::<[u8; FB_WIDTH * FB_HEIGHT * 4]>();
     for i in (0..data.len()).step_by(4) {
@@ -496,6 +745,12 @@ pub fn main() {
         screen_focused: true,
         input: input::State::new(keymap),
         input_editor: None,
+        rewinding: false,
+        cheat_manager: None,
+        controller_devices: config::RuntimeModifiable::global(
+            global_config.contents.controller_devices,
+        ),
+        light_gun: Default::default(),
 
         audio_channel,
         audio_volume: global_config.contents.audio_volume,
@@ -549,8 +804,8 @@ pub fn main() {
 
     window_builder.run(
         state,
-        |_, state, event| {
-            use winit::event::{Event, WindowEvent};
+        |window, state, event| {
+            use winit::event::{ElementState, Event, MouseButton, WindowEvent};
 
             if let Event::WindowEvent {
                 event: WindowEvent::DroppedFile(path),
@@ -561,6 +816,27 @@ pub fn main() {
             }
 
             state.input.process_event(event, state.screen_focused);
+            if let Event::WindowEvent { event, .. } = event {
+                match event {
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: MouseButton::Left,
+                        ..
+                    } if state.screen_focused
+                        && state.emu_thread.is_some()
+                        && state
+                            .controller_devices
+                            .value
+                            .contains(&config::ControllerDevice::Mouse) =>
+                    {
+                        state.input.set_mouse_captured(&window.window, true);
+                    }
+                    WindowEvent::Focused(false) => {
+                        state.input.set_mouse_captured(&window.window, false);
+                    }
+                    _ => {}
+                }
+            }
             if let Some(input_editor) = &mut state.input_editor {
                 input_editor.process_event(event, &mut state.input);
             }
@@ -616,17 +892,39 @@ pub fn main() {
                 window.window.set_title("Ness - No game loaded");
             }
 
+            if state.input.mouse_captured()
+                && (state.emu_thread.is_none()
+                    || !state
+                        .controller_devices
+                        .value
+                        .contains(&config::ControllerDevice::Mouse))
+            {
+                state.input.set_mouse_captured(&window.window, false);
+            }
+
             if state.playing {
                 if let Some(changes) = state.input.drain_changes() {
                     state.send_message(emu::Message::UpdateInput(changes));
                 }
+                if let Some(changes) = state.input.drain_mouse_changes() {
+                    state.send_message(emu::Message::UpdateMouse(changes));
+                }
+                let rewinding = matches!(
+                    &state.global_config.contents.rewind_trigger,
+                    Some(trigger) if state.input.trigger_activated(trigger)
+                );
+                if rewinding != state.rewinding {
+                    state.rewinding = rewinding;
+                    state.send_message(emu::Message::Rewind(rewinding));
+                }
             }
 
-            if state.global_config.contents.fullscreen_render
-                && ui.is_key_pressed(imgui::Key::Escape)
-                && !ui.is_any_item_focused()
-            {
-                state.show_menu_bar = !state.show_menu_bar;
+            if ui.is_key_pressed(imgui::Key::Escape) && !ui.is_any_item_focused() {
+                if state.input.mouse_captured() {
+                    state.input.set_mouse_captured(&window.window, false);
+                } else if state.global_config.contents.fullscreen_render {
+                    state.show_menu_bar = !state.show_menu_bar;
+                }
             }
 
             if state.show_menu_bar {
@@ -669,6 +967,80 @@ pub fn main() {
                             clear_fb_texture(state.fb_texture_id, window);
                         }
 
+                        ui.separator();
+
+                        if imgui::MenuItem::new("Save state...")
+                            .enabled(state.emu_thread.is_some())
+                            .build(ui)
+                        {
+                            if let Some(path) = FileDialog::new()
+                                .add_filter("Save state file", SAVE_STATE_EXTENSIONS)
+                                .set_file_name("save.state")
+                                .save_file()
+                            {
+                                state.send_message(emu::Message::SaveState(path));
+                            }
+                        }
+
+                        if imgui::MenuItem::new("Load state...")
+                            .enabled(state.emu_thread.is_some())
+                            .build(ui)
+                        {
+                            if let Some(path) = FileDialog::new()
+                                .add_filter("Save state file", SAVE_STATE_EXTENSIONS)
+                                .pick_file()
+                            {
+                                state.send_message(emu::Message::LoadState(path));
+                            }
+                        }
+
+                        ui.menu_with_enabled("Movie", state.emu_thread.is_some(), || {
+                            for (label, from_power_on) in [
+                                ("Record from power-on...", true),
+                                ("Record from current state...", false),
+                            ] {
+                                if imgui::MenuItem::new(label).build(ui) {
+                                    if let Some(path) = FileDialog::new()
+                                        .add_filter("Movie file", MOVIE_EXTENSIONS)
+                                        .set_file_name("recording.movie")
+                                        .save_file()
+                                    {
+                                        state.send_message(emu::Message::RecordMovie(
+                                            path,
+                                            from_power_on,
+                                        ));
+                                    }
+                                }
+                            }
+
+                            if imgui::MenuItem::new("Play...").build(ui) {
+                                if let Some(path) = FileDialog::new()
+                                    .add_filter("Movie file", MOVIE_EXTENSIONS)
+                                    .pick_file()
+                                {
+                                    state.send_message(emu::Message::PlayMovie(path));
+                                }
+                            }
+
+                            if imgui::MenuItem::new("Stop").build(ui) {
+                                state.send_message(emu::Message::StopMovie);
+                            }
+                        });
+
+                        let mut show_cheats = state.cheat_manager.is_some();
+                        if imgui::MenuItem::new("Cheats")
+                            .enabled(state.game_config.is_some())
+                            .build_with_ref(ui, &mut show_cheats)
+                        {
+                            state.cheat_manager = if show_cheats {
+                                Some(cheats::Manager::new())
+                            } else {
+                                None
+                            };
+                        }
+
+                        ui.separator();
+
                         if imgui::MenuItem::new("Load game...").build(ui) {
                             if let Some(path) = FileDialog::new()
                                 .add_filter("SNES ROM file", ALLOWED_ROM_EXTENSIONS)
@@ -786,6 +1158,97 @@ pub fn main() {
                             state.show_menu_bar = !state.global_config.contents.fullscreen_render;
                         }
 
+                        ui.menu("Controllers", || {
+                            static CONTROLLER_DEVICES: [config::ControllerDevice; 6] = [
+                                config::ControllerDevice::None,
+                                config::ControllerDevice::Joypad,
+                                config::ControllerDevice::Multitap,
+                                config::ControllerDevice::Mouse,
+                                config::ControllerDevice::SuperScope,
+                                config::ControllerDevice::Justifier,
+                            ];
+                            let mut updated = false;
+                            for (port, device) in
+                                state.controller_devices.value.iter_mut().enumerate()
+                            {
+                                let mut i =
+                                    CONTROLLER_DEVICES.iter().position(|d| d == device).unwrap();
+                                if ui.combo(
+                                    &format!("Port {}", port + 1),
+                                    &mut i,
+                                    &CONTROLLER_DEVICES,
+                                    |device| {
+                                        match device {
+                                            config::ControllerDevice::None => "None",
+                                            config::ControllerDevice::Joypad => "Joypad",
+                                            config::ControllerDevice::Multitap => "Multitap",
+                                            config::ControllerDevice::Mouse => "Mouse",
+                                            config::ControllerDevice::SuperScope => "Super Scope",
+                                            config::ControllerDevice::Justifier => "Justifier",
+                                        }
+                                        .into()
+                                    },
+                                ) {
+                                    *device = CONTROLLER_DEVICES[i];
+                                    updated = true;
+                                }
+                            }
+                            if updated {
+                                if state.controller_devices.origin == config::SettingOrigin::Game {
+                                    let game_config = state.game_config.as_mut().unwrap();
+                                    game_config.contents.controller_devices =
+                                        Some(state.controller_devices.value);
+                                    game_config.dirty = true;
+                                }
+                                state.global_config.contents.controller_devices =
+                                    state.controller_devices.value;
+                                state.global_config.dirty = true;
+                                state.send_message(emu::Message::UpdateControllerDevices(
+                                    state.controller_devices.value,
+                                ));
+                            }
+                        });
+
+                        ui.menu("Rewind", || {
+                            let mut updated = imgui::MenuItem::new("Enabled").build_with_ref(
+                                ui,
+                                &mut state.global_config.contents.rewind_enabled,
+                            );
+                            let mut interval = state.global_config.contents.rewind_interval as i32;
+                            if imgui::InputInt::new(ui, "Snapshot interval", &mut interval)
+                                .enter_returns_true(true)
+                                .build()
+                            {
+                                state.global_config.contents.rewind_interval =
+                                    interval.max(1) as u32;
+                                updated = true;
+                            }
+                            let mut memory_budget_mib =
+                                state.global_config.contents.rewind_memory_budget_mib as i32;
+                            if imgui::InputInt::new(
+                                ui,
+                                "Memory budget (MiB)",
+                                &mut memory_budget_mib,
+                            )
+                            .enter_returns_true(true)
+                            .build()
+                            {
+                                state.global_config.contents.rewind_memory_budget_mib =
+                                    memory_budget_mib.max(1) as u32;
+                                updated = true;
+                            }
+                            if updated {
+                                state.global_config.dirty = true;
+                                state.send_message(emu::Message::UpdateRewind(
+                                    state.global_config.contents.rewind_enabled,
+                                    state.global_config.contents.rewind_interval,
+                                    (state.global_config.contents.rewind_memory_budget_mib
+                                        as usize)
+                                        << 20,
+                                ));
+                            }
+                        });
+
                         let mut show_input = state.input_editor.is_some();
                         if imgui::MenuItem::new("Input").build_with_ref(ui, &mut show_input) {
                             state.input_editor = if show_input {
@@ -836,6 +1299,22 @@ pub fn main() {
                     .expect("Couldn't send UI message");
             }
 
+            #[cfg(feature = "debug-views")]
+            {
+                let new_cheats = state.debug_views.take_new_cheats();
+                if let (false, Some(game_config)) = (new_cheats.is_empty(), &mut state.game_config)
+                {
+                    game_config.contents.cheats.extend(new_cheats);
+                    game_config.dirty = true;
+                    state
+                        .message_tx
+                        .send(emu::Message::UpdateCheats(cheats::active_codes(
+                            &game_config.contents.cheats,
+                        )))
+                        .expect("Couldn't send UI message");
+                }
+            }
+
             if let Some(input_editor) = &mut state.input_editor {
                 let mut opened = true;
                 input_editor.draw(ui, &mut state.input, &mut opened);
@@ -844,12 +1323,31 @@ pub fn main() {
                 }
             }
 
+            if let (Some(cheat_manager), Some(game_config)) =
+                (&mut state.cheat_manager, &mut state.game_config)
+            {
+                let mut opened = true;
+                if cheat_manager.draw(ui, &mut game_config.contents.cheats, &mut opened) {
+                    game_config.dirty = true;
+                    state
+                        .message_tx
+                        .send(emu::Message::UpdateCheats(cheats::active_codes(
+                            &game_config.contents.cheats,
+                        )))
+                        .expect("Couldn't send UI message");
+                }
+                if !opened {
+                    state.cheat_manager = None;
+                }
+            }
+
             let window_size = window.window.inner_size();
             let aspect_ratio = VIEW_WIDTH as f32 / state.fb_view_height as f32;
             let uv1 = [
                 state.fb_width as f32 / FB_WIDTH as f32,
                 state.fb_height as f32 / FB_HEIGHT as f32,
             ];
+            let mut screen_rect = None;
             if state.global_config.contents.fullscreen_render {
                 let ([x_base, y_base], [width, height]) = scale_to_fit(
                     aspect_ratio,
@@ -866,6 +1364,7 @@ pub fn main() {
                     )
                     .uv_max(uv1)
                     .build();
+                screen_rect = Some(([x_base, y_base], [width, height]));
                 state.screen_focused =
                     !ui.is_window_focused_with_flags(imgui::WindowFocusedFlags::ANY_WINDOW);
             } else {
@@ -897,10 +1396,20 @@ pub fn main() {
                         imgui::Image::new(state.fb_texture_id, [width, height])
                             .uv1(uv1)
                             .build(ui);
+                        screen_rect = Some((ui.item_rect_min(), [width, height]));
                         state.screen_focused = ui.is_window_focused();
                     });
             }
 
+            if state.playing {
+                let light_gun =
+                    light_gun_state(ui, screen_rect, state.fb_view_height, state.screen_focused);
+                if light_gun != state.light_gun {
+                    state.light_gun = light_gun;
+                    state.send_message(emu::Message::UpdateLightGun(light_gun));
+                }
+            }
+
             window::ControlFlow::Continue
         },
         move |window, mut state| {
@@ -2,6 +2,7 @@ use std::{
     env,
     lazy::SyncLazy,
     path::{Path, PathBuf},
+    time::SystemTime,
 };
 
 macro_rules! warning {
@@ -84,3 +85,15 @@ pub fn config_base<'a>() -> &'a Path {
 pub fn data_base<'a>() -> &'a Path {
     &*DATA_BASE
 }
+
+/// Returns the current host time, in seconds since the Unix epoch.
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Returns the path of the real-time clock state file stored alongside the given save RAM file.
+pub fn rtc_path(save_path: &Path) -> PathBuf {
+    save_path.with_extension("rtc")
+}
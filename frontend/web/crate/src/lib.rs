@@ -4,14 +4,41 @@ mod console_log;
 use core::str;
 use js_sys::{Uint32Array, Uint8Array};
 use ness_core::{
-    apu::dsp, cart, controllers::joypad::Keys, emu::Emu, utils::BoxedByteSlice, Model,
+    apu::dsp,
+    cart,
+    controllers::{
+        empty::Empty,
+        joypad::{Joypad, Keys},
+        multitap::Multitap,
+        Device,
+    },
+    emu::Emu,
+    utils::BoxedByteSlice,
+    Model,
 };
 use wasm_bindgen::prelude::*;
 
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ControllerDevice {
+    None,
+    Joypad,
+    Multitap,
+}
+
+fn create_controller_device(device: ControllerDevice) -> Box<dyn Device> {
+    match device {
+        ControllerDevice::None => Box::new(Empty::new()),
+        ControllerDevice::Joypad => Box::new(Joypad::new()),
+        ControllerDevice::Multitap => Box::new(Multitap::new()),
+    }
+}
+
 #[wasm_bindgen]
 pub struct EmuState {
     cart_info: cart::info::Info,
     cart: cart::Cart,
+    controller_devices: [ControllerDevice; 2],
     emu: Emu,
 }
 
@@ -33,6 +60,20 @@ impl EmuState {
             #[cfg(feature = "log")]
             &slog::Logger::root(slog::Discard, slog::o!()),
         );
+        for (port, &device) in self.controller_devices.iter().enumerate() {
+            self.emu
+                .controllers
+                .connect_device(port, create_controller_device(device));
+        }
+    }
+
+    pub fn set_controller_device(&mut self, port: usize, device: ControllerDevice) {
+        if port < 2 && self.controller_devices[port] != device {
+            self.controller_devices[port] = device;
+            self.emu
+                .controllers
+                .connect_device(port, create_controller_device(device));
+        }
     }
 
     pub fn load_save(&mut self, ram_arr: Uint8Array) {
@@ -45,11 +86,18 @@ impl EmuState {
         Uint8Array::from(&self.emu.cart.ram()[..])
     }
 
-    pub fn update_input(&mut self, pressed: u16, released: u16) {
-        if let Some(joypad) = self.emu.controllers.devices[0]
-            .as_any()
-            .downcast_mut::<ness_core::controllers::joypad::Joypad>()
-        {
+    pub fn save_state(&self) -> Uint8Array {
+        Uint8Array::from(&self.emu.save_state()[..])
+    }
+
+    pub fn load_state(&mut self, state_arr: Uint8Array) -> Result<(), JsValue> {
+        self.emu
+            .load_state(&state_arr.to_vec())
+            .map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    pub fn update_input(&mut self, player: usize, pressed: u16, released: u16) {
+        if let Some(joypad) = self.emu.controllers.joypad_mut(player) {
             joypad.modify_keys(
                 Keys::from_bits_truncate(pressed),
                 Keys::from_bits_truncate(released),
@@ -82,7 +130,11 @@ impl EmuState {
 
 // Wasm-bindgen creates invalid output using a constructor, for some reason
 #[wasm_bindgen]
-pub fn create_emu_state(rom_arr: Uint8Array, carts_db: &[u8], boards_db: &[u8]) -> EmuState {
+pub fn create_emu_state(
+    rom_arr: Uint8Array,
+    carts_db: &[u8],
+    boards_db: &[u8],
+) -> Result<EmuState, JsValue> {
     console_error_panic_hook::set_once();
 
     let db = str::from_utf8(carts_db)
@@ -92,24 +144,27 @@ pub fn create_emu_state(rom_arr: Uint8Array, carts_db: &[u8], boards_db: &[u8])
             cart::info::db::Db::load(carts_db_str, boards_db_str).ok()
         });
 
-    let mut rom = BoxedByteSlice::new_zeroed(rom_arr.length() as usize);
-    rom_arr.copy_to(&mut rom[..]);
+    let rom = cart::rom::load(&rom_arr.to_vec()).rom;
     let cart_info = cart::info::Info::new(
         rom.as_byte_slice(),
         db.as_ref()
             .map(|db| (db, <sha2::Sha256 as sha2::Digest>::digest(&rom[..]).into())),
     )
+    .map_err(|err| JsValue::from_str(&format!("Unsupported cartridge: {}", err)))?
     .0;
     let cart = cart::Cart::new(
         rom,
         BoxedByteSlice::new_zeroed(cart_info.ram_size as usize),
         &cart_info,
+        cart::Firmware::default(),
+        vec![],
     )
-    .expect("Couldn't build cart");
+    .map_err(|err| JsValue::from_str(&format!("Couldn't create cart: {}", err)))?;
 
-    EmuState {
+    Ok(EmuState {
         cart_info,
         cart: cart.clone(),
+        controller_devices: [ControllerDevice::Joypad, ControllerDevice::None],
         emu: Emu::new(
             Model::Ntsc,
             cart,
@@ -118,5 +173,5 @@ pub fn create_emu_state(rom_arr: Uint8Array, carts_db: &[u8], boards_db: &[u8])
             #[cfg(feature = "log")]
             &slog::Logger::root(console_log::Console::new(), slog::o!()),
         ),
-    }
+    })
 }
@@ -75,11 +75,19 @@ import type * as wasm from "../../pkg";
         const data = e.data as UiToEmu.Message;
         switch (data.type) {
             case UiToEmu.MessageType.Start: {
-                emu = wasm.create_emu_state(
-                    new Uint8Array(data.romBuffer),
-                    new Uint8Array(data.cartsDB),
-                    new Uint8Array(data.boardsDB)
-                );
+                try {
+                    emu = wasm.create_emu_state(
+                        new Uint8Array(data.romBuffer),
+                        new Uint8Array(data.cartsDB),
+                        new Uint8Array(data.boardsDB)
+                    );
+                } catch (err) {
+                    sendMessage({
+                        type: EmuToUi.MessageType.Error,
+                        message: String(err),
+                    });
+                    close();
+                }
                 break;
             }
 
@@ -107,7 +115,12 @@ import type * as wasm from "../../pkg";
             }
 
             case UiToEmu.MessageType.UpdateInput: {
-                emu!.update_input(data.pressed, data.released);
+                emu!.update_input(data.player, data.pressed, data.released);
+                break;
+            }
+
+            case UiToEmu.MessageType.UpdateControllerDevice: {
+                emu!.set_controller_device(data.port, data.device as number);
                 break;
             }
 
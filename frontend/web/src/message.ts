@@ -13,6 +13,14 @@ export const enum InputBits {
     B = 1 << 15,
 }
 
+export const enum ControllerDevice {
+    None,
+    Joypad,
+    Multitap,
+}
+
+export const MAX_PLAYERS = 5;
+
 export namespace UiToEmu {
     export const enum MessageType {
         Start,
@@ -23,6 +31,7 @@ export namespace UiToEmu {
         UpdateInput,
         UpdatePlaying,
         UpdateLimitFramerate,
+        UpdateControllerDevice,
     }
 
     export interface StartMessage {
@@ -44,10 +53,17 @@ export namespace UiToEmu {
 
     export interface UpdateInputMessage {
         type: MessageType.UpdateInput;
+        player: number;
         pressed: number;
         released: number;
     }
 
+    export interface UpdateControllerDeviceMessage {
+        type: MessageType.UpdateControllerDevice;
+        port: number;
+        device: ControllerDevice;
+    }
+
     export interface UpdateFlagMessage {
         type: MessageType.UpdatePlaying | MessageType.UpdateLimitFramerate;
         value: boolean;
@@ -58,12 +74,14 @@ export namespace UiToEmu {
         | RawMessage
         | LoadSaveMessage
         | UpdateInputMessage
+        | UpdateControllerDeviceMessage
         | UpdateFlagMessage;
 }
 
 export namespace EmuToUi {
     export const enum MessageType {
         Loaded,
+        Error,
         ExportSave,
         RenderFrame,
     }
@@ -72,6 +90,11 @@ export namespace EmuToUi {
         type: MessageType.Loaded;
     }
 
+    export interface ErrorMessage {
+        type: MessageType.Error;
+        message: string;
+    }
+
     export interface ExportSaveMessage {
         type: MessageType.ExportSave;
         buffer: Uint8Array;
@@ -87,6 +110,7 @@ export namespace EmuToUi {
 
     export type Message =
         | LoadedMessage
+        | ErrorMessage
         | ExportSaveMessage
         | RenderFrameMessage;
 }
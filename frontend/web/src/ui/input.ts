@@ -1,20 +1,58 @@
-import { InputBits } from "../message";
+import { InputBits, MAX_PLAYERS } from "../message";
 import { TouchControls, Touch } from "./touch_controls";
 
-const keyToInputBit: { [key: string]: number } = {
-    w: InputBits.R,
-    q: InputBits.L,
-    a: InputBits.Y,
-    s: InputBits.X,
-    z: InputBits.B,
-    x: InputBits.A,
-    Enter: InputBits.Start,
-    Shift: InputBits.Select,
-    ArrowRight: InputBits.Right,
-    ArrowLeft: InputBits.Left,
-    ArrowDown: InputBits.Down,
-    ArrowUp: InputBits.Up,
-};
+// Key mappings for the players that can be controlled through the keyboard; the rest can only be
+// controlled through gamepads
+const keyToInputBit: { [key: string]: number }[] = [
+    {
+        w: InputBits.R,
+        q: InputBits.L,
+        a: InputBits.Y,
+        s: InputBits.X,
+        z: InputBits.B,
+        x: InputBits.A,
+        Enter: InputBits.Start,
+        Shift: InputBits.Select,
+        ArrowRight: InputBits.Right,
+        ArrowLeft: InputBits.Left,
+        ArrowDown: InputBits.Down,
+        ArrowUp: InputBits.Up,
+    },
+    {
+        o: InputBits.R,
+        u: InputBits.L,
+        y: InputBits.Y,
+        h: InputBits.X,
+        n: InputBits.B,
+        m: InputBits.A,
+        p: InputBits.Start,
+        "[": InputBits.Select,
+        l: InputBits.Right,
+        j: InputBits.Left,
+        k: InputBits.Down,
+        i: InputBits.Up,
+    },
+];
+
+// Indexed by button in the standard gamepad mapping
+const gamepadButtonToInputBit: number[] = [
+    InputBits.B,
+    InputBits.A,
+    InputBits.Y,
+    InputBits.X,
+    InputBits.L,
+    InputBits.R,
+    0,
+    0,
+    InputBits.Select,
+    InputBits.Start,
+    0,
+    0,
+    InputBits.Up,
+    InputBits.Down,
+    InputBits.Left,
+    InputBits.Right,
+];
 
 export interface InputChanges {
     pressed: number;
@@ -33,7 +71,7 @@ export class Input {
     private touchMoveCallback: (e: TouchEvent) => void;
     private touchEndCallback: (e: TouchEvent) => void;
 
-    private pressedKeys: number;
+    private pressedKeys: number[];
 
     constructor(touch: boolean, private pauseCallback: () => void) {
         this.controls = document.getElementById("controls")!;
@@ -46,13 +84,17 @@ export class Input {
         this.touchMoveCallback = this.touchMove.bind(this);
         this.touchEndCallback = this.touchEnd.bind(this);
 
-        this.pressedKeys = 0;
+        this.pressedKeys = new Array(keyToInputBit.length).fill(0);
 
         document.body.addEventListener("keydown", (e) => {
-            this.pressedKeys |= keyToInputBit[e.key] ?? 0;
+            keyToInputBit.forEach((map, player) => {
+                this.pressedKeys[player] |= map[e.key] ?? 0;
+            });
         });
         document.body.addEventListener("keyup", (e) => {
-            this.pressedKeys &= ~(keyToInputBit[e.key] ?? 0);
+            keyToInputBit.forEach((map, player) => {
+                this.pressedKeys[player] &= ~(map[e.key] ?? 0);
+            });
         });
 
         this.touch = touch;
@@ -150,14 +192,33 @@ export class Input {
         }
     }
 
-    process(): number {
-        let input = this.pressedKeys;
+    // Returns the pressed keys for each player; touch controls drive player 1, and each gamepad
+    // drives the player corresponding to its index.
+    process(): number[] {
+        const input = new Array(MAX_PLAYERS).fill(0);
+        this.pressedKeys.forEach((keys, player) => {
+            input[player] |= keys;
+        });
         if (this.touchControls) {
             this.touchControls.resetTouches();
             for (const touch of this.touches.values()) {
-                input = this.touchControls.processTouch(touch, input);
+                input[0] = this.touchControls.processTouch(touch, input[0]);
             }
         }
+        for (const gamepad of navigator.getGamepads?.() ?? []) {
+            if (
+                !gamepad ||
+                gamepad.mapping !== "standard" ||
+                gamepad.index >= MAX_PLAYERS
+            ) {
+                continue;
+            }
+            gamepad.buttons.forEach((button, i) => {
+                if (button.pressed) {
+                    input[gamepad.index] |= gamepadButtonToInputBit[i] ?? 0;
+                }
+            });
+        }
         return input;
     }
 }
@@ -1,4 +1,9 @@
-import { UiToEmu, EmuToUi } from "../message";
+import {
+    UiToEmu,
+    EmuToUi,
+    ControllerDevice,
+    MAX_PLAYERS,
+} from "../message";
 import { FileId, Files } from "./files";
 import { Input } from "./input";
 import vertShaderSource from "raw-loader!../shaders/screen.vert";
@@ -9,7 +14,8 @@ export class Ui {
     private canvasContainer: HTMLElement;
     private canvas: HTMLCanvasElement;
     private input: Input;
-    private inputState: number;
+    private inputState: number[];
+    private deviceSelects: HTMLSelectElement[];
     private menuContainer: HTMLElement;
     private playButton: HTMLButtonElement;
     private resetButton: HTMLButtonElement;
@@ -36,7 +42,13 @@ export class Ui {
         ) as HTMLDivElement;
         this.canvas = document.getElementById("canvas") as HTMLCanvasElement;
         this.input = new Input(touch, this.pause.bind(this));
-        this.inputState = 0;
+        this.inputState = new Array(MAX_PLAYERS).fill(0);
+        this.deviceSelects = [1, 2].map(
+            (port) =>
+                document.getElementById(
+                    `port-${port}-device`
+                ) as HTMLSelectElement
+        );
         this.menuContainer = document.getElementById(
             "menu-container"
         ) as HTMLElement;
@@ -87,6 +99,12 @@ export class Ui {
             });
         });
 
+        this.deviceSelects.forEach((select, port) => {
+            select.addEventListener("change", () => {
+                this.sendControllerDevice(port);
+            });
+        });
+
         const gl = this.canvas.getContext("webgl", {
             alpha: false,
             depth: false,
@@ -206,13 +224,17 @@ export class Ui {
 
         const prevInputState = this.inputState;
         this.inputState = this.input.process();
-        if (this.inputState != prevInputState) {
-            this.sendMessage({
-                type: UiToEmu.MessageType.UpdateInput,
-                pressed: this.inputState & ~prevInputState,
-                released: prevInputState & ~this.inputState,
-            });
-        }
+        this.inputState.forEach((state, player) => {
+            const prevState = prevInputState[player]!;
+            if (state != prevState) {
+                this.sendMessage({
+                    type: UiToEmu.MessageType.UpdateInput,
+                    player,
+                    pressed: state & ~prevState,
+                    released: prevState & ~state,
+                });
+            }
+        });
 
         requestAnimationFrame(this.frame.bind(this));
     }
@@ -221,6 +243,16 @@ export class Ui {
         this.worker?.postMessage(message, transfer as any);
     }
 
+    sendControllerDevice(port: number) {
+        this.sendMessage({
+            type: UiToEmu.MessageType.UpdateControllerDevice,
+            port,
+            device: parseInt(
+                this.deviceSelects[port]!.value
+            ) as ControllerDevice,
+        });
+    }
+
     play() {
         document.body.classList.remove("paused");
         this.sendMessage({
@@ -267,6 +299,9 @@ export class Ui {
                 },
                 [romBuffer]
             );
+            for (let port = 0; port < this.deviceSelects.length; port++) {
+                this.sendControllerDevice(port);
+            }
             this.worker!.onmessage = (e) => {
                 this.handleWorkerEvent(e.data);
             };
@@ -275,6 +310,12 @@ export class Ui {
 
     handleWorkerEvent(event: EmuToUi.Message) {
         switch (event.type) {
+            case EmuToUi.MessageType.Error: {
+                this.stop();
+                alert(event.message);
+                break;
+            }
+
             case EmuToUi.MessageType.ExportSave: {
                 // TODO
                 console.error("TODO: Export save");